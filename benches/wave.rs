@@ -0,0 +1,112 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use face::wave::{
+    build_line_mesh, catmull_rom, interpolate_samples, sample_noise, smooth_samples, wave_points,
+    write_line_mesh,
+};
+use noise::{BasicMulti, MultiFractal, Perlin};
+
+/// Segment counts of the 480 and 800 pixel wide panels at 5 and 2 pixel segments
+/// and of a 4k desktop window
+const POINT_COUNTS: [usize; 4] = [98, 162, 402, 770];
+
+const WIDTH_DIVIDER: f64 = 60.0;
+const SEGMENT_WIDTH: f32 = 5.0;
+const HEIGHT_MULTIPLIER: f64 = 400.0;
+const LINE_WIDTH: f32 = 2.0;
+
+fn generator(octaves: usize) -> BasicMulti<Perlin> {
+    BasicMulti::<Perlin>::new(100).set_octaves(octaves)
+}
+
+fn bench_sample_noise(c: &mut Criterion) {
+    let mut group = c.benchmark_group("sample_noise");
+    for octaves in [1, 2, 4] {
+        let generator = generator(octaves);
+        for count in POINT_COUNTS {
+            group.bench_with_input(
+                BenchmarkId::new(format!("{octaves}_octaves"), count),
+                &count,
+                |b, &count| {
+                    b.iter(|| sample_noise(&generator, black_box(1.5), WIDTH_DIVIDER, count))
+                },
+            );
+        }
+    }
+    group.finish();
+}
+
+fn bench_wave_points(c: &mut Criterion) {
+    let generator = generator(2);
+    let mut group = c.benchmark_group("wave_points");
+    for count in POINT_COUNTS {
+        let previous = sample_noise(&generator, 1.0, WIDTH_DIVIDER, count);
+        let current = sample_noise(&generator, 1.1, WIDTH_DIVIDER, count);
+        group.bench_with_input(BenchmarkId::from_parameter(count), &count, |b, _| {
+            b.iter(|| {
+                wave_points(
+                    interpolate_samples(&previous, &current, black_box(0.5)),
+                    -240.0,
+                    SEGMENT_WIDTH,
+                    HEIGHT_MULTIPLIER,
+                )
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_smoothing(c: &mut Criterion) {
+    let generator = generator(2);
+    let mut group = c.benchmark_group("smoothing");
+    for count in POINT_COUNTS {
+        let samples = sample_noise(&generator, 1.0, WIDTH_DIVIDER, count);
+        group.bench_with_input(
+            BenchmarkId::new("moving_average", count),
+            &samples,
+            |b, samples| b.iter(|| smooth_samples(samples, black_box(5))),
+        );
+        let points = wave_points(
+            samples.iter().copied(),
+            -240.0,
+            SEGMENT_WIDTH,
+            HEIGHT_MULTIPLIER,
+        );
+        group.bench_with_input(
+            BenchmarkId::new("catmull_rom", count),
+            &points,
+            |b, points| b.iter(|| catmull_rom(points, black_box(3))),
+        );
+    }
+    group.finish();
+}
+
+fn bench_line_mesh(c: &mut Criterion) {
+    let generator = generator(2);
+    let mut group = c.benchmark_group("line_mesh");
+    for count in POINT_COUNTS {
+        let samples = sample_noise(&generator, 1.0, WIDTH_DIVIDER, count);
+        let points = wave_points(
+            samples.iter().copied(),
+            -240.0,
+            SEGMENT_WIDTH,
+            HEIGHT_MULTIPLIER,
+        );
+        group.bench_with_input(BenchmarkId::new("build", count), &points, |b, points| {
+            b.iter(|| build_line_mesh(points, LINE_WIDTH))
+        });
+        let mut mesh = build_line_mesh(&points, LINE_WIDTH);
+        group.bench_with_input(BenchmarkId::new("write", count), &points, |b, points| {
+            b.iter(|| write_line_mesh(&mut mesh, points, LINE_WIDTH))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_sample_noise,
+    bench_wave_points,
+    bench_smoothing,
+    bench_line_mesh
+);
+criterion_main!(benches);
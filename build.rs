@@ -0,0 +1,20 @@
+use std::process::Command;
+
+fn main() {
+    // reported on face/version so deployed builds can be traced back to a commit
+    let git_hash = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|hash| hash.trim().to_owned())
+        .unwrap_or_else(|| String::from("unknown"));
+    println!("cargo:rustc-env=FACE_GIT_HASH={git_hash}");
+    println!(
+        "cargo:rustc-env=FACE_BUILD_PROFILE={}",
+        std::env::var("PROFILE").unwrap_or_default()
+    );
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+}
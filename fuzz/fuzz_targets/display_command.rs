@@ -0,0 +1,15 @@
+#![no_main]
+
+use face::{display::DisplayControlMessage, messaging::parse_json_payload};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(DisplayControlMessage {
+        mode: Some(mode), ..
+    }) = parse_json_payload(data)
+    {
+        if mode.validate().is_ok() {
+            _ = mode.to_string();
+        }
+    }
+});
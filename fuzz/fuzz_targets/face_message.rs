@@ -0,0 +1,70 @@
+#![no_main]
+
+use std::sync::OnceLock;
+
+use bevy::prelude::*;
+use face::{
+    gaze::GazePlugin,
+    greeting::GreetingPlugin,
+    messaging::{parse_face_message, parse_reply_to, FaceMessage},
+    mood::MoodPlugin,
+    shutdown::ShutdownPlugin,
+    topics::TopicRegistry,
+    weather::WeatherPlugin,
+};
+use libfuzzer_sys::fuzz_target;
+
+/// Every topic routed through `parse_face_message`
+const KEY_EXPRS: [&str; 7] = [
+    "face/settings",
+    "face/calibration",
+    "face/audio",
+    "face/draw/polyline",
+    "face/imu",
+    "face/batch",
+    "face/wave/raw",
+];
+
+/// Topics registered by the plugins
+fn topics() -> &'static TopicRegistry {
+    static TOPICS: OnceLock<TopicRegistry> = OnceLock::new();
+    TOPICS.get_or_init(|| {
+        let mut app = App::new();
+        app.add_plugins((
+            GazePlugin {
+                follow_mouse: false,
+            },
+            GreetingPlugin,
+            MoodPlugin,
+            ShutdownPlugin,
+            WeatherPlugin,
+        ));
+        app.world.resource::<TopicRegistry>().clone()
+    })
+}
+
+fn validate(message: &FaceMessage) {
+    match message {
+        FaceMessage::Polyline(polyline) => {
+            _ = polyline.validate();
+        }
+        FaceMessage::Batch(messages) => messages.iter().for_each(validate),
+        _ => {}
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    for key_expr in KEY_EXPRS {
+        for binary in [false, true] {
+            if let Ok(message) = parse_face_message(key_expr, data, binary) {
+                validate(&message);
+            }
+        }
+    }
+    for topic in topics().iter() {
+        for binary in [false, true] {
+            _ = topic.parse(data, binary);
+        }
+    }
+    _ = parse_reply_to(data);
+});
@@ -0,0 +1,95 @@
+use std::path::Path;
+
+use anyhow::Context;
+use bevy::prelude::*;
+
+/// Rules limiting which sources may send which commands
+///
+/// Commands name their publisher in a `source` field. Keys no rule covers are open to everyone,
+/// a covered key is only accepted from the sources listed by the rules covering it.
+/// Sources aren't authenticated, the rules keep well meaning nodes apart
+/// while zenoh TLS and user names keep others out
+#[derive(Resource, serde::Deserialize, Clone, Debug, Default)]
+pub struct CommandAcl {
+    #[serde(default)]
+    pub rules: Vec<AclRule>,
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct AclRule {
+    /// Command keys such as `face/display`, each also covers the keys below it
+    pub keys: Vec<String>,
+    /// Sources allowed to send the covered commands
+    pub sources: Vec<String>,
+}
+
+impl CommandAcl {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let data =
+            std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_slice(&data)
+            .with_context(|| format!("Invalid access control file {}", path.display()))
+    }
+
+    /// Whether a command with `payload` may be applied on `key_expr`, the reason if not
+    pub fn check(&self, key_expr: &str, payload: &[u8]) -> Result<(), String> {
+        let mut covering = self
+            .rules
+            .iter()
+            .filter(|rule| rule.keys.iter().any(|key| covers(key, key_expr)))
+            .peekable();
+        if covering.peek().is_none() {
+            return Ok(());
+        }
+        let Some(source) = parse_source(payload) else {
+            return Err(format!("{key_expr} needs a source"));
+        };
+        if covering
+            .flat_map(|rule| &rule.sources)
+            .any(|allowed| *allowed == source)
+        {
+            Ok(())
+        } else {
+            Err(format!("{source} may not send {key_expr}"))
+        }
+    }
+}
+
+fn covers(rule_key: &str, key_expr: &str) -> bool {
+    let rule_key = rule_key.trim_end_matches('/');
+    key_expr
+        .strip_prefix(rule_key)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// Read the `source` key of a json command
+fn parse_source(payload: &[u8]) -> Option<String> {
+    serde_json::from_slice::<serde_json::Value>(payload)
+        .ok()
+        .and_then(|value| value.get("source")?.as_str().map(str::to_owned))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn covered_keys_need_a_listed_source() {
+        let acl: CommandAcl = serde_json::from_value(serde_json::json!({
+            "rules": [
+                {"keys": ["face/display", "face/command"], "sources": ["supervisor"]},
+                {"keys": ["face/display"], "sources": ["display-manager"]},
+            ]
+        }))
+        .unwrap();
+        let from = |source: &str| format!(r#"{{"source": "{source}"}}"#).into_bytes();
+
+        assert!(acl.check("face/display", &from("supervisor")).is_ok());
+        assert!(acl.check("face/display", &from("display-manager")).is_ok());
+        assert!(acl.check("face/command", &from("display-manager")).is_err());
+        assert!(acl.check("face/command", b"{}").is_err());
+        assert!(acl.check("face/command", b"not json").is_err());
+        assert!(acl.check("face/commands", b"{}").is_ok());
+        assert!(acl.check("face/gaze", &from("anyone")).is_ok());
+    }
+}
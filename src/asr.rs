@@ -0,0 +1,201 @@
+use std::time::Duration;
+
+use bevy::{prelude::*, sprite::Anchor, text::Text2dBounds};
+
+use crate::{
+    dnd::DoNotDisturb, face_clock::FaceClock, rotation::FaceRotation, safe_area::SafeArea,
+    scene::FaceScene, topics::RegisterTopic,
+};
+
+/// Live transcription of what the robot hears along the bottom of the face
+///
+/// A speech recognizer streams its guesses to `face/asr/partial` while someone talks,
+/// showing them lets people see they are being heard. Partial guesses are dimmed,
+/// the final one lights up before fading away
+pub struct AsrSubtitlePlugin;
+
+impl Plugin for AsrSubtitlePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_topic::<AsrTranscript>("face/asr/partial")
+            .init_resource::<Transcript>()
+            .init_resource::<DoNotDisturb>()
+            .add_systems(Startup, spawn_transcript)
+            .add_systems(
+                Update,
+                (
+                    process_asr_transcripts,
+                    update_transcript,
+                    position_transcript,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Partial guesses are dropped when the recognizer goes quiet for this long
+const PARTIAL_TIMEOUT: Duration = Duration::from_secs(3);
+/// Keep the final transcript up this long
+const FINAL_LINGER: Duration = Duration::from_secs(2);
+/// Longer transcripts only show their end, which is what is being said right now
+const MAX_TEXT_LENGTH: usize = 120;
+const TRANSCRIPT_FONT_SIZE: f32 = 28.0;
+const TRANSCRIPT_MARGIN: f32 = 16.0;
+/// Share of the safe area width the transcript may take up
+const TRANSCRIPT_MAX_WIDTH: f32 = 0.9;
+const PARTIAL_COLOR: Color = Color::rgba(0.6, 0.8, 1.0, 0.6);
+const FINAL_COLOR: Color = Color::rgb(0.6, 0.8, 1.0);
+
+/// Transcription of what the robot hears from a streaming speech recognizer
+#[derive(Event, serde::Deserialize, Debug)]
+pub struct AsrTranscript {
+    /// Everything recognized of the current utterance so far, empty hides the transcript
+    pub text: String,
+    /// Set on the last transcript of an utterance
+    #[serde(default, rename = "final")]
+    pub is_final: bool,
+}
+
+#[derive(Resource, Default)]
+struct Transcript {
+    text: Option<String>,
+    is_final: bool,
+    hide_at: Option<Duration>,
+}
+
+#[derive(Component)]
+struct TranscriptText;
+
+/// End of `text` at most `max_length` characters long, cut at a word when possible
+fn transcript_tail(text: &str, max_length: usize) -> String {
+    let text = text.trim();
+    let length = text.chars().count();
+    if length <= max_length {
+        return text.to_owned();
+    }
+    let tail: String = text.chars().skip(length - max_length).collect();
+    let tail = match tail.split_once(char::is_whitespace) {
+        Some((_, rest)) if !rest.trim().is_empty() => rest.trim_start(),
+        _ => &tail,
+    };
+    format!("...{tail}")
+}
+
+fn spawn_transcript(mut commands: Commands) {
+    commands.spawn((
+        Text2dBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: TRANSCRIPT_FONT_SIZE,
+                    color: PARTIAL_COLOR,
+                    ..default()
+                },
+            )
+            .with_justify(JustifyText::Center),
+            text_anchor: Anchor::BottomCenter,
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        TranscriptText,
+    ));
+}
+
+fn process_asr_transcripts(
+    mut messages: EventReader<AsrTranscript>,
+    mut transcript: ResMut<Transcript>,
+    clock: Res<FaceClock>,
+) {
+    for message in messages.read() {
+        debug!(
+            text = message.text,
+            is_final = message.is_final,
+            "ASR transcript"
+        );
+        let text = transcript_tail(&message.text, MAX_TEXT_LENGTH);
+        if text.is_empty() {
+            *transcript = Transcript::default();
+            continue;
+        }
+        let linger = if message.is_final {
+            FINAL_LINGER
+        } else {
+            PARTIAL_TIMEOUT
+        };
+        transcript.text = Some(text);
+        transcript.is_final = message.is_final;
+        transcript.hide_at = Some(clock.elapsed() + linger);
+    }
+}
+
+fn update_transcript(
+    mut transcript: ResMut<Transcript>,
+    do_not_disturb: Res<DoNotDisturb>,
+    scene: Res<State<FaceScene>>,
+    clock: Res<FaceClock>,
+    mut texts: Query<(&mut Text, &mut Visibility), With<TranscriptText>>,
+) {
+    if transcript
+        .hide_at
+        .is_some_and(|hide_at| clock.elapsed() >= hide_at)
+    {
+        *transcript = Transcript::default();
+    }
+
+    // test patterns need the whole screen
+    let covered = matches!(scene.get(), FaceScene::Calibration | FaceScene::SelfTest);
+    let visible = transcript.text.is_some() && !covered && !do_not_disturb.active();
+    for (mut text, mut visibility) in texts.iter_mut() {
+        let target = if visible {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        if *visibility != target {
+            *visibility = target;
+        }
+        if !transcript.is_changed() {
+            continue;
+        }
+        let section = &mut text.sections[0];
+        let value = transcript.text.as_deref().unwrap_or_default();
+        if section.value != value {
+            section.value = value.to_owned();
+        }
+        section.style.color = if transcript.is_final {
+            FINAL_COLOR
+        } else {
+            PARTIAL_COLOR
+        };
+    }
+}
+
+/// Keep the transcript at the bottom of the safe area and wrap it to its width
+fn position_transcript(
+    mut texts: Query<(&mut Transform, &mut Text2dBounds), With<TranscriptText>>,
+    query_camera: Query<Ref<OrthographicProjection>>,
+    safe_area: Res<SafeArea>,
+    rotation: Res<FaceRotation>,
+) {
+    for camera in query_camera.iter() {
+        if !camera.is_changed() && !safe_area.is_changed() {
+            continue;
+        }
+        let area = safe_area.apply(rotation.area(camera.area));
+        for (mut transform, mut bounds) in texts.iter_mut() {
+            transform.translation = Vec3::new(area.center().x, area.min.y + TRANSCRIPT_MARGIN, 2.0);
+            bounds.size.x = (area.width() * TRANSCRIPT_MAX_WIDTH).max(0.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_transcripts_show_their_end() {
+        assert_eq!(transcript_tail("  hello there ", 20), "hello there");
+        assert_eq!(transcript_tail("hello there robot", 10), "...robot");
+        assert_eq!(transcript_tail("abcdefghij", 4), "...ghij");
+    }
+}
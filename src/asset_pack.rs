@@ -0,0 +1,205 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use bevy::{asset::io::file::FileAssetReader, prelude::*};
+use sha2::{Digest, Sha256};
+
+/// Reload assets of packs installed over `face/assets/install`
+///
+/// Packs are gzipped tarballs downloaded by the zenoh worker with `curl`,
+/// verified against their sha256 checksum and unpacked with `tar` into `assets/packs/<name>`
+pub struct AssetPackPlugin;
+
+impl Plugin for AssetPackPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<AssetPackInstalled>()
+            .add_systems(Update, reload_asset_packs);
+    }
+}
+
+/// Directory inside of the assets directory packs are installed into
+const ASSET_PACK_DIR: &str = "packs";
+
+/// Request to download and install an asset pack
+#[derive(serde::Deserialize, serde::Serialize, clap::Args, Debug)]
+pub struct AssetPackMessage {
+    /// Pack is installed into `assets/packs/<name>`, replacing any previous version
+    #[arg(long)]
+    pub name: String,
+    /// Url of the gzipped tarball
+    #[arg(long)]
+    pub url: String,
+    /// Hex encoded sha256 checksum of the tarball
+    #[arg(long)]
+    pub sha256: String,
+    /// Key to publish an acknowledgement to once installed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub reply_to: Option<String>,
+}
+
+/// Sent once a pack was installed
+#[derive(Event, Debug)]
+pub struct AssetPackInstalled {
+    pub name: String,
+    /// Installed files as asset paths
+    pub assets: Vec<String>,
+}
+
+/// Root of the assets directory the same way bevy resolves it
+pub fn assets_dir() -> PathBuf {
+    FileAssetReader::get_base_path().join("assets")
+}
+
+/// Download, verify and unpack the pack into `assets_dir`
+pub async fn install_asset_pack(
+    assets_dir: &Path,
+    message: &AssetPackMessage,
+) -> anyhow::Result<AssetPackInstalled> {
+    let name = &message.name;
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        anyhow::bail!("Invalid asset pack name {name:?}");
+    }
+    let url = validate_url(&message.url)?;
+    let packs_dir = assets_dir.join(ASSET_PACK_DIR);
+    let download = packs_dir.join(format!(".{name}.tar.gz"));
+    let staging = packs_dir.join(format!(".{name}.staging"));
+    let target = packs_dir.join(name);
+
+    tokio::fs::create_dir_all(&packs_dir)
+        .await
+        .with_context(|| format!("Failed to create {}", packs_dir.display()))?;
+    let result = async {
+        run("curl", &["-fsSL", "-o", path_arg(&download)?, "--", url]).await?;
+        verify_checksum(&download, &message.sha256).await?;
+
+        _ = tokio::fs::remove_dir_all(&staging).await;
+        tokio::fs::create_dir_all(&staging).await?;
+        run(
+            "tar",
+            &["-xzf", path_arg(&download)?, "-C", path_arg(&staging)?],
+        )
+        .await?;
+
+        _ = tokio::fs::remove_dir_all(&target).await;
+        tokio::fs::rename(&staging, &target)
+            .await
+            .with_context(|| format!("Failed to install {}", target.display()))?;
+        anyhow::Ok(())
+    }
+    .await;
+    _ = tokio::fs::remove_file(&download).await;
+    _ = tokio::fs::remove_dir_all(&staging).await;
+    result?;
+
+    let mut assets = vec![];
+    collect_assets(assets_dir, &target, &mut assets)?;
+    Ok(AssetPackInstalled {
+        name: name.clone(),
+        assets,
+    })
+}
+
+/// Only plain http and https downloads, anything else could be read as a curl option
+fn validate_url(url: &str) -> anyhow::Result<&str> {
+    let scheme_ok = ["http://", "https://"].iter().any(|scheme| {
+        url.get(..scheme.len())
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case(scheme))
+    });
+    if !scheme_ok || url.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        anyhow::bail!("Asset pack url {url:?} must be an http or https url");
+    }
+    Ok(url)
+}
+
+fn path_arg(path: &Path) -> anyhow::Result<&str> {
+    path.to_str()
+        .with_context(|| format!("Path {} is not valid utf-8", path.display()))
+}
+
+async fn run(program: &str, args: &[&str]) -> anyhow::Result<()> {
+    let output = tokio::process::Command::new(program)
+        .args(args)
+        .output()
+        .await
+        .with_context(|| format!("Failed to run {program}"))?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "{program} failed with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+async fn verify_checksum(path: &Path, expected: &str) -> anyhow::Result<()> {
+    let data = tokio::fs::read(path)
+        .await
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let checksum = format!("{:x}", Sha256::digest(&data));
+    if !checksum.eq_ignore_ascii_case(expected.trim()) {
+        anyhow::bail!("Checksum mismatch, expected {expected} but got {checksum}");
+    }
+    Ok(())
+}
+
+/// Add every file under `dir` to `assets` as a path relative to `assets_dir`
+fn collect_assets(assets_dir: &Path, dir: &Path, assets: &mut Vec<String>) -> anyhow::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_assets(assets_dir, &path, assets)?;
+        } else if let Ok(relative) = path.strip_prefix(assets_dir) {
+            // asset paths always use forward slashes
+            let components: Vec<_> = relative
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect();
+            assets.push(components.join("/"));
+        }
+    }
+    Ok(())
+}
+
+/// Reload already loaded assets of a freshly installed pack
+fn reload_asset_packs(
+    mut installed: EventReader<AssetPackInstalled>,
+    asset_server: Res<AssetServer>,
+) {
+    for pack in installed.read() {
+        info!(
+            name = pack.name,
+            assets = pack.assets.len(),
+            "Reloading asset pack"
+        );
+        for asset in &pack.assets {
+            asset_server.reload(asset.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_http_urls_are_downloaded() {
+        assert!(validate_url("https://example.com/packs/eyes.tar.gz").is_ok());
+        assert!(validate_url("HTTP://example.com/eyes.tar.gz").is_ok());
+        for url in [
+            "--config=/etc/passwd",
+            "-o/tmp/evil",
+            "file:///etc/passwd",
+            "ftp://example.com/eyes.tar.gz",
+            "https://example.com/eyes.tar.gz -o /tmp/evil",
+            "",
+        ] {
+            assert!(validate_url(url).is_err(), "{url} accepted");
+        }
+    }
+}
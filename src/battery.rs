@@ -0,0 +1,87 @@
+use bevy::prelude::*;
+
+use crate::topics::RegisterTopic;
+
+/// Battery of the robot as reported on `face/battery`
+///
+/// The face only keeps the latest reading for the status OLED
+pub struct BatteryPlugin;
+
+impl Plugin for BatteryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Battery>()
+            .add_topic::<BatteryMessage>("face/battery")
+            .add_systems(Update, update_battery);
+    }
+}
+
+/// Battery reading of the robot
+#[derive(Event, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct BatteryMessage {
+    /// Charge between 0 and 100
+    pub percent: f32,
+    #[serde(default)]
+    pub charging: bool,
+}
+
+/// Latest battery reading rounded to whole percents, unknown until one arrives
+#[derive(Resource, Default, Debug, PartialEq)]
+pub struct Battery(pub Option<BatteryMessage>);
+
+impl Battery {
+    /// Short summary such as "73% charging"
+    pub fn summary(&self) -> String {
+        match &self.0 {
+            Some(reading) if reading.charging => format!("{:.0}% charging", reading.percent),
+            Some(reading) => format!("{:.0}%", reading.percent),
+            None => "unknown".to_owned(),
+        }
+    }
+}
+
+fn update_battery(mut messages: EventReader<BatteryMessage>, mut battery: ResMut<Battery>) {
+    if let Some(message) = messages.read().last() {
+        let percent = if message.percent.is_nan() {
+            0.0
+        } else {
+            message.percent.clamp(0.0, 100.0).round()
+        };
+        // readers such as the status OLED only redraw when the shown percentage changes
+        battery.set_if_neq(Battery(Some(BatteryMessage {
+            percent,
+            charging: message.charging,
+        })));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latest_reading_is_kept_within_range() {
+        let mut app = App::new();
+        app.init_resource::<Battery>()
+            .add_event::<BatteryMessage>()
+            .add_systems(Update, update_battery);
+        assert_eq!(app.world.resource::<Battery>().summary(), "unknown");
+
+        app.world.send_event(BatteryMessage {
+            percent: 40.0,
+            charging: false,
+        });
+        app.world.send_event(BatteryMessage {
+            percent: 140.0,
+            charging: true,
+        });
+        app.update();
+        assert_eq!(app.world.resource::<Battery>().summary(), "100% charging");
+
+        app.world.send_event(BatteryMessage {
+            percent: 72.6,
+            charging: false,
+        });
+        app.update();
+        assert_eq!(app.world.resource::<Battery>().summary(), "73%");
+    }
+}
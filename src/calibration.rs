@@ -0,0 +1,184 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_prototype_lyon::prelude::*;
+
+use crate::{
+    messaging::{AckSender, CommandAck},
+    priority::Priority,
+    rotation::FaceRotation,
+    safe_area::SafeArea,
+    scene::{FaceScene, SceneRequests},
+};
+
+pub struct CalibrationPlugin;
+
+impl Plugin for CalibrationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<CalibrationMessage>()
+            .add_systems(OnEnter(FaceScene::Calibration), spawn_calibration_pattern)
+            .add_systems(OnExit(FaceScene::Calibration), despawn_calibration_pattern)
+            .add_systems(
+                Update,
+                (
+                    process_calibration_messages,
+                    update_calibration_pattern.run_if(in_state(FaceScene::Calibration)),
+                ),
+            );
+    }
+}
+
+/// Radii of the reference circles in pixels
+const CALIBRATION_CIRCLE_RADII: [f32; 4] = [50.0, 100.0, 150.0, 200.0];
+const CALIBRATION_LINE_WIDTH: f32 = 1.0;
+/// Longest time a timed calibration request is shown
+const MAX_CALIBRATION_DURATION: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Event, serde::Deserialize, serde::Serialize, clap::Args, Debug)]
+pub struct CalibrationMessage {
+    /// Show the pattern, it is hidden otherwise
+    #[serde(default)]
+    #[arg(long)]
+    pub enabled: bool,
+    #[serde(default)]
+    #[arg(long, value_enum, default_value_t)]
+    pub priority: Priority,
+    /// Hide the pattern again after this many seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub duration_s: Option<f32>,
+    /// Key to publish an acknowledgement to once applied
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub reply_to: Option<String>,
+}
+
+#[derive(Component)]
+struct CalibrationPattern;
+
+fn spawn_calibration_pattern(
+    mut commands: Commands,
+    query_camera: Query<&OrthographicProjection>,
+    safe_area: Res<SafeArea>,
+    rotation: Res<FaceRotation>,
+) {
+    let mut resolution = Rect::default();
+    for camera in query_camera.iter() {
+        resolution = rotation.area(camera.area);
+    }
+
+    commands.spawn((
+        ShapeBundle {
+            path: build_calibration_path(resolution, &safe_area),
+            ..default()
+        },
+        Stroke::new(Color::WHITE, CALIBRATION_LINE_WIDTH),
+        Fill::color(Color::NONE),
+        CalibrationPattern,
+    ));
+}
+
+fn despawn_calibration_pattern(
+    mut commands: Commands,
+    query: Query<Entity, With<CalibrationPattern>>,
+) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Rebuild the pattern when the visible area or the safe area changes
+/// so that the border keeps hugging the edges of the panel
+fn update_calibration_pattern(
+    mut query: Query<&mut Path, With<CalibrationPattern>>,
+    query_camera: Query<Ref<OrthographicProjection>>,
+    safe_area: Res<SafeArea>,
+    rotation: Res<FaceRotation>,
+) {
+    for camera in query_camera.iter() {
+        if !camera.is_changed() && !safe_area.is_changed() {
+            continue;
+        }
+        for mut path in query.iter_mut() {
+            *path = build_calibration_path(rotation.area(camera.area), &safe_area);
+        }
+    }
+}
+
+/// Border of the panel and of the safe area with a crosshair and circles at the center
+fn build_calibration_path(area: Rect, safe_area: &SafeArea) -> Path {
+    // inset border by the line width so that it's fully on screen
+    let border = shapes::Rectangle {
+        extents: area.size() - Vec2::splat(CALIBRATION_LINE_WIDTH * 2.0),
+        origin: shapes::RectangleOrigin::CustomCenter(area.center()),
+    };
+    let horizontal = shapes::Line(
+        Vec2::new(area.min.x, area.center().y),
+        Vec2::new(area.max.x, area.center().y),
+    );
+    let vertical = shapes::Line(
+        Vec2::new(area.center().x, area.min.y),
+        Vec2::new(area.center().x, area.max.y),
+    );
+
+    let mut builder = GeometryBuilder::new()
+        .add(&border)
+        .add(&horizontal)
+        .add(&vertical);
+    let safe = safe_area.apply(area);
+    if safe != area {
+        builder = builder.add(&shapes::Rectangle {
+            extents: safe.size(),
+            origin: shapes::RectangleOrigin::CustomCenter(safe.center()),
+        });
+    }
+    for radius in CALIBRATION_CIRCLE_RADII {
+        builder = builder.add(&shapes::Circle {
+            radius,
+            center: area.center(),
+        });
+    }
+    builder.build()
+}
+
+fn process_calibration_messages(
+    mut messages: EventReader<CalibrationMessage>,
+    mut scene_requests: ResMut<SceneRequests>,
+    ack_sender: Res<AckSender>,
+) {
+    for (message, id) in messages.read_with_id() {
+        let _trace = ack_sender.trace(id);
+        if message.enabled {
+            let duration = match message.duration_s {
+                Some(duration_s) if duration_s.is_finite() && duration_s > 0.0 => Some(
+                    Duration::from_secs_f32(duration_s.min(MAX_CALIBRATION_DURATION.as_secs_f32())),
+                ),
+                Some(duration_s) => {
+                    warn!(
+                        duration_s,
+                        "Ignoring calibration request with invalid duration"
+                    );
+                    ack_sender.send(
+                        message.reply_to.as_deref(),
+                        CommandAck::rejected(format!(
+                            "duration_s must be above zero, got {duration_s}"
+                        )),
+                    );
+                    continue;
+                }
+                None => None,
+            };
+            info!(priority = ?message.priority, ?duration, "Requesting calibration pattern");
+            scene_requests.push(message.priority, FaceScene::Calibration, duration);
+        } else {
+            info!("Hiding calibration pattern");
+            scene_requests.remove(&FaceScene::Calibration);
+        }
+        ack_sender.send(
+            message.reply_to.as_deref(),
+            CommandAck::applied(serde_json::json!({
+                "scene": scene_requests.current().copied().unwrap_or_default(),
+            })),
+        );
+    }
+}
@@ -0,0 +1,152 @@
+use std::{
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::Duration,
+};
+
+use anyhow::Context;
+use bevy::prelude::*;
+use zenoh::prelude::r#async::*;
+
+use crate::messaging::ErrorWrapper;
+
+/// Key the time authority answers queries on
+pub const CLOCK_KEY: &str = "face/time";
+/// How often followers measure their offset to the authority
+const CLOCK_SYNC_INTERVAL: Duration = Duration::from_secs(30);
+/// Queries per measurement, the one with the shortest round trip wins
+const CLOCK_SYNC_SAMPLES: usize = 5;
+const CLOCK_QUERY_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Answer of the time authority
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct ClockReply {
+    pub unix_ms: f64,
+}
+
+#[derive(Default)]
+struct ClockState {
+    /// Added to the local clock to get the clock of the authority
+    offset_ms: f64,
+    /// Round trip of the query the offset was measured with
+    round_trip_ms: Option<f64>,
+}
+
+/// Wall clock shared by several faces so their animations line up
+///
+/// The instance started with `--time-authority` answers queries on `face/time`,
+/// every other instance estimates its offset to it the way NTP does
+#[derive(Resource, Clone, Default)]
+pub struct SharedClock {
+    pub authority: bool,
+    state: Arc<Mutex<ClockState>>,
+}
+
+impl SharedClock {
+    pub fn new(authority: bool) -> Self {
+        Self {
+            authority,
+            ..default()
+        }
+    }
+
+    /// Milliseconds since the unix epoch on the clock of the authority
+    pub fn now_ms(&self) -> f64 {
+        local_unix_ms() + self.state().offset_ms
+    }
+
+    /// Whether an offset was measured, always true for the authority
+    pub fn is_synced(&self) -> bool {
+        self.authority || self.state().round_trip_ms.is_some()
+    }
+
+    fn state(&self) -> MutexGuard<'_, ClockState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Milliseconds since the unix epoch on the local clock
+pub fn local_unix_ms() -> f64 {
+    chrono::Utc::now().timestamp_micros() as f64 / 1000.0
+}
+
+/// Offset of the authority clock from a reply sent at `server_ms`,
+/// assuming the reply took half of the round trip
+pub fn estimate_offset(sent_ms: f64, server_ms: f64, received_ms: f64) -> f64 {
+    server_ms + (received_ms - sent_ms) / 2.0 - received_ms
+}
+
+/// Periodically measure the offset to the time authority
+pub async fn sync_clock(session: Arc<Session>, clock: SharedClock, key_expr: String) {
+    let mut interval = tokio::time::interval(CLOCK_SYNC_INTERVAL);
+    loop {
+        interval.tick().await;
+        let mut best: Option<(f64, f64)> = None;
+        for _ in 0..CLOCK_SYNC_SAMPLES {
+            match query_offset(&session, &key_expr).await {
+                Ok(Some((offset_ms, round_trip_ms))) => {
+                    if best.is_none_or(|(_, best_round_trip)| round_trip_ms < best_round_trip) {
+                        best = Some((offset_ms, round_trip_ms));
+                    }
+                }
+                Ok(None) => {}
+                Err(error) => {
+                    // the session is gone, the reconnected worker starts a new sync
+                    error!(?error, "Failed to query time authority");
+                    return;
+                }
+            }
+        }
+        let Some((offset_ms, round_trip_ms)) = best else {
+            debug!("No time authority answered");
+            continue;
+        };
+        let mut state = clock.state();
+        if state.round_trip_ms.is_none() {
+            info!(offset_ms, round_trip_ms, "Synchronized clock");
+        }
+        state.offset_ms = offset_ms;
+        state.round_trip_ms = Some(round_trip_ms);
+    }
+}
+
+/// Offset and round trip of a single query, none when nobody answered
+async fn query_offset(session: &Session, key_expr: &str) -> anyhow::Result<Option<(f64, f64)>> {
+    let sent_ms = local_unix_ms();
+    let replies = session
+        .get(key_expr)
+        .timeout(CLOCK_QUERY_TIMEOUT)
+        .res()
+        .await
+        .map_err(ErrorWrapper::ZenohError)
+        .context("Failed to query time")?;
+    let Ok(reply) = replies.recv_async().await else {
+        return Ok(None);
+    };
+    let received_ms = local_unix_ms();
+    let sample = reply
+        .sample
+        .map_err(|error| anyhow::anyhow!("Time authority replied with an error: {error:?}"))?;
+    let reply: ClockReply = serde_json::from_slice(&sample.value.payload.contiguous())?;
+    Ok(Some((
+        estimate_offset(sent_ms, reply.unix_ms, received_ms),
+        received_ms - sent_ms,
+    )))
+}
+
+/// Answer of the authority with its local clock
+pub fn clock_reply() -> ClockReply {
+    ClockReply {
+        unix_ms: local_unix_ms(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offset_accounts_for_half_the_round_trip() {
+        // authority is 500ms ahead and each direction takes 20ms
+        assert_eq!(estimate_offset(1_000.0, 1_520.0, 1_040.0), 500.0);
+    }
+}
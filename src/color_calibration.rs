@@ -0,0 +1,277 @@
+use bevy::{
+    asset::load_internal_asset,
+    core_pipeline::{
+        core_2d::graph::{Core2d, Node2d},
+        fullscreen_vertex_shader::fullscreen_shader_vertex_state,
+    },
+    ecs::query::QueryItem,
+    prelude::*,
+    render::{
+        extract_component::{ComponentUniforms, ExtractComponentPlugin, UniformComponentPlugin},
+        render_graph::{
+            NodeRunError, RenderGraphApp, RenderGraphContext, RenderLabel, ViewNode, ViewNodeRunner,
+        },
+        render_resource::{
+            binding_types::{sampler, texture_2d, uniform_buffer},
+            *,
+        },
+        renderer::{RenderContext, RenderDevice},
+        texture::BevyDefault,
+        view::ViewTarget,
+        RenderApp,
+    },
+};
+
+use self::uniform::ColorCalibrationUniform;
+use crate::params::{RegisterTunable, Tunable};
+
+/// Per channel gamma and white point correction applied to the whole rendered face
+///
+/// Compensates panels with a color cast, adjustable at runtime as the `color` tunable
+pub struct ColorCalibrationPlugin {
+    pub calibration: ColorCalibration,
+}
+
+impl Plugin for ColorCalibrationPlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(
+            app,
+            COLOR_CALIBRATION_SHADER_HANDLE,
+            "color_calibration.wgsl",
+            Shader::from_wgsl
+        );
+        app.insert_resource(self.calibration.clone())
+            .register_tunable::<ColorCalibration>("color")
+            .add_plugins((
+                ExtractComponentPlugin::<ColorCalibrationUniform>::default(),
+                UniformComponentPlugin::<ColorCalibrationUniform>::default(),
+            ))
+            .add_systems(PostUpdate, update_camera_calibration);
+
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .add_render_graph_node::<ViewNodeRunner<ColorCalibrationNode>>(
+                Core2d,
+                ColorCalibrationLabel,
+            )
+            .add_render_graph_edges(
+                Core2d,
+                (
+                    Node2d::Tonemapping,
+                    ColorCalibrationLabel,
+                    Node2d::EndMainPassPostProcessing,
+                ),
+            );
+    }
+
+    fn finish(&self, app: &mut App) {
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app.init_resource::<ColorCalibrationPipeline>();
+    }
+}
+
+const COLOR_CALIBRATION_SHADER_HANDLE: Handle<Shader> =
+    Handle::weak_from_u128(0x5c1f_0b7e_93d4_4a2e_8f61_2d7a_c0e4_b913);
+
+/// Gamma values are kept in this range
+pub const GAMMA_RANGE: std::ops::RangeInclusive<f32> = 0.1..=10.0;
+
+/// Color correction for the display panel
+#[derive(Resource, Reflect, Clone, Debug, PartialEq)]
+#[reflect(Resource)]
+pub struct ColorCalibration {
+    pub gamma_red: f32,
+    pub gamma_green: f32,
+    pub gamma_blue: f32,
+    /// White is drawn in this color, a warm white offsets a panel with a blue cast
+    pub white_point: Color,
+}
+
+impl Default for ColorCalibration {
+    fn default() -> Self {
+        Self {
+            gamma_red: 1.0,
+            gamma_green: 1.0,
+            gamma_blue: 1.0,
+            white_point: Color::WHITE,
+        }
+    }
+}
+
+impl ColorCalibration {
+    fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn uniform(&self) -> ColorCalibrationUniform {
+        let [red, green, blue, _] = self.white_point.as_linear_rgba_f32();
+        ColorCalibrationUniform {
+            inverse_gamma: Vec3::new(self.gamma_red, self.gamma_green, self.gamma_blue).recip(),
+            white_point: Vec3::new(red, green, blue),
+        }
+    }
+}
+
+impl Tunable for ColorCalibration {
+    fn sanitize(&mut self) {
+        for gamma in [
+            &mut self.gamma_red,
+            &mut self.gamma_green,
+            &mut self.gamma_blue,
+        ] {
+            *gamma = if gamma.is_finite() {
+                gamma.clamp(*GAMMA_RANGE.start(), *GAMMA_RANGE.end())
+            } else {
+                1.0
+            };
+        }
+        self.white_point = self.white_point.as_rgba();
+    }
+}
+
+mod uniform {
+    // the ShaderType derive generates size checks that are never called
+    #![allow(dead_code)]
+
+    use bevy::{
+        prelude::*,
+        render::{extract_component::ExtractComponent, render_resource::ShaderType},
+    };
+
+    /// Calibration of a camera as passed to the shader
+    #[derive(Component, Clone, Copy, ExtractComponent, ShaderType)]
+    pub(super) struct ColorCalibrationUniform {
+        pub(super) inverse_gamma: Vec3,
+        pub(super) white_point: Vec3,
+    }
+}
+
+/// Add the calibration to 2d cameras, or remove it to skip the pass when it changes nothing
+fn update_camera_calibration(
+    mut commands: Commands,
+    calibration: Res<ColorCalibration>,
+    cameras: Query<Entity, With<Camera2d>>,
+    added_cameras: Query<(), Added<Camera2d>>,
+) {
+    if !calibration.is_changed() && added_cameras.is_empty() {
+        return;
+    }
+    for camera in cameras.iter() {
+        if calibration.is_identity() {
+            commands.entity(camera).remove::<ColorCalibrationUniform>();
+        } else {
+            commands.entity(camera).insert(calibration.uniform());
+        }
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+struct ColorCalibrationLabel;
+
+#[derive(Default)]
+struct ColorCalibrationNode;
+
+impl ViewNode for ColorCalibrationNode {
+    type ViewQuery = (&'static ViewTarget, &'static ColorCalibrationUniform);
+
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        (view_target, _): QueryItem<Self::ViewQuery>,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let calibration_pipeline = world.resource::<ColorCalibrationPipeline>();
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let Some(pipeline) = pipeline_cache.get_render_pipeline(calibration_pipeline.pipeline_id)
+        else {
+            return Ok(());
+        };
+        let uniforms = world.resource::<ComponentUniforms<ColorCalibrationUniform>>();
+        let Some(uniform_binding) = uniforms.uniforms().binding() else {
+            return Ok(());
+        };
+
+        let post_process = view_target.post_process_write();
+        let bind_group = render_context.render_device().create_bind_group(
+            "color_calibration_bind_group",
+            &calibration_pipeline.layout,
+            &BindGroupEntries::sequential((
+                post_process.source,
+                &calibration_pipeline.sampler,
+                uniform_binding,
+            )),
+        );
+        let mut render_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+            label: Some("color_calibration_pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: post_process.destination,
+                resolve_target: None,
+                ops: Operations::default(),
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_pass.set_render_pipeline(pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+        Ok(())
+    }
+}
+
+#[derive(Resource)]
+struct ColorCalibrationPipeline {
+    layout: BindGroupLayout,
+    sampler: Sampler,
+    pipeline_id: CachedRenderPipelineId,
+}
+
+impl FromWorld for ColorCalibrationPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+        let layout = render_device.create_bind_group_layout(
+            "color_calibration_bind_group_layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::FRAGMENT,
+                (
+                    texture_2d(TextureSampleType::Float { filterable: true }),
+                    sampler(SamplerBindingType::Filtering),
+                    uniform_buffer::<ColorCalibrationUniform>(false),
+                ),
+            ),
+        );
+        let sampler = render_device.create_sampler(&SamplerDescriptor::default());
+        let pipeline_id =
+            world
+                .resource_mut::<PipelineCache>()
+                .queue_render_pipeline(RenderPipelineDescriptor {
+                    label: Some("color_calibration_pipeline".into()),
+                    layout: vec![layout.clone()],
+                    vertex: fullscreen_shader_vertex_state(),
+                    fragment: Some(FragmentState {
+                        shader: COLOR_CALIBRATION_SHADER_HANDLE,
+                        shader_defs: vec![],
+                        entry_point: "fragment".into(),
+                        targets: vec![Some(ColorTargetState {
+                            format: TextureFormat::bevy_default(),
+                            blend: None,
+                            write_mask: ColorWrites::ALL,
+                        })],
+                    }),
+                    primitive: PrimitiveState::default(),
+                    depth_stencil: None,
+                    multisample: MultisampleState::default(),
+                    push_constant_ranges: vec![],
+                });
+        Self {
+            layout,
+            sampler,
+            pipeline_id,
+        }
+    }
+}
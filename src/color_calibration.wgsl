@@ -0,0 +1,19 @@
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+
+struct ColorCalibration {
+    // reciprocal of the gamma of each channel
+    inverse_gamma: vec3<f32>,
+    // linear scale of each channel
+    white_point: vec3<f32>,
+}
+
+@group(0) @binding(0) var screen_texture: texture_2d<f32>;
+@group(0) @binding(1) var texture_sampler: sampler;
+@group(0) @binding(2) var<uniform> calibration: ColorCalibration;
+
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(screen_texture, texture_sampler, in.uv);
+    let corrected = pow(color.rgb, calibration.inverse_gamma) * calibration.white_point;
+    return vec4<f32>(corrected, color.a);
+}
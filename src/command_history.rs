@@ -0,0 +1,117 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+use bevy::prelude::*;
+
+use crate::clock_sync::local_unix_ms;
+
+/// Key the command history is answered on
+pub const HISTORY_KEY: &str = "face/history";
+/// Commands kept unless configured otherwise
+pub const DEFAULT_HISTORY_CAPACITY: u16 = 100;
+/// Longer payloads are cut off, drawings can be large
+const MAX_PAYLOAD_LENGTH: usize = 512;
+
+/// Command accepted by the zenoh worker
+#[derive(serde::Serialize, Debug, Clone, PartialEq)]
+pub struct CommandRecord {
+    pub key_expr: String,
+    /// Local time the command arrived
+    pub time: String,
+    pub unix_ms: f64,
+    /// Payload as received, cut off past a few hundred bytes
+    pub payload: String,
+}
+
+#[derive(Default)]
+struct CommandHistoryState {
+    capacity: usize,
+    records: VecDeque<CommandRecord>,
+}
+
+/// Last commands with their key and arrival time, answered on `face/history`
+///
+/// Shared by bevy and the zenoh worker, which records every command that parsed
+#[derive(Resource, Clone)]
+pub struct CommandHistory(Arc<Mutex<CommandHistoryState>>);
+
+impl Default for CommandHistory {
+    fn default() -> Self {
+        Self::new(DEFAULT_HISTORY_CAPACITY.into())
+    }
+}
+
+impl CommandHistory {
+    /// History keeping the last `capacity` commands, 0 keeps none
+    pub fn new(capacity: usize) -> Self {
+        Self(Arc::new(Mutex::new(CommandHistoryState {
+            capacity,
+            records: VecDeque::new(),
+        })))
+    }
+
+    pub fn record(&self, key_expr: &str, payload: &[u8], binary: bool) {
+        let mut state = self.state();
+        if state.capacity == 0 {
+            return;
+        }
+        let payload = if binary {
+            format!("<{} bytes>", payload.len())
+        } else {
+            truncate(&String::from_utf8_lossy(payload), MAX_PAYLOAD_LENGTH)
+        };
+        if state.records.len() == state.capacity {
+            state.records.pop_front();
+        }
+        state.records.push_back(CommandRecord {
+            key_expr: key_expr.to_owned(),
+            time: chrono::Local::now().to_rfc3339(),
+            unix_ms: local_unix_ms(),
+            payload,
+        });
+    }
+
+    /// Recorded commands, oldest first
+    pub fn records(&self) -> Vec<CommandRecord> {
+        self.state().records.iter().cloned().collect()
+    }
+
+    fn state(&self) -> MutexGuard<'_, CommandHistoryState> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+fn truncate(text: &str, max_length: usize) -> String {
+    match text.char_indices().nth(max_length) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn history_keeps_the_latest_commands() {
+        let history = CommandHistory::new(2);
+        history.record("face/gaze", br#"{"x": 0.5}"#, false);
+        history.record("face/wave/raw", &[0; 16], true);
+        history.record("face/say", "é".repeat(1000).as_bytes(), false);
+
+        let records = history.records();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].payload, "<16 bytes>");
+        assert_eq!(records[1].key_expr, "face/say");
+        assert_eq!(
+            records[1].payload.chars().count(),
+            MAX_PAYLOAD_LENGTH + "...".len()
+        );
+
+        let disabled = CommandHistory::new(0);
+        disabled.record("face/gaze", b"{}", false);
+        assert!(disabled.records().is_empty());
+    }
+}
@@ -0,0 +1,156 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+
+use crate::{
+    messaging::{KeyPrefix, ZenohMode},
+    noise_plugin::NoiseGeneratorSettingsUpdate,
+    wave_layers::WaveLayerSettings,
+};
+
+/// Startup settings loaded from the TOML file passed with `--config`
+///
+/// Every value is optional and command line flags take precedence over the file
+#[derive(serde::Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct FaceConfig {
+    pub window: WindowConfig,
+    pub display: DisplayConfig,
+    pub zenoh: ZenohConfig,
+    /// Wave settings applied at startup, persisted settings are applied on top
+    pub wave: Option<NoiseGeneratorSettingsUpdate>,
+    /// Wave layers drawn behind the main wave
+    pub waves: Vec<WaveLayerSettings>,
+}
+
+#[derive(serde::Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct WindowConfig {
+    /// Window size before rotation, 480 by 800 unless set
+    pub width: Option<f32>,
+    pub height: Option<f32>,
+    /// Run borderless fullscreen, `--windowed` and dev mode turn it off either way
+    pub fullscreen: Option<bool>,
+}
+
+#[derive(serde::Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct DisplayConfig {
+    /// Name of the display output
+    pub output: Option<String>,
+    /// Minutes between wake pulses while the display is off, no pulses unless set
+    pub wake_pulse_interval_min: Option<f32>,
+    /// Seconds the display stays on for each wake pulse
+    pub wake_pulse_duration_s: Option<f32>,
+}
+
+#[derive(serde::Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ZenohConfig {
+    /// Key expression the `face/` keys are moved under, such as `robots/alice/face`
+    pub key_prefix: Option<String>,
+    /// Zenoh json5 configuration file, relative paths start at the config file
+    pub config_file: Option<PathBuf>,
+    pub mode: Option<ZenohMode>,
+    /// Endpoints to connect to such as `tls/router.local:7447`
+    pub connect: Vec<String>,
+    pub listen: Vec<String>,
+    /// Seconds zenoh has to connect at boot before the face shows it is offline
+    pub connect_timeout_s: Option<f32>,
+}
+
+/// Window sizes outside of this are rejected
+const WINDOW_SIZE_RANGE: std::ops::RangeInclusive<f32> = 16.0..=8_192.0;
+
+impl FaceConfig {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config {}", path.display()))?;
+        let mut config =
+            Self::parse(&text).with_context(|| format!("Invalid config {}", path.display()))?;
+        if let (Some(zenoh_config), Some(dir)) = (&config.zenoh.config_file, path.parent()) {
+            config.zenoh.config_file = Some(dir.join(zenoh_config));
+        }
+        Ok(config)
+    }
+
+    fn parse(text: &str) -> anyhow::Result<Self> {
+        let config: Self = toml::from_str(text)?;
+        for size in [config.window.width, config.window.height]
+            .into_iter()
+            .flatten()
+        {
+            if !WINDOW_SIZE_RANGE.contains(&size) {
+                anyhow::bail!(
+                    "Window size {size} is outside of {}..={}",
+                    WINDOW_SIZE_RANGE.start(),
+                    WINDOW_SIZE_RANGE.end()
+                );
+            }
+        }
+        config.key_prefix()?;
+        Ok(config)
+    }
+
+    /// Configured key prefix, `face` unless set
+    pub fn key_prefix(&self) -> anyhow::Result<KeyPrefix> {
+        match &self.zenoh.key_prefix {
+            Some(prefix) => KeyPrefix::new(prefix),
+            None => Ok(KeyPrefix::default()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_sections_are_optional() {
+        let config = FaceConfig::parse(
+            r#"
+            [window]
+            width = 600
+            fullscreen = false
+
+            [display]
+            wake_pulse_interval_min = 10
+
+            [zenoh]
+            key_prefix = "robots/alice/face"
+            mode = "client"
+            connect = ["tcp/router.local:7447"]
+            connect_timeout_s = 30
+
+            [wave]
+            height_multiplier = 450
+            color = "ff0000"
+
+            [[waves]]
+            frequency = 2.0
+            amplitude = 0.5
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.window.width, Some(600.0));
+        assert_eq!(config.window.height, None);
+        assert_eq!(config.display.output, None);
+        assert_eq!(config.display.wake_pulse_interval_min, Some(10.0));
+        assert_eq!(config.zenoh.mode, Some(ZenohMode::Client));
+        assert_eq!(config.zenoh.connect, ["tcp/router.local:7447"]);
+        assert_eq!(config.zenoh.connect_timeout_s, Some(30.0));
+        let wave = config.wave.as_ref().unwrap();
+        assert_eq!(wave.height_multiplier, Some(450.0));
+        assert_eq!(wave.color.as_deref(), Some("ff0000"));
+        assert_eq!(config.waves.len(), 1);
+        assert_eq!(config.waves[0].frequency, 2.0);
+        let prefix = config.key_prefix().unwrap();
+        assert_eq!(prefix.apply("face/gaze"), "robots/alice/face/gaze");
+        assert_eq!(prefix.strip("robots/alice/face/gaze"), "face/gaze");
+
+        assert!(FaceConfig::parse("").is_ok());
+        assert!(FaceConfig::parse("[window]\nwidth = 0").is_err());
+        assert!(FaceConfig::parse("[zenoh]\nkey_prefix = \"robots/*\"").is_err());
+        assert!(FaceConfig::parse("[windw]\nwidth = 600").is_err());
+    }
+}
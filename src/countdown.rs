@@ -0,0 +1,312 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{
+    dnd::DoNotDisturb,
+    face_clock::FaceClock,
+    messaging::{AckSender, CommandAck},
+    priority::Priority,
+    rotation::FaceRotation,
+    safe_area::SafeArea,
+    scene::{FaceScene, SceneRequests},
+    topics::RegisterTopic,
+    trace::{CommandTrace, TraceGuard},
+};
+
+/// Large mm:ss countdown over the face started with `face/timer`
+///
+/// Once it runs out the face can flash, switch to another scene for a while
+/// and publish an event, as set per timer
+pub struct CountdownPlugin;
+
+impl Plugin for CountdownPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_topic::<TimerMessage>("face/timer")
+            .init_resource::<Countdown>()
+            .init_resource::<DoNotDisturb>()
+            .add_systems(Startup, spawn_countdown)
+            .add_systems(
+                Update,
+                (
+                    process_timer_messages,
+                    tick_countdown,
+                    update_countdown_text,
+                    update_countdown_flash,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Event published when a timer with `publish` runs out
+pub const TIMER_DONE_KEY: &str = "face/event/timer";
+/// Longest timer accepted, longer ones are clamped
+const MAX_TIMER_DURATION: Duration = Duration::from_secs(100 * 60 - 1);
+/// How long the end of the timer is shown
+const END_DURATION: Duration = Duration::from_secs(5);
+/// Flashes per second at the end
+const FLASH_FREQUENCY: f32 = 2.0;
+const FLASH_COLOR: Color = Color::WHITE;
+const COUNTDOWN_FONT_SIZE: f32 = 160.0;
+
+/// Start or cancel the countdown
+#[derive(Event, serde::Deserialize, serde::Serialize, clap::Args, Debug)]
+pub struct TimerMessage {
+    /// Seconds to count down from, leave out or set 0 to cancel the running timer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub duration_s: Option<f32>,
+    /// Name of the timer included in the end event
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub label: Option<String>,
+    /// Flash the screen when the timer runs out
+    #[serde(default)]
+    #[arg(long)]
+    pub flash: bool,
+    /// Show this scene for a few seconds when the timer runs out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[arg(long, value_enum)]
+    pub scene: Option<FaceScene>,
+    /// Publish an event on `face/event/timer` when the timer runs out
+    #[serde(default)]
+    #[arg(long)]
+    pub publish: bool,
+    /// Key to publish an acknowledgement to once applied
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub reply_to: Option<String>,
+}
+
+#[derive(serde::Serialize, Debug)]
+struct TimerDone<'a> {
+    label: Option<&'a str>,
+    duration_s: f32,
+}
+
+#[derive(Resource, Default)]
+struct Countdown {
+    running: Option<RunningTimer>,
+    /// Time the last timer ran out, the end is shown for a while after
+    ended_at: Option<Duration>,
+    flash: bool,
+}
+
+struct RunningTimer {
+    timer: Timer,
+    label: Option<String>,
+    flash: bool,
+    scene: Option<FaceScene>,
+    publish: bool,
+    /// Trace of the command, `face/event/timer` is published under it
+    trace: Option<CommandTrace>,
+}
+
+#[derive(Component)]
+struct CountdownText;
+
+#[derive(Component)]
+struct CountdownFlash;
+
+/// `mm:ss` rounding up so the timer shows 00:00 only once it ran out
+fn format_remaining(remaining: Duration) -> String {
+    let seconds = remaining.as_secs_f32().ceil() as u32;
+    format!("{:02}:{:02}", seconds / 60, seconds % 60)
+}
+
+fn spawn_countdown(mut commands: Commands) {
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: FLASH_COLOR.with_a(0.0),
+                ..default()
+            },
+            transform: Transform::from_xyz(0.0, 0.0, 3.0),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        CountdownFlash,
+    ));
+    commands.spawn((
+        Text2dBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: COUNTDOWN_FONT_SIZE,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            ),
+            transform: Transform::from_xyz(0.0, 0.0, 3.1),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        CountdownText,
+    ));
+}
+
+fn process_timer_messages(
+    mut messages: EventReader<TimerMessage>,
+    mut countdown: ResMut<Countdown>,
+    ack_sender: Res<AckSender>,
+) {
+    for (message, id) in messages.read_with_id() {
+        let _trace = ack_sender.trace(id);
+        let duration = message
+            .duration_s
+            .filter(|duration_s| duration_s.is_finite() && *duration_s > 0.0)
+            .map(|duration_s| {
+                Duration::from_secs_f32(duration_s.min(MAX_TIMER_DURATION.as_secs_f32()))
+            });
+        countdown.ended_at = None;
+        match duration {
+            Some(duration) => {
+                info!(?duration, label = message.label, "Starting countdown");
+                countdown.running = Some(RunningTimer {
+                    timer: Timer::new(duration, TimerMode::Once),
+                    label: message.label.clone(),
+                    flash: message.flash,
+                    scene: message.scene,
+                    publish: message.publish,
+                    trace: TraceGuard::current(),
+                });
+            }
+            None => {
+                info!("Cancelling countdown");
+                countdown.running = None;
+            }
+        }
+        ack_sender.send(
+            message.reply_to.as_deref(),
+            CommandAck::applied(serde_json::json!({
+                "remaining_s": duration.map(|duration| duration.as_secs_f32()),
+            })),
+        );
+    }
+}
+
+fn tick_countdown(
+    mut countdown: ResMut<Countdown>,
+    mut scene_requests: ResMut<SceneRequests>,
+    ack_sender: Res<AckSender>,
+    clock: Res<FaceClock>,
+) {
+    let Some(running) = &mut countdown.running else {
+        if countdown
+            .ended_at
+            .is_some_and(|ended_at| clock.elapsed() - ended_at >= END_DURATION)
+        {
+            countdown.ended_at = None;
+        }
+        return;
+    };
+    if !running.timer.tick(clock.delta()).finished() {
+        return;
+    }
+
+    let _trace = TraceGuard::enter(running.trace.clone());
+    info!(label = running.label, "Countdown finished");
+    if let Some(scene) = running.scene {
+        scene_requests.push(Priority::Commanded, scene, Some(END_DURATION));
+    }
+    if running.publish {
+        ack_sender.publish(
+            TIMER_DONE_KEY,
+            &TimerDone {
+                label: running.label.as_deref(),
+                duration_s: running.timer.duration().as_secs_f32(),
+            },
+        );
+    }
+    countdown.flash = running.flash;
+    countdown.ended_at = Some(clock.elapsed());
+    countdown.running = None;
+}
+
+/// Show the remaining time in the center of the safe area
+fn update_countdown_text(
+    (countdown, do_not_disturb): (Res<Countdown>, Res<DoNotDisturb>),
+    clock: Res<FaceClock>,
+    mut query: Query<(&mut Text, &mut Transform, &mut Visibility), With<CountdownText>>,
+    query_camera: Query<&OrthographicProjection>,
+    (safe_area, rotation): (Res<SafeArea>, Res<FaceRotation>),
+) {
+    let remaining = match (&countdown.running, countdown.ended_at) {
+        (Some(running), _) => Some(running.timer.remaining()),
+        (None, Some(_)) => Some(Duration::ZERO),
+        (None, None) => None,
+    }
+    .filter(|_| !do_not_disturb.active());
+    let mut center = Vec2::ZERO;
+    for camera in query_camera.iter() {
+        center = safe_area.apply(rotation.area(camera.area)).center();
+    }
+
+    for (mut text, mut transform, mut visibility) in query.iter_mut() {
+        let Some(remaining) = remaining else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        // blink the zeros once the timer ran out
+        let blink_on = countdown.ended_at.is_none_or(|ended_at| {
+            ((clock.elapsed() - ended_at).as_secs_f32() * FLASH_FREQUENCY).fract() < 0.5
+        });
+        *visibility = if blink_on {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        let value = format_remaining(remaining);
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+        transform.translation.x = center.x;
+        transform.translation.y = center.y;
+    }
+}
+
+/// Pulse the whole screen after a timer with `flash` ran out
+fn update_countdown_flash(
+    (countdown, do_not_disturb): (Res<Countdown>, Res<DoNotDisturb>),
+    clock: Res<FaceClock>,
+    mut query: Query<(&mut Sprite, &mut Transform, &mut Visibility), With<CountdownFlash>>,
+    query_camera: Query<&OrthographicProjection>,
+    rotation: Res<FaceRotation>,
+) {
+    let since_end = countdown
+        .ended_at
+        .filter(|_| countdown.flash && !do_not_disturb.active())
+        .map(|ended_at| (clock.elapsed() - ended_at).as_secs_f32());
+    let mut area = Rect::default();
+    for camera in query_camera.iter() {
+        area = rotation.area(camera.area);
+    }
+
+    for (mut sprite, mut transform, mut visibility) in query.iter_mut() {
+        let Some(since_end) = since_end else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        *visibility = Visibility::Inherited;
+        let pulse = (since_end * FLASH_FREQUENCY * std::f32::consts::TAU).cos() * 0.5 + 0.5;
+        sprite.color.set_a(pulse);
+        sprite.custom_size = Some(area.size());
+        transform.translation.x = area.center().x;
+        transform.translation.y = area.center().y;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remaining_time_rounds_up() {
+        assert_eq!(format_remaining(Duration::from_secs(90)), "01:30");
+        assert_eq!(format_remaining(Duration::from_millis(59_001)), "01:00");
+        assert_eq!(format_remaining(Duration::from_millis(10)), "00:01");
+        assert_eq!(format_remaining(Duration::ZERO), "00:00");
+        assert_eq!(format_remaining(MAX_TIMER_DURATION), "99:59");
+    }
+}
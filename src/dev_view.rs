@@ -0,0 +1,159 @@
+use bevy::{
+    input::{
+        mouse::{MouseMotion, MouseScrollUnit, MouseWheel},
+        touchpad::TouchpadMagnify,
+    },
+    prelude::*,
+};
+
+/// Zoom and pan a windowed face to inspect the shapes up close
+///
+/// Scrolling or pinching zooms, dragging pans and Home goes back to the whole face.
+/// The camera is scaled through its transform so layouts that follow the projection area stay put
+pub struct DevViewPlugin;
+
+impl Plugin for DevViewPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DevView>().add_systems(
+            Update,
+            (zoom_view, pan_view, reset_view, apply_view).chain(),
+        );
+    }
+}
+
+const ZOOM_RANGE: std::ops::RangeInclusive<f32> = 0.5..=32.0;
+/// Doubling of the zoom per line scrolled
+const ZOOM_PER_LINE: f32 = 0.25;
+/// Touchpads scroll in pixels, about this many to a line
+const PIXELS_PER_LINE: f32 = 40.0;
+const RESET_KEY: KeyCode = KeyCode::Home;
+
+#[derive(Resource, Debug, PartialEq)]
+struct DevView {
+    zoom: f32,
+    /// Offset of the camera in world units
+    pan: Vec2,
+}
+
+impl Default for DevView {
+    fn default() -> Self {
+        Self {
+            zoom: 1.0,
+            pan: Vec2::ZERO,
+        }
+    }
+}
+
+impl DevView {
+    fn zoom_by(&mut self, factor: f32) {
+        if factor.is_finite() && factor > 0.0 {
+            self.zoom = (self.zoom * factor).clamp(*ZOOM_RANGE.start(), *ZOOM_RANGE.end());
+        }
+    }
+
+    /// Move the view along with a drag of `delta` window pixels on a camera turned by `rotation`
+    fn drag(&mut self, delta: Vec2, rotation: Quat) {
+        // window coordinates point down, the dragged content follows the pointer
+        let world = rotation * Vec3::new(-delta.x, delta.y, 0.0) / self.zoom;
+        self.pan += world.truncate();
+    }
+}
+
+fn zoom_view(
+    mut wheel: EventReader<MouseWheel>,
+    mut magnify: EventReader<TouchpadMagnify>,
+    touches: Res<Touches>,
+    mut view: ResMut<DevView>,
+) {
+    for event in wheel.read() {
+        let lines = match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / PIXELS_PER_LINE,
+        };
+        view.zoom_by(2.0_f32.powf(lines * ZOOM_PER_LINE));
+    }
+    for event in magnify.read() {
+        view.zoom_by(1.0 + event.0);
+    }
+    let pinch: Vec<_> = touches.iter().collect();
+    if let [first, second] = pinch[..] {
+        let previous = first
+            .previous_position()
+            .distance(second.previous_position());
+        let current = first.position().distance(second.position());
+        if previous > 0.0 {
+            view.zoom_by(current / previous);
+        }
+    }
+}
+
+fn pan_view(
+    mut motion: EventReader<MouseMotion>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    touches: Res<Touches>,
+    cameras: Query<&Transform, With<Camera2d>>,
+    mut view: ResMut<DevView>,
+) {
+    let rotation = cameras
+        .get_single()
+        .map(|transform| transform.rotation)
+        .unwrap_or_default();
+    let dragged: Vec2 = motion.read().map(|motion| motion.delta).sum();
+    if mouse.pressed(MouseButton::Left) && dragged != Vec2::ZERO {
+        view.drag(dragged, rotation);
+    }
+    // two fingers pinch, one drags
+    let mut active = touches.iter();
+    if let (Some(touch), None) = (active.next(), active.next()) {
+        view.drag(touch.delta(), rotation);
+    }
+}
+
+fn reset_view(input: Res<ButtonInput<KeyCode>>, mut view: ResMut<DevView>) {
+    if input.just_pressed(RESET_KEY) {
+        info!("Resetting dev view");
+        *view = DevView::default();
+    }
+}
+
+/// Scale the camera and move it by the change in pan, so screen shakes add on top
+fn apply_view(
+    view: Res<DevView>,
+    mut cameras: Query<&mut Transform, With<Camera2d>>,
+    mut applied_pan: Local<Vec2>,
+) {
+    if !view.is_changed() {
+        return;
+    }
+    let moved = view.pan - *applied_pan;
+    *applied_pan = view.pan;
+    for mut transform in cameras.iter_mut() {
+        transform.scale = Vec3::new(1.0 / view.zoom, 1.0 / view.zoom, 1.0);
+        transform.translation += moved.extend(0.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drag_follows_the_pointer_on_rotated_cameras() {
+        let mut view = DevView::default();
+        view.zoom_by(4.0);
+        view.drag(Vec2::new(8.0, 0.0), Quat::IDENTITY);
+        assert!(view.pan.abs_diff_eq(Vec2::new(-2.0, 0.0), 1e-5));
+
+        let mut view = DevView::default();
+        view.drag(
+            Vec2::new(8.0, 0.0),
+            Quat::from_rotation_z(std::f32::consts::FRAC_PI_2),
+        );
+        assert!(view.pan.abs_diff_eq(Vec2::new(0.0, -8.0), 1e-5));
+
+        view.zoom_by(1000.0);
+        assert_eq!(view.zoom, *ZOOM_RANGE.end());
+        view.zoom_by(f32::NAN);
+        assert_eq!(view.zoom, *ZOOM_RANGE.end());
+    }
+}
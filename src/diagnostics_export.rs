@@ -0,0 +1,204 @@
+//! Periodic diagnostics snapshots appended to a JSON lines file
+//!
+//! Meant for soak tests on the robot, the file can be analyzed once the test is over
+
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use bevy::{
+    app::AppExit,
+    diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin},
+    prelude::*,
+    tasks::IoTaskPool,
+};
+
+use crate::messaging::MessageQueueStats;
+
+pub struct DiagnosticsExportPlugin {
+    pub path: PathBuf,
+    pub interval: Duration,
+}
+
+impl Plugin for DiagnosticsExportPlugin {
+    fn build(&self, app: &mut App) {
+        info!(path = %self.path.display(), interval = ?self.interval, "Exporting diagnostics");
+        app.insert_resource(DiagnosticsExport {
+            path: self.path.clone(),
+            timer: Timer::new(self.interval, TimerMode::Repeating),
+            frames: 0,
+            longest_frame: Duration::ZERO,
+            messages_received: 0,
+            cpu_time: None,
+        })
+        .add_systems(Last, (export_diagnostics, flush_diagnostics_on_exit));
+    }
+}
+
+/// Single line of the diagnostics file
+#[derive(serde::Serialize, Debug)]
+pub struct DiagnosticsSnapshot {
+    /// Local time in RFC 3339 format
+    pub time: String,
+    pub uptime_s: f64,
+    pub fps: Option<f64>,
+    pub frames: u64,
+    /// Longest frame since the previous snapshot
+    pub longest_frame_ms: f64,
+    /// Cpu time used by the process since the previous snapshot in percent of one core
+    pub cpu_percent: Option<f64>,
+    pub memory_rss_kb: Option<u64>,
+    pub messages_received: u64,
+    pub messages_per_s: f64,
+    pub dropped_messages: u64,
+}
+
+#[derive(Resource)]
+struct DiagnosticsExport {
+    path: PathBuf,
+    timer: Timer,
+    frames: u64,
+    longest_frame: Duration,
+    /// Total at the previous snapshot
+    messages_received: u64,
+    /// Process cpu time at the previous snapshot
+    cpu_time: Option<Duration>,
+}
+
+fn export_diagnostics(
+    mut export: ResMut<DiagnosticsExport>,
+    time: Res<Time<Real>>,
+    diagnostics: Res<DiagnosticsStore>,
+    stats: Res<MessageQueueStats>,
+) {
+    export.frames += 1;
+    export.longest_frame = export.longest_frame.max(time.delta());
+    if !export.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    let interval = export.timer.duration().as_secs_f64();
+    let Some(line) = take_snapshot(&mut export, interval, &time, &diagnostics, &stats) else {
+        return;
+    };
+    // written off the main thread so a slow sd card doesn't drop frames
+    let path = export.path.clone();
+    IoTaskPool::get()
+        .spawn(async move { append_line(&path, &line) })
+        .detach();
+}
+
+/// Write a last snapshot covering the time since the previous one before the app exits
+///
+/// Written right away as pending io tasks are dropped on exit
+fn flush_diagnostics_on_exit(
+    mut exit: EventReader<AppExit>,
+    mut export: ResMut<DiagnosticsExport>,
+    time: Res<Time<Real>>,
+    diagnostics: Res<DiagnosticsStore>,
+    stats: Res<MessageQueueStats>,
+) {
+    if exit.read().last().is_none() {
+        return;
+    }
+    let interval = export.timer.elapsed().as_secs_f64().max(f64::EPSILON);
+    if let Some(line) = take_snapshot(&mut export, interval, &time, &diagnostics, &stats) {
+        append_line(&export.path, &line);
+    }
+}
+
+/// Snapshot of the `interval` seconds since the previous one as a JSON line
+fn take_snapshot(
+    export: &mut DiagnosticsExport,
+    interval: f64,
+    time: &Time<Real>,
+    diagnostics: &DiagnosticsStore,
+    stats: &MessageQueueStats,
+) -> Option<String> {
+    let cpu_time = process_cpu_time();
+    let cpu_percent = match (export.cpu_time, cpu_time) {
+        (Some(previous), Some(current)) => {
+            Some(current.saturating_sub(previous).as_secs_f64() / interval * 100.0)
+        }
+        _ => None,
+    };
+    let snapshot = DiagnosticsSnapshot {
+        time: chrono::Local::now().to_rfc3339(),
+        uptime_s: time.elapsed_seconds_f64(),
+        fps: diagnostics
+            .get(&FrameTimeDiagnosticsPlugin::FPS)
+            .and_then(|fps| fps.smoothed()),
+        frames: export.frames,
+        longest_frame_ms: export.longest_frame.as_secs_f64() * 1000.0,
+        cpu_percent,
+        memory_rss_kb: process_memory_rss_kb(),
+        messages_received: stats.received,
+        messages_per_s: (stats.received - export.messages_received) as f64 / interval,
+        dropped_messages: stats.dropped,
+    };
+    export.frames = 0;
+    export.longest_frame = Duration::ZERO;
+    export.messages_received = stats.received;
+    export.cpu_time = cpu_time;
+
+    match serde_json::to_string(&snapshot) {
+        Ok(line) => Some(line),
+        Err(error) => {
+            error!(?error, "Failed to serialize diagnostics");
+            None
+        }
+    }
+}
+
+fn append_line(path: &Path, line: &str) {
+    let result = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| writeln!(file, "{line}"));
+    if let Err(error) = result {
+        error!(?error, path = %path.display(), "Failed to write diagnostics");
+    }
+}
+
+/// Clock ticks per second used by `/proc`, the same on all Raspberry Pi kernels
+#[cfg(target_os = "linux")]
+const CLOCK_TICKS_PER_SECOND: u64 = 100;
+
+/// User and system time used by this process
+#[cfg(target_os = "linux")]
+fn process_cpu_time() -> Option<Duration> {
+    let stat = std::fs::read_to_string("/proc/self/stat").ok()?;
+    // the command name can contain spaces, fields are counted after its closing parenthesis
+    let mut fields = stat.rsplit_once(')')?.1.split_whitespace();
+    // utime and stime are the 14th and 15th fields, the first after the name is the 3rd
+    let user: u64 = fields.nth(11)?.parse().ok()?;
+    let system: u64 = fields.next()?.parse().ok()?;
+    Some(Duration::from_millis(
+        (user + system) * 1000 / CLOCK_TICKS_PER_SECOND,
+    ))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn process_cpu_time() -> Option<Duration> {
+    None
+}
+
+#[cfg(target_os = "linux")]
+fn process_memory_rss_kb() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))?
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse()
+        .ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn process_memory_rss_kb() -> Option<u64> {
+    None
+}
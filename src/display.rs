@@ -1,47 +1,600 @@
+use std::sync::{Arc, Mutex, PoisonError};
+
+use anyhow::Context;
 use bevy::prelude::*;
 
-#[derive(serde::Deserialize)]
-pub struct DisplayControlMessage {
+use crate::maintenance::MaintenanceOverlay;
+
+/// Requested change to a single output
+///
+/// `None` fields keep the current value
+#[derive(Debug, Default, Clone)]
+pub struct OutputChange {
+    pub enabled: Option<bool>,
+    pub transform: Option<String>,
+    pub mode: Option<DisplayMode>,
+}
+
+/// Output resolution and refresh rate
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct DisplayMode {
+    pub width: i32,
+    pub height: i32,
+    /// Refresh rate in Hz, the highest available rate is used when omitted
     #[serde(default)]
-    pub display_on: bool,
-}
-
-#[cfg(not(target_os = "linux"))]
-pub async fn turn_on_display() -> anyhow::Result<()> {
-    info!("Ignoring turn_on_display on windows");
-    Ok(())
-}
-
-#[cfg(target_os = "linux")]
-pub async fn turn_on_display() -> anyhow::Result<()> {
-    // wlr-randr --output HDMI-A-1 --on --transform 90
-    let status = tokio::process::Command::new("wlr-randr")
-        .arg("--output")
-        .arg("HDMI-A-1")
-        .arg("--on")
-        .arg("--transform")
-        .arg("270")
-        .status()
-        .await?;
-    info!("Turning on display {:?}", status);
-    Ok(())
-}
-
-#[cfg(not(target_os = "linux"))]
-pub async fn turn_off_display() -> anyhow::Result<()> {
-    info!("Ignoring turn_off_display on windows");
-    Ok(())
-}
-
-#[cfg(target_os = "linux")]
-pub async fn turn_off_display() -> anyhow::Result<()> {
-    // wlr-randr --output HDMI-A-1 --off
-    let status = tokio::process::Command::new("wlr-randr")
-        .arg("--output")
-        .arg("HDMI-A-1")
-        .arg("--off")
-        .status()
-        .await?;
-    info!("Turning off display {:?}", status);
-    Ok(())
+    pub refresh: Option<f32>,
+}
+
+/// Largest resolution accepted in a mode request
+const MAX_DISPLAY_DIMENSION: i32 = 16_384;
+const MAX_DISPLAY_REFRESH: f32 = 1_000.0;
+
+impl DisplayMode {
+    /// Reject modes no display could support before handing them to the compositor
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let dimensions = 1..=MAX_DISPLAY_DIMENSION;
+        if !dimensions.contains(&self.width) || !dimensions.contains(&self.height) {
+            anyhow::bail!("Invalid display resolution {}x{}", self.width, self.height);
+        }
+        if let Some(refresh) = self.refresh {
+            if refresh <= 0.0 || !(..=MAX_DISPLAY_REFRESH).contains(&refresh) {
+                anyhow::bail!("Invalid display refresh rate {refresh}");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses modes such as `1920x1080` or `1920x1080@60Hz`
+impl std::str::FromStr for DisplayMode {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> anyhow::Result<Self> {
+        let (resolution, refresh) = match value.split_once('@') {
+            Some((resolution, refresh)) => (resolution, Some(refresh)),
+            None => (value, None),
+        };
+        let (width, height) = resolution
+            .split_once('x')
+            .with_context(|| format!("Display mode {value} is not in the WIDTHxHEIGHT form"))?;
+        let mode = DisplayMode {
+            width: width.trim().parse().context("Invalid display width")?,
+            height: height.trim().parse().context("Invalid display height")?,
+            refresh: refresh
+                .map(|refresh| refresh.trim().trim_end_matches("Hz").parse())
+                .transpose()
+                .context("Invalid display refresh rate")?,
+        };
+        mode.validate()?;
+        Ok(mode)
+    }
+}
+
+impl std::fmt::Display for DisplayMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}x{}", self.width, self.height)?;
+        if let Some(refresh) = self.refresh {
+            write!(f, "@{refresh}Hz")?;
+        }
+        Ok(())
+    }
+}
+
+pub const DEFAULT_DISPLAY_OUTPUT: &str = "HDMI-A-1";
+pub const DEFAULT_DISPLAY_TRANSFORM: &str = "270";
+pub const DEFAULT_DISPLAY_ON_COMMAND: &str =
+    "wlr-randr --output {output} --on --transform {transform}";
+pub const DEFAULT_DISPLAY_OFF_COMMAND: &str = "wlr-randr --output {output} --off";
+pub const DEFAULT_DISPLAY_ROTATE_COMMAND: &str =
+    "wlr-randr --output {output} --transform {transform}";
+pub const DEFAULT_DISPLAY_MODE_COMMAND: &str = "wlr-randr --output {output} --mode {mode}";
+
+pub struct DisplayPlugin;
+
+impl Plugin for DisplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(DisplayPower { on: true })
+            .init_resource::<DisplayState>()
+            .add_event::<DisplayPowerChanged>()
+            .add_systems(Startup, setup_virtual_display)
+            .add_systems(PreUpdate, (update_display_power, mirror_display_state))
+            .add_systems(
+                Update,
+                show_virtual_display.run_if(resource_changed::<DisplayState>),
+            );
+    }
+}
+
+/// Draw the turned off virtual display behind the shutdown fade
+const VIRTUAL_DISPLAY_OFF_Z: f32 = 90.0;
+
+/// Power state of the display as last commanded over zenoh
+#[derive(Resource)]
+pub struct DisplayPower {
+    pub on: bool,
+}
+
+/// Sent after a display power command succeeded
+#[derive(Event)]
+pub struct DisplayPowerChanged {
+    pub on: bool,
+}
+
+fn update_display_power(
+    mut events: EventReader<DisplayPowerChanged>,
+    mut display_power: ResMut<DisplayPower>,
+) {
+    for event in events.read() {
+        display_power.on = event.on;
+    }
+}
+
+/// Display state as commanded through [`DisplayControl`]
+///
+/// Updated on every platform, but it's the only effect of display commands
+/// where they can't be applied, so display control can be developed off the robot
+#[derive(Resource, serde::Serialize, Debug, Clone, PartialEq)]
+pub struct DisplayState {
+    pub on: bool,
+    /// `None` until a transform was applied
+    pub transform: Option<String>,
+    /// `None` until a mode was applied
+    pub mode: Option<DisplayMode>,
+}
+
+impl Default for DisplayState {
+    fn default() -> Self {
+        Self {
+            on: true,
+            transform: None,
+            mode: None,
+        }
+    }
+}
+
+/// [`DisplayState`] shared between the zenoh worker and bevy
+#[derive(Clone, Debug, Default)]
+pub struct VirtualDisplay(Arc<Mutex<DisplayState>>);
+
+impl VirtualDisplay {
+    pub fn state(&self) -> DisplayState {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    fn update(&self, update: impl FnOnce(&mut DisplayState)) {
+        update(&mut self.0.lock().unwrap_or_else(PoisonError::into_inner));
+    }
+}
+
+/// Whether display commands only change the [`DisplayState`]
+pub fn display_is_simulated(display_control: &DisplayControl) -> bool {
+    cfg!(not(target_os = "linux")) || display_control.dry_run
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug)]
+pub struct DisplayControlMessage {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_on: Option<bool>,
+    /// Output transform such as "normal", "90" or "270"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transform: Option<String>,
+    /// Output resolution and refresh rate
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<DisplayMode>,
+    /// Tint the face to keep it from lighting up a dark room
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub night_mode: Option<bool>,
+    /// Key to publish an acknowledgement to once applied
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reply_to: Option<String>,
+}
+
+/// How display commands are carried out
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DisplayBackend {
+    /// Configure the output directly using the wlr-output-management protocol
+    #[default]
+    Native,
+    /// Run the external command templates
+    Command,
+}
+
+/// Settings used to control the display
+///
+/// `{output}`, `{transform}` and `{mode}` are substituted in every argument of the command templates
+#[derive(Resource, Clone, Debug)]
+pub struct DisplayControl {
+    pub backend: DisplayBackend,
+    pub output: String,
+    pub transform: String,
+    /// Last requested mode, kept when the display is turned back on
+    pub mode: Option<DisplayMode>,
+    pub on: String,
+    pub off: String,
+    pub rotate: String,
+    pub set_mode: String,
+    /// Log display changes instead of applying them
+    pub dry_run: bool,
+    pub virtual_display: VirtualDisplay,
+}
+
+impl Default for DisplayControl {
+    fn default() -> Self {
+        Self {
+            backend: DisplayBackend::default(),
+            output: DEFAULT_DISPLAY_OUTPUT.to_owned(),
+            transform: DEFAULT_DISPLAY_TRANSFORM.to_owned(),
+            mode: None,
+            on: DEFAULT_DISPLAY_ON_COMMAND.to_owned(),
+            off: DEFAULT_DISPLAY_OFF_COMMAND.to_owned(),
+            rotate: DEFAULT_DISPLAY_ROTATE_COMMAND.to_owned(),
+            set_mode: DEFAULT_DISPLAY_MODE_COMMAND.to_owned(),
+            dry_run: false,
+            virtual_display: VirtualDisplay::default(),
+        }
+    }
+}
+
+impl DisplayControl {
+    pub async fn turn_on_display(&self) -> anyhow::Result<()> {
+        let result = match self.backend {
+            DisplayBackend::Native => {
+                self.configure_output(OutputChange {
+                    enabled: Some(true),
+                    transform: Some(self.transform.clone()),
+                    mode: self.mode,
+                })
+                .await
+            }
+            DisplayBackend::Command => self.run(&self.on).await,
+        };
+        self.record(result, |state| {
+            state.on = true;
+            state.transform = Some(self.transform.clone());
+            state.mode = self.mode;
+        })
+    }
+
+    pub async fn turn_off_display(&self) -> anyhow::Result<()> {
+        let result = match self.backend {
+            DisplayBackend::Native => {
+                self.configure_output(OutputChange {
+                    enabled: Some(false),
+                    ..default()
+                })
+                .await
+            }
+            DisplayBackend::Command => self.run(&self.off).await,
+        };
+        self.record(result, |state| state.on = false)
+    }
+
+    /// Rotate the display and keep the transform for future turn on commands
+    ///
+    /// A failed rotation keeps the previous transform
+    pub async fn rotate_display(&mut self, transform: &str) -> anyhow::Result<()> {
+        let rotated = Self {
+            transform: transform.to_owned(),
+            ..self.clone()
+        };
+        let result = match self.backend {
+            DisplayBackend::Native => {
+                rotated
+                    .configure_output(OutputChange {
+                        transform: Some(rotated.transform.clone()),
+                        ..default()
+                    })
+                    .await
+            }
+            DisplayBackend::Command => rotated.run(&rotated.rotate).await,
+        };
+        self.record(result, |state| {
+            state.transform = Some(rotated.transform.clone());
+        })?;
+        self.transform = rotated.transform;
+        Ok(())
+    }
+
+    /// Switch resolution and refresh rate and keep the mode for future turn on commands
+    ///
+    /// A failed switch keeps the previous mode
+    pub async fn set_display_mode(&mut self, mode: DisplayMode) -> anyhow::Result<()> {
+        mode.validate()?;
+        let switched = Self {
+            mode: Some(mode),
+            ..self.clone()
+        };
+        let result = match self.backend {
+            DisplayBackend::Native => {
+                switched
+                    .configure_output(OutputChange {
+                        mode: Some(mode),
+                        ..default()
+                    })
+                    .await
+            }
+            DisplayBackend::Command => switched.run(&switched.set_mode).await,
+        };
+        self.record(result, |state| state.mode = Some(mode))?;
+        self.mode = Some(mode);
+        Ok(())
+    }
+
+    /// Apply a successful command to the virtual display
+    fn record(
+        &self,
+        result: anyhow::Result<()>,
+        update: impl FnOnce(&mut DisplayState),
+    ) -> anyhow::Result<()> {
+        if result.is_ok() {
+            self.virtual_display.update(update);
+        }
+        result
+    }
+
+    /// Only the power of the monitors can be changed on Windows and macOS
+    #[cfg(any(target_os = "windows", target_os = "macos"))]
+    async fn configure_output(&self, change: OutputChange) -> anyhow::Result<()> {
+        if let Some(on) = change.enabled {
+            if self.dry_run {
+                info!(on, "Dry run, not changing monitor power");
+            } else {
+                tokio::task::spawn_blocking(move || crate::monitor_power::set_monitor_power(on))
+                    .await??;
+                info!(on, "Changed monitor power");
+            }
+        }
+        if change.transform.is_some() || change.mode.is_some() {
+            info!(
+                ?change,
+                "Ignoring output transform and mode on this platform"
+            );
+        }
+        Ok(())
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
+    async fn configure_output(&self, change: OutputChange) -> anyhow::Result<()> {
+        info!(?change, "Ignoring output configuration on this platform");
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn configure_output(&self, change: OutputChange) -> anyhow::Result<()> {
+        let output = self.output.clone();
+        let applied_change = change.clone();
+        let dry_run = self.dry_run;
+        tokio::task::spawn_blocking(move || {
+            crate::wlr_output::configure_output(&output, &applied_change, dry_run)
+        })
+        .await??;
+        if !self.dry_run {
+            info!(output = self.output, ?change, "Configured output");
+        }
+        Ok(())
+    }
+
+    /// Expand a template into program and arguments
+    fn expand(&self, template: &str) -> anyhow::Result<(String, Vec<String>)> {
+        let mode = self.mode.map(|mode| mode.to_string()).unwrap_or_default();
+        let mut args = template.split_whitespace().map(|arg| {
+            arg.replace("{output}", &self.output)
+                .replace("{transform}", &self.transform)
+                .replace("{mode}", &mode)
+        });
+        let program = args.next().context("Display command template is empty")?;
+        Ok((program, args.collect()))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    async fn run(&self, template: &str) -> anyhow::Result<()> {
+        let (program, args) = self.expand(template)?;
+        info!(
+            program,
+            ?args,
+            "Ignoring display command on non linux platform"
+        );
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn run(&self, template: &str) -> anyhow::Result<()> {
+        let (program, args) = self.expand(template)?;
+        if self.dry_run {
+            info!(program, ?args, "Dry run, not running display command");
+            return Ok(());
+        }
+        let status = tokio::process::Command::new(&program)
+            .args(&args)
+            .status()
+            .await
+            .with_context(|| format!("Failed to run {program}"))?;
+        info!(program, ?args, "Ran display command {:?}", status);
+        anyhow::ensure!(status.success(), "{program} failed with {status}");
+        Ok(())
+    }
+}
+
+fn mirror_display_state(display_control: Res<DisplayControl>, mut state: ResMut<DisplayState>) {
+    let virtual_state = display_control.virtual_display.state();
+    if *state != virtual_state {
+        *state = virtual_state;
+    }
+}
+
+#[derive(Component)]
+struct VirtualDisplayOff;
+
+fn setup_virtual_display(mut commands: Commands, display_control: Res<DisplayControl>) {
+    if !display_is_simulated(&display_control) {
+        return;
+    }
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: Color::BLACK.with_a(0.8),
+                custom_size: Some(Vec2::splat(100_000.0)),
+                ..default()
+            },
+            transform: Transform::from_xyz(0.0, 0.0, VIRTUAL_DISPLAY_OFF_Z),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        VirtualDisplayOff,
+    ));
+}
+
+/// Darken the face while the simulated display is off and list its state on the overlay
+fn show_virtual_display(
+    state: Res<DisplayState>,
+    display_control: Res<DisplayControl>,
+    mut overlay: ResMut<MaintenanceOverlay>,
+    mut query: Query<&mut Visibility, With<VirtualDisplayOff>>,
+) {
+    if !display_is_simulated(&display_control) {
+        return;
+    }
+    for mut visibility in query.iter_mut() {
+        *visibility = if state.on {
+            Visibility::Hidden
+        } else {
+            Visibility::Visible
+        };
+    }
+    let mode = state.mode.map(|mode| mode.to_string());
+    overlay.set_section(
+        "virtual display",
+        format!(
+            "on: {}\ntransform: {}\nmode: {}",
+            state.on,
+            state.transform.as_deref().unwrap_or("unchanged"),
+            mode.as_deref().unwrap_or("unchanged")
+        ),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    proptest! {
+        #[test]
+        fn display_message_parses_any_fields(
+            display_on in proptest::option::of(any::<bool>()),
+            transform in proptest::option::of(".*"),
+            width in any::<i32>(),
+            height in any::<i32>(),
+            refresh in proptest::option::of(any::<f32>().prop_filter("finite", |v| v.is_finite())),
+        ) {
+            let json = serde_json::json!({
+                "display_on": display_on,
+                "transform": transform,
+                "mode": {"width": width, "height": height, "refresh": refresh},
+                "unknown_field": 1,
+            });
+            let message: DisplayControlMessage = serde_json::from_value(json).unwrap();
+            prop_assert_eq!(message.display_on, display_on);
+            prop_assert_eq!(message.transform, transform);
+            prop_assert_eq!(message.mode, Some(DisplayMode { width, height, refresh }));
+        }
+
+        #[test]
+        fn display_message_never_panics_on_arbitrary_input(json in ".*") {
+            _ = serde_json::from_str::<DisplayControlMessage>(&json);
+        }
+
+        #[test]
+        fn valid_modes_are_accepted(
+            width in 1..=MAX_DISPLAY_DIMENSION,
+            height in 1..=MAX_DISPLAY_DIMENSION,
+            refresh in proptest::option::of(1.0..=MAX_DISPLAY_REFRESH),
+        ) {
+            let mode = DisplayMode { width, height, refresh };
+            prop_assert!(mode.validate().is_ok());
+        }
+
+        #[test]
+        fn out_of_range_modes_are_rejected(width in i32::MIN..=0, height in any::<i32>()) {
+            let mode = DisplayMode { width, height, refresh: None };
+            prop_assert!(mode.validate().is_err());
+            let mode = DisplayMode { width: height, height: width, refresh: None };
+            prop_assert!(mode.validate().is_err());
+        }
+
+        #[test]
+        fn expanded_values_never_add_arguments(transform in ".*", output in ".*") {
+            let display_control = DisplayControl {
+                output,
+                transform,
+                ..default()
+            };
+            let (program, args) = display_control.expand(DEFAULT_DISPLAY_ON_COMMAND).unwrap();
+            prop_assert_eq!(program, "wlr-randr");
+            prop_assert_eq!(args.len(), DEFAULT_DISPLAY_ON_COMMAND.split_whitespace().count() - 1);
+        }
+    }
+
+    #[test]
+    fn invalid_refresh_is_rejected() {
+        for refresh in [0.0, -60.0, f32::NAN, f32::INFINITY, 10_000.0] {
+            let mode = DisplayMode {
+                width: 480,
+                height: 800,
+                refresh: Some(refresh),
+            };
+            assert!(mode.validate().is_err(), "{refresh} should be rejected");
+        }
+    }
+
+    #[tokio::test]
+    async fn dry_run_updates_virtual_display() {
+        let mut display_control = DisplayControl {
+            backend: DisplayBackend::Command,
+            dry_run: true,
+            ..default()
+        };
+        display_control.turn_off_display().await.unwrap();
+        display_control.rotate_display("90").await.unwrap();
+        let state = display_control.virtual_display.state();
+        assert!(!state.on);
+        assert_eq!(state.transform.as_deref(), Some("90"));
+
+        display_control.turn_on_display().await.unwrap();
+        assert!(display_control.virtual_display.state().on);
+    }
+
+    #[tokio::test]
+    async fn failed_rotation_keeps_the_transform() {
+        let mut display_control = DisplayControl {
+            backend: DisplayBackend::Command,
+            rotate: String::new(),
+            dry_run: true,
+            ..default()
+        };
+        assert!(display_control.rotate_display("90").await.is_err());
+        assert_eq!(display_control.transform, DEFAULT_DISPLAY_TRANSFORM);
+        assert_eq!(display_control.virtual_display.state().transform, None);
+    }
+
+    #[tokio::test]
+    async fn failed_mode_switch_keeps_the_mode() {
+        let mut display_control = DisplayControl {
+            backend: DisplayBackend::Command,
+            set_mode: String::new(),
+            dry_run: true,
+            ..default()
+        };
+        let mode = DisplayMode {
+            width: 480,
+            height: 800,
+            refresh: None,
+        };
+        assert!(display_control.set_display_mode(mode).await.is_err());
+        assert_eq!(display_control.mode, None);
+    }
 }
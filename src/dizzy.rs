@@ -0,0 +1,162 @@
+use std::{f32::consts::TAU, time::Duration};
+
+use bevy::prelude::*;
+use bevy_prototype_lyon::prelude::*;
+
+use crate::{
+    dnd::DoNotDisturb,
+    eyes::EyeSides,
+    face_clock::FaceClock,
+    noise_plugin::NoiseWave,
+    priority::Priority,
+    scene::{FaceScene, SceneRequests},
+};
+
+/// Dizzy reaction when the robot gets shaken
+///
+/// Shows spiral eyes over a wobbling wave
+pub struct DizzyPlugin;
+
+impl Plugin for DizzyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ImuMessage>()
+            .init_resource::<DoNotDisturb>()
+            .add_systems(OnEnter(FaceScene::Dizzy), spawn_dizzy_eyes)
+            .add_systems(
+                OnExit(FaceScene::Dizzy),
+                (despawn_dizzy_eyes, reset_wave_wobble),
+            )
+            .add_systems(
+                Update,
+                (
+                    detect_shaking,
+                    (spin_dizzy_eyes, shape_dizzy_eyes, wobble_wave)
+                        .run_if(in_state(FaceScene::Dizzy)),
+                ),
+            );
+    }
+}
+
+const STANDARD_GRAVITY: f32 = 9.81;
+/// Deviation from gravity in m/s² that counts as shaking
+const SHAKE_THRESHOLD: f32 = 15.0;
+const DIZZY_DURATION: Duration = Duration::from_secs(3);
+
+const SPIRAL_TURNS: f32 = 3.0;
+const SPIRAL_RADIUS: f32 = 60.0;
+const SPIRAL_SEGMENTS: usize = 96;
+const SPIRAL_LINE_WIDTH: f32 = 4.0;
+/// Spiral eye offset from the center of the screen
+const SPIRAL_EYE_OFFSET: Vec2 = Vec2::new(110.0, 150.0);
+/// Rotation speed of the spirals in radians per second
+const SPIRAL_SPEED: f32 = 6.0;
+/// Wobble amplitude of the wave in radians
+const WOBBLE_ANGLE: f32 = 0.25;
+const WOBBLE_SPEED: f32 = 8.0;
+
+/// Reading from an inertial measurement unit
+#[derive(Event, serde::Deserialize)]
+pub struct ImuMessage {
+    /// Linear acceleration including gravity in m/s²
+    pub linear_acceleration: [f32; 3],
+}
+
+#[derive(Component)]
+struct DizzyEye {
+    /// Spiral direction so that both eyes spin the same way on screen
+    direction: f32,
+}
+
+fn detect_shaking(
+    mut messages: EventReader<ImuMessage>,
+    mut scene_requests: ResMut<SceneRequests>,
+    do_not_disturb: Res<DoNotDisturb>,
+) {
+    let shaken = messages.read().any(|message| {
+        let acceleration = Vec3::from(message.linear_acceleration).length();
+        (acceleration - STANDARD_GRAVITY).abs() > SHAKE_THRESHOLD
+    });
+    if !shaken {
+        return;
+    }
+    if do_not_disturb.active() {
+        debug!("Shaken, not feeling dizzy while do-not-disturb is on");
+        return;
+    }
+    // repeated shaking extends the reaction
+    info!("Shaken, feeling dizzy");
+    scene_requests.push(Priority::Reaction, FaceScene::Dizzy, Some(DIZZY_DURATION));
+}
+
+fn spawn_dizzy_eyes(mut commands: Commands, sides: Res<EyeSides>) {
+    for direction in [-1.0, 1.0] {
+        let base = Vec2::new(SPIRAL_EYE_OFFSET.x * direction, SPIRAL_EYE_OFFSET.y);
+        let shape = sides.side(base.x);
+        commands.spawn((
+            ShapeBundle {
+                path: build_spiral_path(shape.vertical_scale()),
+                spatial: SpatialBundle::from_transform(Transform::from_translation(
+                    (base + shape.offset()).extend(1.0),
+                )),
+                ..default()
+            },
+            Stroke::new(Color::WHITE, SPIRAL_LINE_WIDTH),
+            Fill::color(Color::NONE),
+            DizzyEye { direction },
+        ));
+    }
+}
+
+fn despawn_dizzy_eyes(mut commands: Commands, query: Query<Entity, With<DizzyEye>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Spiral squashed to `vertical_scale` as the eye closes
+fn build_spiral_path(vertical_scale: f32) -> Path {
+    let points = (0..=SPIRAL_SEGMENTS)
+        .map(|segment| {
+            let progress = segment as f32 / SPIRAL_SEGMENTS as f32;
+            let angle = progress * SPIRAL_TURNS * TAU;
+            Vec2::from_angle(angle) * progress * SPIRAL_RADIUS * Vec2::new(1.0, vertical_scale)
+        })
+        .collect();
+    GeometryBuilder::build_as(&shapes::Polygon {
+        points,
+        closed: false,
+    })
+}
+
+fn spin_dizzy_eyes(clock: Res<FaceClock>, mut query: Query<(&mut Transform, &DizzyEye)>) {
+    for (mut transform, eye) in query.iter_mut() {
+        transform.rotate_z(SPIRAL_SPEED * eye.direction * clock.delta_seconds());
+    }
+}
+
+fn shape_dizzy_eyes(
+    sides: Res<EyeSides>,
+    mut query: Query<(&mut Transform, &mut Path), With<DizzyEye>>,
+) {
+    if !sides.is_changed() {
+        return;
+    }
+    for (mut transform, mut path) in query.iter_mut() {
+        let shape = sides.side(transform.translation.x);
+        *path = build_spiral_path(shape.vertical_scale());
+        transform.translation.y = SPIRAL_EYE_OFFSET.y + shape.offset().y;
+    }
+}
+
+fn wobble_wave(clock: Res<FaceClock>, mut query: Query<&mut Transform, With<NoiseWave>>) {
+    let angle = (clock.elapsed_seconds_wrapped() * WOBBLE_SPEED).sin() * WOBBLE_ANGLE;
+    for mut transform in query.iter_mut() {
+        transform.rotation = Quat::from_rotation_z(angle);
+    }
+}
+
+fn reset_wave_wobble(mut query: Query<&mut Transform, With<NoiseWave>>) {
+    for mut transform in query.iter_mut() {
+        transform.rotation = Quat::IDENTITY;
+    }
+}
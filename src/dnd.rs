@@ -0,0 +1,112 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{
+    face_clock::FaceClock,
+    messaging::{AckSender, CommandAck},
+    topics::RegisterTopic,
+};
+
+/// Quiet face for filming and quiet rooms, switched with `face/dnd`
+///
+/// While active no sounds play, reactions, screen shakes and greetings are skipped and the speech bubble,
+/// transcript, countdown and weather overlays stay hidden.
+/// Error scenes and display commands still get through
+pub struct DoNotDisturbPlugin;
+
+impl Plugin for DoNotDisturbPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_topic::<DoNotDisturbMessage>("face/dnd")
+            .init_resource::<DoNotDisturb>()
+            .add_systems(
+                Update,
+                (process_do_not_disturb_messages, end_do_not_disturb).chain(),
+            );
+    }
+}
+
+/// Longest timed do-not-disturb, longer requests end after this
+const MAX_DO_NOT_DISTURB_DURATION: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+#[derive(Event, serde::Deserialize, serde::Serialize, clap::Args, Debug)]
+pub struct DoNotDisturbMessage {
+    /// Turn do-not-disturb on, it is turned off otherwise
+    #[serde(default)]
+    #[arg(long)]
+    pub enabled: bool,
+    /// Turn do-not-disturb off again after this many seconds, stays on until turned off without
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub duration_s: Option<f32>,
+    /// Key to publish an acknowledgement to once applied
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub reply_to: Option<String>,
+}
+
+/// Whether non-critical output is suppressed
+///
+/// Plugins holding back output while active init this resource themselves
+/// so they keep working without the do-not-disturb plugin
+#[derive(Resource, Default, Debug)]
+pub struct DoNotDisturb {
+    active: bool,
+    ends_at: Option<Duration>,
+}
+
+impl DoNotDisturb {
+    pub fn active(&self) -> bool {
+        self.active
+    }
+}
+
+fn process_do_not_disturb_messages(
+    mut messages: EventReader<DoNotDisturbMessage>,
+    mut do_not_disturb: ResMut<DoNotDisturb>,
+    ack_sender: Res<AckSender>,
+    clock: Res<FaceClock>,
+) {
+    for (message, id) in messages.read_with_id() {
+        let _trace = ack_sender.trace(id);
+        let duration = match message.duration_s {
+            Some(duration_s) if duration_s.is_finite() && duration_s > 0.0 => Some(
+                Duration::from_secs_f32(duration_s.min(MAX_DO_NOT_DISTURB_DURATION.as_secs_f32())),
+            ),
+            Some(duration_s) => {
+                warn!(duration_s, "Invalid do-not-disturb duration");
+                ack_sender.send(
+                    message.reply_to.as_deref(),
+                    CommandAck::rejected("duration_s must be positive"),
+                );
+                continue;
+            }
+            None => None,
+        }
+        .filter(|_| message.enabled);
+        info!(
+            enabled = message.enabled,
+            ?duration,
+            "Updating do-not-disturb"
+        );
+        do_not_disturb.active = message.enabled;
+        do_not_disturb.ends_at = duration.map(|duration| clock.elapsed() + duration);
+        ack_sender.send(
+            message.reply_to.as_deref(),
+            CommandAck::applied(serde_json::json!({
+                "enabled": do_not_disturb.active,
+                "duration_s": duration.map(|duration| duration.as_secs_f32()),
+            })),
+        );
+    }
+}
+
+fn end_do_not_disturb(mut do_not_disturb: ResMut<DoNotDisturb>, clock: Res<FaceClock>) {
+    if do_not_disturb
+        .ends_at
+        .is_some_and(|ends_at| clock.elapsed() >= ends_at)
+    {
+        info!("Do-not-disturb ran out");
+        *do_not_disturb = DoNotDisturb::default();
+    }
+}
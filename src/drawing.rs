@@ -0,0 +1,163 @@
+use bevy::{prelude::*, utils::HashMap};
+use bevy_prototype_lyon::prelude::*;
+
+use crate::messaging::{AckSender, CommandAck};
+
+/// Caller supplied strokes drawn on top of the face
+///
+/// Useful for simple diagrams and debug overlays from external nodes
+pub struct DrawingPlugin;
+
+impl Plugin for DrawingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PolylineMessage>()
+            .add_systems(Update, process_polyline_messages);
+    }
+}
+
+const DEFAULT_POLYLINE_COLOR: Color = Color::WHITE;
+const DEFAULT_POLYLINE_WIDTH: f32 = 2.0;
+/// Draw polylines in front of the wave
+const POLYLINE_Z: f32 = 1.0;
+const MAX_POLYLINE_POINTS: usize = 4096;
+const MAX_POLYLINE_WIDTH: f32 = 100.0;
+
+#[derive(Event, serde::Deserialize)]
+pub struct PolylineMessage {
+    /// Polylines with the same id replace each other
+    pub id: String,
+    /// Points in pixels relative to the center of the screen
+    #[serde(default)]
+    pub points: Vec<[f32; 2]>,
+    /// Hex color such as "ff0000"
+    #[serde(default)]
+    pub color: Option<String>,
+    #[serde(default)]
+    pub width: Option<f32>,
+    /// Connect the last point back to the first one
+    #[serde(default)]
+    pub closed: bool,
+    /// Remove the polyline with this id, `"*"` removes all of them
+    #[serde(default)]
+    pub remove: bool,
+    /// Key to publish an acknowledgement to once applied
+    #[serde(default)]
+    pub reply_to: Option<String>,
+}
+
+impl PolylineMessage {
+    /// Reject polylines that can't be tessellated before they reach lyon
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.points.len() > MAX_POLYLINE_POINTS {
+            anyhow::bail!(
+                "Polyline has {} points, at most {MAX_POLYLINE_POINTS} are supported",
+                self.points.len()
+            );
+        }
+        if let Some([x, y]) = self
+            .points
+            .iter()
+            .find(|point| !point.iter().all(|value| value.is_finite()))
+        {
+            anyhow::bail!("Invalid polyline point {x},{y}");
+        }
+        if let Some(width) = self.width {
+            if width <= 0.0 || !(..=MAX_POLYLINE_WIDTH).contains(&width) {
+                anyhow::bail!("Invalid polyline width {width}");
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Component)]
+struct Polyline {
+    id: String,
+}
+
+fn process_polyline_messages(
+    mut commands: Commands,
+    mut messages: EventReader<PolylineMessage>,
+    query: Query<(Entity, &Polyline)>,
+    ack_sender: Res<AckSender>,
+) {
+    let mut polylines: HashMap<String, Entity> = query
+        .iter()
+        .map(|(entity, polyline)| (polyline.id.clone(), entity))
+        .collect();
+
+    for (message, id) in messages.read_with_id() {
+        let _trace = ack_sender.trace(id);
+        let reply_to = message.reply_to.as_deref();
+
+        if message.remove {
+            info!(id = message.id, "Removing polyline");
+            polylines.retain(|id, entity| {
+                let removed = message.id == "*" || *id == message.id;
+                if removed {
+                    commands.entity(*entity).despawn_recursive();
+                }
+                !removed
+            });
+            ack_sender.send(
+                reply_to,
+                CommandAck::applied(serde_json::json!({ "id": message.id, "removed": true })),
+            );
+            continue;
+        }
+
+        if let Err(error) = message.validate() {
+            error!(?error, id = message.id, "Invalid polyline");
+            ack_sender.send(reply_to, CommandAck::rejected(error));
+            continue;
+        }
+
+        let color = match message.color.as_deref().map(Color::hex).transpose() {
+            Ok(color) => color.unwrap_or(DEFAULT_POLYLINE_COLOR),
+            Err(error) => {
+                error!(?error, id = message.id, "Invalid polyline color");
+                ack_sender.send(reply_to, CommandAck::rejected(error));
+                continue;
+            }
+        };
+        let width = message.width.unwrap_or(DEFAULT_POLYLINE_WIDTH);
+        let shape = shapes::Polygon {
+            points: message.points.iter().copied().map(Vec2::from).collect(),
+            closed: message.closed,
+        };
+
+        info!(
+            id = message.id,
+            points = message.points.len(),
+            "Drawing polyline"
+        );
+        let bundle = (
+            ShapeBundle {
+                path: GeometryBuilder::build_as(&shape),
+                spatial: SpatialBundle::from_transform(Transform::from_xyz(0.0, 0.0, POLYLINE_Z)),
+                ..default()
+            },
+            Stroke::new(color, width),
+            Fill::color(Color::NONE),
+            Polyline {
+                id: message.id.clone(),
+            },
+        );
+        match polylines.get(&message.id) {
+            Some(entity) => {
+                commands.entity(*entity).insert(bundle);
+            }
+            None => {
+                let entity = commands.spawn(bundle).id();
+                polylines.insert(message.id.clone(), entity);
+            }
+        }
+        ack_sender.send(
+            reply_to,
+            CommandAck::applied(serde_json::json!({
+                "id": message.id,
+                "points": message.points.len(),
+            })),
+        );
+    }
+}
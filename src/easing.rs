@@ -0,0 +1,76 @@
+use std::f32::consts::{PI, TAU};
+
+/// Named curves shaping how a transition moves from start to end
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Default,
+    serde::Deserialize,
+    serde::Serialize,
+    clap::ValueEnum,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Easing {
+    /// Constant speed
+    Linear,
+    /// Speeds up and slows down gently
+    #[default]
+    Cubic,
+    /// Snaps past the end and wobbles back onto it
+    Elastic,
+    /// Overshoots once and settles like a damped spring
+    Spring,
+}
+
+impl Easing {
+    /// Eased progress for linear `progress` between 0.0 and 1.0
+    ///
+    /// Starts at 0.0 and ends at 1.0, elastic and spring overshoot in between
+    pub fn ease(self, progress: f32) -> f32 {
+        if progress <= 0.0 {
+            return 0.0;
+        }
+        if progress >= 1.0 {
+            return 1.0;
+        }
+        match self {
+            Easing::Linear => progress,
+            Easing::Cubic if progress < 0.5 => 4.0 * progress.powi(3),
+            Easing::Cubic => 1.0 - (2.0 - 2.0 * progress).powi(3) / 2.0,
+            Easing::Elastic => {
+                2.0_f32.powf(-10.0 * progress) * ((10.0 * progress - 0.75) * TAU / 3.0).sin() + 1.0
+            }
+            Easing::Spring => 1.0 - (-6.0 * progress).exp() * (2.0 * PI * progress).cos(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn curves_start_and_end_in_place() {
+        for easing in [
+            Easing::Linear,
+            Easing::Cubic,
+            Easing::Elastic,
+            Easing::Spring,
+        ] {
+            assert_eq!(easing.ease(0.0), 0.0);
+            assert_eq!(easing.ease(1.0), 1.0);
+            assert!((easing.ease(0.999) - 1.0).abs() < 0.01, "{easing:?}");
+        }
+        assert_eq!(Easing::Linear.ease(0.25), 0.25);
+        assert!((Easing::Cubic.ease(0.5) - 0.5).abs() < 1e-6);
+        assert!(Easing::Cubic.ease(0.25) < 0.25);
+        let overshoot =
+            |easing: Easing| (1..100).any(|step| easing.ease(step as f32 / 100.0) > 1.0);
+        assert!(overshoot(Easing::Elastic));
+        assert!(overshoot(Easing::Spring));
+        assert!(!overshoot(Easing::Cubic));
+    }
+}
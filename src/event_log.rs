@@ -0,0 +1,229 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+use bevy::prelude::*;
+use chrono::DateTime;
+
+use crate::{
+    display::DisplayPower, night_mode::NightMode, safe_area::SafeAreaNode, scene::FaceScene,
+};
+
+/// Recent commands, errors and state changes kept in memory
+///
+/// Shown on a hidden log screen toggled with the L key
+pub struct EventLogPlugin;
+
+impl Plugin for EventLogPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EventLog>()
+            .add_systems(Startup, setup_event_log_screen)
+            .add_systems(
+                Update,
+                (
+                    log_state_changes,
+                    toggle_event_log_screen,
+                    update_event_log_screen,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Oldest entries are dropped past this
+const EVENT_LOG_CAPACITY: usize = 200;
+/// Entries that fit on the screen
+const EVENT_LOG_SCREEN_LINES: usize = 30;
+const EVENT_LOG_FONT_SIZE: f32 = 14.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    Command,
+    Error,
+    State,
+}
+
+#[derive(Debug, Clone)]
+struct EventLogEntry {
+    time: DateTime<chrono::Local>,
+    kind: EventKind,
+    text: String,
+    /// Identical consecutive events are folded into one entry
+    repeats: u32,
+}
+
+#[derive(Default)]
+struct EventLogState {
+    entries: VecDeque<EventLogEntry>,
+    /// Bumped on every change so the screen only redraws when needed
+    generation: u64,
+    /// Errors recorded since start, including folded repeats
+    errors: u64,
+}
+
+/// Bounded event log shared by bevy and the zenoh worker
+#[derive(Resource, Clone, Default)]
+pub struct EventLog(Arc<Mutex<EventLogState>>);
+
+impl EventLog {
+    pub fn record(&self, kind: EventKind, text: impl Into<String>) {
+        let text = text.into();
+        let mut state = self.state();
+        state.generation += 1;
+        if kind == EventKind::Error {
+            state.errors += 1;
+        }
+        if let Some(last) = state.entries.back_mut() {
+            if last.kind == kind && last.text == text {
+                last.time = chrono::Local::now();
+                last.repeats += 1;
+                return;
+            }
+        }
+        if state.entries.len() == EVENT_LOG_CAPACITY {
+            state.entries.pop_front();
+        }
+        state.entries.push_back(EventLogEntry {
+            time: chrono::Local::now(),
+            kind,
+            text,
+            repeats: 1,
+        });
+    }
+
+    /// Number of errors recorded since start
+    pub fn error_count(&self) -> u64 {
+        self.state().errors
+    }
+
+    /// Latest `count` entries formatted one per line, oldest first
+    pub fn lines(&self, count: usize) -> Vec<String> {
+        let state = self.state();
+        state
+            .entries
+            .iter()
+            .skip(state.entries.len().saturating_sub(count))
+            .map(|entry| {
+                let repeats = if entry.repeats > 1 {
+                    format!(" (x{})", entry.repeats)
+                } else {
+                    String::new()
+                };
+                format!(
+                    "{} {:?} {}{repeats}",
+                    entry.time.format("%H:%M:%S"),
+                    entry.kind,
+                    entry.text
+                )
+            })
+            .collect()
+    }
+
+    fn generation(&self) -> u64 {
+        self.state().generation
+    }
+
+    fn state(&self) -> MutexGuard<'_, EventLogState> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[derive(Component)]
+struct EventLogText;
+
+fn setup_event_log_screen(mut commands: Commands) {
+    let mut text = TextBundle::from_section(
+        "",
+        TextStyle {
+            font_size: EVENT_LOG_FONT_SIZE,
+            color: Color::WHITE,
+            ..default()
+        },
+    )
+    .with_style(Style {
+        position_type: PositionType::Absolute,
+        top: Val::Px(10.0),
+        right: Val::Px(10.0),
+        ..default()
+    })
+    .with_background_color(Color::BLACK.with_a(0.7));
+    text.visibility = Visibility::Hidden;
+
+    commands.spawn((text, EventLogText, SafeAreaNode { offset: 10.0 }));
+}
+
+fn log_state_changes(
+    event_log: Res<EventLog>,
+    scene: Res<State<FaceScene>>,
+    display_power: Res<DisplayPower>,
+    night_mode: Res<NightMode>,
+) {
+    if scene.is_changed() {
+        event_log.record(EventKind::State, format!("scene {:?}", scene.get()));
+    }
+    if display_power.is_changed() {
+        event_log.record(
+            EventKind::State,
+            format!("display on: {}", display_power.on),
+        );
+    }
+    if night_mode.is_changed() {
+        event_log.record(
+            EventKind::State,
+            format!("night mode: {}", night_mode.active),
+        );
+    }
+}
+
+fn toggle_event_log_screen(
+    input: Res<ButtonInput<KeyCode>>,
+    mut query: Query<&mut Visibility, With<EventLogText>>,
+) {
+    if !input.just_pressed(KeyCode::KeyL) {
+        return;
+    }
+    for mut visibility in query.iter_mut() {
+        *visibility = match *visibility {
+            Visibility::Hidden => Visibility::Visible,
+            _ => Visibility::Hidden,
+        };
+    }
+}
+
+fn update_event_log_screen(
+    event_log: Res<EventLog>,
+    mut drawn_generation: Local<Option<u64>>,
+    mut query: Query<&mut Text, With<EventLogText>>,
+) {
+    let generation = event_log.generation();
+    if *drawn_generation == Some(generation) {
+        return;
+    }
+    *drawn_generation = Some(generation);
+    let lines = event_log.lines(EVENT_LOG_SCREEN_LINES).join("\n");
+    for mut text in query.iter_mut() {
+        text.sections[0].value = format!("event log\n{lines}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeated_events_are_folded_and_old_ones_dropped() {
+        let event_log = EventLog::default();
+        event_log.record(EventKind::Command, "face/gaze");
+        event_log.record(EventKind::Command, "face/gaze");
+        assert_eq!(event_log.lines(10).len(), 1);
+        assert!(event_log.lines(10)[0].ends_with("Command face/gaze (x2)"));
+
+        for index in 0..EVENT_LOG_CAPACITY {
+            event_log.record(EventKind::State, index.to_string());
+        }
+        let lines = event_log.lines(EVENT_LOG_CAPACITY + 1);
+        assert_eq!(lines.len(), EVENT_LOG_CAPACITY);
+        assert!(lines[0].ends_with("State 0"));
+    }
+}
@@ -0,0 +1,195 @@
+use bevy::prelude::*;
+
+use crate::{
+    messaging::{AckSender, CommandAck},
+    noise_plugin::{hex_color, NoiseGeneratorSettingsUpdate},
+    params::{RegisterTunable, Tunable},
+    topics::RegisterTopic,
+};
+
+/// Single `excitement` level the behavior engine can express arousal with
+///
+/// The level is mapped through a curve per wave setting, both the level and the curves
+/// are adjustable as the `excitement` tunable and the level is also set over `face/excitement`
+pub struct ExcitementPlugin;
+
+impl Plugin for ExcitementPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Excitement::default())
+            .register_tunable::<Excitement>("excitement")
+            .add_topic::<ExcitementUpdate>("face/excitement")
+            .add_systems(
+                Update,
+                (
+                    process_excitement_updates,
+                    apply_excitement.run_if(resource_changed::<Excitement>),
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Curve exponents are kept in this range
+pub const EXPONENT_RANGE: std::ops::RangeInclusive<f64> = 0.1..=10.0;
+
+/// Mapping of the excitement level onto a single setting
+#[derive(Reflect, Clone, Debug, PartialEq)]
+pub struct ExcitementCurve {
+    /// Value at an excitement of 0.0
+    pub calm: f64,
+    /// Value at an excitement of 1.0
+    pub excited: f64,
+    /// 1.0 is linear, larger values stay calm for longer
+    pub exponent: f64,
+}
+
+impl ExcitementCurve {
+    fn new(calm: f64, excited: f64) -> Self {
+        Self {
+            calm,
+            excited,
+            exponent: 1.0,
+        }
+    }
+
+    pub fn at(&self, excitement: f64) -> f64 {
+        self.calm + (self.excited - self.calm) * excitement.clamp(0.0, 1.0).powf(self.exponent)
+    }
+}
+
+#[derive(Resource, Reflect, Clone, Debug, PartialEq)]
+#[reflect(Resource)]
+pub struct Excitement {
+    /// Between 0.0 for calm and 1.0 for excited
+    pub level: f64,
+    pub frame_time_divider: ExcitementCurve,
+    pub height_multiplier: ExcitementCurve,
+    pub line_width: ExcitementCurve,
+    /// Blend from `calm_color` at 0.0 to `excited_color` at 1.0
+    pub warmth: ExcitementCurve,
+    pub calm_color: Color,
+    pub excited_color: Color,
+}
+
+impl Default for Excitement {
+    fn default() -> Self {
+        Self {
+            level: 0.0,
+            frame_time_divider: ExcitementCurve::new(16.0, 2.0),
+            height_multiplier: ExcitementCurve::new(200.0, 600.0),
+            line_width: ExcitementCurve::new(2.0, 4.0),
+            warmth: ExcitementCurve::new(0.0, 1.0),
+            calm_color: Color::WHITE,
+            excited_color: Color::rgb_u8(0xff, 0x80, 0x40),
+        }
+    }
+}
+
+impl Excitement {
+    /// Wave settings at the current level
+    pub fn settings(&self) -> NoiseGeneratorSettingsUpdate {
+        let warmth = self.warmth.at(self.level).clamp(0.0, 1.0) as f32;
+        let calm = self.calm_color.as_rgba_f32();
+        let excited = self.excited_color.as_rgba_f32();
+        let [red, green, blue, alpha] =
+            std::array::from_fn(|index| calm[index] * (1.0 - warmth) + excited[index] * warmth);
+        NoiseGeneratorSettingsUpdate {
+            frame_time_divider: Some(self.frame_time_divider.at(self.level)),
+            height_multiplier: Some(self.height_multiplier.at(self.level)),
+            line_width: Some(self.line_width.at(self.level) as f32),
+            color: Some(hex_color(Color::rgba(red, green, blue, alpha))),
+            ..default()
+        }
+    }
+}
+
+impl Tunable for Excitement {
+    fn sanitize(&mut self) {
+        self.level = if self.level.is_finite() {
+            self.level.clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        for curve in [
+            &mut self.frame_time_divider,
+            &mut self.height_multiplier,
+            &mut self.line_width,
+            &mut self.warmth,
+        ] {
+            curve.exponent = if curve.exponent.is_finite() {
+                curve
+                    .exponent
+                    .clamp(*EXPONENT_RANGE.start(), *EXPONENT_RANGE.end())
+            } else {
+                1.0
+            };
+        }
+    }
+}
+
+#[derive(Event, serde::Deserialize)]
+pub struct ExcitementUpdate {
+    /// Between 0.0 for calm and 1.0 for excited
+    excitement: f64,
+    /// Key to publish an acknowledgement to once applied
+    #[serde(default)]
+    reply_to: Option<String>,
+}
+
+fn process_excitement_updates(
+    mut messages: EventReader<ExcitementUpdate>,
+    mut excitement: ResMut<Excitement>,
+    ack_sender: Res<AckSender>,
+) {
+    for (message, id) in messages.read_with_id() {
+        let _trace = ack_sender.trace(id);
+        excitement.level = message.excitement;
+        excitement.sanitize();
+        info!(level = excitement.level, "Updating excitement");
+        ack_sender.send(
+            message.reply_to.as_deref(),
+            CommandAck::applied(excitement.settings()),
+        );
+    }
+}
+
+/// Send the mapped wave settings whenever the level or a curve changes
+///
+/// Nothing is sent at startup so the configured wave settings stay until excitement is used
+fn apply_excitement(
+    excitement: Res<Excitement>,
+    mut settings_events: EventWriter<NoiseGeneratorSettingsUpdate>,
+) {
+    if excitement.is_added() {
+        return;
+    }
+    settings_events.send(excitement.settings());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn curves_map_the_level_between_calm_and_excited() {
+        let curve = ExcitementCurve::new(16.0, 2.0);
+        assert_eq!(curve.at(0.0), 16.0);
+        assert_eq!(curve.at(1.0), 2.0);
+        assert_eq!(curve.at(0.5), 9.0);
+        assert_eq!(curve.at(7.0), 2.0);
+
+        let curve = ExcitementCurve {
+            exponent: 2.0,
+            ..ExcitementCurve::new(0.0, 1.0)
+        };
+        assert_eq!(curve.at(0.5), 0.25);
+
+        let excitement = Excitement {
+            level: 1.0,
+            ..default()
+        };
+        let settings = excitement.settings();
+        assert_eq!(settings.color, Some(hex_color(excitement.excited_color)));
+        assert_eq!(settings.line_width, Some(4.0));
+    }
+}
@@ -0,0 +1,501 @@
+use std::{path::PathBuf, time::Duration};
+
+use anyhow::Context;
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    asset_pack::assets_dir,
+    easing::Easing,
+    eyes::{EyeShape, EyeShapeUpdate, EyeSides},
+    face_clock::FaceClock,
+    messaging::{AckSender, CommandAck},
+    noise_plugin::{NoiseGeneratorSettings, NoiseGeneratorSettingsUpdate},
+    sound::{PlaySound, SoundHook},
+    topics::RegisterTopic,
+};
+
+/// Emotional expression of the face set over `face/expression`
+///
+/// Each expression has its own wave color, height and speed and its own eye shape.
+/// Changes blend over `transition_s` and going back to neutral restores
+/// the wave as it was before the first expression.
+/// Presets saved from the tuning inspector add expressions or replace the built-in ones,
+/// presets can also play sounds when the face changes into or out of their expression
+pub struct ExpressionPlugin;
+
+impl Plugin for ExpressionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_topic::<ExpressionMessage>("face/expression")
+            .add_event::<SaveExpressionPreset>()
+            .add_event::<PlaySound>()
+            .init_resource::<FaceExpression>()
+            .init_resource::<ExpressionPresets>()
+            .init_resource::<EyeSides>()
+            .add_systems(Startup, load_expression_presets)
+            .add_systems(
+                Update,
+                (
+                    save_expression_presets,
+                    process_expression_messages,
+                    blend_expression_eyes,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Transition used when a message doesn't set one
+const DEFAULT_TRANSITION: Duration = Duration::from_millis(800);
+/// Slower transitions are sped up to this
+const MAX_TRANSITION: Duration = Duration::from_secs(60);
+const EXPRESSION_EASING: Easing = Easing::Cubic;
+const NEUTRAL: &str = "neutral";
+
+/// Built-in expressions, presets can add more
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expression {
+    Neutral,
+    Happy,
+    Sad,
+    Surprised,
+    Angry,
+    Sleepy,
+}
+
+impl Expression {
+    pub const ALL: [Expression; 6] = [
+        Expression::Neutral,
+        Expression::Happy,
+        Expression::Sad,
+        Expression::Surprised,
+        Expression::Angry,
+        Expression::Sleepy,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Expression::Neutral => NEUTRAL,
+            Expression::Happy => "happy",
+            Expression::Sad => "sad",
+            Expression::Surprised => "surprised",
+            Expression::Angry => "angry",
+            Expression::Sleepy => "sleepy",
+        }
+    }
+
+    /// Look of the expression, neutral restores the wave from before the first expression
+    fn preset(self) -> Option<ExpressionPreset> {
+        let preset = |color: &str, height_multiplier, frame_time_divider, openness, raise| {
+            ExpressionPreset {
+                wave: NoiseGeneratorSettingsUpdate {
+                    color: Some(color.to_owned()),
+                    height_multiplier: Some(height_multiplier),
+                    frame_time_divider: Some(frame_time_divider),
+                    ..default()
+                },
+                eyes: EyeShapeUpdate {
+                    openness: Some(openness),
+                    raise: Some(raise),
+                },
+                sound: default(),
+            }
+        };
+        match self {
+            Expression::Neutral => None,
+            Expression::Happy => Some(preset("ffd860", 450.0, 5.0, 0.7, 0.3)),
+            Expression::Sad => Some(preset("6080ff", 200.0, 16.0, 0.6, -0.4)),
+            Expression::Surprised => Some(preset("ffffff", 600.0, 3.0, 1.0, 0.8)),
+            Expression::Angry => Some(preset("ff3020", 550.0, 3.0, 0.5, -0.6)),
+            Expression::Sleepy => Some(preset("8070a0", 150.0, 20.0, 0.2, -0.2)),
+        }
+    }
+}
+
+/// Wave settings and eye shape of an expression, saved as `assets/expressions/<name>.json`
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Default)]
+pub struct ExpressionPreset {
+    #[serde(default)]
+    pub wave: NoiseGeneratorSettingsUpdate,
+    #[serde(default)]
+    pub eyes: EyeShapeUpdate,
+    /// Played when the face changes into or out of the expression, unless sound hooks are disabled
+    #[serde(default)]
+    pub sound: SoundHook,
+}
+
+impl ExpressionPreset {
+    fn eye_shape(&self) -> EyeShape {
+        let mut shape = EyeShape::default();
+        shape.apply(&self.eyes);
+        shape
+    }
+}
+
+#[derive(Event, serde::Deserialize, serde::Serialize, clap::Args, Debug)]
+pub struct ExpressionMessage {
+    /// Built-in expression such as happy or sleepy, or the name of a saved preset
+    pub expression: String,
+    /// Seconds to blend into the expression, 0.8 unless set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub transition_s: Option<f32>,
+    /// Key to publish an acknowledgement to once applied
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub reply_to: Option<String>,
+}
+
+/// Save the preset under `name` and make it available right away
+#[derive(Event, Debug)]
+pub struct SaveExpressionPreset {
+    pub name: String,
+    pub preset: ExpressionPreset,
+}
+
+/// Presets loaded from `assets/expressions`, by name
+#[derive(Resource, Default)]
+pub struct ExpressionPresets(HashMap<String, ExpressionPreset>);
+
+impl ExpressionPresets {
+    /// Saved preset or built-in look of `name`, `None` for neutral
+    fn get(&self, name: &str) -> anyhow::Result<Option<ExpressionPreset>> {
+        if name == NEUTRAL {
+            return Ok(None);
+        }
+        if let Some(preset) = self.0.get(name) {
+            return Ok(Some(preset.clone()));
+        }
+        Expression::ALL
+            .into_iter()
+            .find(|expression| expression.name() == name)
+            .map(Expression::preset)
+            .with_context(|| format!("Unknown expression {name:?}"))
+    }
+}
+
+/// Current expression and the blend of the eyes into it
+#[derive(Resource)]
+pub struct FaceExpression {
+    pub name: String,
+    eyes: EyeShape,
+    /// Eye shape the blend started from
+    eyes_from: EyeShape,
+    blend: Option<Timer>,
+    /// Wave settings to go back to for neutral
+    baseline: Option<NoiseGeneratorSettingsUpdate>,
+}
+
+impl Default for FaceExpression {
+    fn default() -> Self {
+        Self {
+            name: NEUTRAL.to_owned(),
+            eyes: default(),
+            eyes_from: default(),
+            blend: None,
+            baseline: None,
+        }
+    }
+}
+
+fn presets_dir() -> PathBuf {
+    assets_dir().join("expressions")
+}
+
+/// Preset names end up in file names, so they are kept to plain lowercase words
+fn validate_preset_name(name: &str) -> anyhow::Result<()> {
+    let valid = !name.is_empty()
+        && name.len() <= 64
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-');
+    if !valid {
+        anyhow::bail!("Preset names are lowercase letters, digits, - and _, got {name:?}");
+    }
+    if name == NEUTRAL {
+        anyhow::bail!("Neutral restores the wave from before the first expression");
+    }
+    Ok(())
+}
+
+fn load_expression_presets(mut presets: ResMut<ExpressionPresets>) {
+    let dir = presets_dir();
+    let Ok(entries) = std::fs::read_dir(&dir) else {
+        return;
+    };
+    for path in entries.flatten().map(|entry| entry.path()) {
+        if path.extension().is_none_or(|extension| extension != "json") {
+            continue;
+        }
+        let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+            continue;
+        };
+        let preset = validate_preset_name(name).and_then(|()| {
+            let data = std::fs::read(&path)?;
+            Ok(serde_json::from_slice::<ExpressionPreset>(&data)?)
+        });
+        match preset {
+            Ok(preset) => {
+                info!(name, "Loaded expression preset");
+                presets.0.insert(name.to_owned(), preset);
+            }
+            Err(error) => {
+                error!(?error, path = %path.display(), "Failed to load expression preset")
+            }
+        }
+    }
+}
+
+fn save_expression_presets(
+    mut saves: EventReader<SaveExpressionPreset>,
+    mut presets: ResMut<ExpressionPresets>,
+) {
+    for save in saves.read() {
+        let mut preset = save.preset.clone();
+        // the inspector doesn't edit sounds, saving over a preset keeps its sounds
+        if preset.sound.on_enter.is_none() && preset.sound.on_exit.is_none() {
+            if let Some(saved) = presets.0.get(&save.name) {
+                preset.sound = saved.sound.clone();
+            }
+        }
+        let saved = validate_preset_name(&save.name).and_then(|()| {
+            let dir = presets_dir();
+            std::fs::create_dir_all(&dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+            let path = dir.join(format!("{}.json", save.name));
+            std::fs::write(&path, serde_json::to_vec_pretty(&preset)?)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+            Ok(path)
+        });
+        match saved {
+            Ok(path) => {
+                info!(name = save.name, path = %path.display(), "Saved expression preset");
+                presets.0.insert(save.name.clone(), preset);
+            }
+            Err(error) => error!(?error, name = save.name, "Failed to save expression preset"),
+        }
+    }
+}
+
+fn process_expression_messages(
+    mut messages: EventReader<ExpressionMessage>,
+    mut face_expression: ResMut<FaceExpression>,
+    mut settings_updates: EventWriter<NoiseGeneratorSettingsUpdate>,
+    (settings, sides, presets): (
+        Res<NoiseGeneratorSettings>,
+        Res<EyeSides>,
+        Res<ExpressionPresets>,
+    ),
+    ack_sender: Res<AckSender>,
+    mut play_sound: EventWriter<PlaySound>,
+) {
+    for (message, id) in messages.read_with_id() {
+        let _trace = ack_sender.trace(id);
+        let transition = match message.transition_s {
+            Some(transition_s) if transition_s.is_finite() && transition_s >= 0.0 => {
+                Duration::from_secs_f32(transition_s.min(MAX_TRANSITION.as_secs_f32()))
+            }
+            Some(transition_s) => {
+                warn!(transition_s, "Invalid expression transition");
+                ack_sender.send(
+                    message.reply_to.as_deref(),
+                    CommandAck::rejected("transition_s must not be negative"),
+                );
+                continue;
+            }
+            None => DEFAULT_TRANSITION,
+        };
+        let preset = match presets.get(&message.expression) {
+            Ok(preset) => preset,
+            Err(error) => {
+                warn!(?error, "Rejecting expression");
+                ack_sender.send(
+                    message.reply_to.as_deref(),
+                    CommandAck::rejected(format!("{error:#}")),
+                );
+                continue;
+            }
+        };
+        info!(
+            expression = message.expression,
+            ?transition,
+            "Changing expression"
+        );
+
+        if message.expression != face_expression.name {
+            let on_exit = presets
+                .get(&face_expression.name)
+                .ok()
+                .flatten()
+                .and_then(|preset| preset.sound.on_exit);
+            let on_enter = preset
+                .as_ref()
+                .and_then(|preset| preset.sound.on_enter.clone());
+            for sound in on_exit.into_iter().chain(on_enter) {
+                play_sound.send(PlaySound(sound));
+            }
+        }
+
+        let (update, eyes) = match preset {
+            Some(preset) => {
+                if face_expression.baseline.is_none() {
+                    face_expression.baseline = Some(NoiseGeneratorSettingsUpdate {
+                        // showing and hiding the wave is left to the scenes
+                        hidden: None,
+                        background: None,
+                        ..settings.to_update()
+                    });
+                }
+                let eyes = preset.eye_shape();
+                (preset.wave, eyes)
+            }
+            None => (
+                face_expression.baseline.take().unwrap_or_default(),
+                EyeShape::default(),
+            ),
+        };
+        settings_updates.send(NoiseGeneratorSettingsUpdate {
+            transition_s: Some(transition.as_secs_f32()),
+            easing: Some(EXPRESSION_EASING),
+            persist: false,
+            reply_to: None,
+            ..update
+        });
+
+        face_expression.name.clone_from(&message.expression);
+        face_expression.eyes = eyes;
+        face_expression.eyes_from = sides.left;
+        face_expression.blend = Some(Timer::new(transition, TimerMode::Once));
+        ack_sender.send(
+            message.reply_to.as_deref(),
+            CommandAck::applied(serde_json::json!({
+                "expression": message.expression,
+                "transition_s": transition.as_secs_f32(),
+            })),
+        );
+    }
+}
+
+/// Move both eyes into the shape of the expression
+fn blend_expression_eyes(
+    mut face_expression: ResMut<FaceExpression>,
+    mut sides: ResMut<EyeSides>,
+    clock: Res<FaceClock>,
+) {
+    let face_expression = &mut *face_expression;
+    let Some(blend) = &mut face_expression.blend else {
+        return;
+    };
+    let progress = EXPRESSION_EASING.ease(blend.tick(clock.delta()).fraction());
+    let shape = face_expression
+        .eyes_from
+        .lerp(face_expression.eyes, progress);
+    sides.left = shape;
+    sides.right = shape;
+    if blend.finished() {
+        face_expression.blend = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{messaging::KeyPrefix, outbound::OutboundQueue, trace::CommandTraces};
+
+    #[test]
+    fn expressions_have_valid_looks() {
+        assert!(Expression::Neutral.preset().is_none());
+        for expression in Expression::ALL.into_iter().skip(1) {
+            let preset = expression.preset().unwrap();
+            let color = preset.wave.color.as_deref().unwrap();
+            assert!(Color::hex(color).is_ok(), "{expression:?}");
+            let eyes = preset.eye_shape();
+            assert_eq!(eyes.openness, preset.eyes.openness.unwrap());
+            assert_eq!(eyes.raise, preset.eyes.raise.unwrap());
+        }
+    }
+
+    #[test]
+    fn saved_presets_extend_the_built_in_ones() {
+        let mut presets = ExpressionPresets::default();
+        let grumpy = ExpressionPreset {
+            eyes: EyeShapeUpdate {
+                openness: Some(0.4),
+                raise: None,
+            },
+            ..default()
+        };
+        presets.0.insert("grumpy".to_owned(), grumpy);
+        assert_eq!(
+            presets.get("grumpy").unwrap().unwrap().eye_shape(),
+            EyeShape {
+                openness: 0.4,
+                raise: 0.0
+            }
+        );
+        assert!(presets.get("happy").unwrap().is_some());
+        assert!(presets.get(NEUTRAL).unwrap().is_none());
+        assert!(presets.get("bored").is_err());
+
+        assert!(validate_preset_name("grumpy-2").is_ok());
+        assert!(validate_preset_name("../grumpy").is_err());
+        assert!(validate_preset_name(NEUTRAL).is_err());
+    }
+
+    #[test]
+    fn preset_sounds_play_on_expression_changes() {
+        let mut presets = ExpressionPresets::default();
+        let sound = |on_enter: &str, on_exit: &str| SoundHook {
+            on_enter: Some(on_enter.to_owned()),
+            on_exit: Some(on_exit.to_owned()),
+        };
+        for (name, hook) in [
+            ("grumpy", sound("grumpy_in.ogg", "grumpy_out.ogg")),
+            ("giddy", sound("giddy_in.ogg", "giddy_out.ogg")),
+        ] {
+            presets.0.insert(
+                name.to_owned(),
+                ExpressionPreset {
+                    sound: hook,
+                    ..default()
+                },
+            );
+        }
+        let mut app = App::new();
+        app.insert_resource(presets)
+            .init_resource::<FaceExpression>()
+            .init_resource::<NoiseGeneratorSettings>()
+            .init_resource::<EyeSides>()
+            .insert_resource(AckSender::new(
+                OutboundQueue::new(16),
+                KeyPrefix::default(),
+                CommandTraces::default(),
+            ))
+            .add_event::<ExpressionMessage>()
+            .add_event::<NoiseGeneratorSettingsUpdate>()
+            .add_event::<PlaySound>()
+            .add_systems(Update, process_expression_messages);
+        let played = |app: &mut App, expression: &str| {
+            app.world.send_event(ExpressionMessage {
+                expression: expression.to_owned(),
+                transition_s: None,
+                reply_to: None,
+            });
+            app.update();
+            app.world
+                .resource_mut::<Events<PlaySound>>()
+                .drain()
+                .map(|PlaySound(path)| path)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(played(&mut app, "grumpy"), ["grumpy_in.ogg"]);
+        // staying in the expression doesn't play it again
+        assert!(played(&mut app, "grumpy").is_empty());
+        assert_eq!(
+            played(&mut app, "giddy"),
+            ["grumpy_out.ogg", "giddy_in.ogg"]
+        );
+        assert_eq!(played(&mut app, NEUTRAL), ["giddy_out.ogg"]);
+        assert!(played(&mut app, "happy").is_empty());
+    }
+}
@@ -0,0 +1,451 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{
+    face_clock::FaceClock,
+    messaging::{AckSender, CommandAck},
+    params::{RegisterTunable, Tunable},
+    spring::{sanitize_spring, Spring},
+    topics::RegisterTopic,
+};
+
+/// Shape of the left and right eye set independently over `face/eyes`
+///
+/// Scenes that draw eyes apply the shape of each side, so one eye can wink
+/// or be raised while the other stays as it is.
+/// Shapes change right away unless the `eye_spring` tunable eases them in on a spring.
+/// The eyes blink now and then as set by the `blink` tunable, `face/eyes` can blink or wink them
+pub struct EyesPlugin;
+
+impl Plugin for EyesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_topic::<EyesMessage>("face/eyes")
+            .init_resource::<EyeSides>()
+            .init_resource::<EyeSpring>()
+            .init_resource::<BlinkTiming>()
+            .init_resource::<Blinks>()
+            .register_tunable::<EyeSpring>("eye_spring")
+            .register_tunable::<BlinkTiming>("blink")
+            .add_systems(
+                Update,
+                (
+                    process_eyes_messages,
+                    reset_eye_sides,
+                    move_eyes,
+                    blink_eyes,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// How far a fully raised eye moves up in pixels
+const EYE_RAISE_DISTANCE: f32 = 40.0;
+/// Closed eyes are drawn this open so they stay visible as a line
+pub const MIN_EYE_OPENNESS: f32 = 0.05;
+/// Longest time a temporary eye shape is held before resetting
+const MAX_EYE_SHAPE_DURATION: Duration = Duration::from_secs(60 * 60);
+
+/// Shape of a single eye
+#[derive(serde::Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct EyeShape {
+    /// 1.0 is fully open and 0.0 closed
+    pub openness: f32,
+    /// Between -1.0 for lowered and 1.0 for raised
+    pub raise: f32,
+}
+
+impl Default for EyeShape {
+    fn default() -> Self {
+        Self {
+            openness: 1.0,
+            raise: 0.0,
+        }
+    }
+}
+
+impl EyeShape {
+    /// Vertical scale of the eye outline
+    pub fn vertical_scale(&self) -> f32 {
+        self.openness.max(MIN_EYE_OPENNESS)
+    }
+
+    /// Offset of the eye from where the scene places it
+    pub fn offset(&self) -> Vec2 {
+        Vec2::Y * self.raise * EYE_RAISE_DISTANCE
+    }
+
+    /// Shape `progress` of the way to `other`
+    pub fn lerp(self, other: Self, progress: f32) -> Self {
+        Self::from_vec2(self.to_vec2().lerp(other.to_vec2(), progress))
+    }
+
+    fn to_vec2(self) -> Vec2 {
+        Vec2::new(self.openness, self.raise)
+    }
+
+    fn from_vec2(values: Vec2) -> Self {
+        Self {
+            openness: values.x.clamp(0.0, 1.0),
+            raise: values.y.clamp(-1.0, 1.0),
+        }
+    }
+
+    pub fn apply(&mut self, update: &EyeShapeUpdate) {
+        if let Some(openness) = update.openness.filter(|openness| openness.is_finite()) {
+            self.openness = openness.clamp(0.0, 1.0);
+        }
+        if let Some(raise) = update.raise.filter(|raise| raise.is_finite()) {
+            self.raise = raise.clamp(-1.0, 1.0);
+        }
+    }
+}
+
+/// Changed values of an eye, left out values are kept
+#[derive(serde::Deserialize, serde::Serialize, Debug, Default, Clone)]
+pub struct EyeShapeUpdate {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub openness: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raise: Option<f32>,
+}
+
+#[derive(Event, serde::Deserialize, Debug)]
+pub struct EyesMessage {
+    /// Applied to both eyes before the per side values
+    #[serde(default)]
+    pub both: Option<EyeShapeUpdate>,
+    /// Eye on the left of the screen
+    #[serde(default)]
+    pub left: Option<EyeShapeUpdate>,
+    /// Eye on the right of the screen
+    #[serde(default)]
+    pub right: Option<EyeShapeUpdate>,
+    /// Go back to open and level eyes after this many seconds, such as for a wink
+    #[serde(default)]
+    pub duration_s: Option<f32>,
+    /// Blink both eyes or wink one of them once
+    #[serde(default)]
+    pub blink: Option<Blink>,
+    /// Key to publish an acknowledgement to once applied
+    #[serde(default)]
+    pub reply_to: Option<String>,
+}
+
+#[derive(Resource, Default, serde::Serialize)]
+pub struct EyeSides {
+    /// Shape the left eye is set to
+    pub left: EyeShape,
+    /// Shape the right eye is set to
+    pub right: EyeShape,
+    /// Shapes currently drawn on their way to the set ones, left first
+    #[serde(skip)]
+    shown: [EyeShape; 2],
+    /// How far each eye is closed by a blink, left first
+    #[serde(skip)]
+    blink_closure: [f32; 2],
+    #[serde(skip)]
+    reset_at: Option<Duration>,
+}
+
+impl EyeSides {
+    /// Shape drawn for the eye at horizontal position `x`, negative is the left side
+    pub fn side(&self, x: f32) -> EyeShape {
+        let index = usize::from(x >= 0.0);
+        let shape = self.shown[index];
+        EyeShape {
+            openness: shape.openness * (1.0 - self.blink_closure[index]),
+            ..shape
+        }
+    }
+}
+
+/// Spring the eye shapes follow changes on, the spring is off by default
+#[derive(Resource, Reflect, Clone, Debug)]
+#[reflect(Resource)]
+pub struct EyeSpring {
+    pub enabled: bool,
+    pub stiffness: f32,
+    /// Below 2 * sqrt(stiffness) the eyes overshoot a little before settling
+    pub damping: f32,
+}
+
+impl Default for EyeSpring {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            stiffness: 300.0,
+            damping: 25.0,
+        }
+    }
+}
+
+impl Tunable for EyeSpring {
+    fn sanitize(&mut self) {
+        let defaults = Self::default();
+        sanitize_spring(
+            &mut self.stiffness,
+            &mut self.damping,
+            (defaults.stiffness, defaults.damping),
+        );
+    }
+}
+
+fn process_eyes_messages(
+    mut messages: EventReader<EyesMessage>,
+    mut sides: ResMut<EyeSides>,
+    mut blinks: ResMut<Blinks>,
+    ack_sender: Res<AckSender>,
+    clock: Res<FaceClock>,
+) {
+    for (message, id) in messages.read_with_id() {
+        let _trace = ack_sender.trace(id);
+        if let Some(blink) = message.blink {
+            debug!(?blink, "Blinking");
+            let now = clock.elapsed();
+            if blink != Blink::Right {
+                blinks.started[0] = Some(now);
+            }
+            if blink != Blink::Left {
+                blinks.started[1] = Some(now);
+            }
+            if message.both.is_none()
+                && message.left.is_none()
+                && message.right.is_none()
+                && message.duration_s.is_none()
+            {
+                // a blink alone leaves a running wink alone
+                ack_sender.send(message.reply_to.as_deref(), CommandAck::applied(&*sides));
+                continue;
+            }
+        }
+        if let Some(both) = &message.both {
+            sides.left.apply(both);
+            sides.right.apply(both);
+        }
+        if let Some(left) = &message.left {
+            sides.left.apply(left);
+        }
+        if let Some(right) = &message.right {
+            sides.right.apply(right);
+        }
+        sides.reset_at = message
+            .duration_s
+            .filter(|duration_s| duration_s.is_finite() && *duration_s > 0.0)
+            .map(|duration_s| {
+                clock.elapsed()
+                    + Duration::from_secs_f32(duration_s.min(MAX_EYE_SHAPE_DURATION.as_secs_f32()))
+            });
+        info!(left = ?sides.left, right = ?sides.right, "Updating eyes");
+        ack_sender.send(message.reply_to.as_deref(), CommandAck::applied(&*sides));
+    }
+}
+
+fn reset_eye_sides(mut sides: ResMut<EyeSides>, clock: Res<FaceClock>) {
+    if sides
+        .reset_at
+        .is_some_and(|reset_at| clock.elapsed() >= reset_at)
+    {
+        debug!("Resetting eyes");
+        sides.left = EyeShape::default();
+        sides.right = EyeShape::default();
+        sides.reset_at = None;
+    }
+}
+
+/// Move the drawn shapes to the set ones, on the spring if enabled
+fn move_eyes(
+    mut sides: ResMut<EyeSides>,
+    eye_spring: Res<EyeSpring>,
+    mut springs: Local<[Option<Spring>; 2]>,
+    clock: Res<FaceClock>,
+) {
+    let targets = [sides.left, sides.right];
+    for (index, target) in targets.into_iter().enumerate() {
+        let resting = springs[index].is_none() && sides.shown[index] == target;
+        let shown = if !eye_spring.enabled || resting {
+            springs[index] = None;
+            target
+        } else {
+            let start = sides.shown[index].to_vec2();
+            let spring = springs[index].get_or_insert_with(|| Spring::new(start));
+            spring.step(
+                target.to_vec2(),
+                eye_spring.stiffness,
+                eye_spring.damping,
+                clock.delta_seconds(),
+            );
+            if spring.settled(target.to_vec2()) {
+                springs[index] = None;
+                target
+            } else {
+                EyeShape::from_vec2(spring.position)
+            }
+        };
+        if sides.shown[index] != shown {
+            sides.shown[index] = shown;
+        }
+    }
+}
+
+/// Eyes to blink
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Blink {
+    Both,
+    /// Wink the eye on the left of the screen
+    Left,
+    /// Wink the eye on the right of the screen
+    Right,
+}
+
+/// Longest time between automatic blinks in seconds
+const MAX_BLINK_INTERVAL_S: f32 = 10.0 * 60.0;
+
+/// Automatic blinking
+#[derive(Resource, Reflect, Clone, Debug)]
+#[reflect(Resource)]
+pub struct BlinkTiming {
+    pub enabled: bool,
+    /// Seconds between blinks, varies between the two
+    pub min_interval_s: f32,
+    pub max_interval_s: f32,
+    /// Seconds from open to closed and open again
+    pub duration_s: f32,
+}
+
+impl Default for BlinkTiming {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_interval_s: 2.0,
+            max_interval_s: 6.0,
+            duration_s: 0.15,
+        }
+    }
+}
+
+impl Tunable for BlinkTiming {
+    fn sanitize(&mut self) {
+        let defaults = Self::default();
+        let positive = |value: f32, default: f32| {
+            if value.is_finite() && value > 0.0 {
+                value
+            } else {
+                default
+            }
+        };
+        self.min_interval_s =
+            positive(self.min_interval_s, defaults.min_interval_s).min(MAX_BLINK_INTERVAL_S);
+        self.max_interval_s =
+            positive(self.max_interval_s, defaults.max_interval_s).min(MAX_BLINK_INTERVAL_S);
+        self.max_interval_s = self.max_interval_s.max(self.min_interval_s);
+        self.duration_s = positive(self.duration_s, defaults.duration_s).min(2.0);
+    }
+}
+
+/// Golden ratio steps spread the intervals evenly without an rng, keeping replays deterministic
+const BLINK_SEQUENCE_STEP: f32 = 0.618_034;
+/// Share of a blink spent closing, eyes close faster than they open
+const BLINK_CLOSING: f32 = 0.4;
+
+#[derive(Resource, Default)]
+struct Blinks {
+    /// Time the current blink of each eye started, left first
+    started: [Option<Duration>; 2],
+    next_blink_at: Option<Duration>,
+    /// Automatic blinks so far, drives the variation between intervals
+    count: u32,
+}
+
+impl Blinks {
+    fn interval(&self, timing: &BlinkTiming) -> Duration {
+        let variation = (self.count as f32 * BLINK_SEQUENCE_STEP).fract();
+        Duration::from_secs_f32(
+            timing.min_interval_s + (timing.max_interval_s - timing.min_interval_s) * variation,
+        )
+    }
+}
+
+/// How far the eye is closed `progress` of the way through a blink
+fn blink_closure(progress: f32) -> f32 {
+    if !(0.0..1.0).contains(&progress) {
+        0.0
+    } else if progress < BLINK_CLOSING {
+        progress / BLINK_CLOSING
+    } else {
+        (1.0 - progress) / (1.0 - BLINK_CLOSING)
+    }
+}
+
+fn blink_eyes(
+    mut sides: ResMut<EyeSides>,
+    mut blinks: ResMut<Blinks>,
+    timing: Res<BlinkTiming>,
+    clock: Res<FaceClock>,
+) {
+    let now = clock.elapsed();
+    if !timing.enabled {
+        blinks.next_blink_at = None;
+    } else if blinks.next_blink_at.is_some_and(|next| now >= next) {
+        blinks.started = [Some(now); 2];
+        blinks.count += 1;
+        blinks.next_blink_at = None;
+    }
+    if timing.enabled && blinks.next_blink_at.is_none() {
+        blinks.next_blink_at = Some(now + blinks.interval(&timing));
+    }
+
+    let duration = Duration::from_secs_f32(timing.duration_s);
+    for index in 0..2 {
+        let closure = match blinks.started[index] {
+            Some(started) if now - started < duration => {
+                blink_closure((now - started).as_secs_f32() / timing.duration_s)
+            }
+            Some(_) => {
+                blinks.started[index] = None;
+                0.0
+            }
+            None => 0.0,
+        };
+        if sides.blink_closure[index] != closure {
+            sides.blink_closure[index] = closure;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn updates_keep_left_out_values() {
+        let mut shape = EyeShape::default();
+        shape.apply(&EyeShapeUpdate {
+            openness: Some(-1.0),
+            raise: None,
+        });
+        assert_eq!(shape.openness, 0.0);
+        assert_eq!(shape.vertical_scale(), MIN_EYE_OPENNESS);
+        shape.apply(&EyeShapeUpdate {
+            openness: Some(f32::NAN),
+            raise: Some(0.5),
+        });
+        assert_eq!(
+            shape,
+            EyeShape {
+                openness: 0.0,
+                raise: 0.5
+            }
+        );
+    }
+
+    #[test]
+    fn blinks_close_quickly_and_open_again() {
+        assert_eq!(blink_closure(0.0), 0.0);
+        assert_eq!(blink_closure(BLINK_CLOSING), 1.0);
+        assert!(blink_closure(0.2) > blink_closure(0.8));
+        assert_eq!(blink_closure(1.0), 0.0);
+    }
+}
@@ -0,0 +1,178 @@
+use std::time::Duration;
+
+use bevy::{prelude::*, time::TimeSystem};
+use chrono::{DateTime, Local, TimeZone, Timelike};
+
+use crate::clock_sync::{local_unix_ms, SharedClock};
+
+/// Time of the face, read once at the start of every frame
+///
+/// Animations and timers read [`FaceClock`] instead of [`Time`] or the system clock,
+/// so replays and synchronized faces only differ in the [`ClockSource`] it was created with.
+/// Its elapsed time and frame delta follow the virtual [`Time`], which replays advance
+/// by a fixed step, while the wall clock and the animation phase follow the source.
+///
+/// Two kinds of systems keep reading bevy's clocks. Diagnostics measuring real frame times
+/// and timeouts, such as stutter detection or the boot connection timeout, read [`Time<Real>`]
+/// because a replay would hide what they measure. The noise simulation steps on [`Time<Fixed>`]
+/// so the wave speed doesn't depend on the frame rate, the fixed clock follows the virtual one
+pub struct FaceClockPlugin;
+
+impl Plugin for FaceClockPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FaceClock>()
+            .add_systems(First, tick_face_clock.after(TimeSystem));
+    }
+}
+
+/// Where the face clock takes its time from
+#[derive(Clone)]
+pub enum ClockSource {
+    /// Local system clock
+    Real,
+    /// Starts at `start_unix_ms` and advances with [`Time`], so replays render the same frames
+    Simulated { start_unix_ms: f64 },
+    /// Clock of the time authority, the local clock until an offset was measured
+    Synced(SharedClock),
+}
+
+impl ClockSource {
+    /// Simulated clock starting at `hour` of a fixed local day
+    pub fn simulated_from_hour(hour: u32) -> Self {
+        let start = Local
+            .with_ymd_and_hms(2024, 1, 1, hour.min(23), 0, 0)
+            .earliest()
+            .map_or(0.0, |start| start.timestamp_millis() as f64);
+        ClockSource::Simulated {
+            start_unix_ms: start,
+        }
+    }
+}
+
+/// Animation phases wrap around after this long to keep f32 precision on long running faces
+const PHASE_WRAP_MS: f64 = 60.0 * 60.0 * 1000.0;
+
+#[derive(Resource, Clone)]
+pub struct FaceClock {
+    source: ClockSource,
+    now_ms: f64,
+    elapsed: Duration,
+    delta: Duration,
+}
+
+impl Default for FaceClock {
+    fn default() -> Self {
+        Self::new(ClockSource::Real)
+    }
+}
+
+impl FaceClock {
+    pub fn new(source: ClockSource) -> Self {
+        let mut clock = Self {
+            source,
+            now_ms: 0.0,
+            elapsed: Duration::ZERO,
+            delta: Duration::ZERO,
+        };
+        clock.tick(Duration::ZERO, Duration::ZERO);
+        clock
+    }
+
+    /// Time since the face started, for deadlines and timeouts
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn elapsed_seconds(&self) -> f32 {
+        self.elapsed.as_secs_f32()
+    }
+
+    /// Seconds for periodic animations, wrapping around every hour
+    ///
+    /// Follows the wall clock of the source, so faces sharing a clock animate in phase
+    pub fn elapsed_seconds_wrapped(&self) -> f32 {
+        (self.now_ms.rem_euclid(PHASE_WRAP_MS) / 1000.0) as f32
+    }
+
+    /// Time since the last frame
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    pub fn delta_seconds_f64(&self) -> f64 {
+        self.delta.as_secs_f64()
+    }
+
+    /// Milliseconds since the unix epoch at the start of this frame
+    pub fn now_ms(&self) -> f64 {
+        self.now_ms
+    }
+
+    pub fn now_s(&self) -> f64 {
+        self.now_ms / 1000.0
+    }
+
+    /// Local date and time at the start of this frame
+    pub fn local_time(&self) -> DateTime<Local> {
+        Local
+            .timestamp_millis_opt(self.now_ms as i64)
+            .earliest()
+            .unwrap_or_else(Local::now)
+    }
+
+    /// Local hour of the day
+    pub fn hour(&self) -> u32 {
+        self.local_time().hour()
+    }
+
+    /// Whether faces sharing the clock see the same time
+    pub fn is_synced(&self) -> bool {
+        match &self.source {
+            ClockSource::Synced(clock) => clock.is_synced(),
+            ClockSource::Real | ClockSource::Simulated { .. } => false,
+        }
+    }
+
+    /// Read the source, `elapsed` is the time since the app started
+    fn tick(&mut self, elapsed: Duration, delta: Duration) {
+        self.elapsed = elapsed;
+        self.delta = delta;
+        self.now_ms = match &self.source {
+            ClockSource::Real => local_unix_ms(),
+            ClockSource::Simulated { start_unix_ms } => {
+                start_unix_ms + elapsed.as_secs_f64() * 1000.0
+            }
+            ClockSource::Synced(clock) => clock.now_ms(),
+        };
+    }
+}
+
+fn tick_face_clock(mut clock: ResMut<FaceClock>, time: Res<Time>) {
+    clock.tick(time.elapsed(), time.delta());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simulated_clock_follows_elapsed_time() {
+        let mut clock = FaceClock::new(ClockSource::simulated_from_hour(22));
+        assert_eq!(clock.hour(), 22);
+        let start_ms = clock.now_ms();
+
+        clock.tick(Duration::from_secs(3 * 3600), Duration::from_millis(20));
+        assert_eq!(clock.hour(), 1);
+        assert_eq!(clock.now_ms() - start_ms, 3.0 * 3600.0 * 1000.0);
+        assert_eq!(clock.elapsed(), Duration::from_secs(3 * 3600));
+        assert_eq!(clock.delta(), Duration::from_millis(20));
+        assert!(!clock.is_synced());
+
+        let authority = FaceClock::new(ClockSource::Synced(SharedClock::new(true)));
+        assert!(authority.is_synced());
+    }
+}
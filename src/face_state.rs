@@ -0,0 +1,76 @@
+use std::time::Duration;
+
+use bevy::{
+    diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin},
+    prelude::*,
+};
+
+use crate::{
+    display::DisplayPower,
+    expression::FaceExpression,
+    messaging::AckSender,
+    night_mode::NightMode,
+    noise_plugin::{NoiseGeneratorSettings, NoiseGeneratorSettingsUpdate},
+    scene::FaceScene,
+};
+
+/// Publish what the face currently shows on `face/state`
+///
+/// Other nodes can confirm their commands took effect and notice a stalled face
+/// without having to query it
+pub struct FaceStatePlugin {
+    /// How often the state is published
+    pub interval: Duration,
+}
+
+impl Plugin for FaceStatePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(FaceStateTimer(Timer::new(
+            self.interval,
+            TimerMode::Repeating,
+        )))
+        .add_systems(Last, publish_face_state);
+    }
+}
+
+pub const FACE_STATE_KEY: &str = "face/state";
+pub const DEFAULT_FACE_STATE_INTERVAL_MS: u64 = 1000;
+
+/// State published on `face/state`
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+pub struct FaceState {
+    pub scene: FaceScene,
+    pub expression: String,
+    pub wave: NoiseGeneratorSettingsUpdate,
+    pub display_on: bool,
+    pub night_mode: bool,
+    pub fps: Option<f64>,
+}
+
+#[derive(Resource)]
+struct FaceStateTimer(Timer);
+
+fn publish_face_state(
+    time: Res<Time<Real>>,
+    mut timer: ResMut<FaceStateTimer>,
+    ack_sender: Res<AckSender>,
+    scene: Res<State<FaceScene>>,
+    (settings, expression): (Res<NoiseGeneratorSettings>, Res<FaceExpression>),
+    (display_power, night_mode): (Res<DisplayPower>, Res<NightMode>),
+    diagnostics: Res<DiagnosticsStore>,
+) {
+    if !timer.0.tick(time.delta()).just_finished() {
+        return;
+    }
+    let state = FaceState {
+        scene: *scene.get(),
+        expression: expression.name.clone(),
+        wave: settings.to_update(),
+        display_on: display_power.on,
+        night_mode: night_mode.active,
+        fps: diagnostics
+            .get(&FrameTimeDiagnosticsPlugin::FPS)
+            .and_then(|fps| fps.smoothed()),
+    };
+    ack_sender.publish(FACE_STATE_KEY, &state);
+}
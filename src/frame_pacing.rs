@@ -0,0 +1,191 @@
+//! Stutter detection for correlating visual hitches with telemetry
+//!
+//! Frames taking longer than the threshold are counted and published on `face/event/stutter`
+//! with what the face was doing at the time
+
+use std::time::Duration;
+
+use bevy::{
+    diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin},
+    prelude::*,
+    sprite::Mesh2dHandle,
+};
+
+#[cfg(feature = "hw-telemetry")]
+use crate::hw_telemetry::HardwareTelemetry;
+use crate::{
+    maintenance::MaintenanceOverlay, messaging::AckSender, noise_plugin::NoiseWave,
+    scene::FaceScene,
+};
+
+pub struct FramePacingPlugin {
+    /// Frames taking longer than this count as a stutter
+    pub threshold: Duration,
+}
+
+impl Plugin for FramePacingPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(FramePacing {
+            threshold: self.threshold,
+            stutters: 0,
+            last_stutter: None,
+            last_published: None,
+            cpu_temperature_c: None,
+        })
+        .add_systems(Last, (detect_stutters, update_frame_pacing_section).chain());
+        #[cfg(feature = "hw-telemetry")]
+        app.add_event::<HardwareTelemetry>()
+            .add_systems(Update, record_cpu_temperature);
+    }
+}
+
+pub const STUTTER_EVENT_KEY: &str = "face/event/stutter";
+pub const DEFAULT_STUTTER_THRESHOLD_MS: u64 = 50;
+/// Loading assets and creating the window make the first frames slow
+const STARTUP_GRACE: Duration = Duration::from_secs(3);
+/// Stutters within this long of a published one are only counted, a struggling Pi
+/// stutters every frame and would flood the outbound queue
+const MIN_EVENT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Event published on `face/event/stutter`
+#[derive(serde::Serialize, Debug, Clone, PartialEq)]
+pub struct StutterEvent {
+    pub frame_ms: f64,
+    pub threshold_ms: f64,
+    /// Stutters since the face started, including the ones that weren't published
+    pub stutters: u64,
+    pub scene: FaceScene,
+    /// Points of the main wave line
+    pub wave_points: usize,
+    pub fps: Option<f64>,
+    /// Last reported cpu temperature, only known with the `hw-telemetry` feature
+    pub cpu_temperature_c: Option<f32>,
+}
+
+#[derive(Resource)]
+struct FramePacing {
+    threshold: Duration,
+    stutters: u64,
+    last_stutter: Option<Duration>,
+    /// Real time the last event was published at
+    last_published: Option<Duration>,
+    cpu_temperature_c: Option<f32>,
+}
+
+impl FramePacing {
+    /// Count a frame taking `delta` that ended at `now`, true if it should be published
+    fn record(&mut self, delta: Duration, now: Duration) -> bool {
+        if now < STARTUP_GRACE || delta <= self.threshold {
+            return false;
+        }
+        self.stutters += 1;
+        self.last_stutter = Some(delta);
+        if self
+            .last_published
+            .is_some_and(|published| now - published < MIN_EVENT_INTERVAL)
+        {
+            return false;
+        }
+        self.last_published = Some(now);
+        true
+    }
+}
+
+fn detect_stutters(
+    mut pacing: ResMut<FramePacing>,
+    time: Res<Time<Real>>,
+    ack_sender: Res<AckSender>,
+    scene: Res<State<FaceScene>>,
+    wave: Query<&Mesh2dHandle, With<NoiseWave>>,
+    meshes: Res<Assets<Mesh>>,
+    diagnostics: Res<DiagnosticsStore>,
+) {
+    let delta = time.delta();
+    if !pacing.record(delta, time.elapsed()) {
+        return;
+    }
+    let wave_points = wave
+        .iter()
+        .filter_map(|mesh| meshes.get(&mesh.0))
+        // line meshes have two vertices per point
+        .map(|mesh| mesh.count_vertices() / 2)
+        .sum();
+    let event = StutterEvent {
+        frame_ms: delta.as_secs_f64() * 1000.0,
+        threshold_ms: pacing.threshold.as_secs_f64() * 1000.0,
+        stutters: pacing.stutters,
+        scene: *scene.get(),
+        wave_points,
+        fps: diagnostics
+            .get(&FrameTimeDiagnosticsPlugin::FPS)
+            .and_then(|fps| fps.smoothed()),
+        cpu_temperature_c: pacing.cpu_temperature_c,
+    };
+    warn!(
+        frame_ms = event.frame_ms,
+        stutters = event.stutters,
+        scene = ?event.scene,
+        wave_points,
+        "Frame stutter"
+    );
+    ack_sender.publish(STUTTER_EVENT_KEY, &event);
+}
+
+#[cfg(feature = "hw-telemetry")]
+fn record_cpu_temperature(
+    mut events: EventReader<HardwareTelemetry>,
+    mut pacing: ResMut<FramePacing>,
+) {
+    if let Some(telemetry) = events.read().last() {
+        pacing.cpu_temperature_c = telemetry.cpu_temperature_c;
+    }
+}
+
+fn update_frame_pacing_section(
+    pacing: Res<FramePacing>,
+    mut overlay: ResMut<MaintenanceOverlay>,
+    mut shown: Local<Option<u64>>,
+) {
+    if *shown == Some(pacing.stutters) {
+        return;
+    }
+    *shown = Some(pacing.stutters);
+    let last_stutter = pacing.last_stutter.map_or_else(
+        || "none".to_owned(),
+        |frame| format!("{:.1} ms", frame.as_secs_f64() * 1000.0),
+    );
+    overlay.set_section(
+        "frame pacing",
+        format!(
+            "stutters: {}\nlast stutter: {last_stutter}",
+            pacing.stutters
+        ),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stutter_events_are_rate_limited() {
+        let mut pacing = FramePacing {
+            threshold: Duration::from_millis(50),
+            stutters: 0,
+            last_stutter: None,
+            last_published: None,
+            cpu_temperature_c: None,
+        };
+        let slow = Duration::from_millis(80);
+        let at = |ms| STARTUP_GRACE + Duration::from_millis(ms);
+
+        assert!(!pacing.record(slow, Duration::from_secs(1)));
+        assert!(!pacing.record(Duration::from_millis(16), at(0)));
+        assert_eq!(pacing.stutters, 0);
+
+        assert!(pacing.record(slow, at(100)));
+        assert!(!pacing.record(slow, at(200)));
+        assert!(pacing.record(slow, at(1100)));
+        assert_eq!(pacing.stutters, 3);
+    }
+}
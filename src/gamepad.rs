@@ -0,0 +1,122 @@
+use std::time::Duration;
+
+use bevy::{input::gamepad::GamepadButtonType, prelude::*};
+
+use crate::{
+    excitement::Excitement,
+    gaze::GazeMessage,
+    heartbeat::Heartbeat,
+    params::Tunable,
+    priority::Priority,
+    scene::{FaceScene, SceneRequests},
+};
+
+/// Puppeteer the face with a gamepad for booth demos
+///
+/// The left stick moves the gaze, the right trigger sets the excitement,
+/// south and east show the greeting and dizzy scenes, north toggles the heartbeat
+/// and start goes back to the wave
+pub struct GamepadPlugin;
+
+impl Plugin for GamepadPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (gamepad_gaze, gamepad_excitement, gamepad_buttons));
+    }
+}
+
+/// Stick deflection below this counts as centered
+const STICK_DEADZONE: f32 = 0.15;
+/// Trigger changes below this are ignored so a resting trigger doesn't fight other publishers
+const TRIGGER_THRESHOLD: f32 = 0.02;
+const EXPRESSION_DURATION: Duration = Duration::from_secs(3);
+
+fn gamepad_gaze(
+    gamepads: Res<Gamepads>,
+    axes: Res<Axis<GamepadAxis>>,
+    mut stick_active: Local<bool>,
+    mut gaze_messages: EventWriter<GazeMessage>,
+) {
+    let Some(gamepad) = gamepads.iter().next() else {
+        return;
+    };
+    let axis = |axis_type| {
+        axes.get(GamepadAxis::new(gamepad, axis_type))
+            .unwrap_or_default()
+    };
+    let stick = Vec2::new(
+        axis(GamepadAxisType::LeftStickX),
+        axis(GamepadAxisType::LeftStickY),
+    );
+    if stick.length() > STICK_DEADZONE {
+        *stick_active = true;
+        gaze_messages.send(GazeMessage {
+            x: stick.x,
+            y: stick.y,
+        });
+    } else if *stick_active {
+        // look straight ahead once the stick is released
+        *stick_active = false;
+        gaze_messages.send(GazeMessage { x: 0.0, y: 0.0 });
+    }
+}
+
+fn gamepad_excitement(
+    gamepads: Res<Gamepads>,
+    triggers: Res<Axis<GamepadButton>>,
+    mut last_trigger: Local<Option<f32>>,
+    mut excitement: ResMut<Excitement>,
+) {
+    let Some(gamepad) = gamepads.iter().next() else {
+        return;
+    };
+    let Some(trigger) = triggers.get(GamepadButton::new(
+        gamepad,
+        GamepadButtonType::RightTrigger2,
+    )) else {
+        return;
+    };
+    if last_trigger.is_some_and(|last| (last - trigger).abs() < TRIGGER_THRESHOLD) {
+        return;
+    }
+    let first_reading = last_trigger.replace(trigger).is_none();
+    if first_reading && trigger < TRIGGER_THRESHOLD {
+        return;
+    }
+    excitement.level = trigger.into();
+    excitement.sanitize();
+}
+
+fn gamepad_buttons(
+    gamepads: Res<Gamepads>,
+    buttons: Res<ButtonInput<GamepadButton>>,
+    mut scene_requests: ResMut<SceneRequests>,
+    mut heartbeat: ResMut<Heartbeat>,
+) {
+    for gamepad in gamepads.iter() {
+        let pressed = |button_type| buttons.just_pressed(GamepadButton::new(gamepad, button_type));
+        if pressed(GamepadButtonType::South) {
+            scene_requests.push(
+                Priority::Commanded,
+                FaceScene::Greeting,
+                Some(EXPRESSION_DURATION),
+            );
+        }
+        if pressed(GamepadButtonType::East) {
+            scene_requests.push(
+                Priority::Commanded,
+                FaceScene::Dizzy,
+                Some(EXPRESSION_DURATION),
+            );
+        }
+        if pressed(GamepadButtonType::North) {
+            heartbeat.enabled = !heartbeat.enabled;
+            info!(
+                enabled = heartbeat.enabled,
+                "Toggling heartbeat from gamepad"
+            );
+        }
+        if pressed(GamepadButtonType::Start) {
+            scene_requests.clear(Priority::Commanded);
+        }
+    }
+}
@@ -0,0 +1,474 @@
+use std::time::Duration;
+
+use bevy::{prelude::*, utils::HashMap, window::PrimaryWindow};
+
+use crate::{
+    face_clock::FaceClock,
+    messaging::{parse_json_payload, AckSender, CommandAck, OverflowPolicy},
+    params::{RegisterTunable, Tunable},
+    scene::FaceScene,
+    spring::{sanitize_spring, Spring},
+    topics::RegisterTopic,
+};
+
+/// Where the eyes are looking
+///
+/// Set over `face/gaze` or by following the mouse cursor in dev mode.
+/// With `avert` the eyes glance away now and then while fixating the same point,
+/// timing is the `gaze_aversion` tunable.
+/// The eyes follow smoothly, with saccades or on a spring depending on the scene, set over `face/gaze/motion`.
+/// The spring is the `gaze_spring` tunable
+pub struct GazePlugin {
+    pub follow_mouse: bool,
+    pub avert: bool,
+}
+
+impl Plugin for GazePlugin {
+    fn build(&self, app: &mut App) {
+        // gaze targets are state so only the latest one matters
+        app.add_topic_with::<GazeMessage>(
+            "face/gaze",
+            Some(OverflowPolicy::LatestWins),
+            |payload, _| parse_json_payload(payload),
+        )
+        .init_resource::<Gaze>()
+        .insert_resource(GazeAversion {
+            enabled: self.avert,
+            ..default()
+        })
+        .init_resource::<AversionState>()
+        .init_resource::<GazeMotion>()
+        .init_resource::<GazeMovementState>()
+        .init_resource::<GazeSpring>()
+        .register_tunable::<GazeAversion>("gaze_aversion")
+        .register_tunable::<GazeSpring>("gaze_spring")
+        .add_topic::<GazeMotionUpdate>("face/gaze/motion")
+        .add_systems(
+            Update,
+            (
+                process_gaze_messages,
+                process_gaze_motion_updates,
+                avert_gaze,
+                move_gaze,
+            )
+                .chain(),
+        );
+        if self.follow_mouse {
+            app.add_systems(Update, follow_mouse_cursor.before(process_gaze_messages));
+        }
+    }
+}
+
+/// Point to look at with both coordinates between -1.0 and 1.0
+#[derive(Event, serde::Deserialize, serde::Serialize, clap::Args, Debug, Clone, Copy)]
+pub struct GazeMessage {
+    /// Positive is to the right of the screen
+    #[arg(long, allow_negative_numbers = true)]
+    pub x: f32,
+    /// Positive is toward the top of the screen
+    #[serde(default)]
+    #[arg(long, allow_negative_numbers = true, default_value_t = 0.0)]
+    pub y: f32,
+}
+
+/// Current gaze target with both coordinates between -1.0 and 1.0
+#[derive(Resource, Default)]
+pub struct Gaze {
+    /// Where the eyes are currently pointed on their way to the target
+    pub position: Vec2,
+    /// Where the eyes look, including glances away
+    pub target: Vec2,
+    /// Latest point asked for over `face/gaze`
+    pub requested: Vec2,
+}
+
+/// How the eyes move toward a new target
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum GazeMovement {
+    /// Ease toward the target
+    #[default]
+    Smooth,
+    /// Jump to far targets with a small overshoot and corrections, ease toward near ones
+    Saccade,
+    /// Pulled toward the target by a spring, keeps its momentum when tracking moving targets
+    Spring,
+}
+
+/// Movement of the eyes per scene, scenes that aren't listed move smoothly
+#[derive(Resource, Default, serde::Serialize)]
+pub struct GazeMotion {
+    pub scenes: HashMap<FaceScene, GazeMovement>,
+}
+
+#[derive(Event, serde::Deserialize)]
+pub struct GazeMotionUpdate {
+    /// Replaces the movement of the listed scenes
+    #[serde(default)]
+    scenes: HashMap<FaceScene, GazeMovement>,
+    /// Key to publish an acknowledgement to once applied
+    #[serde(default)]
+    reply_to: Option<String>,
+}
+
+/// How quickly smooth movement follows the target
+const GAZE_SPEED: f32 = 5.0;
+/// Targets at least this far away are reached with a saccade
+const SACCADE_MIN_DISTANCE: f32 = 0.2;
+/// Share of the jump the eyes overshoot by
+const SACCADE_OVERSHOOT: f32 = 0.12;
+/// Share of the overshoot the first correction undershoots by
+const SACCADE_CORRECTION: f32 = 0.3;
+/// Time to reach each waypoint: the overshoot, the correction and the target
+const SACCADE_STEP_DURATIONS: [Duration; 3] = [
+    Duration::from_millis(50),
+    Duration::from_millis(90),
+    Duration::from_millis(70),
+];
+
+/// Fast jump past the target followed by small corrections back onto it
+#[derive(Debug, Clone)]
+struct Saccade {
+    from: Vec2,
+    waypoints: [Vec2; 3],
+    step: usize,
+    elapsed: Duration,
+}
+
+impl Saccade {
+    fn new(from: Vec2, to: Vec2) -> Self {
+        let jump = to - from;
+        let overshoot = to + jump * SACCADE_OVERSHOOT;
+        let correction = to - jump * SACCADE_OVERSHOOT * SACCADE_CORRECTION;
+        Self {
+            from,
+            waypoints: [
+                overshoot.clamp(Vec2::NEG_ONE, Vec2::ONE),
+                correction.clamp(Vec2::NEG_ONE, Vec2::ONE),
+                to,
+            ],
+            step: 0,
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    fn target(&self) -> Vec2 {
+        self.waypoints[self.waypoints.len() - 1]
+    }
+
+    /// Position after `delta` more time, `None` once the target was reached
+    fn advance(&mut self, delta: Duration) -> Option<Vec2> {
+        self.elapsed += delta;
+        while let Some(duration) = SACCADE_STEP_DURATIONS.get(self.step) {
+            if self.elapsed < *duration {
+                let start = match self.step {
+                    0 => self.from,
+                    step => self.waypoints[step - 1],
+                };
+                let progress = self.elapsed.as_secs_f32() / duration.as_secs_f32();
+                // eyes decelerate into each waypoint
+                let eased = 1.0 - (1.0 - progress).powi(2);
+                return Some(start.lerp(self.waypoints[self.step], eased));
+            }
+            self.elapsed -= *duration;
+            self.step += 1;
+        }
+        None
+    }
+}
+
+#[derive(Resource, Default)]
+struct GazeMovementState {
+    saccade: Option<Saccade>,
+    /// Only moving while the spring movement is used
+    spring: Option<Spring>,
+}
+
+/// Spring the eyes follow the target on with the spring movement
+#[derive(Resource, Reflect, Clone, Debug)]
+#[reflect(Resource)]
+pub struct GazeSpring {
+    pub stiffness: f32,
+    /// Below 2 * sqrt(stiffness) the eyes overshoot a little before settling
+    pub damping: f32,
+}
+
+impl Default for GazeSpring {
+    fn default() -> Self {
+        Self {
+            stiffness: 150.0,
+            damping: 20.0,
+        }
+    }
+}
+
+impl Tunable for GazeSpring {
+    fn sanitize(&mut self) {
+        let defaults = Self::default();
+        sanitize_spring(
+            &mut self.stiffness,
+            &mut self.damping,
+            (defaults.stiffness, defaults.damping),
+        );
+    }
+}
+
+/// Longest fixation or aversion in seconds
+const MAX_AVERSION_TIMING_S: f32 = 10.0 * 60.0;
+
+/// Brief glances away while fixating the same point for long, so the face doesn't stare
+#[derive(Resource, Reflect, Clone, Debug)]
+#[reflect(Resource)]
+pub struct GazeAversion {
+    pub enabled: bool,
+    /// Seconds of fixation before glancing away, varies between the two
+    pub min_fixation_s: f32,
+    pub max_fixation_s: f32,
+    /// Seconds spent looking away, varies between the two
+    pub min_aversion_s: f32,
+    pub max_aversion_s: f32,
+    /// How far the eyes glance away in gaze units
+    pub distance: f32,
+    /// Targets moving less than this still count as the same fixation
+    pub fixation_radius: f32,
+}
+
+impl Default for GazeAversion {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_fixation_s: 3.0,
+            max_fixation_s: 7.0,
+            min_aversion_s: 0.4,
+            max_aversion_s: 1.2,
+            distance: 0.5,
+            fixation_radius: 0.15,
+        }
+    }
+}
+
+impl Tunable for GazeAversion {
+    fn sanitize(&mut self) {
+        let defaults = Self::default();
+        let positive = |value: f32, default: f32| {
+            if value.is_finite() && value > 0.0 {
+                value
+            } else {
+                default
+            }
+        };
+        let seconds =
+            |value: f32, default: f32| positive(value, default).min(MAX_AVERSION_TIMING_S);
+        self.min_fixation_s = seconds(self.min_fixation_s, defaults.min_fixation_s);
+        self.max_fixation_s = seconds(self.max_fixation_s, defaults.max_fixation_s);
+        self.min_aversion_s = seconds(self.min_aversion_s, defaults.min_aversion_s);
+        self.max_aversion_s = seconds(self.max_aversion_s, defaults.max_aversion_s);
+        self.max_fixation_s = self.max_fixation_s.max(self.min_fixation_s);
+        self.max_aversion_s = self.max_aversion_s.max(self.min_aversion_s);
+        self.distance = positive(self.distance, defaults.distance).min(2.0);
+        self.fixation_radius = positive(self.fixation_radius, defaults.fixation_radius);
+    }
+}
+
+/// Golden ratio steps spread the timings evenly without an rng, keeping replays deterministic
+const AVERSION_SEQUENCE_STEP: f32 = 0.618_034;
+
+#[derive(Resource, Default)]
+struct AversionState {
+    /// Point being fixated and since when
+    fixation: Option<(Vec2, Duration)>,
+    /// Glancing away by this offset until the given time
+    averted: Option<(Vec2, Duration)>,
+    /// Aversions so far, drives the variation between them
+    count: u32,
+}
+
+impl AversionState {
+    /// Value between 0.0 and 1.0 that changes with every aversion
+    fn variation(&self, salt: f32) -> f32 {
+        (self.count as f32 * AVERSION_SEQUENCE_STEP + salt).fract()
+    }
+
+    fn fixation_time(&self, aversion: &GazeAversion) -> Duration {
+        Duration::from_secs_f32(
+            aversion.min_fixation_s
+                + (aversion.max_fixation_s - aversion.min_fixation_s) * self.variation(0.0),
+        )
+    }
+}
+
+fn process_gaze_messages(mut messages: EventReader<GazeMessage>, mut gaze: ResMut<Gaze>) {
+    for message in messages.read() {
+        let target = Vec2::new(message.x, message.y);
+        if !target.is_finite() {
+            warn!(?message, "Ignoring invalid gaze");
+            continue;
+        }
+        gaze.requested = target.clamp(Vec2::NEG_ONE, Vec2::ONE);
+    }
+}
+
+fn process_gaze_motion_updates(
+    mut messages: EventReader<GazeMotionUpdate>,
+    mut motion: ResMut<GazeMotion>,
+    ack_sender: Res<AckSender>,
+) {
+    for (message, id) in messages.read_with_id() {
+        let _trace = ack_sender.trace(id);
+        for (scene, movement) in &message.scenes {
+            info!(?scene, ?movement, "Updating gaze movement");
+            motion.scenes.insert(*scene, *movement);
+        }
+        ack_sender.send(message.reply_to.as_deref(), CommandAck::applied(&*motion));
+    }
+}
+
+fn avert_gaze(
+    mut gaze: ResMut<Gaze>,
+    mut state: ResMut<AversionState>,
+    aversion: Res<GazeAversion>,
+    clock: Res<FaceClock>,
+) {
+    let now = clock.elapsed();
+    let requested = gaze.requested;
+    let moved = state
+        .fixation
+        .is_none_or(|(point, _)| requested.distance(point) > aversion.fixation_radius);
+    if !aversion.enabled || moved {
+        // a new target also ends a glance early
+        state.averted = None;
+        state.fixation = aversion.enabled.then_some((requested, now));
+    } else if let Some((_, until)) = state.averted {
+        if now >= until {
+            state.averted = None;
+            state.fixation = Some((requested, now));
+        }
+    } else if let Some((_, since)) = state.fixation {
+        if now - since >= state.fixation_time(&aversion) {
+            // mostly sideways and a little down
+            let side = if state.variation(0.9) < 0.5 {
+                1.0
+            } else {
+                -1.0
+            };
+            let down = -0.5 * state.variation(0.3);
+            let offset = Vec2::new(side, down).normalize() * aversion.distance;
+            let duration = aversion.min_aversion_s
+                + (aversion.max_aversion_s - aversion.min_aversion_s) * state.variation(0.6);
+            debug!(?offset, duration, "Glancing away");
+            state.averted = Some((offset, now + Duration::from_secs_f32(duration)));
+            state.count += 1;
+        }
+    }
+
+    let target = match state.averted {
+        Some((offset, _)) => (requested + offset).clamp(Vec2::NEG_ONE, Vec2::ONE),
+        None => requested,
+    };
+    if gaze.target != target {
+        gaze.target = target;
+    }
+}
+
+/// Move the eyes toward the target the way the current scene asks for
+fn move_gaze(
+    mut gaze: ResMut<Gaze>,
+    mut state: ResMut<GazeMovementState>,
+    (motion, spring): (Res<GazeMotion>, Res<GazeSpring>),
+    scene: Res<State<FaceScene>>,
+    clock: Res<FaceClock>,
+) {
+    let target = gaze.target;
+    let movement = motion.scenes.get(scene.get()).copied().unwrap_or_default();
+    // a saccade to an old target is abandoned once the target moves on
+    if state
+        .saccade
+        .as_ref()
+        .is_some_and(|saccade| movement != GazeMovement::Saccade || saccade.target() != target)
+    {
+        state.saccade = None;
+    }
+    if movement == GazeMovement::Saccade
+        && state.saccade.is_none()
+        && gaze.position.distance(target) >= SACCADE_MIN_DISTANCE
+    {
+        state.saccade = Some(Saccade::new(gaze.position, target));
+    }
+
+    if movement != GazeMovement::Spring {
+        state.spring = None;
+    }
+
+    let position = match &mut state.saccade {
+        Some(saccade) => match saccade.advance(clock.delta()) {
+            Some(position) => position,
+            None => {
+                state.saccade = None;
+                target
+            }
+        },
+        None if movement == GazeMovement::Spring => {
+            let position = gaze.position;
+            state
+                .spring
+                .get_or_insert_with(|| Spring::new(position))
+                .step(
+                    target,
+                    spring.stiffness,
+                    spring.damping,
+                    clock.delta_seconds(),
+                )
+        }
+        None => {
+            let blend = (GAZE_SPEED * clock.delta_seconds()).min(1.0);
+            gaze.position.lerp(target, blend)
+        }
+    };
+    if gaze.position != position {
+        gaze.position = position;
+    }
+}
+
+fn follow_mouse_cursor(
+    mut cursor_moved: EventReader<CursorMoved>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut gaze_messages: EventWriter<GazeMessage>,
+) {
+    let Some(cursor_moved) = cursor_moved.read().last() else {
+        return;
+    };
+    let Ok(window) = windows.get(cursor_moved.window) else {
+        return;
+    };
+    // window coordinates start in the top left corner
+    let position = cursor_moved.position / Vec2::new(window.width(), window.height());
+    gaze_messages.send(GazeMessage {
+        x: position.x * 2.0 - 1.0,
+        y: 1.0 - position.y * 2.0,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saccade_overshoots_and_settles_on_target() {
+        let mut saccade = Saccade::new(Vec2::ZERO, Vec2::new(0.5, 0.0));
+        let mut furthest = 0.0f32;
+        while let Some(position) = saccade.advance(Duration::from_millis(5)) {
+            furthest = furthest.max(position.x);
+        }
+        assert!(furthest > 0.5);
+        assert!(furthest <= 0.5 * (1.0 + SACCADE_OVERSHOOT) + 1e-6);
+        assert_eq!(saccade.advance(Duration::from_millis(5)), None);
+    }
+
+    #[test]
+    fn saccade_is_quick() {
+        let mut saccade = Saccade::new(Vec2::ZERO, Vec2::ONE);
+        let total: Duration = SACCADE_STEP_DURATIONS.iter().sum();
+        assert!(saccade.advance(total - Duration::from_millis(1)).is_some());
+        assert!(saccade.advance(Duration::from_millis(1)).is_none());
+    }
+}
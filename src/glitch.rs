@@ -0,0 +1,279 @@
+use std::time::Duration;
+
+use bevy::{
+    prelude::*,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+};
+
+use crate::{
+    face_clock::FaceClock,
+    messaging::{AckSender, CommandAck},
+    noise_plugin::NoiseWave,
+    topics::RegisterTopic,
+};
+
+/// Glitching wave for dramatizing errors or a low battery
+///
+/// `face/glitch` tears the wave sideways, drops segments of it
+/// and splits it into red and cyan copies for a while
+pub struct GlitchPlugin;
+
+impl Plugin for GlitchPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_topic::<GlitchMessage>("face/glitch")
+            .init_resource::<Glitch>()
+            .add_systems(
+                Update,
+                (
+                    spawn_rgb_split,
+                    process_glitch_messages,
+                    advance_glitch,
+                    update_rgb_split,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Glitches are cut off after this long
+const MAX_GLITCH_DURATION: Duration = Duration::from_secs(60);
+const DEFAULT_GLITCH_DURATION: Duration = Duration::from_secs(1);
+/// Tears jump to new places this often
+const GLITCH_PATTERN_INTERVAL: Duration = Duration::from_millis(60);
+/// Tears and drops at full intensity
+const MAX_TEARS: f32 = 6.0;
+const MAX_DROPS: f32 = 4.0;
+/// Longest tear and drop as a share of the wave at full intensity
+const MAX_TEAR_LENGTH: f32 = 0.15;
+const MAX_DROP_LENGTH: f32 = 0.08;
+/// Largest tear offset in pixels at full intensity
+const MAX_TEAR_OFFSET: Vec2 = Vec2::new(60.0, 40.0);
+/// Offset of the red and cyan copies in pixels at full intensity
+const MAX_SPLIT_OFFSET: f32 = 12.0;
+const SPLIT_COLORS: [Color; 2] = [Color::RED, Color::CYAN];
+
+#[derive(Event, serde::Deserialize, serde::Serialize, clap::Args, Debug)]
+pub struct GlitchMessage {
+    /// Between 0.0 for none and 1.0 for heavy glitching
+    #[arg(long, default_value_t = 0.5)]
+    pub intensity: f32,
+    /// Stop glitching after this many seconds, defaults to a single second
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub duration_s: Option<f32>,
+    /// Key to publish an acknowledgement to once applied
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub reply_to: Option<String>,
+}
+
+/// Current glitch applied to the wave
+#[derive(Resource, Default)]
+pub struct Glitch {
+    intensity: f32,
+    remaining: Duration,
+    /// Picks where the tears are, changes every few frames
+    pattern: u32,
+    since_pattern: Duration,
+}
+
+impl Glitch {
+    pub fn is_active(&self) -> bool {
+        self.intensity > 0.0 && !self.remaining.is_zero()
+    }
+
+    /// Tear parts of the wave sideways and return the width scale of every point,
+    /// 0.0 for dropped points
+    pub fn distort(&self, points: &mut [Vec2]) -> Vec<f32> {
+        let mut widths = vec![1.0; points.len()];
+        if !self.is_active() || points.is_empty() {
+            return widths;
+        }
+        let count = points.len() as f32;
+        let tears = (MAX_TEARS * self.intensity).ceil() as u32;
+        for tear in 0..tears {
+            let range = self.range(tear * 4, count * MAX_TEAR_LENGTH * self.intensity, count);
+            let offset = Vec2::new(
+                self.random(tear * 4 + 2) * 2.0 - 1.0,
+                self.random(tear * 4 + 3) * 2.0 - 1.0,
+            ) * MAX_TEAR_OFFSET
+                * self.intensity;
+            for point in &mut points[range] {
+                *point += offset;
+            }
+        }
+        let drops = (MAX_DROPS * self.intensity).ceil() as u32;
+        for drop in 0..drops {
+            let seed = 1000 + drop * 2;
+            let range = self.range(seed, count * MAX_DROP_LENGTH * self.intensity, count);
+            widths[range].fill(0.0);
+        }
+        widths
+    }
+
+    /// Offset of the red and cyan copies
+    pub fn split_offset(&self) -> f32 {
+        if self.is_active() {
+            MAX_SPLIT_OFFSET * self.intensity * (0.5 + self.random(2000))
+        } else {
+            0.0
+        }
+    }
+
+    fn range(&self, seed: u32, max_length: f32, count: f32) -> std::ops::Range<usize> {
+        let start = (self.random(seed) * count) as usize;
+        let length = (self.random(seed + 1) * max_length).ceil() as usize;
+        start..(start + length).min(count as usize)
+    }
+
+    /// Value between 0.0 and 1.0 that only depends on the pattern and `seed`
+    fn random(&self, seed: u32) -> f32 {
+        // wang hash, an rng would make replays differ
+        let mut value = self.pattern.wrapping_mul(0x9e37_79b9) ^ seed;
+        value = (value ^ 61) ^ (value >> 16);
+        value = value.wrapping_mul(9);
+        value ^= value >> 4;
+        value = value.wrapping_mul(0x27d4_eb2d);
+        value ^= value >> 15;
+        value as f32 / u32::MAX as f32
+    }
+}
+
+/// Copy of the wave behind it, shifted sideways while glitching
+#[derive(Component)]
+struct RgbSplitLayer {
+    direction: f32,
+}
+
+fn spawn_rgb_split(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    waves: Query<(Entity, &Mesh2dHandle), Added<NoiseWave>>,
+) {
+    for (wave, mesh) in waves.iter() {
+        commands.entity(wave).with_children(|parent| {
+            for (color, direction) in SPLIT_COLORS.into_iter().zip([-1.0, 1.0]) {
+                parent.spawn((
+                    MaterialMesh2dBundle {
+                        mesh: mesh.clone(),
+                        material: materials.add(ColorMaterial::from(color)),
+                        transform: Transform::from_xyz(0.0, 0.0, -0.1),
+                        visibility: Visibility::Hidden,
+                        ..default()
+                    },
+                    RgbSplitLayer { direction },
+                ));
+            }
+        });
+    }
+}
+
+fn process_glitch_messages(
+    mut messages: EventReader<GlitchMessage>,
+    mut glitch: ResMut<Glitch>,
+    ack_sender: Res<AckSender>,
+) {
+    for (message, id) in messages.read_with_id() {
+        let _trace = ack_sender.trace(id);
+        let intensity = if message.intensity.is_finite() {
+            message.intensity.clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let duration = match message.duration_s {
+            Some(duration_s) if duration_s.is_finite() && duration_s > 0.0 => {
+                Duration::from_secs_f32(duration_s.min(MAX_GLITCH_DURATION.as_secs_f32()))
+            }
+            _ => DEFAULT_GLITCH_DURATION,
+        };
+        info!(intensity, ?duration, "Glitching");
+        glitch.intensity = intensity;
+        glitch.remaining = duration;
+        ack_sender.send(
+            message.reply_to.as_deref(),
+            CommandAck::applied(serde_json::json!({
+                "intensity": intensity,
+                "duration_s": duration.as_secs_f32(),
+            })),
+        );
+    }
+}
+
+fn advance_glitch(mut glitch: ResMut<Glitch>, clock: Res<FaceClock>) {
+    if !glitch.is_active() {
+        return;
+    }
+    glitch.remaining = glitch.remaining.saturating_sub(clock.delta());
+    glitch.since_pattern += clock.delta();
+    if glitch.since_pattern >= GLITCH_PATTERN_INTERVAL {
+        glitch.since_pattern = Duration::ZERO;
+        glitch.pattern = glitch.pattern.wrapping_add(1);
+    }
+}
+
+/// Keep the copies on the latest wave mesh and shift them apart
+fn update_rgb_split(
+    glitch: Res<Glitch>,
+    waves: Query<&Mesh2dHandle, With<NoiseWave>>,
+    mut layers: Query<
+        (
+            &Parent,
+            &mut Mesh2dHandle,
+            &mut Transform,
+            &mut Visibility,
+            &RgbSplitLayer,
+        ),
+        Without<NoiseWave>,
+    >,
+) {
+    let offset = glitch.split_offset();
+    for (parent, mut mesh, mut transform, mut visibility, layer) in layers.iter_mut() {
+        if offset == 0.0 {
+            if *visibility != Visibility::Hidden {
+                *visibility = Visibility::Hidden;
+            }
+            continue;
+        }
+        *visibility = Visibility::Inherited;
+        transform.translation.x = offset * layer.direction;
+        if let Ok(wave_mesh) = waves.get(parent.get()) {
+            if mesh.0 != wave_mesh.0 {
+                *mesh = wave_mesh.clone();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glitch(intensity: f32) -> Glitch {
+        Glitch {
+            intensity,
+            remaining: Duration::from_secs(1),
+            pattern: 7,
+            since_pattern: Duration::ZERO,
+        }
+    }
+
+    #[test]
+    fn inactive_glitch_keeps_the_wave() {
+        let mut points = vec![Vec2::ZERO; 100];
+        let widths = glitch(0.0).distort(&mut points);
+        assert!(points.iter().all(|point| *point == Vec2::ZERO));
+        assert!(widths.iter().all(|width| *width == 1.0));
+    }
+
+    #[test]
+    fn glitch_tears_and_drops_within_bounds() {
+        let mut points = vec![Vec2::ZERO; 500];
+        let widths = glitch(1.0).distort(&mut points);
+        assert_eq!(widths.len(), points.len());
+        assert!(widths.contains(&0.0));
+        assert!(points.iter().any(|point| *point != Vec2::ZERO));
+        assert!(points
+            .iter()
+            .all(|point| point.abs().cmple(MAX_TEAR_OFFSET * MAX_TEARS).all()));
+    }
+}
@@ -0,0 +1,171 @@
+use std::{f32::consts::PI, time::Duration};
+
+use bevy::{prelude::*, utils::HashMap};
+use bevy_prototype_lyon::prelude::*;
+
+use crate::{
+    dnd::DoNotDisturb,
+    eyes::EyeSides,
+    face_clock::FaceClock,
+    gaze::{Gaze, GazeMessage},
+    priority::Priority,
+    scene::{FaceScene, SceneRequests},
+    topics::RegisterTopic,
+};
+
+/// Greet people approaching the robot
+///
+/// Shows happy eyes looking toward the person
+pub struct GreetingPlugin;
+
+impl Plugin for GreetingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_topic::<ProximityMessage>("face/proximity")
+            .init_resource::<Greetings>()
+            .init_resource::<DoNotDisturb>()
+            .add_systems(OnEnter(FaceScene::Greeting), spawn_greeting_eyes)
+            .add_systems(OnExit(FaceScene::Greeting), despawn_greeting_eyes)
+            .add_systems(
+                Update,
+                (
+                    process_proximity_messages,
+                    look_at_person.run_if(in_state(FaceScene::Greeting)),
+                ),
+            );
+    }
+}
+
+/// People closer than this are greeted
+const GREETING_DISTANCE_M: f32 = 1.5;
+const GREETING_DURATION: Duration = Duration::from_secs(3);
+/// Don't greet the same person again within this time
+const GREETING_COOLDOWN: Duration = Duration::from_secs(60);
+
+const GREETING_EYE_RADIUS: f32 = 40.0;
+const GREETING_EYE_SEGMENTS: usize = 24;
+const GREETING_LINE_WIDTH: f32 = 6.0;
+/// Eye offset from the center of the screen
+const GREETING_EYE_OFFSET: Vec2 = Vec2::new(110.0, 150.0);
+/// Eye movement in pixels when looking fully to the side
+const GAZE_OFFSET: f32 = 80.0;
+
+/// Person detected near the robot
+#[derive(Event, serde::Deserialize)]
+pub struct ProximityMessage {
+    /// Stable id of the tracked person, all people without one share a cooldown
+    #[serde(default)]
+    pub person_id: Option<String>,
+    pub distance_m: f32,
+    /// Direction of the person, 0 is straight ahead and positive is to the right of the screen
+    #[serde(default)]
+    pub bearing_rad: f32,
+}
+
+#[derive(Resource, Default)]
+struct Greetings {
+    /// Time each person was last greeted at
+    last_greeted: HashMap<Option<String>, Duration>,
+}
+
+#[derive(Component)]
+struct GreetingEye {
+    base: Vec2,
+}
+
+fn process_proximity_messages(
+    mut messages: EventReader<ProximityMessage>,
+    mut greetings: ResMut<Greetings>,
+    mut scene_requests: ResMut<SceneRequests>,
+    mut gaze_messages: EventWriter<GazeMessage>,
+    scene: Res<State<FaceScene>>,
+    (clock, do_not_disturb): (Res<FaceClock>, Res<DoNotDisturb>),
+) {
+    let now = clock.elapsed();
+    greetings
+        .last_greeted
+        .retain(|_, greeted_at| now - *greeted_at < GREETING_COOLDOWN);
+
+    for message in messages.read() {
+        if !message.distance_m.is_finite() || !message.bearing_rad.is_finite() {
+            warn!(person_id = ?message.person_id, "Ignoring invalid proximity reading");
+            continue;
+        }
+        let look_at_person = GazeMessage {
+            x: message.bearing_rad.sin(),
+            y: 0.0,
+        };
+        if *scene.get() == FaceScene::Greeting {
+            // keep following whoever is being greeted
+            gaze_messages.send(look_at_person);
+        }
+        if message.distance_m > GREETING_DISTANCE_M
+            || do_not_disturb.active()
+            || greetings.last_greeted.contains_key(&message.person_id)
+        {
+            continue;
+        }
+        info!(person_id = ?message.person_id, distance_m = message.distance_m, "Greeting person");
+        greetings
+            .last_greeted
+            .insert(message.person_id.clone(), now);
+        gaze_messages.send(look_at_person);
+        scene_requests.push(
+            Priority::Reaction,
+            FaceScene::Greeting,
+            Some(GREETING_DURATION),
+        );
+    }
+}
+
+/// Happy eyes are upper half circles, squashed as they close
+fn build_greeting_eye(vertical_scale: f32) -> Path {
+    let points = (0..=GREETING_EYE_SEGMENTS)
+        .map(|segment| {
+            let angle = segment as f32 / GREETING_EYE_SEGMENTS as f32 * PI;
+            Vec2::from_angle(angle) * GREETING_EYE_RADIUS * Vec2::new(1.0, vertical_scale)
+        })
+        .collect();
+    GeometryBuilder::build_as(&shapes::Polygon {
+        points,
+        closed: false,
+    })
+}
+
+fn spawn_greeting_eyes(mut commands: Commands, sides: Res<EyeSides>) {
+    for side in [-1.0, 1.0] {
+        let base = Vec2::new(GREETING_EYE_OFFSET.x * side, GREETING_EYE_OFFSET.y);
+        commands.spawn((
+            ShapeBundle {
+                path: build_greeting_eye(sides.side(base.x).vertical_scale()),
+                spatial: SpatialBundle::from_transform(Transform::from_translation(
+                    base.extend(1.0),
+                )),
+                ..default()
+            },
+            Stroke::new(Color::WHITE, GREETING_LINE_WIDTH),
+            Fill::color(Color::NONE),
+            GreetingEye { base },
+        ));
+    }
+}
+
+fn despawn_greeting_eyes(mut commands: Commands, query: Query<Entity, With<GreetingEye>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn look_at_person(
+    gaze: Res<Gaze>,
+    sides: Res<EyeSides>,
+    mut query: Query<(&mut Transform, &mut Path, &GreetingEye)>,
+) {
+    for (mut transform, mut path, eye) in query.iter_mut() {
+        let shape = sides.side(eye.base.x);
+        let position = eye.base + gaze.position * GAZE_OFFSET + shape.offset();
+        transform.translation = position.extend(transform.translation.z);
+        if sides.is_changed() {
+            *path = build_greeting_eye(shape.vertical_scale());
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use std::{
+    sync::{Arc, Mutex, PoisonError},
+    time::Instant,
+};
+
+use bevy::{
+    diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin},
+    prelude::*,
+};
+
+use crate::{
+    display::DisplayPower,
+    messaging::{MessageQueueStats, ZenohConnection},
+};
+
+/// Health of the face served on `face/health`
+///
+/// Bevy records its state every frame and the zenoh worker answers queries with it,
+/// so a frozen face shows up as a growing `last_frame_age_s`
+pub struct HealthPlugin;
+
+impl Plugin for HealthPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HealthMonitor>()
+            .add_systems(Last, record_health);
+    }
+}
+
+/// Health report answered on `face/health`
+#[derive(serde::Serialize, Debug, Clone, PartialEq)]
+pub struct HealthReport {
+    pub zenoh_connected: bool,
+    /// Seconds since the last command arrived
+    pub last_command_age_s: Option<f64>,
+    /// Seconds since bevy last finished a frame
+    pub last_frame_age_s: Option<f64>,
+    pub fps: Option<f64>,
+    pub display_on: bool,
+    pub dropped_messages: u64,
+}
+
+#[derive(Default)]
+struct HealthState {
+    zenoh_connected: bool,
+    last_command: Option<Instant>,
+    last_frame: Option<Instant>,
+    fps: Option<f64>,
+    display_on: bool,
+    dropped_messages: u64,
+}
+
+/// Latest health state shared by bevy and the zenoh worker
+#[derive(Resource, Clone, Default)]
+pub struct HealthMonitor(Arc<Mutex<HealthState>>);
+
+impl HealthMonitor {
+    /// Health with ages measured from now
+    pub fn report(&self) -> HealthReport {
+        let state = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        let age = |instant: Option<Instant>| instant.map(|instant| instant.elapsed().as_secs_f64());
+        HealthReport {
+            zenoh_connected: state.zenoh_connected,
+            last_command_age_s: age(state.last_command),
+            last_frame_age_s: age(state.last_frame),
+            fps: state.fps,
+            display_on: state.display_on,
+            dropped_messages: state.dropped_messages,
+        }
+    }
+}
+
+fn record_health(
+    health: Res<HealthMonitor>,
+    zenoh_connection: Res<ZenohConnection>,
+    stats: Res<MessageQueueStats>,
+    display_power: Res<DisplayPower>,
+    diagnostics: Res<DiagnosticsStore>,
+) {
+    let mut state = health.0.lock().unwrap_or_else(PoisonError::into_inner);
+    state.zenoh_connected = zenoh_connection.connected;
+    state.last_command = stats.last_command;
+    state.last_frame = Some(Instant::now());
+    state.fps = diagnostics
+        .get(&FrameTimeDiagnosticsPlugin::FPS)
+        .and_then(|fps| fps.smoothed());
+    state.display_on = display_power.on;
+    state.dropped_messages = stats.dropped;
+}
@@ -0,0 +1,123 @@
+use std::ops::RangeInclusive;
+
+use bevy::prelude::*;
+
+use crate::{
+    messaging::{AckSender, CommandAck},
+    params::{RegisterTunable, Tunable},
+    topics::RegisterTopic,
+};
+
+/// Calm idle look with an ECG like heartbeat scrolling through the wave
+///
+/// Switched and paced over `face/heartbeat`, the rest is adjustable as the `heartbeat` tunable
+pub struct HeartbeatPlugin;
+
+impl Plugin for HeartbeatPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Heartbeat>()
+            .register_tunable::<Heartbeat>("heartbeat")
+            .add_topic::<HeartbeatUpdate>("face/heartbeat")
+            .add_systems(Update, process_heartbeat_updates);
+    }
+}
+
+/// Seconds a beat takes to scroll across the whole wave
+pub const HEARTBEAT_SWEEP: f64 = 3.0;
+pub const BPM_RANGE: RangeInclusive<f64> = 20.0..=240.0;
+pub const NOISE_SCALE_RANGE: RangeInclusive<f64> = 0.0..=1.0;
+
+#[derive(Resource, Reflect, Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[reflect(Resource)]
+pub struct Heartbeat {
+    pub enabled: bool,
+    pub bpm: f64,
+    /// Height of the R spike relative to the noise, which stays within -1.0..1.0
+    pub amplitude: f64,
+    /// Noise is scaled by this while the heartbeat is shown so the beats stand out
+    pub noise_scale: f64,
+}
+
+impl Default for Heartbeat {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bpm: 60.0,
+            amplitude: 0.8,
+            noise_scale: 0.15,
+        }
+    }
+}
+
+impl Tunable for Heartbeat {
+    fn sanitize(&mut self) {
+        self.bpm = clamp(self.bpm, BPM_RANGE);
+        self.noise_scale = clamp(self.noise_scale, NOISE_SCALE_RANGE);
+        if !self.amplitude.is_finite() {
+            self.amplitude = Self::default().amplitude;
+        }
+    }
+}
+
+/// Clamp into `range`, NaN falls back to the lower bound
+fn clamp(value: f64, range: RangeInclusive<f64>) -> f64 {
+    if value.is_nan() {
+        *range.start()
+    } else {
+        value.clamp(*range.start(), *range.end())
+    }
+}
+
+#[derive(Event, serde::Deserialize)]
+pub struct HeartbeatUpdate {
+    #[serde(default)]
+    enabled: Option<bool>,
+    #[serde(default)]
+    bpm: Option<f64>,
+    /// Key to publish an acknowledgement to once applied
+    #[serde(default)]
+    reply_to: Option<String>,
+}
+
+fn process_heartbeat_updates(
+    mut messages: EventReader<HeartbeatUpdate>,
+    mut heartbeat: ResMut<Heartbeat>,
+    ack_sender: Res<AckSender>,
+) {
+    for (message, id) in messages.read_with_id() {
+        let _trace = ack_sender.trace(id);
+        if let Some(enabled) = message.enabled {
+            info!(enabled, "Updating heartbeat enabled");
+            heartbeat.enabled = enabled;
+        }
+        if let Some(bpm) = message.bpm {
+            heartbeat.bpm = bpm;
+            heartbeat.sanitize();
+            info!(bpm = heartbeat.bpm, "Updating heartbeat bpm");
+        }
+        ack_sender.send(
+            message.reply_to.as_deref(),
+            CommandAck::applied(&*heartbeat),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::wave::{add_heartbeat, heartbeat};
+
+    #[test]
+    fn beats_spike_once_per_period() {
+        assert!((heartbeat(0.23) - 1.0).abs() < 0.01);
+        assert!((heartbeat(1.23) - 1.0).abs() < 0.01);
+        assert!(heartbeat(0.8).abs() < 0.01);
+
+        // one beat per second scrolling across two seconds shows two spikes
+        let mut samples = vec![0.0; 201];
+        add_heartbeat(&mut samples, 10.0, 60.0, 2.0, 1.0);
+        let spikes = samples
+            .windows(3)
+            .filter(|w| w[1] > 0.5 && w[1] >= w[0] && w[1] > w[2]);
+        assert_eq!(spikes.count(), 2);
+    }
+}
@@ -0,0 +1,168 @@
+//! Raspberry Pi hardware telemetry
+//!
+//! Thermal throttling is the most common cause of dropped frames on the robot
+
+use anyhow::Context;
+use bevy::prelude::*;
+
+use crate::maintenance::MaintenanceOverlay;
+
+pub struct HardwareTelemetryPlugin;
+
+impl Plugin for HardwareTelemetryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<HardwareTelemetry>()
+            .add_systems(Update, update_hardware_telemetry_overlay);
+    }
+}
+
+const CPU_TEMPERATURE_PATH: &str = "/sys/class/thermal/thermal_zone0/temp";
+
+// bits reported by `vcgencmd get_throttled`
+const UNDER_VOLTAGE: u32 = 1 << 0;
+const FREQUENCY_CAPPED: u32 = 1 << 1;
+const THROTTLED: u32 = 1 << 2;
+const SOFT_TEMPERATURE_LIMIT: u32 = 1 << 3;
+const UNDER_VOLTAGE_OCCURRED: u32 = 1 << 16;
+const FREQUENCY_CAPPED_OCCURRED: u32 = 1 << 17;
+const THROTTLED_OCCURRED: u32 = 1 << 18;
+const SOFT_TEMPERATURE_LIMIT_OCCURRED: u32 = 1 << 19;
+
+#[derive(Event, serde::Serialize, Debug, Clone, Default)]
+pub struct HardwareTelemetry {
+    pub cpu_temperature_c: Option<f32>,
+    pub core_voltage_v: Option<f32>,
+    /// Raw value of `vcgencmd get_throttled`
+    pub throttled_flags: Option<u32>,
+    pub under_voltage: bool,
+    pub frequency_capped: bool,
+    pub throttled: bool,
+    pub soft_temperature_limit: bool,
+    pub under_voltage_occurred: bool,
+    pub frequency_capped_occurred: bool,
+    pub throttled_occurred: bool,
+    pub soft_temperature_limit_occurred: bool,
+}
+
+impl HardwareTelemetry {
+    /// Read all values, missing sources are logged and left empty
+    pub async fn read() -> Self {
+        let mut telemetry = Self::default();
+
+        match read_cpu_temperature().await {
+            Ok(temperature) => telemetry.cpu_temperature_c = Some(temperature),
+            Err(error) => warn!(?error, "Failed to read cpu temperature"),
+        }
+        match read_core_voltage().await {
+            Ok(voltage) => telemetry.core_voltage_v = Some(voltage),
+            Err(error) => warn!(?error, "Failed to read core voltage"),
+        }
+        match read_throttled_flags().await {
+            Ok(flags) => {
+                telemetry.throttled_flags = Some(flags);
+                telemetry.under_voltage = flags & UNDER_VOLTAGE != 0;
+                telemetry.frequency_capped = flags & FREQUENCY_CAPPED != 0;
+                telemetry.throttled = flags & THROTTLED != 0;
+                telemetry.soft_temperature_limit = flags & SOFT_TEMPERATURE_LIMIT != 0;
+                telemetry.under_voltage_occurred = flags & UNDER_VOLTAGE_OCCURRED != 0;
+                telemetry.frequency_capped_occurred = flags & FREQUENCY_CAPPED_OCCURRED != 0;
+                telemetry.throttled_occurred = flags & THROTTLED_OCCURRED != 0;
+                telemetry.soft_temperature_limit_occurred =
+                    flags & SOFT_TEMPERATURE_LIMIT_OCCURRED != 0;
+            }
+            Err(error) => warn!(?error, "Failed to read throttled flags"),
+        }
+
+        telemetry
+    }
+}
+
+async fn read_cpu_temperature() -> anyhow::Result<f32> {
+    let millidegrees = tokio::fs::read_to_string(CPU_TEMPERATURE_PATH).await?;
+    let millidegrees: f32 = millidegrees.trim().parse()?;
+    Ok(millidegrees / 1000.0)
+}
+
+async fn vcgencmd(args: &[&str]) -> anyhow::Result<String> {
+    let output = tokio::process::Command::new("vcgencmd")
+        .args(args)
+        .output()
+        .await
+        .context("Failed to run vcgencmd")?;
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+/// Parses `volt=0.8563V`
+async fn read_core_voltage() -> anyhow::Result<f32> {
+    let output = vcgencmd(&["measure_volts", "core"]).await?;
+    let voltage = output
+        .trim()
+        .strip_prefix("volt=")
+        .and_then(|voltage| voltage.strip_suffix('V'))
+        .with_context(|| format!("Unexpected vcgencmd output {output}"))?;
+    Ok(voltage.parse()?)
+}
+
+/// Parses `throttled=0x50005`
+async fn read_throttled_flags() -> anyhow::Result<u32> {
+    let output = vcgencmd(&["get_throttled"]).await?;
+    let flags = output
+        .trim()
+        .strip_prefix("throttled=0x")
+        .with_context(|| format!("Unexpected vcgencmd output {output}"))?;
+    Ok(u32::from_str_radix(flags, 16)?)
+}
+
+fn update_hardware_telemetry_overlay(
+    mut events: EventReader<HardwareTelemetry>,
+    mut overlay: ResMut<MaintenanceOverlay>,
+) {
+    let Some(telemetry) = events.read().last() else {
+        return;
+    };
+
+    let mut lines = vec![
+        match telemetry.cpu_temperature_c {
+            Some(temperature) => format!("cpu temperature: {temperature:.1} C"),
+            None => "cpu temperature: unknown".to_owned(),
+        },
+        match telemetry.core_voltage_v {
+            Some(voltage) => format!("core voltage: {voltage:.3} V"),
+            None => "core voltage: unknown".to_owned(),
+        },
+    ];
+    match telemetry.throttled_flags {
+        Some(flags) => lines.push(format!("throttled: {flags:#x}")),
+        None => lines.push("throttled: unknown".to_owned()),
+    }
+    for (active, occurred, name) in [
+        (
+            telemetry.under_voltage,
+            telemetry.under_voltage_occurred,
+            "under voltage",
+        ),
+        (
+            telemetry.frequency_capped,
+            telemetry.frequency_capped_occurred,
+            "frequency capped",
+        ),
+        (
+            telemetry.throttled,
+            telemetry.throttled_occurred,
+            "throttled",
+        ),
+        (
+            telemetry.soft_temperature_limit,
+            telemetry.soft_temperature_limit_occurred,
+            "soft temperature limit",
+        ),
+    ] {
+        if active {
+            lines.push(format!("  {name} NOW"));
+        } else if occurred {
+            lines.push(format!("  {name} since boot"));
+        }
+    }
+
+    overlay.set_section("hardware", lines.join("\n"));
+}
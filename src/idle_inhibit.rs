@@ -0,0 +1,173 @@
+use anyhow::Context;
+use bevy::{
+    prelude::*,
+    window::{PrimaryWindow, RawHandleWrapper},
+};
+use raw_window_handle::{RawDisplayHandle, RawWindowHandle};
+use wayland_client::{
+    backend::{Backend, ObjectId},
+    delegate_noop,
+    globals::{registry_queue_init, GlobalListContents},
+    protocol::{wl_registry::WlRegistry, wl_surface::WlSurface},
+    Connection, Dispatch, EventQueue, Proxy, QueueHandle,
+};
+use wayland_protocols::wp::idle_inhibit::zv1::client::{
+    zwp_idle_inhibit_manager_v1::ZwpIdleInhibitManagerV1, zwp_idle_inhibitor_v1::ZwpIdleInhibitorV1,
+};
+
+use crate::{display::DisplayPower, night_mode::NightMode};
+
+/// Keeps the compositor from blanking the screen while the display is on and not in night mode
+pub struct IdleInhibitPlugin;
+
+impl Plugin for IdleInhibitPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_non_send_resource(IdleInhibit::default())
+            .add_systems(Update, update_idle_inhibit);
+    }
+}
+
+#[derive(Default)]
+enum IdleInhibit {
+    /// Window is not created yet
+    #[default]
+    Pending,
+    Connected(Box<WaylandIdleInhibitor>),
+    /// Not running under wayland or the compositor lacks the protocol
+    Unsupported,
+}
+
+struct WaylandIdleInhibitor {
+    connection: Connection,
+    queue: EventQueue<IdleInhibitState>,
+    manager: ZwpIdleInhibitManagerV1,
+    surface: WlSurface,
+    inhibitor: Option<ZwpIdleInhibitorV1>,
+}
+
+impl WaylandIdleInhibitor {
+    fn connect(handles: &RawHandleWrapper) -> anyhow::Result<Self> {
+        let (RawDisplayHandle::Wayland(display), RawWindowHandle::Wayland(window)) =
+            (handles.display_handle, handles.window_handle)
+        else {
+            anyhow::bail!("Not running under wayland");
+        };
+
+        // Safety: winit owns the display and surface and keeps them alive for the lifetime of the window
+        let backend = unsafe { Backend::from_foreign_display(display.display.as_ptr().cast()) };
+        let connection = Connection::from_backend(backend);
+        let surface_id =
+            unsafe { ObjectId::from_ptr(WlSurface::interface(), window.surface.as_ptr().cast()) }
+                .context("Invalid wayland surface")?;
+        let surface = WlSurface::from_id(&connection, surface_id).context("Invalid surface")?;
+
+        let (globals, queue) = registry_queue_init::<IdleInhibitState>(&connection)
+            .context("Failed to list wayland globals")?;
+        let manager = globals
+            .bind(&queue.handle(), 1..=1, ())
+            .context("Compositor does not support idle inhibit")?;
+
+        Ok(Self {
+            connection,
+            queue,
+            manager,
+            surface,
+            inhibitor: None,
+        })
+    }
+
+    fn set_inhibited(&mut self, inhibited: bool) -> anyhow::Result<()> {
+        if inhibited == self.inhibitor.is_some() {
+            return Ok(());
+        }
+        if let Some(inhibitor) = self.inhibitor.take() {
+            info!("Releasing compositor idle inhibitor");
+            inhibitor.destroy();
+        } else {
+            info!("Inhibiting compositor idle");
+            let inhibitor = self
+                .manager
+                .create_inhibitor(&self.surface, &self.queue.handle(), ());
+            self.inhibitor = Some(inhibitor);
+        }
+        self.connection.flush().context("Failed to flush wayland")?;
+        Ok(())
+    }
+}
+
+struct IdleInhibitState;
+
+impl Dispatch<WlRegistry, GlobalListContents> for IdleInhibitState {
+    fn event(
+        _state: &mut Self,
+        _proxy: &WlRegistry,
+        _event: <WlRegistry as Proxy>::Event,
+        _data: &GlobalListContents,
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+delegate_noop!(IdleInhibitState: ignore ZwpIdleInhibitManagerV1);
+delegate_noop!(IdleInhibitState: ignore ZwpIdleInhibitorV1);
+
+fn update_idle_inhibit(
+    mut idle_inhibit: NonSendMut<IdleInhibit>,
+    window: Query<&RawHandleWrapper, With<PrimaryWindow>>,
+    display_power: Res<DisplayPower>,
+    night_mode: Res<NightMode>,
+) {
+    if matches!(*idle_inhibit, IdleInhibit::Pending) {
+        let Ok(handles) = window.get_single() else {
+            return;
+        };
+        *idle_inhibit = match WaylandIdleInhibitor::connect(handles) {
+            Ok(inhibitor) => IdleInhibit::Connected(Box::new(inhibitor)),
+            Err(error) => {
+                warn!(?error, "Idle inhibit unavailable");
+                IdleInhibit::Unsupported
+            }
+        };
+    }
+
+    if let IdleInhibit::Connected(inhibitor) = &mut *idle_inhibit {
+        if let Err(error) = inhibitor.set_inhibited(should_inhibit(&display_power, &night_mode)) {
+            error!(?error, "Failed to update idle inhibitor");
+        }
+    }
+}
+
+/// Night mode lets the compositor dim or blank the screen as it would with the face gone
+fn should_inhibit(display_power: &DisplayPower, night_mode: &NightMode) -> bool {
+    display_power.on && !night_mode.active
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inhibitor_is_released_while_off_or_in_night_mode() {
+        let night_mode = |active| NightMode {
+            active,
+            tint: Color::WHITE,
+        };
+        assert!(should_inhibit(
+            &DisplayPower { on: true },
+            &night_mode(false)
+        ));
+        assert!(!should_inhibit(
+            &DisplayPower { on: false },
+            &night_mode(false)
+        ));
+        assert!(!should_inhibit(
+            &DisplayPower { on: true },
+            &night_mode(true)
+        ));
+        assert!(!should_inhibit(
+            &DisplayPower { on: false },
+            &night_mode(true)
+        ));
+    }
+}
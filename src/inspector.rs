@@ -0,0 +1,156 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts, EguiPlugin};
+
+use crate::{
+    display::DisplayState,
+    expression::{Expression, ExpressionMessage, ExpressionPreset, SaveExpressionPreset},
+    eyes::{EyeShapeUpdate, EyeSides},
+    noise_plugin::{
+        NoiseGeneratorSettings, NoiseGeneratorSettingsUpdate, CURVE_SUBDIVISIONS_RANGE,
+        FRAME_TIME_DIVIDER_RANGE, HEIGHT_MULTIPLIER_RANGE, LINE_WIDTH_RANGE,
+        PERLIN_NOISE_OCTAVES_RANGE, SEGMENT_WIDTH_RANGE, SMOOTHING_WINDOW_RANGE,
+        WIDTH_DIVIDER_RANGE,
+    },
+};
+
+/// Panel with sliders for the wave settings and eyes
+///
+/// Changes are sent as regular settings updates
+/// and the resulting json can be copied to reproduce the look over zenoh.
+/// The current wave and eyes can be saved as a named expression preset
+pub struct InspectorPlugin;
+
+impl Plugin for InspectorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(EguiPlugin)
+            .init_resource::<EyeSides>()
+            .add_event::<SaveExpressionPreset>()
+            .add_event::<ExpressionMessage>()
+            .add_systems(Update, show_inspector);
+    }
+}
+
+fn show_inspector(
+    mut contexts: EguiContexts,
+    (settings, display_state): (Res<NoiseGeneratorSettings>, Res<DisplayState>),
+    mut updates: EventWriter<NoiseGeneratorSettingsUpdate>,
+    mut sides: ResMut<EyeSides>,
+    (mut saves, mut expressions): (
+        EventWriter<SaveExpressionPreset>,
+        EventWriter<ExpressionMessage>,
+    ),
+    mut preset_name: Local<String>,
+) {
+    let mut edited = settings.clone();
+    let mut eyes = sides.left;
+
+    egui::Window::new("Tuning").show(contexts.ctx_mut(), |ui| {
+        ui.heading("Wave");
+        ui.add(
+            egui::Slider::new(&mut edited.width_divider, WIDTH_DIVIDER_RANGE)
+                .logarithmic(true)
+                .text("width divider"),
+        );
+        ui.add(
+            egui::Slider::new(&mut edited.height_multiplier, HEIGHT_MULTIPLIER_RANGE)
+                .logarithmic(true)
+                .text("height multiplier"),
+        );
+        ui.add(
+            egui::Slider::new(
+                &mut edited.segment_width,
+                *SEGMENT_WIDTH_RANGE.start() as f32..=*SEGMENT_WIDTH_RANGE.end() as f32,
+            )
+            .logarithmic(true)
+            .text("segment width"),
+        );
+        ui.add(
+            egui::Slider::new(
+                &mut edited.line_width,
+                *LINE_WIDTH_RANGE.start() as f32..=*LINE_WIDTH_RANGE.end() as f32,
+            )
+            .text("line width"),
+        );
+        ui.add(
+            egui::Slider::new(&mut edited.frame_time_divider, FRAME_TIME_DIVIDER_RANGE)
+                .logarithmic(true)
+                .text("frame time divider"),
+        );
+        ui.add(
+            egui::Slider::new(&mut edited.perlin_noise_octaves, PERLIN_NOISE_OCTAVES_RANGE)
+                .text("perlin noise octaves"),
+        );
+        ui.add(
+            egui::Slider::new(&mut edited.smoothing_window, SMOOTHING_WINDOW_RANGE)
+                .text("smoothing window"),
+        );
+        ui.add(
+            egui::Slider::new(&mut edited.curve_subdivisions, CURVE_SUBDIVISIONS_RANGE)
+                .text("curve subdivisions"),
+        );
+        ui.checkbox(&mut edited.hidden, "hidden");
+        ui.horizontal(|ui| {
+            let mut color = edited.color.as_rgba_f32();
+            ui.color_edit_button_rgba_unmultiplied(&mut color);
+            edited.color = Color::rgba(color[0], color[1], color[2], color[3]);
+            ui.label("color");
+        });
+
+        ui.heading("Eyes");
+        ui.add(egui::Slider::new(&mut eyes.openness, 0.0..=1.0).text("openness"));
+        ui.add(egui::Slider::new(&mut eyes.raise, -1.0..=1.0).text("raise"));
+
+        ui.separator();
+        ui.heading("Expression");
+        ui.horizontal_wrapped(|ui| {
+            for expression in Expression::ALL {
+                if ui.button(expression.name()).clicked() {
+                    expressions.send(ExpressionMessage {
+                        expression: expression.name().to_owned(),
+                        transition_s: None,
+                        reply_to: None,
+                    });
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut *preset_name);
+            if ui.button("Save preset").clicked() {
+                saves.send(SaveExpressionPreset {
+                    name: preset_name.trim().to_owned(),
+                    preset: ExpressionPreset {
+                        wave: NoiseGeneratorSettingsUpdate {
+                            hidden: None,
+                            background: None,
+                            ..settings.to_update()
+                        },
+                        eyes: EyeShapeUpdate {
+                            openness: Some(sides.left.openness),
+                            raise: Some(sides.left.raise),
+                        },
+                        sound: default(),
+                    },
+                });
+            }
+        });
+
+        ui.separator();
+        let json = serde_json::to_string(&settings.to_update()).unwrap_or_default();
+        if ui.button("Copy as JSON").clicked() {
+            ui.output_mut(|output| output.copied_text.clone_from(&json));
+        }
+        ui.monospace(json);
+
+        ui.separator();
+        ui.heading("Display");
+        ui.monospace(serde_json::to_string_pretty(&*display_state).unwrap_or_default());
+    });
+
+    if edited != *settings {
+        updates.send(edited.to_update());
+    }
+    if eyes != sides.left {
+        sides.left = eyes;
+        sides.right = eyes;
+    }
+}
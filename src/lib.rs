@@ -0,0 +1,87 @@
+//! Robot face plugins, shared by the binary, benchmarks and fuzz targets
+
+pub mod acl;
+pub mod asr;
+pub mod asset_pack;
+pub mod battery;
+pub mod calibration;
+pub mod clock_sync;
+pub mod color_calibration;
+pub mod command_history;
+pub mod config;
+pub mod countdown;
+pub mod dev_view;
+pub mod diagnostics_export;
+pub mod display;
+pub mod dizzy;
+pub mod dnd;
+pub mod drawing;
+pub mod easing;
+pub mod event_log;
+pub mod excitement;
+pub mod expression;
+pub mod eyes;
+pub mod face_clock;
+pub mod face_state;
+pub mod frame_pacing;
+#[cfg(feature = "gamepad")]
+pub mod gamepad;
+pub mod gaze;
+pub mod glitch;
+pub mod greeting;
+pub mod health;
+pub mod heartbeat;
+#[cfg(feature = "hw-telemetry")]
+pub mod hw_telemetry;
+#[cfg(target_os = "linux")]
+pub mod idle_inhibit;
+#[cfg(feature = "inspector")]
+pub mod inspector;
+pub mod maintenance;
+pub mod messaging;
+pub mod mirror;
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+pub mod monitor_power;
+pub mod mood;
+pub mod mouth;
+pub mod night_mode;
+pub mod noise_plugin;
+pub mod offline;
+#[cfg(feature = "oled")]
+pub mod oled;
+pub mod outbound;
+pub mod params;
+pub mod ping;
+pub mod present_mode;
+pub mod priority;
+pub mod reaction;
+pub mod replay;
+pub mod rotation;
+pub mod safe_area;
+pub mod scene;
+pub mod screenshot;
+pub mod self_test;
+pub mod send;
+pub mod settings_file;
+pub mod shake;
+pub mod shutdown;
+pub mod simulation;
+pub mod sound;
+pub mod speech;
+pub mod split_preview;
+pub mod spring;
+pub mod startup;
+pub mod temperament;
+pub mod topics;
+pub mod trace;
+pub mod utils;
+pub mod velocity;
+pub mod version;
+pub mod voice_signature;
+pub mod wake_pulse;
+pub mod wave;
+pub mod wave_layers;
+pub mod weather;
+pub mod window_watchdog;
+#[cfg(target_os = "linux")]
+pub mod wlr_output;
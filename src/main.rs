@@ -1,47 +1,495 @@
-mod display;
-mod messaging;
-mod noise_plugin;
-mod utils;
-
 use bevy::{
     diagnostic::{
         EntityCountDiagnosticsPlugin, FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin,
         SystemInformationDiagnosticsPlugin,
     },
     prelude::*,
-    window::{CursorGrabMode, PresentMode, WindowLevel, WindowResolution, WindowTheme},
+    window::{
+        CursorGrabMode, ExitCondition, PresentMode, WindowLevel, WindowResolution, WindowTheme,
+    },
 };
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use iyes_perf_ui::PerfUiPlugin;
 
-use crate::{
-    messaging::start_zenoh_worker,
+use face::{
+    acl::CommandAcl,
+    asr::AsrSubtitlePlugin,
+    asset_pack::AssetPackPlugin,
+    battery::BatteryPlugin,
+    calibration::CalibrationPlugin,
+    clock_sync::SharedClock,
+    color_calibration::{ColorCalibration, ColorCalibrationPlugin},
+    command_history::{CommandHistory, DEFAULT_HISTORY_CAPACITY},
+    config::FaceConfig,
+    countdown::CountdownPlugin,
+    dev_view::DevViewPlugin,
+    diagnostics_export::DiagnosticsExportPlugin,
+    display::{
+        DisplayBackend, DisplayControl, DisplayPlugin, DEFAULT_DISPLAY_MODE_COMMAND,
+        DEFAULT_DISPLAY_OFF_COMMAND, DEFAULT_DISPLAY_ON_COMMAND, DEFAULT_DISPLAY_OUTPUT,
+        DEFAULT_DISPLAY_ROTATE_COMMAND, DEFAULT_DISPLAY_TRANSFORM,
+    },
+    dizzy::DizzyPlugin,
+    dnd::DoNotDisturbPlugin,
+    drawing::DrawingPlugin,
+    event_log::EventLogPlugin,
+    excitement::ExcitementPlugin,
+    expression::ExpressionPlugin,
+    eyes::EyesPlugin,
+    face_clock::{ClockSource, FaceClock, FaceClockPlugin},
+    face_state::{FaceStatePlugin, DEFAULT_FACE_STATE_INTERVAL_MS},
+    frame_pacing::{FramePacingPlugin, DEFAULT_STUTTER_THRESHOLD_MS},
+    gaze::GazePlugin,
+    glitch::GlitchPlugin,
+    greeting::GreetingPlugin,
+    health::HealthPlugin,
+    heartbeat::HeartbeatPlugin,
+    maintenance::MaintenancePlugin,
+    messaging::{
+        KeyPrefix, MessageQueueSettings, MessagingPlugin, OverflowPolicy, ZenohMode, ZenohSettings,
+    },
+    mirror::{MirrorPlugin, MirrorRole},
+    mood::MoodPlugin,
+    mouth::MouthPlugin,
+    night_mode::{parse_color, NightModePlugin, DEFAULT_NIGHT_MODE_TINT},
     noise_plugin::NoisePlugin,
+    offline::{OfflinePlugin, DEFAULT_CONNECT_TIMEOUT_S},
+    outbound::{parse_outbound_rate, OutboundSettings},
+    params::ParamsPlugin,
+    ping::PingPlugin,
+    present_mode::PresentModePlugin,
+    priority::Priority,
+    reaction::ReactionPlugin,
+    replay::{parse_replay_fps, ReplayPlugin, ReplaySettings},
+    rotation::{RotationPlugin, RotationSetting},
+    safe_area::{SafeArea, SafeAreaPlugin},
+    scene::{FaceScene, ScenePlugin, SceneRequests},
+    screenshot::{parse_key_code, ScreenshotHotkeyPlugin, DEFAULT_SCREENSHOT_KEY},
+    self_test::SelfTestPlugin,
+    send::{send_command, SendArgs},
+    settings_file::SettingsFile,
+    shake::ScreenShakePlugin,
+    shutdown::{RequestedExitCode, ShutdownPlugin},
+    simulation::SimulationPlugin,
+    sound::SoundPlugin,
+    speech::SpeechBubblePlugin,
+    split_preview::SplitPreviewPlugin,
+    startup::StartupPlugin,
+    temperament::TemperamentPlugin,
     utils::{close_on_right_click, make_visible, toggle_fullscreen, toggle_perf_ui},
+    velocity::VelocityPlugin,
+    voice_signature::VoiceSignaturePlugin,
+    wake_pulse::{WakePulsePlugin, DEFAULT_WAKE_PULSE_DURATION_S},
+    wave_layers::{WaveLayersMessage, WaveLayersPlugin},
+    weather::WeatherPlugin,
+    window_watchdog::WindowWatchdogPlugin,
 };
 
 /// Run robot face animation
 #[derive(Parser, Debug)]
 #[command(version, about, long_about)]
 struct Args {
-    /// Run in dev mode
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// TOML file with the window size, fullscreen, display output, zenoh key prefix
+    /// and startup wave settings. Flags given on the command line take precedence
+    #[arg(long)]
+    config: Option<std::path::PathBuf>,
+
+    /// Run in dev mode, implies all of the window flags below
     #[arg(short, long)]
     dev_mode: bool,
+
+    /// Run in a window instead of borderless fullscreen
+    #[arg(long)]
+    windowed: bool,
+
+    /// Don't keep the face above other windows
+    #[arg(long)]
+    not_always_on_top: bool,
+
+    /// Show the cursor and don't confine it to the window
+    #[arg(long)]
+    show_cursor: bool,
+
+    /// Close the face with escape, a right click or the window manager.
+    /// Off by default so stray input can't end the face on the robot
+    #[arg(long, alias = "allow-close-keys")]
+    allow_local_close: bool,
+
+    /// Start with the calibration pattern shown
+    #[arg(long)]
+    calibrate: bool,
+
+    /// Run the self test after starting
+    #[arg(long)]
+    self_test: bool,
+
+    /// Idle scene to boot into, overrides the startup section of the settings file
+    #[arg(long, value_enum)]
+    startup_scene: Option<FaceScene>,
+
+    /// Glance away now and then instead of staring at the same point
+    #[arg(long)]
+    gaze_aversion: bool,
+
+    /// How the display is controlled
+    #[arg(long, value_enum, default_value_t)]
+    display_backend: DisplayBackend,
+
+    /// Name of the display output, HDMI-A-1 unless set in the config file
+    #[arg(long)]
+    display_output: Option<String>,
+
+    /// Minutes between brief wake ups of a display that was turned off, no wake ups unless set
+    #[arg(long)]
+    wake_pulse_interval_min: Option<f32>,
+
+    /// Seconds the display stays on for each wake up
+    #[arg(long)]
+    wake_pulse_duration_s: Option<f32>,
+
+    /// Display transform applied when turning the display on
+    #[arg(long, default_value = DEFAULT_DISPLAY_TRANSFORM)]
+    display_transform: String,
+
+    /// Command used to turn the display on. Supports {output}, {transform} and {mode} placeholders
+    #[arg(long, default_value = DEFAULT_DISPLAY_ON_COMMAND)]
+    display_on_command: String,
+
+    /// Command used to turn the display off. Supports {output}, {transform} and {mode} placeholders
+    #[arg(long, default_value = DEFAULT_DISPLAY_OFF_COMMAND)]
+    display_off_command: String,
+
+    /// Command used to rotate the display. Supports {output}, {transform} and {mode} placeholders
+    #[arg(long, default_value = DEFAULT_DISPLAY_ROTATE_COMMAND)]
+    display_rotate_command: String,
+
+    /// Command used to change the display mode. Supports {output}, {transform} and {mode} placeholders
+    #[arg(long, default_value = DEFAULT_DISPLAY_MODE_COMMAND)]
+    display_mode_command: String,
+
+    /// Log display commands and output configuration requests instead of running them
+    #[arg(long)]
+    display_dry_run: bool,
+
+    /// Hex color multiplied with the face colors while night mode is on
+    #[arg(long, default_value = DEFAULT_NIGHT_MODE_TINT, value_parser = parse_color)]
+    night_mode_tint: Color,
+
+    /// Gamma of the red channel, values above 1 brighten it
+    #[arg(long, default_value_t = 1.0)]
+    gamma_red: f32,
+
+    /// Gamma of the green channel, values above 1 brighten it
+    #[arg(long, default_value_t = 1.0)]
+    gamma_green: f32,
+
+    /// Gamma of the blue channel, values above 1 brighten it
+    #[arg(long, default_value_t = 1.0)]
+    gamma_blue: f32,
+
+    /// Hex color white is drawn in, to correct the color cast of the panel
+    #[arg(long, default_value = "ffffff", value_parser = parse_color)]
+    white_point: Color,
+
+    /// Rotate the face inside the window instead of through the compositor transform
+    #[arg(long, value_enum, default_value_t)]
+    rotation: RotationSetting,
+
+    /// Pixels along the top edge hidden behind the face plate
+    #[arg(long, default_value_t = 0.0)]
+    safe_area_top: f32,
+
+    /// Pixels along the bottom edge hidden behind the face plate
+    #[arg(long, default_value_t = 0.0)]
+    safe_area_bottom: f32,
+
+    /// Pixels along the left edge hidden behind the face plate
+    #[arg(long, default_value_t = 0.0)]
+    safe_area_left: f32,
+
+    /// Pixels along the right edge hidden behind the face plate
+    #[arg(long, default_value_t = 0.0)]
+    safe_area_right: f32,
+
+    /// Zenoh json5 configuration file
+    #[arg(long)]
+    zenoh_config: Option<std::path::PathBuf>,
+
+    /// Whether the zenoh session runs as a peer, client or router
+    #[arg(long, value_enum)]
+    zenoh_mode: Option<ZenohMode>,
+
+    /// Key expression the face/ keys are moved under, such as robot1/face
+    #[arg(long)]
+    zenoh_key_prefix: Option<String>,
+
+    /// Zenoh endpoint to connect to such as tls/router.local:7447, can be repeated
+    #[arg(long)]
+    zenoh_connect: Vec<String>,
+
+    /// Seconds zenoh has to connect at boot before the face shows it is offline, 10 unless set
+    #[arg(long)]
+    zenoh_connect_timeout_s: Option<f32>,
+
+    /// Zenoh endpoint to listen on, can be repeated
+    #[arg(long)]
+    zenoh_listen: Vec<String>,
+
+    /// Certificate authority used to verify zenoh TLS endpoints
+    #[arg(long)]
+    zenoh_tls_root_ca: Option<std::path::PathBuf>,
+
+    /// Client certificate for zenoh mutual TLS
+    #[arg(long, requires = "zenoh_tls_private_key")]
+    zenoh_tls_certificate: Option<std::path::PathBuf>,
+
+    /// Client private key for zenoh mutual TLS
+    #[arg(long, requires = "zenoh_tls_certificate")]
+    zenoh_tls_private_key: Option<std::path::PathBuf>,
+
+    /// Zenoh user name
+    #[arg(long, requires = "zenoh_password")]
+    zenoh_user: Option<String>,
+
+    /// Zenoh password
+    #[arg(
+        long,
+        env = "FACE_ZENOH_PASSWORD",
+        hide_env_values = true,
+        requires = "zenoh_user"
+    )]
+    zenoh_password: Option<String>,
+
+    /// Number of messages queued between zenoh and the face before the overflow policy applies
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u16).range(1..))]
+    message_queue_capacity: u16,
+
+    /// What happens to json commands when the message queue is full
+    #[arg(long, value_enum, default_value_t)]
+    command_overflow_policy: OverflowPolicy,
+
+    /// Number of acks, events and telemetry messages waiting to be published before the oldest are dropped
+    #[arg(long, default_value_t = 64, value_parser = clap::value_parser!(u16).range(1..))]
+    outbound_queue_capacity: u16,
+
+    /// Most messages the face publishes per second
+    #[arg(long, default_value_t = 100.0, value_parser = parse_outbound_rate)]
+    outbound_rate: f64,
+
+    /// JSON file with rules limiting which sources may send which commands
+    #[arg(long)]
+    acl_file: Option<std::path::PathBuf>,
+
+    /// Number of recent commands answered on `face/history`, 0 keeps none
+    #[arg(long, default_value_t = DEFAULT_HISTORY_CAPACITY, value_parser = clap::value_parser!(u16).range(..=10_000))]
+    command_history: u16,
+
+    /// Name of this robot, the idle wave is derived from it so robots sharing a config look distinct
+    #[arg(long, env = "FACE_ID")]
+    face_id: Option<String>,
+
+    /// Generate proximity, speech, driving, shake and battery readings to try the face without a robot
+    #[arg(long)]
+    simulate: bool,
+
+    /// Advance time by a fixed step every frame instead of following the wall clock
+    #[arg(long)]
+    replay: bool,
+
+    /// Frame rate simulated in replay mode
+    #[arg(long, default_value_t = 60.0, value_parser = parse_replay_fps)]
+    replay_fps: f64,
+
+    /// Noise seed used in replay mode
+    #[arg(long, default_value_t = 100)]
+    replay_seed: u32,
+
+    /// Local hour the replay starts at
+    #[arg(long, default_value_t = 12)]
+    replay_start_hour: u32,
+
+    /// Frame to save as a screenshot in replay mode, can be repeated. Exits after the last one
+    #[arg(long)]
+    screenshot_frame: Vec<u32>,
+
+    /// Directory replay and hotkey screenshots are saved to
+    #[arg(long, default_value = "screenshots")]
+    screenshot_dir: std::path::PathBuf,
+
+    /// Key that saves a screenshot in dev mode, F1 to F12, a letter or a digit
+    #[arg(long, default_value = DEFAULT_SCREENSHOT_KEY, value_parser = parse_key_code)]
+    screenshot_key: KeyCode,
+
+    /// JSON file settings sent with persist are written to and loaded from at startup
+    #[arg(long)]
+    settings_file: Option<std::path::PathBuf>,
+
+    /// Append diagnostics snapshots as JSON lines to this file
+    #[arg(long)]
+    diagnostics_file: Option<std::path::PathBuf>,
+
+    /// Seconds between diagnostics snapshots
+    #[arg(long, default_value_t = 60, value_parser = clap::value_parser!(u64).range(1..))]
+    diagnostics_interval_s: u64,
+
+    /// Milliseconds a frame can take before it counts as a stutter and is published on `face/event/stutter`
+    #[arg(long, default_value_t = DEFAULT_STUTTER_THRESHOLD_MS, value_parser = clap::value_parser!(u64).range(1..))]
+    stutter_threshold_ms: u64,
+
+    /// Mirror the face across devices, the leader publishes its state for followers to show
+    #[arg(long, value_enum)]
+    mirror: Option<MirrorRole>,
+
+    /// Milliseconds between states published by the mirror leader
+    #[arg(long, default_value_t = 500, value_parser = clap::value_parser!(u64).range(50..))]
+    mirror_interval_ms: u64,
+
+    /// Milliseconds between states published on `face/state`
+    #[arg(long, default_value_t = DEFAULT_FACE_STATE_INTERVAL_MS, value_parser = clap::value_parser!(u64).range(100..))]
+    state_interval_ms: u64,
+
+    /// Answer time queries on `face/time` for the other faces to synchronize their clocks with
+    #[arg(long)]
+    time_authority: bool,
+
+    /// Reload assets when they change on disk
+    #[cfg(feature = "hot-reload")]
+    #[arg(long)]
+    hot_reload: bool,
+
+    /// I2C bus of the SSD1306 status OLED
+    #[cfg(feature = "oled")]
+    #[arg(long, default_value = "/dev/i2c-1")]
+    oled_i2c_bus: String,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Publish a command to a running face over zenoh and exit
+    Send(SendArgs),
 }
 
 fn main() {
     let args = Args::parse();
 
+    let config = match &args.config {
+        Some(path) => match FaceConfig::load(path) {
+            Ok(config) => config,
+            Err(error) => {
+                eprintln!("{error:?}");
+                std::process::exit(1);
+            }
+        },
+        None => FaceConfig::default(),
+    };
+    let key_prefix = match &args.zenoh_key_prefix {
+        Some(prefix) => KeyPrefix::new(prefix),
+        None => config.key_prefix(),
+    };
+    let key_prefix = match key_prefix {
+        Ok(key_prefix) => key_prefix,
+        Err(error) => {
+            eprintln!("{error:?}");
+            std::process::exit(1);
+        }
+    };
+
+    let connect_timeout_s = args
+        .zenoh_connect_timeout_s
+        .or(config.zenoh.connect_timeout_s)
+        .unwrap_or(DEFAULT_CONNECT_TIMEOUT_S);
+    let zenoh_settings = ZenohSettings {
+        config_file: args.zenoh_config.or(config.zenoh.config_file),
+        mode: args.zenoh_mode.or(config.zenoh.mode),
+        connect: if args.zenoh_connect.is_empty() {
+            config.zenoh.connect
+        } else {
+            args.zenoh_connect
+        },
+        listen: if args.zenoh_listen.is_empty() {
+            config.zenoh.listen
+        } else {
+            args.zenoh_listen
+        },
+        tls_root_ca_certificate: args.zenoh_tls_root_ca,
+        tls_client_certificate: args.zenoh_tls_certificate,
+        tls_client_private_key: args.zenoh_tls_private_key,
+        user: args.zenoh_user,
+        password: args.zenoh_password,
+        key_prefix,
+    };
+
+    if let Some(Command::Send(command)) = &args.command {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("Failed to build tokio runtime");
+        if let Err(error) = runtime.block_on(send_command(&zenoh_settings, command)) {
+            eprintln!("{error:?}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let acl = match &args.acl_file {
+        Some(path) => match CommandAcl::load(path) {
+            Ok(acl) => acl,
+            Err(error) => {
+                eprintln!("{error:?}");
+                std::process::exit(1);
+            }
+        },
+        None => CommandAcl::default(),
+    };
+
+    let mut display_control = DisplayControl {
+        backend: args.display_backend,
+        output: args
+            .display_output
+            .or(config.display.output)
+            .unwrap_or_else(|| DEFAULT_DISPLAY_OUTPUT.to_owned()),
+        transform: args.display_transform,
+        mode: None,
+        on: args.display_on_command,
+        off: args.display_off_command,
+        rotate: args.display_rotate_command,
+        set_mode: args.display_mode_command,
+        dry_run: args.display_dry_run,
+        virtual_display: default(),
+    };
+
+    let wake_pulse_interval = args
+        .wake_pulse_interval_min
+        .or(config.display.wake_pulse_interval_min)
+        .filter(|interval_min| *interval_min > 0.0)
+        .and_then(|interval_min| std::time::Duration::try_from_secs_f32(interval_min * 60.0).ok());
+    let wake_pulse_duration_s = args
+        .wake_pulse_duration_s
+        .or(config.display.wake_pulse_duration_s)
+        .unwrap_or(DEFAULT_WAKE_PULSE_DURATION_S);
+
+    let rotation = args.rotation.resolve(&mut display_control);
+    let (window_width, window_height) = rotation.window_size(
+        config.window.width.unwrap_or(480.),
+        config.window.height.unwrap_or(800.),
+    );
+
+    let allow_local_close = args.dev_mode || args.allow_local_close;
+
     let mut window_settings = Window {
         title: "robot face".into(),
         name: Some("face.app".into()),
-        resolution: WindowResolution::new(480., 800.).with_scale_factor_override(1.0),
+        resolution: WindowResolution::new(window_width, window_height)
+            .with_scale_factor_override(1.0),
         present_mode: PresentMode::AutoVsync,
         window_theme: Some(WindowTheme::Dark),
         enabled_buttons: bevy::window::EnabledButtons {
             maximize: false,
             minimize: false,
-            ..Default::default()
+            close: allow_local_close,
         },
         visible: false,
         window_level: WindowLevel::AlwaysOnTop,
@@ -54,39 +502,270 @@ fn main() {
         ..default()
     };
 
-    if args.dev_mode {
+    if args.dev_mode || args.not_always_on_top {
         window_settings.window_level = WindowLevel::Normal;
+    }
+    let windowed = args.dev_mode || args.windowed || config.window.fullscreen == Some(false);
+    if windowed {
         window_settings.mode = bevy::window::WindowMode::Windowed;
+    }
+    if args.dev_mode || args.show_cursor {
         window_settings.cursor.grab_mode = CursorGrabMode::None;
         window_settings.cursor.visible = true;
     }
 
-    App::new()
-        .insert_resource(Msaa::Sample4)
+    let queue_settings = MessageQueueSettings {
+        capacity: args.message_queue_capacity.into(),
+        command_overflow: args.command_overflow_policy,
+    };
+    let outbound_settings = OutboundSettings {
+        capacity: args.outbound_queue_capacity.into(),
+        rate_hz: args.outbound_rate,
+    };
+
+    let mut scene_requests = SceneRequests::default();
+    if args.calibrate {
+        scene_requests.push(Priority::Commanded, FaceScene::Calibration, None);
+    }
+    let initial_scene = scene_requests.current().copied().unwrap_or_default();
+
+    let asset_plugin = AssetPlugin {
+        #[cfg(feature = "hot-reload")]
+        watch_for_changes_override: Some(args.hot_reload),
+        ..default()
+    };
+
+    let exit_code = RequestedExitCode::default();
+    let shared_clock = SharedClock::new(args.time_authority);
+
+    let mut app = App::new();
+    app.insert_resource(Msaa::Sample4)
+        .insert_resource(exit_code.clone())
+        .insert_resource(display_control)
+        .insert_resource(zenoh_settings)
+        .insert_resource(queue_settings)
+        .insert_resource(outbound_settings)
+        .insert_resource(SettingsFile {
+            path: args.settings_file,
+        })
+        .insert_resource(shared_clock.clone())
+        .insert_resource(FaceClock::new(ClockSource::Synced(shared_clock)))
+        .insert_resource(CommandHistory::new(args.command_history.into()))
+        .insert_resource(acl)
         .add_plugins((
-            DefaultPlugins.set(WindowPlugin {
-                primary_window: Some(window_settings),
-                ..default()
-            }),
+            DefaultPlugins
+                .set(WindowPlugin {
+                    primary_window: Some(window_settings.clone()),
+                    // ignore alt+f4 and the like on the robot
+                    close_when_requested: allow_local_close,
+                    // a lost window is respawned rather than ending the face
+                    exit_condition: if allow_local_close {
+                        ExitCondition::OnAllClosed
+                    } else {
+                        ExitCondition::DontExit
+                    },
+                })
+                .set(asset_plugin),
             LogDiagnosticsPlugin::default(),
             FrameTimeDiagnosticsPlugin,
             EntityCountDiagnosticsPlugin,
             SystemInformationDiagnosticsPlugin,
-            NoisePlugin,
             PerfUiPlugin,
         ))
-        .add_systems(Startup, (start_zenoh_worker, setup_camera_system))
+        .add_plugins((
+            MessagingPlugin,
+            DisplayPlugin,
+            MaintenancePlugin,
+            NoisePlugin,
+            ScenePlugin,
+            CalibrationPlugin,
+            DrawingPlugin,
+            DizzyPlugin,
+            GazePlugin {
+                follow_mouse: args.dev_mode,
+                avert: args.gaze_aversion,
+            },
+            GreetingPlugin,
+            HealthPlugin,
+            MoodPlugin,
+            ShutdownPlugin,
+            SoundPlugin,
+            WeatherPlugin,
+        ))
+        .add_plugins((
+            AssetPackPlugin,
+            ColorCalibrationPlugin {
+                calibration: ColorCalibration {
+                    gamma_red: args.gamma_red,
+                    gamma_green: args.gamma_green,
+                    gamma_blue: args.gamma_blue,
+                    white_point: args.white_point,
+                },
+            },
+            CountdownPlugin,
+            EventLogPlugin,
+            ExcitementPlugin,
+            EyesPlugin,
+            HeartbeatPlugin,
+            NightModePlugin {
+                tint: args.night_mode_tint,
+            },
+            ParamsPlugin,
+            RotationPlugin { rotation },
+            SafeAreaPlugin {
+                safe_area: SafeArea {
+                    top: args.safe_area_top,
+                    bottom: args.safe_area_bottom,
+                    left: args.safe_area_left,
+                    right: args.safe_area_right,
+                },
+            },
+            SelfTestPlugin {
+                run_at_startup: args.self_test,
+            },
+            SpeechBubblePlugin,
+            TemperamentPlugin,
+            VelocityPlugin,
+        ))
+        .add_plugins((
+            GlitchPlugin,
+            ReactionPlugin,
+            VoiceSignaturePlugin {
+                face_id: args.face_id,
+            },
+            StartupPlugin {
+                scene: args.startup_scene,
+            },
+            PingPlugin,
+            AsrSubtitlePlugin,
+            DoNotDisturbPlugin,
+            ScreenShakePlugin,
+            PresentModePlugin,
+            ExpressionPlugin,
+            MouthPlugin,
+            WaveLayersPlugin,
+            FaceClockPlugin,
+            FaceStatePlugin {
+                interval: std::time::Duration::from_millis(args.state_interval_ms),
+            },
+            FramePacingPlugin {
+                threshold: std::time::Duration::from_millis(args.stutter_threshold_ms),
+            },
+        ))
+        .add_plugins(BatteryPlugin)
+        .insert_state(initial_scene)
+        .insert_resource(scene_requests)
+        .add_systems(Startup, setup_camera_system)
         .add_systems(
             Update,
             (
                 toggle_perf_ui.before(iyes_perf_ui::PerfUiSet::Setup),
                 toggle_fullscreen,
-                bevy::window::close_on_esc,
-                close_on_right_click,
                 make_visible,
             ),
-        )
-        .run();
+        );
+
+    if let Some(wave) = config.wave {
+        // queued before the persisted settings so those are applied on top
+        app.world.send_event(wave);
+    }
+    if !config.waves.is_empty() {
+        app.world.send_event(WaveLayersMessage {
+            waves: config.waves,
+            reply_to: None,
+        });
+    }
+
+    if allow_local_close {
+        app.add_systems(Update, (bevy::window::close_on_esc, close_on_right_click));
+    } else {
+        app.add_plugins(WindowWatchdogPlugin {
+            window: window_settings,
+        });
+    }
+
+    if windowed {
+        // zooming a fullscreen face on the robot would only confuse
+        app.add_plugins(DevViewPlugin);
+    }
+
+    if args.dev_mode {
+        app.add_plugins(ScreenshotHotkeyPlugin {
+            key: args.screenshot_key,
+            dir: args.screenshot_dir.clone(),
+        })
+        .add_plugins(SplitPreviewPlugin);
+    }
+
+    if args.replay {
+        app.add_plugins(ReplayPlugin {
+            settings: ReplaySettings {
+                step: std::time::Duration::from_secs_f64(1.0 / args.replay_fps),
+                seed: args.replay_seed,
+                start_hour: args.replay_start_hour,
+                screenshot_frames: args.screenshot_frame,
+                screenshot_dir: args.screenshot_dir,
+            },
+        });
+    }
+
+    if args.simulate {
+        app.add_plugins(SimulationPlugin);
+    }
+
+    if let Some(interval) = wake_pulse_interval {
+        app.add_plugins(WakePulsePlugin {
+            interval,
+            duration: std::time::Duration::try_from_secs_f32(wake_pulse_duration_s.max(0.0))
+                .unwrap_or(std::time::Duration::from_secs_f32(
+                    DEFAULT_WAKE_PULSE_DURATION_S,
+                )),
+        });
+    }
+
+    if !args.replay {
+        // replays render the same frames whether a router is around or not
+        app.add_plugins(OfflinePlugin {
+            connect_timeout: std::time::Duration::try_from_secs_f32(connect_timeout_s.max(0.0))
+                .unwrap_or(std::time::Duration::MAX),
+        });
+    }
+
+    #[cfg(target_os = "linux")]
+    app.add_plugins(face::idle_inhibit::IdleInhibitPlugin);
+
+    if let Some(path) = args.diagnostics_file {
+        app.add_plugins(DiagnosticsExportPlugin {
+            path,
+            interval: std::time::Duration::from_secs(args.diagnostics_interval_s),
+        });
+    }
+
+    if let Some(role) = args.mirror {
+        app.add_plugins(MirrorPlugin {
+            role,
+            interval: std::time::Duration::from_millis(args.mirror_interval_ms),
+        });
+    }
+
+    #[cfg(feature = "hw-telemetry")]
+    app.add_plugins(face::hw_telemetry::HardwareTelemetryPlugin);
+
+    #[cfg(feature = "inspector")]
+    if args.dev_mode {
+        app.add_plugins(face::inspector::InspectorPlugin);
+    }
+
+    #[cfg(feature = "oled")]
+    app.add_plugins(face::oled::OledStatusPlugin {
+        i2c_bus: args.oled_i2c_bus,
+    });
+
+    #[cfg(feature = "gamepad")]
+    app.add_plugins(face::gamepad::GamepadPlugin);
+
+    app.run();
+    std::process::exit(exit_code.get().into());
 }
 
 fn setup_camera_system(mut commands: Commands) {
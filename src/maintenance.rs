@@ -0,0 +1,117 @@
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+
+use crate::{display::DisplayPower, safe_area::SafeAreaNode, scene::FaceScene};
+
+/// Text overlay with diagnostic information for maintenance
+///
+/// Hidden by default and toggled with the M key
+pub struct MaintenancePlugin;
+
+impl Plugin for MaintenancePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(MaintenanceOverlay::default())
+            .add_systems(Startup, setup_maintenance_overlay)
+            .add_systems(
+                Update,
+                (
+                    toggle_maintenance_overlay,
+                    update_status_section,
+                    update_maintenance_overlay,
+                )
+                    .chain(),
+            );
+    }
+}
+
+const MAINTENANCE_FONT_SIZE: f32 = 16.0;
+
+/// Sections of the maintenance overlay keyed by title
+///
+/// Modules write their own section and the overlay renders all of them
+#[derive(Resource, Default)]
+pub struct MaintenanceOverlay {
+    pub visible: bool,
+    pub sections: BTreeMap<&'static str, String>,
+}
+
+impl MaintenanceOverlay {
+    pub fn set_section(&mut self, title: &'static str, text: String) {
+        self.sections.insert(title, text);
+    }
+}
+
+#[derive(Component)]
+struct MaintenanceOverlayText;
+
+fn setup_maintenance_overlay(mut commands: Commands) {
+    let mut text = TextBundle::from_section(
+        "",
+        TextStyle {
+            font_size: MAINTENANCE_FONT_SIZE,
+            color: Color::WHITE,
+            ..default()
+        },
+    )
+    .with_style(Style {
+        position_type: PositionType::Absolute,
+        bottom: Val::Px(10.0),
+        left: Val::Px(10.0),
+        ..default()
+    })
+    .with_background_color(Color::BLACK.with_a(0.7));
+    text.visibility = Visibility::Hidden;
+
+    commands.spawn((text, MaintenanceOverlayText, SafeAreaNode { offset: 10.0 }));
+}
+
+fn toggle_maintenance_overlay(
+    mut overlay: ResMut<MaintenanceOverlay>,
+    input: Res<ButtonInput<KeyCode>>,
+) {
+    if input.just_pressed(KeyCode::KeyM) {
+        overlay.visible = !overlay.visible;
+    }
+}
+
+fn update_status_section(
+    mut overlay: ResMut<MaintenanceOverlay>,
+    scene: Res<State<FaceScene>>,
+    display_power: Res<DisplayPower>,
+) {
+    if !scene.is_changed() && !display_power.is_changed() {
+        return;
+    }
+    overlay.set_section(
+        "status",
+        format!(
+            "version: {}\nscene: {:?}\ndisplay on: {}",
+            env!("CARGO_PKG_VERSION"),
+            scene.get(),
+            display_power.on
+        ),
+    );
+}
+
+fn update_maintenance_overlay(
+    overlay: Res<MaintenanceOverlay>,
+    mut query: Query<(&mut Text, &mut Visibility), With<MaintenanceOverlayText>>,
+) {
+    if !overlay.is_changed() {
+        return;
+    }
+    for (mut text, mut visibility) in query.iter_mut() {
+        *visibility = if overlay.visible {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        };
+        text.sections[0].value = overlay
+            .sections
+            .iter()
+            .map(|(title, section)| format!("{title}\n{section}"))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+    }
+}
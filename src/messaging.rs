@@ -1,42 +1,813 @@
+use std::{
+    collections::BTreeMap,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+};
+
 use anyhow::Context;
-use bevy::prelude::*;
+use bevy::{
+    ecs::{event::EventId, system::SystemParam},
+    prelude::*,
+};
 use thiserror::Error;
 use tokio::{
     runtime,
-    sync::mpsc::{channel, Receiver, Sender},
+    sync::mpsc::{
+        channel,
+        error::{SendError, TrySendError},
+        Receiver, Sender,
+    },
 };
 use zenoh::prelude::r#async::*;
 
 use crate::{
-    display::{turn_off_display, turn_on_display, DisplayControlMessage},
-    noise_plugin::NoiseGeneratorSettingsUpdate,
+    acl::CommandAcl,
+    asset_pack::{assets_dir, install_asset_pack, AssetPackMessage},
+    calibration::CalibrationMessage,
+    clock_sync::{clock_reply, local_unix_ms, sync_clock, SharedClock, CLOCK_KEY},
+    command_history::{CommandHistory, HISTORY_KEY},
+    display::{DisplayControl, DisplayControlMessage, DisplayPowerChanged},
+    dizzy::ImuMessage,
+    drawing::PolylineMessage,
+    event_log::{EventKind, EventLog},
+    health::HealthMonitor,
+    maintenance::MaintenanceOverlay,
+    night_mode::NightModeChanged,
+    noise_plugin::{NoiseGeneratorSettingsUpdate, RawWaveSamples, WaveSettingsSnapshot},
+    outbound::{OutboundQueue, OutboundSettings},
+    params::{parse_param_value, ParamSnapshot, ParamUpdate},
+    ping::{PingQuery, PingRequest, PING_KEY},
+    sound::SoundSettingsUpdate,
+    topics::{TopicEvent, TopicRegistry},
+    trace::{annotate, CommandTrace, CommandTraces, TraceGuard},
+    version::BuildInfo,
 };
 
-#[derive(Resource, Deref, DerefMut)]
-pub struct StreamReceiver(Receiver<NoiseGeneratorSettingsUpdate>);
+#[cfg(feature = "hw-telemetry")]
+use crate::hw_telemetry::HardwareTelemetry;
+
+#[cfg(feature = "hw-telemetry")]
+const HARDWARE_TELEMETRY_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+/// Larger raw waves are rejected, the face is only a few hundred pixels wide
+const MAX_WAVE_SAMPLES: usize = 4096;
+const MESSAGE_TELEMETRY_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+
+pub struct MessagingPlugin;
+
+impl Plugin for MessagingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<NoiseGeneratorSettingsUpdate>()
+            .insert_resource(ZenohConnection { connected: false })
+            .init_resource::<MessageQueueSettings>()
+            .init_resource::<OutboundSettings>()
+            .init_resource::<MessageQueueStats>()
+            .init_resource::<TopicRegistry>()
+            .init_resource::<SharedClock>()
+            .init_resource::<CommandHistory>()
+            .init_resource::<CommandAcl>()
+            .init_resource::<CommandTraces>()
+            .init_resource::<WaveSettingsSnapshot>()
+            .add_systems(Startup, start_zenoh_worker)
+            .add_systems(
+                PreUpdate,
+                (dispatch_face_messages, update_messaging_section).chain(),
+            );
+    }
+}
+
+/// Whether the zenoh session is currently up
+#[derive(Resource)]
+pub struct ZenohConnection {
+    pub connected: bool,
+}
+
+/// Status of a command reported back to its `reply_to` key
+#[derive(serde::Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AckStatus {
+    Applied,
+    Rejected,
+}
+
+/// Acknowledgement published to the `reply_to` key of a command
+#[derive(serde::Serialize, Debug)]
+pub struct CommandAck {
+    pub status: AckStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Resulting values after the command was applied
+    #[serde(skip_serializing_if = "serde_json::Value::is_null")]
+    pub values: serde_json::Value,
+}
+
+impl CommandAck {
+    pub fn applied(values: impl serde::Serialize) -> Self {
+        Self {
+            status: AckStatus::Applied,
+            error: None,
+            values: serde_json::to_value(values).unwrap_or_default(),
+        }
+    }
+
+    pub fn rejected(error: impl std::fmt::Display) -> Self {
+        Self {
+            status: AckStatus::Rejected,
+            error: Some(error.to_string()),
+            values: serde_json::Value::Null,
+        }
+    }
+}
+
+/// Queue acknowledgements and events for the zenoh worker to publish
+#[derive(Resource, Clone)]
+pub struct AckSender {
+    outbound: OutboundQueue,
+    key_prefix: KeyPrefix,
+    traces: CommandTraces,
+}
+
+impl AckSender {
+    pub fn new(outbound: OutboundQueue, key_prefix: KeyPrefix, traces: CommandTraces) -> Self {
+        Self {
+            outbound,
+            key_prefix,
+            traces,
+        }
+    }
+
+    /// Send an ack if the command asked for one
+    ///
+    /// `reply_to` is used as is, the sender picked it.
+    /// Acks sent while a traced command is handled carry its `trace_id`
+    pub fn send(&self, reply_to: Option<&str>, ack: CommandAck) {
+        if let Some(reply_to) = reply_to {
+            self.send_json(reply_to.to_owned(), &ack);
+        }
+    }
+
+    /// Publish a json message on the face key `key_expr`
+    ///
+    /// Messages published while a traced command is handled carry its `trace_id`
+    pub fn publish(&self, key_expr: &str, message: &impl serde::Serialize) {
+        self.send_json(self.key_prefix.apply(key_expr), message);
+    }
+
+    /// Handle the command the event `id` was sent for until the guard is dropped
+    pub fn trace<E: Event>(&self, id: EventId<E>) -> TraceGuard {
+        TraceGuard::enter(self.traces.get(id))
+    }
+
+    fn send_json(&self, key_expr: String, message: &impl serde::Serialize) {
+        match serde_json::to_value(message) {
+            Ok(mut json) => {
+                if let Some(trace) = TraceGuard::current() {
+                    annotate(&mut json, "trace_id", trace.trace_id);
+                }
+                self.outbound.push(key_expr, json);
+            }
+            Err(error) => error!(?error, key_expr, "Failed to serialize message"),
+        }
+    }
+}
+
+/// Messages received over zenoh that need to be handled inside of bevy
+pub enum FaceMessage {
+    Connected(bool),
+    Settings(NoiseGeneratorSettingsUpdate),
+    Calibration(CalibrationMessage),
+    Sound(SoundSettingsUpdate),
+    RawWave(RawWaveSamples),
+    Polyline(PolylineMessage),
+    Imu(ImuMessage),
+    DisplayPower(DisplayPowerChanged),
+    Param(ParamUpdate),
+    Ping(PingRequest),
+    /// Event of a topic registered with [`crate::topics::RegisterTopic`]
+    Topic(TopicEvent),
+    #[cfg(feature = "hw-telemetry")]
+    HardwareTelemetry(HardwareTelemetry),
+    /// Messages dispatched together in the same frame
+    Batch(Vec<FaceMessage>),
+    /// Message of a command sent with a `trace_id`
+    Traced(CommandTrace, Box<FaceMessage>),
+}
+
+impl FaceMessage {
+    /// Whether the message comes from a command rather than connection state or telemetry
+    fn is_command(&self) -> bool {
+        match self {
+            FaceMessage::Connected(_) => false,
+            #[cfg(feature = "hw-telemetry")]
+            FaceMessage::HardwareTelemetry(_) => false,
+            // the batched and traced messages are counted once they are unwrapped
+            FaceMessage::Batch(_) | FaceMessage::Traced(..) => false,
+            _ => true,
+        }
+    }
+
+    /// Wrap the message in the trace of the command it was parsed from, if it has one
+    fn traced(self, key_expr: &str, payload: &[u8]) -> Self {
+        match CommandTrace::parse(key_expr, payload) {
+            Some(trace) => FaceMessage::Traced(trace, Box::new(self)),
+            None => self,
+        }
+    }
+}
+
+/// Single command of a batch named after the topic it would be sent on
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum BatchCommand {
+    Settings(NoiseGeneratorSettingsUpdate),
+    Calibration(CalibrationMessage),
+    Audio(SoundSettingsUpdate),
+    Polyline(PolylineMessage),
+}
+
+impl BatchCommand {
+    /// Key the command named `name` would otherwise be sent on
+    fn key_expr(name: &str) -> Option<&'static str> {
+        match name {
+            "settings" => Some("face/settings"),
+            "calibration" => Some("face/calibration"),
+            "audio" => Some("face/audio"),
+            "polyline" => Some("face/draw/polyline"),
+            _ => None,
+        }
+    }
+}
+
+/// Check every command of a batch against the access control rules under the key it replaces
+///
+/// Malformed batches are left for parsing to reject
+fn check_batch(acl: &CommandAcl, payload: &[u8]) -> Result<(), String> {
+    let Ok(commands) =
+        serde_json::from_slice::<Vec<serde_json::Map<String, serde_json::Value>>>(payload)
+    else {
+        return Ok(());
+    };
+    for (name, command) in commands.iter().flatten() {
+        if let Some(key_expr) = BatchCommand::key_expr(name) {
+            acl.check(key_expr, command.to_string().as_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+impl From<BatchCommand> for FaceMessage {
+    fn from(command: BatchCommand) -> Self {
+        match command {
+            BatchCommand::Settings(message) => FaceMessage::Settings(message),
+            BatchCommand::Calibration(message) => FaceMessage::Calibration(message),
+            BatchCommand::Audio(message) => FaceMessage::Sound(message),
+            BatchCommand::Polyline(message) => FaceMessage::Polyline(message),
+        }
+    }
+}
+
+/// Parse the payload of a message received on `key_expr`
+///
+/// `binary` is set for payloads sent with the octet stream encoding
+pub fn parse_face_message(
+    key_expr: &str,
+    payload: &[u8],
+    binary: bool,
+) -> anyhow::Result<FaceMessage> {
+    let message = match key_expr {
+        "face/settings" => FaceMessage::Settings(parse_json_payload(payload)?),
+        "face/calibration" => FaceMessage::Calibration(parse_json_payload(payload)?),
+        "face/audio" => FaceMessage::Sound(parse_json_payload(payload)?),
+        "face/draw/polyline" => FaceMessage::Polyline(parse_json_payload(payload)?),
+        "face/imu" => FaceMessage::Imu(parse_json_payload(payload)?),
+        "face/batch" => {
+            let commands: Vec<BatchCommand> = parse_json_payload(payload)?;
+            FaceMessage::Batch(commands.into_iter().map(FaceMessage::from).collect())
+        }
+        "face/wave/raw" => {
+            FaceMessage::RawWave(RawWaveSamples(parse_wave_payload(payload, binary)?))
+        }
+        _ => anyhow::bail!("No messages are handled on {key_expr}"),
+    };
+    Ok(message)
+}
+
+/// What happens to a message when bevy falls behind and the queue is full
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Wait for room, which holds up further messages on the same topic
+    #[default]
+    Block,
+    /// Drop the new message
+    DropNewest,
+    /// Keep only the newest message of each topic until there is room again
+    ///
+    /// Meant for state such as sensor readings where older values are useless
+    LatestWins,
+}
+
+/// Size and overflow behaviour of the queue between the zenoh worker and bevy
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct MessageQueueSettings {
+    pub capacity: usize,
+    /// Policy for json commands, streamed raw waves and imu readings always use latest wins
+    pub command_overflow: OverflowPolicy,
+}
+
+impl Default for MessageQueueSettings {
+    fn default() -> Self {
+        Self {
+            capacity: 10,
+            command_overflow: OverflowPolicy::Block,
+        }
+    }
+}
+
+#[derive(Resource, Default, Debug)]
+pub struct MessageQueueStats {
+    /// Number of messages lost because the queue was full
+    pub dropped: u64,
+    /// Number of outbound messages lost because the network couldn't keep up
+    pub outbound_dropped: u64,
+    /// When the last message other than connection changes and telemetry arrived
+    pub last_command: Option<std::time::Instant>,
+    /// Number of messages dispatched, counting the messages of a batch individually
+    pub received: u64,
+}
+
+/// Messages that didn't fit into the queue, shared by the zenoh worker and bevy
+#[derive(Default)]
+struct MessageOverflow {
+    /// Newest message of each latest wins topic waiting for room in the queue
+    latest: Mutex<BTreeMap<&'static str, FaceMessage>>,
+    dropped: AtomicU64,
+}
+
+impl MessageOverflow {
+    fn latest(&self) -> std::sync::MutexGuard<'_, BTreeMap<&'static str, FaceMessage>> {
+        self.latest.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn count_drop(&self) {
+        self.dropped.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Sending half of the message queue used by the zenoh worker
+#[derive(Clone)]
+struct MessageSender {
+    tx: Sender<FaceMessage>,
+    overflow: Arc<MessageOverflow>,
+}
+
+impl MessageSender {
+    /// Send a message waiting for room in the queue
+    async fn send(&self, message: FaceMessage) -> Result<(), SendError<FaceMessage>> {
+        self.tx.send(message).await
+    }
+
+    /// Send a message received on `key_expr` handling a full queue according to `policy`
+    async fn send_with_policy(
+        &self,
+        key_expr: &'static str,
+        message: FaceMessage,
+        policy: OverflowPolicy,
+    ) -> anyhow::Result<()> {
+        match policy {
+            OverflowPolicy::Block => {
+                self.send(message)
+                    .await
+                    .context("Failed to send message on channel")?;
+            }
+            OverflowPolicy::DropNewest => match self.tx.try_send(message) {
+                Ok(()) => {}
+                Err(TrySendError::Full(_)) => self.overflow.count_drop(),
+                Err(TrySendError::Closed(_)) => anyhow::bail!("Message channel closed"),
+            },
+            OverflowPolicy::LatestWins => {
+                let mut latest = self.overflow.latest();
+                // once a topic overflowed its messages wait behind the queued one to keep their order
+                if let Some(queued) = latest.get_mut(key_expr) {
+                    *queued = message;
+                    self.overflow.count_drop();
+                    return Ok(());
+                }
+                match self.tx.try_send(message) {
+                    Ok(()) => {}
+                    Err(TrySendError::Full(message)) => {
+                        latest.insert(key_expr, message);
+                    }
+                    Err(TrySendError::Closed(_)) => anyhow::bail!("Message channel closed"),
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Resource)]
+pub struct StreamReceiver {
+    rx: Receiver<FaceMessage>,
+    overflow: Arc<MessageOverflow>,
+}
+
+/// Event writers for every message the zenoh worker can forward
+///
+/// Events of registered topics are sent through commands instead
+#[derive(SystemParam)]
+struct FaceEventWriters<'w> {
+    settings: EventWriter<'w, NoiseGeneratorSettingsUpdate>,
+    calibration: EventWriter<'w, CalibrationMessage>,
+    sound: EventWriter<'w, SoundSettingsUpdate>,
+    raw_wave: EventWriter<'w, RawWaveSamples>,
+    polyline: EventWriter<'w, PolylineMessage>,
+    imu: EventWriter<'w, ImuMessage>,
+    display_power: EventWriter<'w, DisplayPowerChanged>,
+    param: EventWriter<'w, ParamUpdate>,
+    ping: EventWriter<'w, PingRequest>,
+    #[cfg(feature = "hw-telemetry")]
+    hardware_telemetry: EventWriter<'w, HardwareTelemetry>,
+}
+
+/// Forward messages from the zenoh worker as bevy events
+fn dispatch_face_messages(
+    mut receiver: ResMut<StreamReceiver>,
+    mut zenoh_connection: ResMut<ZenohConnection>,
+    mut stats: ResMut<MessageQueueStats>,
+    mut events: FaceEventWriters,
+    mut commands: Commands,
+    (traces, ack_sender): (Res<CommandTraces>, Res<AckSender>),
+) {
+    traces.start_frame();
+    let dropped = receiver.overflow.dropped.load(Ordering::Relaxed);
+    if stats.dropped != dropped {
+        stats.dropped = dropped;
+    }
+    let outbound_dropped = ack_sender.outbound.dropped();
+    if stats.outbound_dropped != outbound_dropped {
+        stats.outbound_dropped = outbound_dropped;
+    }
+
+    // overflowed messages arrived after everything that is already queued
+    let mut overflowed: Vec<_> = std::mem::take(&mut *receiver.overflow.latest())
+        .into_values()
+        .collect();
+    overflowed.reverse();
+
+    // batched messages are expanded in place so they keep their order
+    let mut pending = vec![];
+    while let Some((trace, message)) = pending
+        .pop()
+        .or_else(|| receiver.rx.try_recv().ok().map(|message| (None, message)))
+        .or_else(|| overflowed.pop().map(|message| (None, message)))
+    {
+        if message.is_command() {
+            stats.last_command = Some(std::time::Instant::now());
+        }
+        if !matches!(
+            message,
+            FaceMessage::Connected(_) | FaceMessage::Batch(_) | FaceMessage::Traced(..)
+        ) {
+            stats.received += 1;
+        }
+        match message {
+            FaceMessage::Connected(connected) => {
+                zenoh_connection.connected = connected;
+            }
+            FaceMessage::Settings(message) => {
+                traces.dispatched(events.settings.send(message), trace);
+            }
+            FaceMessage::Calibration(message) => {
+                traces.dispatched(events.calibration.send(message), trace);
+            }
+            FaceMessage::Sound(message) => {
+                traces.dispatched(events.sound.send(message), trace);
+            }
+            FaceMessage::RawWave(message) => {
+                traces.dispatched(events.raw_wave.send(message), trace);
+            }
+            FaceMessage::Polyline(message) => {
+                traces.dispatched(events.polyline.send(message), trace);
+            }
+            FaceMessage::Imu(message) => {
+                traces.dispatched(events.imu.send(message), trace);
+            }
+            FaceMessage::DisplayPower(message) => {
+                traces.dispatched(events.display_power.send(message), trace);
+            }
+            FaceMessage::Param(message) => {
+                traces.dispatched(events.param.send(message), trace);
+            }
+            FaceMessage::Ping(message) => {
+                traces.dispatched(events.ping.send(message), trace);
+            }
+            FaceMessage::Topic(event) => {
+                commands.add(move |world: &mut World| event.send(world, trace));
+            }
+            #[cfg(feature = "hw-telemetry")]
+            FaceMessage::HardwareTelemetry(message) => {
+                traces.dispatched(events.hardware_telemetry.send(message), trace);
+            }
+            FaceMessage::Batch(messages) => {
+                pending.extend(
+                    messages
+                        .into_iter()
+                        .rev()
+                        .map(|message| (trace.clone(), message)),
+                );
+            }
+            FaceMessage::Traced(trace, message) => {
+                info_span!("command", trace_id = trace.trace_id).in_scope(|| {
+                    info!(key_expr = trace.key_expr, "Dispatching traced command");
+                });
+                pending.push((Some(trace), *message));
+            }
+        }
+    }
+}
 
-pub fn start_zenoh_worker(mut commands: Commands) {
-    let (mut tx, rx) = channel::<NoiseGeneratorSettingsUpdate>(10);
+fn update_messaging_section(
+    mut overlay: ResMut<MaintenanceOverlay>,
+    zenoh_connection: Res<ZenohConnection>,
+    stats: Res<MessageQueueStats>,
+    settings: Res<MessageQueueSettings>,
+) {
+    if !zenoh_connection.is_changed() && !stats.is_changed() {
+        return;
+    }
+    overlay.set_section(
+        "messaging",
+        format!(
+            "zenoh connected: {}\nqueue capacity: {}\ndropped messages: {}\ndropped outbound: {}",
+            zenoh_connection.connected, settings.capacity, stats.dropped, stats.outbound_dropped
+        ),
+    );
+}
+
+/// Key expression the `face/` keys are moved under on the network, such as `robots/alice/face`
+///
+/// Inside the face keys always start with `face/`, they are only translated at the zenoh session
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyPrefix(String);
+
+impl Default for KeyPrefix {
+    fn default() -> Self {
+        Self(String::from("face"))
+    }
+}
+
+impl KeyPrefix {
+    pub fn new(prefix: &str) -> anyhow::Result<Self> {
+        let prefix = prefix.trim_end_matches('/');
+        if prefix.is_empty() {
+            anyhow::bail!("Key prefix must not be empty");
+        }
+        if prefix.starts_with('/') || prefix.contains(['*', '$', '?', '#']) {
+            anyhow::bail!("Key prefix {prefix:?} must be a plain key without wildcards");
+        }
+        Ok(Self(prefix.to_owned()))
+    }
+
+    /// Key on the network for a face key such as `face/gaze`, other keys are kept
+    pub fn apply(&self, key_expr: &str) -> String {
+        match key_expr.strip_prefix("face/") {
+            Some(rest) => format!("{}/{rest}", self.0),
+            None => key_expr.to_owned(),
+        }
+    }
+
+    /// Face key for a key received on the network
+    pub fn strip(&self, key_expr: &str) -> String {
+        match key_expr
+            .strip_prefix(self.0.as_str())
+            .and_then(|rest| rest.strip_prefix('/'))
+        {
+            Some(rest) => format!("face/{rest}"),
+            None => key_expr.to_owned(),
+        }
+    }
+}
+
+/// Role of the zenoh session
+#[derive(
+    serde::Deserialize, serde::Serialize, clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ZenohMode {
+    /// Talk to other peers directly and through routers
+    Peer,
+    /// Only talk through a router
+    Client,
+    /// Route for other sessions
+    Router,
+}
+
+/// Zenoh session options
+///
+/// Settings left empty keep the zenoh defaults or the values from `config_file`
+#[derive(Resource, Clone, Default)]
+pub struct ZenohSettings {
+    /// Zenoh json5 configuration file applied before the other settings
+    pub config_file: Option<PathBuf>,
+    /// Peer, client or router, the zenoh default unless set
+    pub mode: Option<ZenohMode>,
+    /// Endpoints to connect to such as `tls/router.local:7447`
+    pub connect: Vec<String>,
+    /// Endpoints to listen on
+    pub listen: Vec<String>,
+    /// Certificate authority used to verify TLS endpoints
+    pub tls_root_ca_certificate: Option<PathBuf>,
+    /// Client certificate and key for mutual TLS
+    pub tls_client_certificate: Option<PathBuf>,
+    pub tls_client_private_key: Option<PathBuf>,
+    /// User name and password for authentication
+    pub user: Option<String>,
+    pub password: Option<String>,
+    /// Where the `face/` keys live on the network
+    pub key_prefix: KeyPrefix,
+}
+
+impl ZenohSettings {
+    pub fn to_config(&self) -> anyhow::Result<zenoh::config::Config> {
+        let mut config = match &self.config_file {
+            Some(path) => zenoh::config::Config::from_file(path)
+                .map_err(ErrorWrapper::ZenohError)
+                .with_context(|| format!("Failed to load zenoh config {path:?}"))?,
+            None => zenoh::config::Config::default(),
+        };
+
+        let mut values = vec![];
+        if let Some(mode) = self.mode {
+            values.push(("mode", serde_json::to_string(&mode)?));
+        }
+        if !self.connect.is_empty() {
+            values.push(("connect/endpoints", serde_json::to_string(&self.connect)?));
+        }
+        if !self.listen.is_empty() {
+            values.push(("listen/endpoints", serde_json::to_string(&self.listen)?));
+        }
+        if let Some(path) = &self.tls_root_ca_certificate {
+            values.push((
+                "transport/link/tls/root_ca_certificate",
+                serde_json::to_string(path)?,
+            ));
+        }
+        if self.tls_client_certificate.is_some() || self.tls_client_private_key.is_some() {
+            let (Some(certificate), Some(private_key)) =
+                (&self.tls_client_certificate, &self.tls_client_private_key)
+            else {
+                anyhow::bail!("Mutual TLS needs both a client certificate and a private key");
+            };
+            values.push((
+                "transport/link/tls/client_auth",
+                serde_json::to_string(&true)?,
+            ));
+            values.push((
+                "transport/link/tls/client_certificate",
+                serde_json::to_string(certificate)?,
+            ));
+            values.push((
+                "transport/link/tls/client_private_key",
+                serde_json::to_string(private_key)?,
+            ));
+        }
+        if self.user.is_some() || self.password.is_some() {
+            let (Some(user), Some(password)) = (&self.user, &self.password) else {
+                anyhow::bail!("Zenoh authentication needs both a user and a password");
+            };
+            values.push(("transport/auth/usrpwd/user", serde_json::to_string(user)?));
+            values.push((
+                "transport/auth/usrpwd/password",
+                serde_json::to_string(password)?,
+            ));
+        }
+
+        for (key, value) in values {
+            config
+                .insert_json5(key, &value)
+                .map_err(|error| anyhow::anyhow!("Failed to set zenoh config {key}: {error:?}"))?;
+        }
+        Ok(config)
+    }
+}
+
+/// Delay between reconnection attempts
+const ZENOH_RETRY_DELAY: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Resources the zenoh worker is started with
+#[derive(SystemParam)]
+struct ZenohWorkerResources<'w> {
+    zenoh_settings: Res<'w, ZenohSettings>,
+    queue_settings: Res<'w, MessageQueueSettings>,
+    outbound_settings: Res<'w, OutboundSettings>,
+    display_control: Res<'w, DisplayControl>,
+    health: Res<'w, HealthMonitor>,
+    params: Res<'w, ParamSnapshot>,
+    topics: Res<'w, TopicRegistry>,
+    event_log: Res<'w, EventLog>,
+    history: Res<'w, CommandHistory>,
+    traces: Res<'w, CommandTraces>,
+    wave_settings: Res<'w, WaveSettingsSnapshot>,
+    acl: Res<'w, CommandAcl>,
+    clock: Res<'w, SharedClock>,
+}
+
+fn start_zenoh_worker(mut commands: Commands, resources: ZenohWorkerResources) {
+    let worker = ZenohWorker {
+        zenoh_settings: resources.zenoh_settings.clone(),
+        queue_settings: *resources.queue_settings,
+        outbound_settings: *resources.outbound_settings,
+        outbound: OutboundQueue::new(resources.outbound_settings.capacity),
+        display_control: resources.display_control.clone(),
+        health: resources.health.clone(),
+        params: resources.params.clone(),
+        topics: resources.topics.clone(),
+        event_log: resources.event_log.clone(),
+        history: resources.history.clone(),
+        wave_settings: resources.wave_settings.clone(),
+        acl: resources.acl.clone(),
+        clock: resources.clock.clone(),
+    };
+    let (tx, rx) = channel::<FaceMessage>(worker.queue_settings.capacity.max(1));
+    let overflow = Arc::new(MessageOverflow::default());
+    let tx = MessageSender {
+        tx,
+        overflow: overflow.clone(),
+    };
+    let outbound = worker.outbound.clone();
 
     std::thread::spawn(move || {
         let rt = runtime::Builder::new_current_thread()
-            .enable_io()
+            .enable_all()
             .build()
             .expect("Failed to build tokio runtime");
         rt.block_on(async {
             loop {
-                if let Err(error) = run_zenoh_loop(&mut tx).await {
+                if let Err(error) = run_zenoh_loop(&tx, &worker).await {
                     error!(?error, "Zenoh loop failed");
+                    worker
+                        .event_log
+                        .record(EventKind::Error, format!("zenoh: {error:#}"));
                 }
+                _ = tx.send(FaceMessage::Connected(false)).await;
+                tokio::time::sleep(ZENOH_RETRY_DELAY).await;
             }
         });
     });
 
-    commands.insert_resource(StreamReceiver(rx));
+    commands.insert_resource(StreamReceiver { rx, overflow });
+    commands.insert_resource(AckSender::new(
+        outbound,
+        resources.zenoh_settings.key_prefix.clone(),
+        resources.traces.clone(),
+    ));
 }
 
-pub async fn run_zenoh_loop(tx: &mut Sender<NoiseGeneratorSettingsUpdate>) -> anyhow::Result<()> {
-    let zenoh_config = zenoh::config::Config::default();
+/// Bevy state the zenoh worker needs to (re)connect and serve queries
+struct ZenohWorker {
+    zenoh_settings: ZenohSettings,
+    queue_settings: MessageQueueSettings,
+    outbound_settings: OutboundSettings,
+    /// Kept across reconnects so nothing queued while offline is lost
+    outbound: OutboundQueue,
+    display_control: DisplayControl,
+    health: HealthMonitor,
+    params: ParamSnapshot,
+    topics: TopicRegistry,
+    event_log: EventLog,
+    history: CommandHistory,
+    wave_settings: WaveSettingsSnapshot,
+    acl: CommandAcl,
+    clock: SharedClock,
+}
+
+async fn run_zenoh_loop(tx: &MessageSender, worker: &ZenohWorker) -> anyhow::Result<()> {
+    let ZenohWorker {
+        zenoh_settings,
+        queue_settings,
+        outbound_settings,
+        outbound,
+        health,
+        params,
+        topics,
+        event_log,
+        history,
+        wave_settings,
+        acl,
+        clock,
+        ..
+    } = worker;
+    let mut display_control = worker.display_control.clone();
+    let key_prefix = &zenoh_settings.key_prefix;
+    let intake = CommandIntake {
+        event_log: event_log.clone(),
+        history: history.clone(),
+        acl: acl.clone(),
+        key_prefix: key_prefix.clone(),
+    };
+    let zenoh_config = zenoh_settings.to_config()?;
     let session = zenoh::open(zenoh_config)
         .res()
         .await
@@ -45,53 +816,1001 @@ pub async fn run_zenoh_loop(tx: &mut Sender<NoiseGeneratorSettingsUpdate>) -> an
         .into_arc();
 
     let settings_subscriber = session
-        .declare_subscriber("face/settings")
+        .declare_subscriber(key_prefix.apply("face/settings"))
         .res()
         .await
         .map_err(ErrorWrapper::ZenohError)
         .context("Failed to create subscriber")?;
 
     let display_subscriber = session
-        .declare_subscriber("face/display")
+        .declare_subscriber(key_prefix.apply("face/display"))
+        .res()
+        .await
+        .map_err(ErrorWrapper::ZenohError)
+        .context("Failed to create subscriber")?;
+
+    for key_expr in [
+        "face/calibration",
+        "face/audio",
+        "face/draw/polyline",
+        "face/batch",
+    ] {
+        forward_messages(
+            &session,
+            key_expr,
+            tx,
+            &intake,
+            queue_settings.command_overflow,
+            move |payload, binary| parse_face_message(key_expr, payload, binary),
+        )
+        .await?;
+    }
+    for topic in topics.iter() {
+        let policy = topic.overflow.unwrap_or(queue_settings.command_overflow);
+        let topic = topic.clone();
+        forward_messages(
+            &session,
+            topic.key_expr,
+            tx,
+            &intake,
+            policy,
+            move |payload, binary| topic.parse(payload, binary),
+        )
+        .await?;
+    }
+
+    let raw_wave_subscriber = session
+        .declare_subscriber(key_prefix.apply("face/wave/raw"))
+        .res()
+        .await
+        .map_err(ErrorWrapper::ZenohError)
+        .context("Failed to create subscriber")?;
+    let raw_wave_tx = tx.clone();
+    let raw_wave_intake = intake.clone();
+    tokio::spawn(async move {
+        while let Ok(message) = raw_wave_subscriber.recv_async().await {
+            match parse_sample(&message, &raw_wave_intake) {
+                Ok(message) => {
+                    // only the latest wave matters if bevy is behind
+                    let sent = raw_wave_tx
+                        .send_with_policy("face/wave/raw", message, OverflowPolicy::LatestWins)
+                        .await;
+                    if sent.is_err() {
+                        break;
+                    }
+                }
+                Err(error) => error!(?error, "Failed to parse raw wave"),
+            }
+        }
+    });
+
+    let imu_subscriber = session
+        .declare_subscriber(key_prefix.apply("face/imu"))
         .res()
         .await
         .map_err(ErrorWrapper::ZenohError)
         .context("Failed to create subscriber")?;
+    let imu_tx = tx.clone();
+    let imu_intake = intake.clone();
+    tokio::spawn(async move {
+        while let Ok(message) = imu_subscriber.recv_async().await {
+            match parse_sample(&message, &imu_intake) {
+                Ok(message) => {
+                    // imu readings come in at a high rate so only keep the latest if bevy is behind
+                    let sent = imu_tx
+                        .send_with_policy("face/imu", message, OverflowPolicy::LatestWins)
+                        .await;
+                    if sent.is_err() {
+                        break;
+                    }
+                }
+                Err(error) => error!(?error, "Failed to parse imu reading"),
+            }
+        }
+    });
+
+    tx.send(FaceMessage::Connected(true))
+        .await
+        .context("Failed to send message on channel")?;
+    event_log.record(EventKind::State, "zenoh connected");
+
+    let build_info = BuildInfo::current();
+    serve_json(&session, key_prefix, "face/version", move || {
+        build_info.clone()
+    })
+    .await?;
+    let health = health.clone();
+    serve_json(&session, key_prefix, "face/health", move || health.report()).await?;
+    if clock.authority {
+        serve_json(&session, key_prefix, CLOCK_KEY, clock_reply).await?;
+    } else {
+        tokio::spawn(sync_clock(
+            session.clone(),
+            clock.clone(),
+            key_prefix.apply(CLOCK_KEY),
+        ));
+    }
+    let wave_settings = wave_settings.clone();
+    serve_json(&session, key_prefix, "face/settings/get", move || {
+        wave_settings.get()
+    })
+    .await?;
+    serve_params(&session, tx, params, &intake).await?;
+    serve_ping(&session, tx, key_prefix).await?;
+    serve_asset_packs(&session, tx, &intake).await?;
+    let served_history = history.clone();
+    serve_json(&session, key_prefix, HISTORY_KEY, move || {
+        served_history.records()
+    })
+    .await?;
+
+    #[cfg(feature = "hw-telemetry")]
+    tokio::spawn(publish_hardware_telemetry(
+        outbound.clone(),
+        tx.clone(),
+        key_prefix.apply("face/telemetry/hw"),
+    ));
 
+    let display_tx = tx.clone();
+    let display_session = session.clone();
+    let display_intake = intake.clone();
     tokio::spawn(async move {
+        let mut display_on = None;
+        let mut night_mode = None;
         while let Ok(message) = display_subscriber.recv_async().await {
-            let json_message: String = message
-                .value
-                .try_into()
-                .expect("Failed to convert value to string");
-            let display_control_message: DisplayControlMessage =
-                serde_json::from_str(&json_message).expect("Failed to parse json");
-            if display_control_message.display_on {
-                info!("Turning on display");
-                turn_on_display().await.expect("failed to turn on display");
-            } else {
-                info!("Turning off display");
-                turn_off_display().await.expect("failed to turn on display");
+            let Some((display_control_message, trace)) =
+                parse_command(&display_session, &display_intake, message, |payload, _| {
+                    parse_json_payload::<DisplayControlMessage>(payload)
+                })
+                .await
+            else {
+                continue;
+            };
+            let reply_to = display_control_message.reply_to.as_deref();
+            let mut errors = vec![];
+            if let Some(transform) = &display_control_message.transform {
+                info!(transform, "Rotating display");
+                if let Err(error) = display_control.rotate_display(transform).await {
+                    error!(?error, "Failed to rotate display");
+                    errors.push(format!("{error:#}"));
+                }
+            }
+            if let Some(mode) = display_control_message.mode {
+                info!(%mode, "Setting display mode");
+                if let Err(error) = display_control.set_display_mode(mode).await {
+                    error!(?error, "Failed to set display mode");
+                    errors.push(format!("{error:#}"));
+                }
+            }
+            match display_control_message.display_on {
+                Some(true) => {
+                    info!("Turning on display");
+                    match display_control.turn_on_display().await {
+                        Ok(()) => {
+                            display_on = Some(true);
+                            _ = display_tx
+                                .send(FaceMessage::DisplayPower(DisplayPowerChanged { on: true }))
+                                .await;
+                        }
+                        Err(error) => {
+                            error!(?error, "Failed to turn on display");
+                            errors.push(format!("{error:#}"));
+                        }
+                    }
+                }
+                Some(false) => {
+                    info!("Turning off display");
+                    match display_control.turn_off_display().await {
+                        Ok(()) => {
+                            display_on = Some(false);
+                            _ = display_tx
+                                .send(FaceMessage::DisplayPower(DisplayPowerChanged { on: false }))
+                                .await;
+                        }
+                        Err(error) => {
+                            error!(?error, "Failed to turn off display");
+                            errors.push(format!("{error:#}"));
+                        }
+                    }
+                }
+                None => {}
+            }
+            if let Some(active) = display_control_message.night_mode {
+                night_mode = Some(active);
+                _ = display_tx
+                    .send(FaceMessage::Topic(TopicEvent::new(NightModeChanged {
+                        active,
+                    })))
+                    .await;
+            }
+            for error in &errors {
+                display_intake
+                    .event_log
+                    .record(EventKind::Error, format!("face/display: {error}"));
+            }
+            if let Some(reply_to) = reply_to {
+                let ack = if errors.is_empty() {
+                    CommandAck::applied(serde_json::json!({
+                        "output": display_control.output,
+                        "transform": display_control.transform,
+                        "mode": display_control.mode,
+                        "display_on": display_on,
+                        "night_mode": night_mode,
+                    }))
+                } else {
+                    CommandAck::rejected(errors.join(", "))
+                };
+                publish_ack(&display_session, reply_to, ack, trace.as_ref()).await;
             }
         }
     });
 
-    while let Ok(message) = settings_subscriber.recv_async().await {
-        let json_message: String = message
-            .value
-            .try_into()
-            .context("Failed to convert value to string")?;
-        let settings_update: NoiseGeneratorSettingsUpdate =
-            serde_json::from_str(&json_message).context("Failed to parse json")?;
-        tx.send(settings_update)
+    let mut telemetry_interval = tokio::time::interval(MESSAGE_TELEMETRY_INTERVAL);
+    let mut outbound_rate = outbound_settings.interval();
+    loop {
+        tokio::select! {
+            message = settings_subscriber.recv_async() => {
+                let Ok(message) = message else {
+                    break;
+                };
+                let key_expr = key_prefix.strip(message.key_expr.as_str());
+                let Some((settings_update, _)) = parse_command(&session, &intake, message, |payload, binary| {
+                    Ok(parse_face_message(&key_expr, payload, binary)?.traced(&key_expr, payload))
+                })
+                .await
+                else {
+                    continue;
+                };
+                tx.send_with_policy("face/settings", settings_update, queue_settings.command_overflow)
+                    .await?;
+            }
+            _ = telemetry_interval.tick() => {
+                queue_message_telemetry(outbound, tx, queue_settings, key_prefix);
+            }
+            (key_expr, json) = outbound.next(&mut outbound_rate) => {
+                publish_json(&session, &key_expr, &json).await;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Periodically publish hardware telemetry and forward it to the maintenance overlay
+#[cfg(feature = "hw-telemetry")]
+async fn publish_hardware_telemetry(outbound: OutboundQueue, tx: MessageSender, key_expr: String) {
+    let mut interval = tokio::time::interval(HARDWARE_TELEMETRY_INTERVAL);
+    loop {
+        interval.tick().await;
+        let telemetry = HardwareTelemetry::read().await;
+        match serde_json::to_value(&telemetry) {
+            Ok(json) => outbound.push(key_expr.clone(), json),
+            Err(error) => error!(?error, "Failed to serialize hardware telemetry"),
+        }
+        if tx
+            .send(FaceMessage::HardwareTelemetry(telemetry))
             .await
-            .context("Failed to send message on channel")?;
+            .is_err()
+        {
+            break;
+        }
     }
+}
+
+/// Queue the message queue statistics for publishing
+fn queue_message_telemetry(
+    outbound: &OutboundQueue,
+    tx: &MessageSender,
+    queue_settings: &MessageQueueSettings,
+    key_prefix: &KeyPrefix,
+) {
+    let json = serde_json::json!({
+        "capacity": queue_settings.capacity,
+        "queued": queue_settings.capacity.saturating_sub(tx.tx.capacity()),
+        "dropped": tx.overflow.dropped.load(Ordering::Relaxed),
+        "outbound_queued": outbound.len(),
+        "outbound_dropped": outbound.dropped(),
+    });
+    outbound.push(key_prefix.apply("face/telemetry/messaging"), json);
+}
+
+/// Subscribe to a topic and forward messages parsed with `parse` to bevy
+///
+/// Messages that fail to parse are logged, rejected and skipped
+async fn forward_messages(
+    session: &Arc<Session>,
+    key_expr: &'static str,
+    tx: &MessageSender,
+    intake: &CommandIntake,
+    policy: OverflowPolicy,
+    parse: impl Fn(&[u8], bool) -> anyhow::Result<FaceMessage> + Send + Sync + 'static,
+) -> anyhow::Result<()> {
+    let subscriber = session
+        .declare_subscriber(intake.key_prefix.apply(key_expr))
+        .res()
+        .await
+        .map_err(ErrorWrapper::ZenohError)
+        .context("Failed to create subscriber")?;
+
+    let tx = tx.clone();
+    let session = session.clone();
+    let intake = intake.clone();
+    tokio::spawn(async move {
+        while let Ok(message) = subscriber.recv_async().await {
+            let parsed = parse_command(&session, &intake, message, |payload, binary| {
+                Ok(parse(payload, binary)?.traced(key_expr, payload))
+            })
+            .await;
+            if let Some((message, _)) = parsed {
+                if tx
+                    .send_with_policy(key_expr, message, policy)
+                    .await
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Answer queries on `key_expr` with the json returned by `reply`
+async fn serve_json<T: serde::Serialize>(
+    session: &Arc<Session>,
+    key_prefix: &KeyPrefix,
+    key_expr: &'static str,
+    reply: impl Fn() -> T + Send + 'static,
+) -> anyhow::Result<()> {
+    let queryable = session
+        .declare_queryable(key_prefix.apply(key_expr))
+        .res()
+        .await
+        .map_err(ErrorWrapper::ZenohError)
+        .context("Failed to create queryable")?;
+
+    tokio::spawn(async move {
+        while let Ok(query) = queryable.recv_async().await {
+            let json = match serde_json::to_string(&reply()) {
+                Ok(json) => json,
+                Err(error) => {
+                    error!(?error, key_expr, "Failed to serialize reply");
+                    continue;
+                }
+            };
+            let value = Value::from(json).encoding(Encoding::APP_JSON);
+            if let Err(error) = query
+                .reply(Ok(Sample::new(query.key_expr().clone(), value)))
+                .res()
+                .await
+            {
+                error!(?error, key_expr, "Failed to reply to query");
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Get tunables with queries on `face/param/**` and set them with puts or queries with a value
+///
+/// Queries with a value are answered with the resulting value once applied
+async fn serve_params(
+    session: &Arc<Session>,
+    tx: &MessageSender,
+    params: &ParamSnapshot,
+    intake: &CommandIntake,
+) -> anyhow::Result<()> {
+    let key_prefix = &intake.key_prefix;
+    let subscriber = session
+        .declare_subscriber(key_prefix.apply("face/param/**"))
+        .res()
+        .await
+        .map_err(ErrorWrapper::ZenohError)
+        .context("Failed to create subscriber")?;
+    let put_tx = tx.clone();
+    let put_session = session.clone();
+    let put_intake = intake.clone();
+    tokio::spawn(async move {
+        while let Ok(sample) = subscriber.recv_async().await {
+            let key_expr = put_intake.key_prefix.strip(sample.key_expr.as_str());
+            let path = key_expr.trim_start_matches("face/param/").to_owned();
+            let Some((value, _)) =
+                parse_command(&put_session, &put_intake, sample, |payload, _| {
+                    parse_param_value(payload)
+                })
+                .await
+            else {
+                continue;
+            };
+            let update = FaceMessage::Param(ParamUpdate {
+                path,
+                value,
+                reply: None,
+            });
+            if put_tx.send(update).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let queryable = session
+        .declare_queryable(key_prefix.apply("face/param/**"))
+        .res()
+        .await
+        .map_err(ErrorWrapper::ZenohError)
+        .context("Failed to create queryable")?;
+    let tx = tx.clone();
+    let params = params.clone();
+    let intake = intake.clone();
+    tokio::spawn(async move {
+        while let Ok(query) = queryable.recv_async().await {
+            let key_expr = query.key_expr().to_string();
+            let face_key = intake.key_prefix.strip(&key_expr);
+            let path = face_key.trim_start_matches("face/param/").to_owned();
+            let Some(value) = query.value() else {
+                let replies = if path.contains('*') {
+                    params
+                        .all()
+                        .into_iter()
+                        .map(|(name, value)| {
+                            (
+                                intake.key_prefix.apply(&format!("face/param/{name}")),
+                                value,
+                            )
+                        })
+                        .collect()
+                } else {
+                    params
+                        .get(&path)
+                        .map(|value| (key_expr, value))
+                        .into_iter()
+                        .collect::<Vec<_>>()
+                };
+                for (key_expr, value) in replies {
+                    reply_json(&query, key_expr, Ok(value)).await;
+                }
+                continue;
+            };
+            let value = intake.accept(
+                &face_key,
+                &value.payload.contiguous(),
+                false,
+                |payload, _| parse_param_value(payload),
+            );
+            if let Err(error) = &value {
+                error!(?error, key_expr = face_key, "Rejecting parameter");
+            }
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                let result = match value {
+                    Ok(value) => {
+                        let (reply, result) = tokio::sync::oneshot::channel();
+                        let update = FaceMessage::Param(ParamUpdate {
+                            path,
+                            value,
+                            reply: Some(reply),
+                        });
+                        match tx.send(update).await {
+                            Ok(()) => result
+                                .await
+                                .unwrap_or_else(|_| Err(String::from("Update was dropped"))),
+                            Err(_) => Err(String::from("Face is shutting down")),
+                        }
+                    }
+                    Err(error) => Err(format!("{error:#}")),
+                };
+                reply_json(&query, key_expr, result).await;
+            });
+        }
+    });
+    Ok(())
+}
+
+/// Answer queries on `face/ping` once bevy handled them, timestamped on arrival
+async fn serve_ping(
+    session: &Arc<Session>,
+    tx: &MessageSender,
+    key_prefix: &KeyPrefix,
+) -> anyhow::Result<()> {
+    let queryable = session
+        .declare_queryable(key_prefix.apply(PING_KEY))
+        .res()
+        .await
+        .map_err(ErrorWrapper::ZenohError)
+        .context("Failed to create queryable")?;
+    let tx = tx.clone();
+    tokio::spawn(async move {
+        while let Ok(query) = queryable.recv_async().await {
+            let received_unix_ms = local_unix_ms();
+            let ping = match query.value() {
+                Some(value) => parse_json_payload::<PingQuery>(&value.payload.contiguous()),
+                None => Ok(PingQuery::default()),
+            };
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                let key_expr = query.key_expr().to_string();
+                let result = match ping {
+                    Ok(ping) => {
+                        let (reply, result) = tokio::sync::oneshot::channel();
+                        let request = FaceMessage::Ping(PingRequest {
+                            query: ping,
+                            received_unix_ms,
+                            reply,
+                        });
+                        match tx.send(request).await {
+                            Ok(()) => match result.await {
+                                Ok(reply) => Ok(serde_json::to_value(reply).unwrap_or_default()),
+                                Err(_) => Err(String::from("Ping was dropped")),
+                            },
+                            Err(_) => Err(String::from("Face is shutting down")),
+                        }
+                    }
+                    Err(error) => Err(format!("{error:#}")),
+                };
+                reply_json(&query, key_expr, result).await;
+            });
+        }
+    });
+    Ok(())
+}
+
+/// Install asset packs requested on `face/assets/install` one at a time
+async fn serve_asset_packs(
+    session: &Arc<Session>,
+    tx: &MessageSender,
+    intake: &CommandIntake,
+) -> anyhow::Result<()> {
+    let subscriber = session
+        .declare_subscriber(intake.key_prefix.apply("face/assets/install"))
+        .res()
+        .await
+        .map_err(ErrorWrapper::ZenohError)
+        .context("Failed to create subscriber")?;
+    let session = session.clone();
+    let tx = tx.clone();
+    let intake = intake.clone();
+    tokio::spawn(async move {
+        let assets_dir = assets_dir();
+        while let Ok(message) = subscriber.recv_async().await {
+            let Some((message, trace)) = parse_command(&session, &intake, message, |payload, _| {
+                parse_json_payload::<AssetPackMessage>(payload)
+            })
+            .await
+            else {
+                continue;
+            };
+            info!(
+                name = message.name,
+                url = message.url,
+                "Installing asset pack"
+            );
+            let ack = match install_asset_pack(&assets_dir, &message).await {
+                Ok(installed) => {
+                    let ack = CommandAck::applied(serde_json::json!({
+                        "name": installed.name,
+                        "assets": installed.assets.len(),
+                    }));
+                    intake.event_log.record(
+                        EventKind::State,
+                        format!("asset pack {} installed", installed.name),
+                    );
+                    if tx
+                        .send(FaceMessage::Topic(TopicEvent::new(installed)))
+                        .await
+                        .is_err()
+                    {
+                        break;
+                    }
+                    ack
+                }
+                Err(error) => {
+                    error!(?error, name = message.name, "Failed to install asset pack");
+                    intake.event_log.record(
+                        EventKind::Error,
+                        format!("asset pack {}: {error:#}", message.name),
+                    );
+                    CommandAck::rejected(format!("{error:#}"))
+                }
+            };
+            if let Some(reply_to) = &message.reply_to {
+                publish_ack(&session, reply_to, ack, trace.as_ref()).await;
+            }
+        }
+    });
     Ok(())
 }
 
+/// Reply to `query` with a json value or an error message
+async fn reply_json(
+    query: &zenoh::queryable::Query,
+    key_expr: String,
+    result: Result<serde_json::Value, String>,
+) {
+    let reply = match result {
+        Ok(value) => match KeyExpr::try_from(key_expr) {
+            Ok(key_expr) => Ok(Sample::new(
+                key_expr,
+                Value::from(value.to_string()).encoding(Encoding::APP_JSON),
+            )),
+            Err(error) => {
+                error!(?error, "Invalid reply key");
+                return;
+            }
+        },
+        Err(error) => Err(Value::from(error)),
+    };
+    if let Err(error) = query.reply(reply).res().await {
+        error!(?error, "Failed to reply to query");
+    }
+}
+
+/// Commands received by the zenoh worker are checked against the access control rules,
+/// recorded in the command history and logged
+#[derive(Clone)]
+struct CommandIntake {
+    event_log: EventLog,
+    history: CommandHistory,
+    acl: CommandAcl,
+    /// Received keys are checked and recorded as face keys
+    key_prefix: KeyPrefix,
+}
+
+impl CommandIntake {
+    /// Check a command received on the face key `key_expr` and parse it with `parse`
+    ///
+    /// Accepted commands are recorded, rejected ones logged as errors
+    fn accept<T>(
+        &self,
+        key_expr: &str,
+        payload: &[u8],
+        binary: bool,
+        parse: impl FnOnce(&[u8], bool) -> anyhow::Result<T>,
+    ) -> anyhow::Result<T> {
+        let parsed = self
+            .acl
+            .check(key_expr, payload)
+            .and_then(|()| match key_expr {
+                "face/batch" => check_batch(&self.acl, payload),
+                _ => Ok(()),
+            })
+            .map_err(anyhow::Error::msg)
+            .and_then(|()| parse(payload, binary));
+        match &parsed {
+            Ok(_) => {
+                self.history.record(key_expr, payload, binary);
+                self.event_log.record(EventKind::Command, key_expr);
+            }
+            Err(error) => self
+                .event_log
+                .record(EventKind::Error, format!("{key_expr}: {error:#}")),
+        }
+        parsed
+    }
+
+    /// Check a sample of the `face/wave/raw` or `face/imu` stream received on the network key
+    /// `key_expr` and parse it
+    ///
+    /// Checked against the access control rules like commands, but not recorded
+    /// since the streams would push the commands out of the history
+    fn accept_stream(
+        &self,
+        key_expr: &str,
+        payload: &[u8],
+        binary: bool,
+    ) -> anyhow::Result<FaceMessage> {
+        let key_expr = self.key_prefix.strip(key_expr);
+        self.acl
+            .check(&key_expr, payload)
+            .map_err(anyhow::Error::msg)?;
+        parse_face_message(&key_expr, payload, binary)
+    }
+}
+
+/// Parse a command with `parse`, returning it with the trace it was sent under
+///
+/// Commands that fail to parse or aren't allowed are logged and rejected
+/// on their `reply_to` key if it can be read
+async fn parse_command<T>(
+    session: &Session,
+    intake: &CommandIntake,
+    sample: Sample,
+    parse: impl FnOnce(&[u8], bool) -> anyhow::Result<T>,
+) -> Option<(T, Option<CommandTrace>)> {
+    let key_expr = intake.key_prefix.strip(sample.key_expr.as_str());
+    let binary = sample.value.encoding == Encoding::APP_OCTET_STREAM;
+    let payload = sample.value.payload.contiguous();
+    let trace = CommandTrace::parse(&key_expr, &payload);
+    let trace_id = trace.as_ref().map(|trace| trace.trace_id.as_str());
+    let span = info_span!("command", key_expr, trace_id);
+    let parsed = span.in_scope(|| intake.accept(&key_expr, &payload, binary, parse));
+    match parsed {
+        Ok(message) => Some((message, trace)),
+        Err(error) => {
+            span.in_scope(|| error!(?error, "Rejecting message"));
+            if let Some(reply_to) = parse_reply_to(&payload) {
+                let ack = CommandAck::rejected(format!("{error:#}"));
+                publish_ack(session, &reply_to, ack, trace.as_ref()).await;
+            }
+            None
+        }
+    }
+}
+
+/// Reply to a command answered outside bevy, carrying its `trace_id` like [`AckSender`] does
+async fn publish_ack(
+    session: &Session,
+    reply_to: &str,
+    ack: CommandAck,
+    trace: Option<&CommandTrace>,
+) {
+    let mut ack = serde_json::json!(ack);
+    if let Some(trace) = trace {
+        annotate(&mut ack, "trace_id", trace.trace_id.as_str());
+    }
+    publish_json(session, reply_to, &ack).await;
+}
+
+/// Read the `reply_to` key of a json command that may otherwise be invalid
+pub fn parse_reply_to(payload: &[u8]) -> Option<String> {
+    serde_json::from_slice::<serde_json::Value>(payload)
+        .ok()
+        .and_then(|value| value.get("reply_to")?.as_str().map(str::to_owned))
+}
+
+/// Publish without waiting for a congested network, which drops the message instead
+async fn publish_json(session: &Session, key_expr: &str, message: &impl serde::Serialize) {
+    let json = serde_json::to_string(message).expect("Failed to serialize message");
+    if let Err(error) = session
+        .put(key_expr, json)
+        .encoding(Encoding::APP_JSON)
+        .congestion_control(CongestionControl::Drop)
+        .res()
+        .await
+    {
+        error!(?error, key_expr, "Failed to publish message");
+    }
+}
+
+fn parse_sample(sample: &Sample, intake: &CommandIntake) -> anyhow::Result<FaceMessage> {
+    let binary = sample.value.encoding == Encoding::APP_OCTET_STREAM;
+    intake.accept_stream(
+        sample.key_expr.as_str(),
+        &sample.value.payload.contiguous(),
+        binary,
+    )
+}
+
+/// Parse wave samples either as a json array
+/// or as little endian f32 values when sent with the octet stream encoding
+pub fn parse_wave_payload(payload: &[u8], binary: bool) -> anyhow::Result<Vec<f32>> {
+    let samples: Vec<f32> = if binary {
+        let chunks = payload.chunks_exact(4);
+        if !chunks.remainder().is_empty() {
+            anyhow::bail!(
+                "Binary wave payload length {} is not a multiple of 4",
+                payload.len()
+            );
+        }
+        chunks
+            .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+            .collect()
+    } else {
+        parse_json_payload(payload)?
+    };
+    if samples.len() > MAX_WAVE_SAMPLES {
+        anyhow::bail!(
+            "Wave has {} samples, at most {MAX_WAVE_SAMPLES} are supported",
+            samples.len()
+        );
+    }
+    if samples.iter().any(|sample| !sample.is_finite()) {
+        anyhow::bail!("Wave samples must be finite");
+    }
+    Ok(samples)
+}
+
+pub fn parse_json_payload<T: serde::de::DeserializeOwned>(payload: &[u8]) -> anyhow::Result<T> {
+    serde_json::from_slice(payload).context("Failed to parse json")
+}
+
 #[derive(Error, Debug)]
 pub enum ErrorWrapper {
     #[error("Zenoh error {0:?}")]
     ZenohError(#[from] zenoh::Error),
 }
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    proptest! {
+        #[test]
+        fn batches_keep_their_order(heights in proptest::collection::vec(0.0..1000.0f64, 0..16)) {
+            let commands: Vec<_> = heights
+                .iter()
+                .map(|height| serde_json::json!({"settings": {"height_multiplier": height}}))
+                .collect();
+            let batch: Vec<BatchCommand> =
+                serde_json::from_value(serde_json::Value::Array(commands)).unwrap();
+            let parsed: Vec<_> = batch
+                .into_iter()
+                .map(|command| match command {
+                    BatchCommand::Settings(settings) => settings.height_multiplier,
+                    _ => None,
+                })
+                .collect();
+            prop_assert_eq!(parsed, heights.into_iter().map(Some).collect::<Vec<_>>());
+        }
+
+        #[test]
+        fn batches_never_panic_on_arbitrary_input(json in ".*") {
+            _ = serde_json::from_str::<Vec<BatchCommand>>(&json);
+        }
+    }
+
+    #[test]
+    fn batch_with_unknown_command_is_rejected() {
+        let json = r#"[{"settings": {"hidden": true}}, {"explode": {}}]"#;
+        assert!(serde_json::from_str::<Vec<BatchCommand>>(json).is_err());
+    }
+
+    #[test]
+    fn binary_wave_with_non_finite_samples_is_rejected() {
+        let payload: Vec<u8> = [0.5, f32::NAN, -0.5]
+            .into_iter()
+            .flat_map(f32::to_le_bytes)
+            .collect();
+        assert!(parse_wave_payload(&payload, true).is_err());
+        assert!(parse_wave_payload(&payload[..4], true).is_ok());
+        assert!(parse_wave_payload(&payload[..5], true).is_err());
+    }
+
+    #[test]
+    fn latest_wins_keeps_the_newest_overflowed_message() {
+        let (tx, mut rx) = channel(1);
+        let overflow = Arc::new(MessageOverflow::default());
+        let sender = MessageSender {
+            tx,
+            overflow: overflow.clone(),
+        };
+        let runtime = runtime::Builder::new_current_thread().build().unwrap();
+        for connected in [true, false, true, false] {
+            runtime
+                .block_on(sender.send_with_policy(
+                    "face/test",
+                    FaceMessage::Connected(connected),
+                    OverflowPolicy::LatestWins,
+                ))
+                .unwrap();
+        }
+
+        assert!(matches!(rx.try_recv(), Ok(FaceMessage::Connected(true))));
+        assert!(matches!(
+            overflow.latest().get("face/test"),
+            Some(FaceMessage::Connected(false))
+        ));
+        assert_eq!(overflow.dropped.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn params_from_denied_sources_are_rejected() {
+        let intake = CommandIntake {
+            event_log: EventLog::default(),
+            history: CommandHistory::default(),
+            acl: serde_json::from_value(serde_json::json!({
+                "rules": [{"keys": ["face/param/wave"], "sources": ["supervisor"]}]
+            }))
+            .unwrap(),
+            key_prefix: KeyPrefix::default(),
+        };
+        let set = |key_expr: &str, payload: &str| {
+            intake.accept(key_expr, payload.as_bytes(), false, |payload, _| {
+                parse_param_value(payload)
+            })
+        };
+        let height = "face/param/wave/height_multiplier";
+
+        assert!(set(height, "400").is_err());
+        assert!(set(height, r#"{"value": 400, "source": "dashboard"}"#).is_err());
+        assert_eq!(
+            set(height, r#"{"value": 400, "source": "supervisor"}"#).unwrap(),
+            serde_json::json!(400)
+        );
+        assert_eq!(
+            set("face/param/blink/enabled", "false").unwrap(),
+            serde_json::json!(false)
+        );
+        let accepted: Vec<_> = intake
+            .history
+            .records()
+            .into_iter()
+            .map(|record| record.key_expr)
+            .collect();
+        assert_eq!(accepted, [height, "face/param/blink/enabled"]);
+    }
+
+    #[test]
+    fn messages_on_unknown_topics_are_rejected() {
+        assert!(parse_face_message("face/display", b"{}", false).is_err());
+        assert!(parse_face_message("face/settings", b"{}", false).is_ok());
+    }
+
+    fn intake(key_prefix: KeyPrefix, acl: serde_json::Value) -> CommandIntake {
+        CommandIntake {
+            event_log: EventLog::default(),
+            history: CommandHistory::default(),
+            acl: serde_json::from_value(acl).unwrap(),
+            key_prefix,
+        }
+    }
+
+    #[test]
+    fn messages_under_a_key_prefix_are_parsed() {
+        let key_prefix = KeyPrefix::new("robot1/face").unwrap();
+        let intake = intake(key_prefix, serde_json::json!({}));
+        let imu = br#"{"linear_acceleration": [0.0, 0.0, 9.8]}"#;
+        assert!(matches!(
+            intake.accept_stream("robot1/face/imu", imu, false),
+            Ok(FaceMessage::Imu(_))
+        ));
+        assert!(matches!(
+            intake.accept_stream("robot1/face/wave/raw", b"[0.5, -0.5]", false),
+            Ok(FaceMessage::RawWave(_))
+        ));
+        assert!(intake.accept_stream("robot2/face/imu", imu, false).is_err());
+    }
+
+    #[test]
+    fn streams_are_checked_against_the_acl() {
+        let intake = intake(
+            KeyPrefix::default(),
+            serde_json::json!({"rules": [{"keys": ["face/imu"], "sources": ["base"]}]}),
+        );
+        let from = |source: &str| {
+            format!(r#"{{"linear_acceleration": [0.0, 0.0, 9.8], "source": "{source}"}}"#)
+        };
+        assert!(intake
+            .accept_stream("face/imu", from("base").as_bytes(), false)
+            .is_ok());
+        assert!(intake
+            .accept_stream("face/imu", from("arm").as_bytes(), false)
+            .is_err());
+        assert!(intake
+            .accept_stream("face/wave/raw", b"[0.5]", false)
+            .is_ok());
+    }
+
+    #[test]
+    fn batches_are_checked_against_the_acl_of_their_commands() {
+        let intake = intake(
+            KeyPrefix::default(),
+            serde_json::json!({"rules": [{"keys": ["face/calibration"], "sources": ["supervisor"]}]}),
+        );
+        let accept = |batch: serde_json::Value| {
+            intake.accept(
+                "face/batch",
+                batch.to_string().as_bytes(),
+                false,
+                |payload, binary| parse_face_message("face/batch", payload, binary),
+            )
+        };
+
+        assert!(accept(serde_json::json!([{"settings": {"hidden": true}}])).is_ok());
+        // a denied command rejects the whole batch
+        let denied = accept(serde_json::json!([
+            {"settings": {"hidden": true}},
+            {"calibration": {"enabled": true, "source": "arm"}},
+        ]));
+        let Err(error) = denied else {
+            panic!("batch with a denied command was accepted");
+        };
+        assert_eq!(error.to_string(), "arm may not send face/calibration");
+        assert!(accept(serde_json::json!([{"calibration": {"enabled": true}}])).is_err());
+        assert!(accept(serde_json::json!([
+            {"calibration": {"enabled": true, "source": "supervisor"}},
+        ]))
+        .is_ok());
+        assert_eq!(intake.history.records().len(), 2);
+    }
+}
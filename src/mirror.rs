@@ -0,0 +1,131 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{
+    face_clock::FaceClock,
+    heartbeat::Heartbeat,
+    messaging::AckSender,
+    night_mode::{NightMode, NightModeChanged},
+    noise_plugin::{NoiseGenerator, NoiseGeneratorSettings, NoiseGeneratorSettingsUpdate},
+    priority::Priority,
+    scene::{FaceScene, SceneRequests},
+    topics::RegisterTopic,
+};
+
+/// Replicate the face of one instance on others, such as a backpack screen mirroring the head
+///
+/// The leader publishes its effective state on `face/mirror/state` and followers apply it
+pub struct MirrorPlugin {
+    pub role: MirrorRole,
+    /// How often the leader publishes its state
+    pub interval: Duration,
+}
+
+impl Plugin for MirrorPlugin {
+    fn build(&self, app: &mut App) {
+        match self.role {
+            MirrorRole::Leader => {
+                app.insert_resource(MirrorTimer(Timer::new(self.interval, TimerMode::Repeating)))
+                    .add_systems(Last, publish_mirror_state);
+            }
+            MirrorRole::Follower => {
+                app.add_topic::<MirrorState>(MIRROR_STATE_KEY)
+                    .add_systems(Update, apply_mirror_state);
+            }
+        }
+    }
+}
+
+const MIRROR_STATE_KEY: &str = "face/mirror/state";
+/// Followers only jump to the wave position of the leader once they drifted this many noise steps
+const WAVE_STEP_TOLERANCE: f64 = 0.05;
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MirrorRole {
+    /// Publish the state of this face
+    Leader,
+    /// Show the state published by the leader
+    Follower,
+}
+
+/// Everything a follower needs to look like the leader
+#[derive(Event, serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+pub struct MirrorState {
+    pub scene: FaceScene,
+    pub wave: NoiseGeneratorSettingsUpdate,
+    /// Position of the leader in the noise
+    pub wave_step: f64,
+    /// Shared clock time the state was taken at
+    pub sent_at_ms: f64,
+    pub night_mode: bool,
+    pub heartbeat: Heartbeat,
+}
+
+#[derive(Resource)]
+struct MirrorTimer(Timer);
+
+fn publish_mirror_state(
+    mut timer: ResMut<MirrorTimer>,
+    ack_sender: Res<AckSender>,
+    scene: Res<State<FaceScene>>,
+    (settings, noise_generator): (Res<NoiseGeneratorSettings>, Option<Res<NoiseGenerator>>),
+    (night_mode, heartbeat, clock): (Res<NightMode>, Res<Heartbeat>, Res<FaceClock>),
+) {
+    if !timer.0.tick(clock.delta()).just_finished() {
+        return;
+    }
+    let state = MirrorState {
+        scene: *scene.get(),
+        wave: settings.to_update(),
+        wave_step: noise_generator.map_or(0.0, |generator| generator.elapsed_step),
+        sent_at_ms: clock.now_ms(),
+        night_mode: night_mode.active,
+        heartbeat: heartbeat.clone(),
+    };
+    ack_sender.publish(MIRROR_STATE_KEY, &state);
+}
+
+/// Apply the parts of the leader state that changed since it was last applied
+fn apply_mirror_state(
+    mut messages: EventReader<MirrorState>,
+    mut applied: Local<Option<MirrorState>>,
+    mut scene_requests: ResMut<SceneRequests>,
+    mut settings_events: EventWriter<NoiseGeneratorSettingsUpdate>,
+    mut night_mode_events: EventWriter<NightModeChanged>,
+    mut heartbeat: ResMut<Heartbeat>,
+    (mut noise_generator, clock): (Option<ResMut<NoiseGenerator>>, Res<FaceClock>),
+) {
+    let Some(state) = messages.read().last() else {
+        return;
+    };
+    let previous = applied.replace(state.clone());
+    if previous.as_ref().map(|previous| previous.scene) != Some(state.scene) {
+        info!(scene = ?state.scene, "Mirroring scene");
+        scene_requests.push(Priority::Commanded, state.scene, None);
+    }
+    if previous.as_ref().map(|previous| &previous.wave) != Some(&state.wave) {
+        settings_events.send(state.wave.clone());
+    }
+    if previous.as_ref().map(|previous| previous.night_mode) != Some(state.night_mode) {
+        night_mode_events.send(NightModeChanged {
+            active: state.night_mode,
+        });
+    }
+    if *heartbeat != state.heartbeat {
+        *heartbeat = state.heartbeat.clone();
+    }
+    if let Some(noise_generator) = noise_generator.as_mut() {
+        // the leader kept moving while the state was on its way
+        let latency_s = if clock.is_synced() {
+            ((clock.now_ms() - state.sent_at_ms) / 1000.0).max(0.0)
+        } else {
+            0.0
+        };
+        let frame_time_divider = state.wave.frame_time_divider.unwrap_or(1.0);
+        let wave_step = state.wave_step + latency_s / frame_time_divider;
+        if (noise_generator.elapsed_step - wave_step).abs() > WAVE_STEP_TOLERANCE {
+            noise_generator.elapsed_step = wave_step;
+        }
+    }
+}
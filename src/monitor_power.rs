@@ -0,0 +1,59 @@
+//! Monitor power control for desktop platforms without the wlr-output-management protocol
+
+/// How long a window can take to handle the power message before it's skipped
+#[cfg(target_os = "windows")]
+const MONITOR_POWER_TIMEOUT_MS: u32 = 1_000;
+
+/// Turn the monitors on or put them to sleep
+#[cfg(target_os = "windows")]
+pub fn set_monitor_power(on: bool) -> anyhow::Result<()> {
+    use windows_sys::Win32::{
+        Foundation::GetLastError,
+        UI::WindowsAndMessaging::{
+            SendMessageTimeoutW, HWND_BROADCAST, SC_MONITORPOWER, SMTO_ABORTIFHUNG, WM_SYSCOMMAND,
+        },
+    };
+
+    // -1 powers the monitors on, 2 turns them off
+    let power_state = if on { -1 } else { 2 };
+    let mut result = 0;
+    // SAFETY: broadcasting a system command with plain integer arguments
+    let sent = unsafe {
+        SendMessageTimeoutW(
+            HWND_BROADCAST,
+            WM_SYSCOMMAND,
+            SC_MONITORPOWER as usize,
+            power_state,
+            SMTO_ABORTIFHUNG,
+            MONITOR_POWER_TIMEOUT_MS,
+            &mut result,
+        )
+    };
+    if sent == 0 {
+        // SAFETY: only reads the error code of this thread
+        let error = unsafe { GetLastError() };
+        anyhow::bail!("Failed to send monitor power message, error {error}");
+    }
+    Ok(())
+}
+
+/// Turn the monitors on or put them to sleep
+#[cfg(target_os = "macos")]
+pub fn set_monitor_power(on: bool) -> anyhow::Result<()> {
+    use anyhow::Context;
+
+    // declaring user activity wakes the display, pmset puts it to sleep right away
+    let (program, args): (&str, &[&str]) = if on {
+        ("caffeinate", &["-u", "-t", "1"])
+    } else {
+        ("pmset", &["displaysleepnow"])
+    };
+    let status = std::process::Command::new(program)
+        .args(args)
+        .status()
+        .with_context(|| format!("Failed to run {program}"))?;
+    if !status.success() {
+        anyhow::bail!("{program} failed with {status}");
+    }
+    Ok(())
+}
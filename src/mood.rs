@@ -0,0 +1,244 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use bevy::prelude::*;
+
+use crate::{
+    easing::Easing,
+    face_clock::FaceClock,
+    messaging::{AckSender, CommandAck},
+    noise_plugin::NoiseGeneratorSettingsUpdate,
+    priority::Priority,
+    scene::{FaceScene, SceneRequests},
+    startup::StartupScene,
+    topics::RegisterTopic,
+};
+
+/// Baseline look of the face depending on the time of day
+///
+/// The baseline is applied when a new period starts so commands
+/// sent during a period stay in effect until the next one
+pub struct MoodPlugin;
+
+impl Plugin for MoodPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(MoodSchedule::default())
+            .init_resource::<StartupScene>()
+            .insert_resource(MoodCheckTimer(Timer::new(
+                MOOD_CHECK_INTERVAL,
+                TimerMode::Repeating,
+            )))
+            .add_topic::<MoodScheduleUpdate>("face/mood")
+            .add_systems(
+                Update,
+                (process_mood_schedule_updates, apply_mood_schedule).chain(),
+            );
+    }
+}
+
+const MOOD_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Baseline of a single period of the day
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub struct MoodPeriod {
+    /// Local hour the period starts at, it lasts until the next period starts
+    pub start_hour: u32,
+    /// Hex color of the wave
+    #[serde(default)]
+    pub color: Option<String>,
+    #[serde(default)]
+    pub frame_time_divider: Option<f64>,
+    #[serde(default)]
+    pub height_multiplier: Option<f64>,
+    /// Idle scene shown when nothing else is requested
+    #[serde(default)]
+    pub scene: Option<FaceScene>,
+    /// Seconds the wave takes to change into the period's look, changes right away without
+    #[serde(default)]
+    pub transition_s: Option<f32>,
+    /// Curve of the transition, cubic unless set
+    #[serde(default)]
+    pub easing: Option<Easing>,
+}
+
+impl MoodPeriod {
+    fn new(start_hour: u32, color: &str, frame_time_divider: f64, height_multiplier: f64) -> Self {
+        Self {
+            start_hour,
+            color: Some(color.to_owned()),
+            frame_time_divider: Some(frame_time_divider),
+            height_multiplier: Some(height_multiplier),
+            scene: None,
+            transition_s: None,
+            easing: None,
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct MoodSchedule {
+    pub enabled: bool,
+    /// Periods keyed by name
+    pub periods: BTreeMap<String, MoodPeriod>,
+}
+
+impl Default for MoodSchedule {
+    fn default() -> Self {
+        let periods = [
+            ("morning", MoodPeriod::new(6, "ffffff", 4.0, 400.0)),
+            ("day", MoodPeriod::new(12, "e0f0ff", 8.0, 400.0)),
+            ("evening", MoodPeriod::new(18, "ffb060", 12.0, 300.0)),
+            ("night", MoodPeriod::new(22, "a05020", 16.0, 200.0)),
+        ]
+        .into_iter()
+        .map(|(name, period)| (name.to_owned(), period))
+        .collect();
+        Self {
+            enabled: true,
+            periods,
+        }
+    }
+}
+
+impl MoodSchedule {
+    /// Period active at `hour`, wrapping around to the last period of the previous day
+    fn period_at(&self, hour: u32) -> Option<(&String, &MoodPeriod)> {
+        let by_start = |(_, period): &(&String, &MoodPeriod)| period.start_hour;
+        self.periods
+            .iter()
+            .filter(|(_, period)| period.start_hour <= hour)
+            .max_by_key(by_start)
+            .or_else(|| self.periods.iter().max_by_key(by_start))
+    }
+}
+
+#[derive(Event, serde::Deserialize)]
+pub struct MoodScheduleUpdate {
+    #[serde(default)]
+    enabled: Option<bool>,
+    /// Replaces the listed periods
+    #[serde(default)]
+    periods: Option<BTreeMap<String, MoodPeriod>>,
+    /// Periods to remove
+    #[serde(default)]
+    remove: Vec<String>,
+    /// Key to publish an acknowledgement to once applied
+    #[serde(default)]
+    reply_to: Option<String>,
+}
+
+#[derive(Resource)]
+struct MoodCheckTimer(Timer);
+
+fn process_mood_schedule_updates(
+    mut messages: EventReader<MoodScheduleUpdate>,
+    mut schedule: ResMut<MoodSchedule>,
+    ack_sender: Res<AckSender>,
+) {
+    for (message, id) in messages.read_with_id() {
+        let _trace = ack_sender.trace(id);
+        if let Some(enabled) = message.enabled {
+            info!(enabled, "Updating mood schedule enabled");
+            schedule.enabled = enabled;
+        }
+        if let Some(periods) = &message.periods {
+            for (name, period) in periods {
+                info!(name, ?period, "Updating mood period");
+                schedule.periods.insert(name.clone(), period.clone());
+            }
+        }
+        for name in &message.remove {
+            info!(name, "Removing mood period");
+            schedule.periods.remove(name);
+        }
+        ack_sender.send(
+            message.reply_to.as_deref(),
+            CommandAck::applied(serde_json::json!({
+                "enabled": schedule.enabled,
+                "periods": schedule.periods,
+            })),
+        );
+    }
+}
+
+fn apply_mood_schedule(
+    mut timer: ResMut<MoodCheckTimer>,
+    schedule: Res<MoodSchedule>,
+    (mut active_period, mut pushed_scene): (Local<Option<String>>, Local<bool>),
+    mut settings_events: EventWriter<NoiseGeneratorSettingsUpdate>,
+    mut scene_requests: ResMut<SceneRequests>,
+    (clock, startup_scene): (Res<FaceClock>, Res<StartupScene>),
+) {
+    let check_due = timer.0.tick(clock.delta()).just_finished();
+    if !check_due && !schedule.is_changed() {
+        return;
+    }
+    if !schedule.enabled {
+        if active_period.take().is_some() && std::mem::take(&mut *pushed_scene) {
+            startup_scene.restore(&mut scene_requests);
+        }
+        return;
+    }
+
+    let hour = clock.hour();
+    let Some((name, period)) = schedule.period_at(hour) else {
+        return;
+    };
+    if active_period.as_ref() == Some(name) && !schedule.is_changed() {
+        return;
+    }
+
+    info!(name, hour, "Applying mood period");
+    *active_period = Some(name.clone());
+    settings_events.send(NoiseGeneratorSettingsUpdate {
+        color: period.color.clone(),
+        frame_time_divider: period.frame_time_divider,
+        height_multiplier: period.height_multiplier,
+        transition_s: period.transition_s,
+        easing: period.easing,
+        ..default()
+    });
+    // periods without a scene show the startup scene
+    match period.scene {
+        Some(scene) => scene_requests.push(Priority::Idle, scene, None),
+        None if *pushed_scene => startup_scene.restore(&mut scene_requests),
+        None => {}
+    }
+    *pushed_scene = period.scene.is_some();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::face_clock::ClockSource;
+
+    #[test]
+    fn startup_scene_returns_after_a_period_with_its_own_scene() {
+        let mut schedule = MoodSchedule::default();
+        schedule.periods.get_mut("morning").unwrap().scene = Some(FaceScene::Greeting);
+        let mut app = App::new();
+        app.insert_resource(schedule)
+            .insert_resource(MoodCheckTimer(Timer::new(
+                MOOD_CHECK_INTERVAL,
+                TimerMode::Repeating,
+            )))
+            .insert_resource(FaceClock::new(ClockSource::simulated_from_hour(7)))
+            .insert_resource(StartupScene(Some(FaceScene::Calibration)))
+            .init_resource::<SceneRequests>()
+            .add_event::<NoiseGeneratorSettingsUpdate>()
+            .add_systems(Update, apply_mood_schedule);
+        app.world.resource_mut::<SceneRequests>().push(
+            Priority::Idle,
+            FaceScene::Calibration,
+            None,
+        );
+        let current = |app: &App| app.world.resource::<SceneRequests>().current().copied();
+
+        app.update();
+        assert_eq!(current(&app), Some(FaceScene::Greeting));
+
+        app.insert_resource(FaceClock::new(ClockSource::simulated_from_hour(13)));
+        app.world.resource_mut::<MoodSchedule>().set_changed();
+        app.update();
+        assert_eq!(current(&app), Some(FaceScene::Calibration));
+    }
+}
@@ -0,0 +1,245 @@
+use std::{collections::VecDeque, time::Duration};
+
+use bevy::prelude::*;
+
+use crate::{
+    face_clock::FaceClock, noise_plugin::RawWaveSamples, speech::SpeechEvent, wave::mouth_wave,
+};
+
+/// Shape the wave like a mouth from viseme timings published with the voice
+///
+/// Viseme sequences on `face/speech` are queued and played back in order, blending from one
+/// mouth shape to the next. Once the queue runs out the mouth closes and the idle wave takes over
+pub struct MouthPlugin;
+
+impl Plugin for MouthPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Mouth>()
+            .add_event::<VisemeSequence>()
+            .add_event::<SpeechEvent>()
+            .add_systems(
+                Update,
+                (queue_visemes, stop_with_the_voice, animate_mouth).chain(),
+            );
+    }
+}
+
+/// Longer sequences are rejected
+pub const MAX_VISEMES: usize = 1024;
+/// Visemes are held for at most this long
+const MAX_VISEME_DURATION: Duration = Duration::from_secs(2);
+/// Time constant of blending between mouth shapes
+const BLEND_TIME_S: f32 = 0.04;
+/// The mouth counts as closed below this openness
+const CLOSED_OPENNESS: f32 = 0.01;
+const MOUTH_WAVE_SAMPLES: usize = 64;
+
+/// Mouth shape of the Oculus viseme set
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Viseme {
+    #[serde(rename = "sil")]
+    Silence,
+    #[serde(rename = "PP")]
+    Pp,
+    #[serde(rename = "FF")]
+    Ff,
+    #[serde(rename = "TH")]
+    Th,
+    #[serde(rename = "DD")]
+    Dd,
+    #[serde(rename = "kk")]
+    Kk,
+    #[serde(rename = "CH")]
+    Ch,
+    #[serde(rename = "SS")]
+    Ss,
+    #[serde(rename = "nn")]
+    Nn,
+    #[serde(rename = "RR")]
+    Rr,
+    #[serde(rename = "aa")]
+    Aa,
+    E,
+    #[serde(rename = "ih", alias = "I")]
+    Ih,
+    #[serde(rename = "oh", alias = "O")]
+    Oh,
+    #[serde(rename = "ou", alias = "U")]
+    Ou,
+}
+
+impl Viseme {
+    /// Openness between 0.0 and 1.0 and wave cycles of the mouth shape
+    fn shape(self) -> MouthShape {
+        let (openness, cycles) = match self {
+            Viseme::Silence | Viseme::Pp => (0.0, 3.0),
+            Viseme::Ff => (0.15, 4.0),
+            Viseme::Th => (0.2, 4.0),
+            Viseme::Dd => (0.3, 3.0),
+            Viseme::Kk => (0.35, 3.0),
+            Viseme::Ch => (0.3, 5.0),
+            Viseme::Ss => (0.2, 6.0),
+            Viseme::Nn => (0.25, 3.0),
+            Viseme::Rr => (0.35, 2.0),
+            Viseme::Aa => (1.0, 3.0),
+            Viseme::E => (0.7, 4.0),
+            Viseme::Ih => (0.5, 5.0),
+            Viseme::Oh => (0.8, 1.5),
+            Viseme::Ou => (0.5, 1.0),
+        };
+        MouthShape { openness, cycles }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct VisemeTiming {
+    pub viseme: Viseme,
+    pub duration_ms: u32,
+}
+
+impl VisemeTiming {
+    fn duration(&self) -> Duration {
+        Duration::from_millis(self.duration_ms.into()).min(MAX_VISEME_DURATION)
+    }
+}
+
+/// Visemes to play after the queued ones, an empty sequence closes the mouth right away
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct VisemeSequence(pub Vec<VisemeTiming>);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct MouthShape {
+    openness: f32,
+    cycles: f32,
+}
+
+impl MouthShape {
+    const CLOSED: Self = Self {
+        openness: 0.0,
+        cycles: 3.0,
+    };
+
+    /// Move toward `target` by `blend` between 0.0 and 1.0
+    fn blend(self, target: Self, blend: f32) -> Self {
+        Self {
+            openness: self.openness + (target.openness - self.openness) * blend,
+            cycles: self.cycles + (target.cycles - self.cycles) * blend,
+        }
+    }
+}
+
+#[derive(Resource)]
+struct Mouth {
+    queue: VecDeque<VisemeTiming>,
+    /// Time the front of the queue started showing
+    started: Option<Duration>,
+    shape: MouthShape,
+}
+
+impl Default for Mouth {
+    fn default() -> Self {
+        Self {
+            queue: VecDeque::new(),
+            started: None,
+            shape: MouthShape::CLOSED,
+        }
+    }
+}
+
+impl Mouth {
+    fn clear(&mut self) {
+        self.queue.clear();
+        self.started = None;
+    }
+
+    /// Viseme showing at `now`, dropping the ones that ran out
+    fn current(&mut self, now: Duration) -> Option<Viseme> {
+        while let Some(front) = self.queue.front() {
+            let started = *self.started.get_or_insert(now);
+            let ends = started + front.duration();
+            if now < ends {
+                return Some(front.viseme);
+            }
+            self.queue.pop_front();
+            // the next viseme starts where this one ended so timings don't drift with the frame rate
+            self.started = Some(ends);
+        }
+        self.started = None;
+        None
+    }
+}
+
+fn queue_visemes(mut sequences: EventReader<VisemeSequence>, mut mouth: ResMut<Mouth>) {
+    for sequence in sequences.read() {
+        if sequence.0.is_empty() {
+            debug!("Closing mouth");
+            mouth.clear();
+        } else {
+            debug!(visemes = sequence.0.len(), "Queueing visemes");
+            mouth.queue.extend(sequence.0.iter().copied());
+        }
+    }
+}
+
+/// Visemes left over once the voice stopped would keep the mouth moving in silence
+fn stop_with_the_voice(mut events: EventReader<SpeechEvent>, mut mouth: ResMut<Mouth>) {
+    if events.read().any(|event| !event.speaking) {
+        mouth.clear();
+    }
+}
+
+fn animate_mouth(
+    mut mouth: ResMut<Mouth>,
+    clock: Res<FaceClock>,
+    mut raw_wave: EventWriter<RawWaveSamples>,
+) {
+    let viseme = mouth.current(clock.elapsed());
+    if viseme.is_none() && mouth.shape.openness < CLOSED_OPENNESS {
+        // no more samples lets the raw wave time out into the idle wave
+        mouth.shape = MouthShape::CLOSED;
+        return;
+    }
+    let target = viseme.map_or(MouthShape::CLOSED, Viseme::shape);
+    let blend = 1.0 - (-clock.delta_seconds() / BLEND_TIME_S).exp();
+    mouth.shape = mouth.shape.blend(target, blend);
+    raw_wave.send(RawWaveSamples(mouth_wave(
+        clock.elapsed_seconds_wrapped(),
+        mouth.shape.openness,
+        mouth.shape.cycles,
+        MOUTH_WAVE_SAMPLES,
+    )));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn visemes_play_back_to_back() {
+        let visemes: Vec<VisemeTiming> = serde_json::from_str(
+            r#"[{"viseme": "aa", "duration_ms": 100}, {"viseme": "O", "duration_ms": 50}]"#,
+        )
+        .unwrap();
+        assert_eq!(visemes[1].viseme, Viseme::Oh);
+
+        let mut mouth = Mouth::default();
+        mouth.queue.extend(visemes);
+        let start = Duration::from_secs(10);
+        assert_eq!(mouth.current(start), Some(Viseme::Aa));
+        assert_eq!(
+            mouth.current(start + Duration::from_millis(99)),
+            Some(Viseme::Aa)
+        );
+        // a late frame skips into the second viseme without shifting its end
+        assert_eq!(
+            mouth.current(start + Duration::from_millis(120)),
+            Some(Viseme::Oh)
+        );
+        assert_eq!(mouth.current(start + Duration::from_millis(150)), None);
+        assert!(mouth.queue.is_empty());
+
+        assert!(
+            serde_json::from_str::<VisemeTiming>(r#"{"viseme": "zz", "duration_ms": 1}"#).is_err()
+        );
+    }
+}
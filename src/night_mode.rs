@@ -0,0 +1,60 @@
+use bevy::prelude::*;
+
+/// Red shifted, dimmed look while the display is in night mode
+///
+/// Night mode is switched with `night_mode` on `face/display`
+pub struct NightModePlugin {
+    /// Multiplied with the colors of the face while night mode is on
+    pub tint: Color,
+}
+
+impl Plugin for NightModePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(NightMode {
+            active: false,
+            tint: self.tint,
+        })
+        .add_event::<NightModeChanged>()
+        .add_systems(PreUpdate, update_night_mode);
+    }
+}
+
+pub const DEFAULT_NIGHT_MODE_TINT: &str = "ff3000";
+
+#[derive(Resource, Debug)]
+pub struct NightMode {
+    pub active: bool,
+    pub tint: Color,
+}
+
+impl NightMode {
+    /// `color` as it should be drawn, tinted while night mode is on
+    pub fn apply(&self, color: Color) -> Color {
+        if !self.active {
+            return color;
+        }
+        let [red, green, blue, alpha] = color.as_rgba_f32();
+        let [tint_red, tint_green, tint_blue, _] = self.tint.as_rgba_f32();
+        Color::rgba(red * tint_red, green * tint_green, blue * tint_blue, alpha)
+    }
+}
+
+/// Sent after night mode was switched over `face/display`
+#[derive(Event)]
+pub struct NightModeChanged {
+    pub active: bool,
+}
+
+/// Parse a hex color argument such as "ff3000"
+pub fn parse_color(color: &str) -> Result<Color, String> {
+    Color::hex(color).map_err(|error| error.to_string())
+}
+
+fn update_night_mode(mut events: EventReader<NightModeChanged>, mut night_mode: ResMut<NightMode>) {
+    for event in events.read() {
+        if night_mode.active != event.active {
+            info!(active = event.active, "Switching night mode");
+            night_mode.active = event.active;
+        }
+    }
+}
@@ -1,19 +1,88 @@
-use bevy::prelude::*;
+use std::{
+    ops::RangeInclusive,
+    sync::{Arc, Mutex, PoisonError},
+    time::Duration,
+};
+
+use bevy::{
+    prelude::*,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+};
 use bevy_prototype_lyon::prelude::*;
-use noise::{BasicMulti, MultiFractal, NoiseFn, Perlin};
+use noise::{BasicMulti, MultiFractal, Perlin};
 
-use crate::messaging::StreamReceiver;
+use crate::{
+    easing::Easing,
+    face_clock::FaceClock,
+    glitch::Glitch,
+    heartbeat::{Heartbeat, HEARTBEAT_SWEEP},
+    messaging::{AckSender, CommandAck},
+    mood::MoodSchedule,
+    night_mode::NightMode,
+    params::{RegisterTunable, Tunable},
+    reaction::ReactionPose,
+    rotation::FaceRotation,
+    safe_area::SafeArea,
+    scene::FaceScene,
+    settings_file::SettingsFile,
+    temperament::Temperament,
+    velocity::RobotMotion,
+    voice_signature::VoiceSignature,
+    wave::{
+        add_heartbeat, build_line_mesh, catmull_rom, interpolate_samples, sample_noise,
+        smooth_samples, wave_points, write_tapered_line_mesh,
+    },
+};
 
 pub struct NoisePlugin;
 
 impl Plugin for NoisePlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(NoiseGeneratorSettings::default())
+            .init_resource::<NoiseSeed>()
+            .init_resource::<RawWave>()
+            .init_resource::<SettingsFile>()
+            .init_resource::<Heartbeat>()
+            .init_resource::<RobotMotion>()
+            .init_resource::<Temperament>()
+            .init_resource::<Glitch>()
+            .init_resource::<ReactionPose>()
+            .init_resource::<VoiceSignature>()
+            .init_resource::<WaveTransition>()
+            .init_resource::<WaveClip>()
+            .init_resource::<ClearColor>()
+            .init_resource::<WaveSettingsSnapshot>()
+            .add_event::<RawWaveSamples>()
+            .register_tunable::<NoiseGeneratorSettings>("wave")
             .add_plugins(ShapePlugin)
-            .add_systems(Startup, setup_noise_system)
+            .add_systems(Startup, (setup_noise_system, load_persisted_settings))
+            .add_systems(FixedUpdate, step_noise_simulation.run_if(wave_visible))
+            .add_systems(
+                Last,
+                snapshot_wave_settings.run_if(resource_changed::<NoiseGeneratorSettings>),
+            )
             .add_systems(
                 Update,
-                (update_noise_plot, process_noise_generator_update_messages),
+                (
+                    wave_visible.pipe(update_noise_visibility),
+                    update_noise_plot.run_if(wave_visible),
+                    process_noise_generator_update_messages,
+                    advance_wave_transition.after(process_noise_generator_update_messages),
+                    start_wave_cross_fade
+                        .after(advance_wave_transition)
+                        .before(apply_noise_generator_settings)
+                        .run_if(resource_changed::<NoiseGeneratorSettings>),
+                    apply_noise_generator_settings
+                        .after(advance_wave_transition)
+                        .run_if(
+                            resource_changed::<NoiseGeneratorSettings>
+                                .or_else(resource_changed::<NightMode>),
+                        ),
+                    update_wave_cross_fade
+                        .after(apply_noise_generator_settings)
+                        .run_if(resource_exists::<WaveCrossFade>),
+                    process_raw_wave_samples,
+                ),
             );
     }
 }
@@ -23,17 +92,56 @@ const HEIGHT_MULTIPLIER: f64 = 400.0;
 const SEGMENT_WIDTH: f32 = 5.0;
 const FRAME_TIME_DIVIDER: f64 = 8.0;
 const PERLIN_NOISE_OCTAVES: usize = 2;
+const SMOOTHING_WINDOW: usize = 1;
+const CURVE_SUBDIVISIONS: usize = 0;
 
 const LINE_WIDTH: f32 = 2.0;
+const LINE_COLOR: Color = Color::WHITE;
+/// Fall back to noise when no raw samples arrived for this long
+const RAW_WAVE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// How long the old wave takes to fade out after a drastic settings change
+const CROSS_FADE_DURATION: Duration = Duration::from_millis(400);
+/// Relative change of a shape setting that counts as drastic
+const CROSS_FADE_THRESHOLD: f64 = 0.33;
+/// Slower settings transitions are sped up to this
+const MAX_TRANSITION: Duration = Duration::from_secs(60);
+/// Draw the fading wave behind the new one
+const CROSS_FADE_Z: f32 = -0.1;
 const PERLIN_NOISE_SEED: u32 = 100;
 
-#[derive(Resource)]
-struct NoiseGeneratorSettings {
-    width_divider: f64,
-    height_multiplier: f64,
-    segment_width: f32,
-    frame_time_divider: f64,
-    hidden: bool,
+// Limits that keep the wave drawable
+// a segment width or divider of zero would need infinite points or speed
+pub const WIDTH_DIVIDER_RANGE: RangeInclusive<f64> = 1.0..=10_000.0;
+pub const HEIGHT_MULTIPLIER_RANGE: RangeInclusive<f64> = 0.0..=10_000.0;
+pub const SEGMENT_WIDTH_RANGE: RangeInclusive<f64> = 1.0..=500.0;
+pub const LINE_WIDTH_RANGE: RangeInclusive<f64> = 0.5..=50.0;
+pub const FRAME_TIME_DIVIDER_RANGE: RangeInclusive<f64> = 0.1..=1_000.0;
+/// More octaves get too slow on the Pi
+pub const PERLIN_NOISE_OCTAVES_RANGE: RangeInclusive<usize> = 1..=8;
+/// A window of 1 turns the moving average off
+pub const SMOOTHING_WINDOW_RANGE: RangeInclusive<usize> = 1..=32;
+/// Extra points per segment, the Pi struggles with much more than a few thousand points
+pub const CURVE_SUBDIVISIONS_RANGE: RangeInclusive<usize> = 0..=8;
+
+/// Current wave settings, changed through [`NoiseGeneratorSettingsUpdate`] events
+#[derive(Resource, Reflect, Clone, PartialEq)]
+#[reflect(Resource)]
+pub struct NoiseGeneratorSettings {
+    pub width_divider: f64,
+    pub height_multiplier: f64,
+    pub segment_width: f32,
+    pub line_width: f32,
+    pub frame_time_divider: f64,
+    pub perlin_noise_octaves: usize,
+    /// Samples averaged into each point of the wave
+    pub smoothing_window: usize,
+    /// Catmull-Rom points added between each pair of points
+    pub curve_subdivisions: usize,
+    pub hidden: bool,
+    pub color: Color,
+    /// Color behind the face
+    pub background: Color,
 }
 
 impl Default for NoiseGeneratorSettings {
@@ -42,201 +150,996 @@ impl Default for NoiseGeneratorSettings {
             width_divider: WIDTH_DIVIDER,
             height_multiplier: HEIGHT_MULTIPLIER,
             segment_width: SEGMENT_WIDTH,
+            line_width: LINE_WIDTH,
             frame_time_divider: FRAME_TIME_DIVIDER,
+            perlin_noise_octaves: PERLIN_NOISE_OCTAVES,
+            smoothing_window: SMOOTHING_WINDOW,
+            curve_subdivisions: CURVE_SUBDIVISIONS,
             hidden: false,
+            color: LINE_COLOR,
+            background: ClearColor::default().0,
+        }
+    }
+}
+
+impl NoiseGeneratorSettings {
+    /// Settings with the values of `update` clamped into their ranges, left out values are kept
+    pub fn updated(
+        &self,
+        update: &NoiseGeneratorSettingsUpdate,
+    ) -> Result<Self, bevy::render::color::HexColorError> {
+        let mut settings = self.clone();
+        if let Some(color) = &update.color {
+            settings.color = Color::hex(color)?;
+        }
+        if let Some(background) = &update.background {
+            settings.background = Color::hex(background)?;
+        }
+        if let Some(width_divider) = update.width_divider {
+            settings.width_divider = clamp_setting(width_divider, WIDTH_DIVIDER_RANGE);
+        }
+        if let Some(height_multiplier) = update.height_multiplier {
+            settings.height_multiplier = clamp_setting(height_multiplier, HEIGHT_MULTIPLIER_RANGE);
+        }
+        if let Some(segment_width) = update.segment_width {
+            settings.segment_width =
+                clamp_setting(segment_width.into(), SEGMENT_WIDTH_RANGE) as f32;
+        }
+        if let Some(line_width) = update.line_width {
+            settings.line_width = clamp_setting(line_width.into(), LINE_WIDTH_RANGE) as f32;
+        }
+        if let Some(frame_time_divider) = update.frame_time_divider {
+            settings.frame_time_divider =
+                clamp_setting(frame_time_divider, FRAME_TIME_DIVIDER_RANGE);
         }
+        if let Some(hidden) = update.hidden {
+            settings.hidden = hidden;
+        }
+        if let Some(perlin_noise_octaves) = update.perlin_noise_octaves {
+            settings.perlin_noise_octaves = perlin_noise_octaves.clamp(
+                *PERLIN_NOISE_OCTAVES_RANGE.start(),
+                *PERLIN_NOISE_OCTAVES_RANGE.end(),
+            );
+        }
+        if let Some(smoothing_window) = update.smoothing_window {
+            settings.smoothing_window = smoothing_window.clamp(
+                *SMOOTHING_WINDOW_RANGE.start(),
+                *SMOOTHING_WINDOW_RANGE.end(),
+            );
+        }
+        if let Some(curve_subdivisions) = update.curve_subdivisions {
+            settings.curve_subdivisions = curve_subdivisions.clamp(
+                *CURVE_SUBDIVISIONS_RANGE.start(),
+                *CURVE_SUBDIVISIONS_RANGE.end(),
+            );
+        }
+        Ok(settings)
+    }
+
+    /// Update that sets every value to the current one
+    pub fn to_update(&self) -> NoiseGeneratorSettingsUpdate {
+        NoiseGeneratorSettingsUpdate {
+            width_divider: Some(self.width_divider),
+            height_multiplier: Some(self.height_multiplier),
+            segment_width: Some(self.segment_width),
+            line_width: Some(self.line_width),
+            frame_time_divider: Some(self.frame_time_divider),
+            perlin_noise_octaves: Some(self.perlin_noise_octaves),
+            smoothing_window: Some(self.smoothing_window),
+            curve_subdivisions: Some(self.curve_subdivisions),
+            hidden: Some(self.hidden),
+            color: Some(hex_color(self.color)),
+            background: Some(hex_color(self.background)),
+            transition_s: None,
+            easing: None,
+            persist: false,
+            reply_to: None,
+        }
+    }
+}
+
+impl Tunable for NoiseGeneratorSettings {
+    fn sanitize(&mut self) {
+        self.width_divider = clamp_setting(self.width_divider, WIDTH_DIVIDER_RANGE);
+        self.height_multiplier = clamp_setting(self.height_multiplier, HEIGHT_MULTIPLIER_RANGE);
+        self.segment_width = clamp_setting(self.segment_width.into(), SEGMENT_WIDTH_RANGE) as f32;
+        self.line_width = clamp_setting(self.line_width.into(), LINE_WIDTH_RANGE) as f32;
+        self.frame_time_divider = clamp_setting(self.frame_time_divider, FRAME_TIME_DIVIDER_RANGE);
+        self.perlin_noise_octaves = self.perlin_noise_octaves.clamp(
+            *PERLIN_NOISE_OCTAVES_RANGE.start(),
+            *PERLIN_NOISE_OCTAVES_RANGE.end(),
+        );
+        self.smoothing_window = self.smoothing_window.clamp(
+            *SMOOTHING_WINDOW_RANGE.start(),
+            *SMOOTHING_WINDOW_RANGE.end(),
+        );
+        self.curve_subdivisions = self.curve_subdivisions.clamp(
+            *CURVE_SUBDIVISIONS_RANGE.start(),
+            *CURVE_SUBDIVISIONS_RANGE.end(),
+        );
+    }
+}
+
+/// Seed of the perlin noise generator
+#[derive(Resource)]
+pub struct NoiseSeed(pub u32);
+
+impl Default for NoiseSeed {
+    fn default() -> Self {
+        Self(PERLIN_NOISE_SEED)
     }
 }
 
 #[derive(Component)]
-struct NoiseWave;
+pub struct NoiseWave;
+
+/// Left edge the wave is cut off at, such as to show another wave next to it
+#[derive(Resource, Default)]
+pub struct WaveClip(pub Option<f32>);
 
-fn setup_noise_system(mut commands: Commands) {
+/// Latest wave settings in the `face/settings` format, shared with the zenoh worker
+/// to answer `face/settings/get`
+#[derive(Resource, Clone)]
+pub struct WaveSettingsSnapshot(Arc<Mutex<NoiseGeneratorSettingsUpdate>>);
+
+impl Default for WaveSettingsSnapshot {
+    fn default() -> Self {
+        Self(Arc::new(Mutex::new(
+            NoiseGeneratorSettings::default().to_update(),
+        )))
+    }
+}
+
+impl WaveSettingsSnapshot {
+    pub fn get(&self) -> NoiseGeneratorSettingsUpdate {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
+
+fn snapshot_wave_settings(
+    settings: Res<NoiseGeneratorSettings>,
+    snapshot: Res<WaveSettingsSnapshot>,
+) {
+    *snapshot.0.lock().unwrap_or_else(PoisonError::into_inner) = settings.to_update();
+}
+
+/// Double buffered wave geometry
+///
+/// New geometry is written into the back mesh which is then swapped in,
+/// so the mesh that's currently displayed is never modified while the Pi is still uploading it
+#[derive(Component)]
+struct WaveMeshes {
+    front: Handle<Mesh>,
+    back: Handle<Mesh>,
+}
+
+fn setup_noise_system(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    seed: Res<NoiseSeed>,
+) {
     let points = [Vec2::new(-1.0, 0.0), Vec2::new(1.0, 0.0)].map(|x| x * 10000.);
 
-    let shape = shapes::Polygon {
-        points: points.into_iter().collect(),
-        // radius: 1.0,
-        closed: false,
-    };
+    let front = meshes.add(build_line_mesh(&points, LINE_WIDTH));
+    let back = meshes.add(build_line_mesh(&points, LINE_WIDTH));
 
-    // spawn two shapes one hidden
-    // to allow 1 frame buffering on the raspberry pi
-    // This prevents flickering while the texture is loading
     commands.spawn((
-        ShapeBundle {
-            path: GeometryBuilder::build_as(&shape),
-            spatial: SpatialBundle {
-                visibility: Visibility::Hidden,
-                ..default()
-            },
+        MaterialMesh2dBundle {
+            mesh: front.clone().into(),
+            material: materials.add(ColorMaterial::from(LINE_COLOR)),
             ..default()
         },
-        Stroke::new(Color::WHITE, LINE_WIDTH),
-        Fill::color(Color::NONE),
-        NoiseWave,
-    ));
-    commands.spawn((
-        ShapeBundle {
-            path: GeometryBuilder::build_as(&shape),
-            spatial: SpatialBundle {
-                visibility: Visibility::Visible,
-                ..default()
-            },
-            ..default()
-        },
-        Stroke::new(Color::WHITE, LINE_WIDTH),
-        Fill::color(Color::NONE),
+        WaveMeshes { front, back },
         NoiseWave,
     ));
 
-    let mut perlin_noise = BasicMulti::<Perlin>::new(PERLIN_NOISE_SEED);
+    let mut perlin_noise = BasicMulti::<Perlin>::new(seed.0);
     perlin_noise = perlin_noise.set_octaves(PERLIN_NOISE_OCTAVES);
 
     commands.insert_resource(NoiseGenerator {
         generator: perlin_noise,
         elapsed_step: 0.0,
+        resolution: Rect::default(),
+        previous: Vec::new(),
+        current: Vec::new(),
     });
 }
 
 #[derive(Resource)]
-struct NoiseGenerator {
+pub struct NoiseGenerator {
     generator: BasicMulti<Perlin>,
     /// keep elapsed steps to maintain continuity
-    elapsed_step: f64,
+    pub elapsed_step: f64,
+    /// Visible area the samples were generated for
+    resolution: Rect,
+    /// Samples of the last two fixed steps, interpolated between at render time
+    previous: Vec<f64>,
+    current: Vec<f64>,
 }
 
-fn update_noise_plot(
-    mut query: Query<(&mut Path, &mut Visibility), With<NoiseWave>>,
-    query_camera: Query<&OrthographicProjection>,
-    time: Res<Time>,
-    mut noise_generator: ResMut<NoiseGenerator>,
+fn wave_visible(
     noise_generator_settings: Res<NoiseGeneratorSettings>,
+    scene: Res<State<FaceScene>>,
+) -> bool {
+    !noise_generator_settings.hidden && matches!(scene.get(), FaceScene::Wave | FaceScene::Dizzy)
+}
+
+fn update_noise_visibility(
+    In(visible): In<bool>,
+    mut query: Query<&mut Visibility, With<NoiseWave>>,
 ) {
-    if noise_generator_settings.hidden {
-        // if we should be hidden hide all
-        for (mut _path, mut visibility) in query.iter_mut() {
-            *visibility = Visibility::Hidden
-        }
-        return;
+    let target = if visible {
+        Visibility::Inherited
     } else {
-        // if we should be visible
-        // check if at least one visible
-        let visible = query
-            .iter()
-            .any(|(_path, visibility)| matches!(visibility, Visibility::Visible));
-        // if not make first one visible
-        if !visible {
-            if let Some((_path, mut visibility)) = query.iter_mut().next() {
-                *visibility = Visibility::Visible;
-            }
+        Visibility::Hidden
+    };
+    for mut visibility in query.iter_mut() {
+        if *visibility != target {
+            *visibility = target;
         }
     }
+}
+
+/// Advance the noise at a fixed rate so the wave speed doesn't depend on the frame rate
+///
+/// Runs in `FixedUpdate` where [`Time`] is the fixed clock, which follows the virtual one
+fn step_noise_simulation(
+    query_camera: Query<&OrthographicProjection>,
+    time: Res<Time>,
+    mut noise_generator: ResMut<NoiseGenerator>,
+    noise_generator_settings: Res<NoiseGeneratorSettings>,
+    safe_area: Res<SafeArea>,
+    rotation: Res<FaceRotation>,
+    (robot_motion, temperament, signature): (
+        Res<RobotMotion>,
+        Res<Temperament>,
+        Res<VoiceSignature>,
+    ),
+) {
     // add to elapsed step to maintain continuity
-    let step_addition = time.delta_seconds_f64() / noise_generator_settings.frame_time_divider;
+    let step_addition = time.delta_seconds_f64() / noise_generator_settings.frame_time_divider
+        * robot_motion.speed_scale
+        * temperament.speed_scale
+        * signature.speed_scale;
     noise_generator.elapsed_step += step_addition;
 
     let step = noise_generator.elapsed_step;
 
     let mut resolution = Rect::default();
     for camera in query_camera.iter() {
-        resolution = camera.area;
+        resolution = safe_area.apply(rotation.area(camera.area));
     }
 
     let width = (resolution.width() / noise_generator_settings.segment_width) as usize;
 
-    let mut noise = Vec::with_capacity(width);
+    let noise = sample_noise(
+        &noise_generator.generator,
+        step,
+        noise_generator_settings.width_divider * signature.width_scale,
+        width + 2,
+    );
 
-    for i in 0..=(width + 1) {
-        let next_noise = noise_generator
-            .generator
-            .get([step, i as f64 / noise_generator_settings.width_divider]);
-        noise.push(next_noise);
+    let noise_generator = &mut *noise_generator;
+    noise_generator.resolution = resolution;
+    noise_generator.previous = std::mem::replace(&mut noise_generator.current, noise);
+    // nothing to interpolate from after a resize
+    if noise_generator.previous.len() != noise_generator.current.len() {
+        noise_generator
+            .previous
+            .clone_from(&noise_generator.current);
     }
+}
+
+fn update_noise_plot(
+    mut query: Query<(&mut Mesh2dHandle, &mut WaveMeshes, &mut Transform), With<NoiseWave>>,
+    fixed_time: Res<Time<Fixed>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    noise_generator: Res<NoiseGenerator>,
+    (noise_generator_settings, heartbeat, robot_motion, temperament): (
+        Res<NoiseGeneratorSettings>,
+        Res<Heartbeat>,
+        Res<RobotMotion>,
+        Res<Temperament>,
+    ),
+    (raw_wave, clock, glitch): (Res<RawWave>, Res<FaceClock>, Res<Glitch>),
+    (reaction_pose, signature, clip): (Res<ReactionPose>, Res<VoiceSignature>, Res<WaveClip>),
+) {
+    let resolution = noise_generator.resolution;
+    let sample_count = noise_generator.current.len();
+
+    let raw_wave_fresh = !raw_wave.samples.is_empty()
+        && raw_wave
+            .received_at
+            .is_some_and(|received_at| clock.elapsed() - received_at < RAW_WAVE_TIMEOUT);
+
+    // blend between the last two fixed steps
+    let blend = fixed_time.overstep_fraction_f64();
 
-    for (mut path, mut visibility) in query.iter_mut() {
-        // swap displayed shape
-        match *visibility {
-            Visibility::Hidden => {
-                // display and do not update on the same loop
-                *visibility = Visibility::Visible;
+    let origin_x = resolution.min.x;
+    let segment_width = noise_generator_settings.segment_width;
+    let height_multiplier = noise_generator_settings.height_multiplier
+        * robot_motion.amplitude_scale
+        * temperament.amplitude_scale
+        * signature.height_scale;
+    let samples: Vec<f64> = if raw_wave_fresh {
+        (0..sample_count)
+            .map(|index| raw_wave.sample_at(index as f32 / (sample_count - 1) as f32) as f64)
+            .collect()
+    } else {
+        interpolate_samples(&noise_generator.previous, &noise_generator.current, blend).collect()
+    };
+    // small width dividers look jagged on the low resolution panel
+    let mut samples = smooth_samples(&samples, noise_generator_settings.smoothing_window);
+    // added after smoothing so the spikes stay sharp
+    if heartbeat.enabled && !raw_wave_fresh {
+        for sample in &mut samples {
+            *sample *= heartbeat.noise_scale;
+        }
+        add_heartbeat(
+            &mut samples,
+            // beats line up across faces sharing a clock
+            clock.now_s(),
+            heartbeat.bpm,
+            HEARTBEAT_SWEEP,
+            heartbeat.amplitude,
+        );
+    }
+    let points = wave_points(
+        samples.into_iter(),
+        origin_x,
+        segment_width,
+        height_multiplier,
+    );
+    let mut points = catmull_rom(&points, noise_generator_settings.curve_subdivisions);
+    if let Some(min_x) = clip.0 {
+        points.retain(|point| point.x >= min_x);
+    }
+    let widths = glitch.distort(&mut points);
+    let line_width = noise_generator_settings.line_width;
+
+    for (mut mesh_handle, mut wave_meshes, mut transform) in query.iter_mut() {
+        // keep the wave centered between the top and bottom margins, moved by reactions
+        transform.translation.x = reaction_pose.offset.x;
+        transform.translation.y = resolution.center().y + reaction_pose.offset.y;
+        transform.scale = reaction_pose.scale.extend(1.0);
+        if let Some(mesh) = meshes.get_mut(&wave_meshes.back) {
+            write_tapered_line_mesh(mesh, &points, |index| line_width * widths[index]);
+        }
+        let wave_meshes = &mut *wave_meshes;
+        std::mem::swap(&mut wave_meshes.front, &mut wave_meshes.back);
+        *mesh_handle = wave_meshes.front.clone().into();
+    }
+}
+
+/// Externally generated wave such as an audio waveform
+///
+/// Samples are expected in the -1.0..1.0 range and are stretched over the full width
+#[derive(Event)]
+pub struct RawWaveSamples(pub Vec<f32>);
+
+#[derive(Resource, Default)]
+struct RawWave {
+    samples: Vec<f32>,
+    received_at: Option<Duration>,
+}
+
+impl RawWave {
+    /// Linearly interpolated sample at a position between 0.0 and 1.0
+    fn sample_at(&self, position: f32) -> f32 {
+        let index = position.clamp(0.0, 1.0) * (self.samples.len() - 1) as f32;
+        let lower = index.floor() as usize;
+        let upper = (lower + 1).min(self.samples.len() - 1);
+        let fraction = index - lower as f32;
+        self.samples[lower] + (self.samples[upper] - self.samples[lower]) * fraction
+    }
+}
+
+fn process_raw_wave_samples(
+    mut messages: EventReader<RawWaveSamples>,
+    mut raw_wave: ResMut<RawWave>,
+    clock: Res<FaceClock>,
+) {
+    if let Some(RawWaveSamples(samples)) = messages.read().last() {
+        raw_wave.samples.clone_from(samples);
+        raw_wave.received_at = Some(clock.elapsed());
+    }
+}
+
+#[derive(
+    Event, serde::Deserialize, serde::Serialize, clap::Args, Debug, Default, Clone, PartialEq,
+)]
+pub struct NoiseGeneratorSettingsUpdate {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub width_divider: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub height_multiplier: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub segment_width: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub line_width: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub frame_time_divider: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub perlin_noise_octaves: Option<usize>,
+    /// Samples averaged into each point, 1 turns smoothing off
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub smoothing_window: Option<usize>,
+    /// Catmull-Rom points added between each pair of points, 0 draws straight segments
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub curve_subdivisions: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub hidden: Option<bool>,
+    /// Hex color of the wave such as "ffffff", or "ffffff80" to see through it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub color: Option<String>,
+    /// Hex color behind the face such as "000000"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub background: Option<String>,
+    /// Move to the new values over this many seconds instead of right away
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub transition_s: Option<f32>,
+    /// Curve of the transition, cubic unless set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[arg(long, value_enum)]
+    pub easing: Option<Easing>,
+    /// Write the resulting settings to `--settings-file` so they are kept across restarts
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    #[arg(long)]
+    pub persist: bool,
+    /// Key to publish an acknowledgement to once applied
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub reply_to: Option<String>,
+}
+
+/// Settings gradually moving toward those of an update with `transition_s`
+struct SettingsTransition {
+    from: NoiseGeneratorSettings,
+    to: NoiseGeneratorSettings,
+    easing: Easing,
+    timer: Timer,
+}
+
+#[derive(Resource, Default)]
+struct WaveTransition(Option<SettingsTransition>);
+
+/// Move the settings along a running transition
+fn advance_wave_transition(
+    mut transition: ResMut<WaveTransition>,
+    mut noise_generator_settings: ResMut<NoiseGeneratorSettings>,
+    clock: Res<FaceClock>,
+) {
+    let Some(running) = &mut transition.0 else {
+        return;
+    };
+    let progress = running.timer.tick(clock.delta()).fraction();
+    *noise_generator_settings = running
+        .from
+        .lerp(&running.to, running.easing.ease(progress));
+    if running.timer.finished() {
+        transition.0 = None;
+    }
+}
+
+/// Section of the settings file wave settings are persisted to
+const SETTINGS_FILE_SECTION: &str = "wave";
+
+/// Apply wave settings persisted by an earlier run
+///
+/// The mood schedule is switched off so it doesn't recolor the persisted wave,
+/// the `startup` section can switch it back on
+pub fn load_persisted_settings(
+    settings_file: Res<SettingsFile>,
+    mut updates: EventWriter<NoiseGeneratorSettingsUpdate>,
+    mut mood_schedule: Option<ResMut<MoodSchedule>>,
+) {
+    match settings_file.load::<NoiseGeneratorSettingsUpdate>(SETTINGS_FILE_SECTION) {
+        Ok(Some(update)) => {
+            info!("Loading persisted wave settings");
+            updates.send(update);
+            disable_mood_schedule(mood_schedule.as_mut());
+        }
+        Ok(None) => {}
+        Err(error) => error!(?error, "Failed to load persisted wave settings"),
+    }
+}
+
+/// Keep the mood schedule from overwriting a persisted wave
+fn disable_mood_schedule(mood_schedule: Option<&mut ResMut<MoodSchedule>>) {
+    if let Some(schedule) = mood_schedule.filter(|schedule| schedule.enabled) {
+        info!("Disabling mood schedule for the persisted wave settings");
+        schedule.enabled = false;
+    }
+}
+
+fn process_noise_generator_update_messages(
+    mut messages: EventReader<NoiseGeneratorSettingsUpdate>,
+    mut noise_generator_settings: ResMut<NoiseGeneratorSettings>,
+    mut transition: ResMut<WaveTransition>,
+    ack_sender: Res<AckSender>,
+    settings_file: Res<SettingsFile>,
+    mut mood_schedule: Option<ResMut<MoodSchedule>>,
+) {
+    for (message, id) in messages.read_with_id() {
+        let _trace = ack_sender.trace(id);
+        // updates during a transition build on where it is heading
+        let settings = match &transition.0 {
+            Some(running) => running.to.clone(),
+            None => noise_generator_settings.clone(),
+        };
+        let settings = match settings.updated(message) {
+            Ok(settings) => settings,
+            Err(error) => {
+                error!(
+                    ?error,
+                    color = message.color,
+                    background = message.background,
+                    "Invalid wave color"
+                );
+                ack_sender.send(message.reply_to.as_deref(), CommandAck::rejected(error));
                 continue;
             }
-            Visibility::Visible => {
-                *visibility = Visibility::Hidden;
+        };
+        info!(update = ?message, "Updating wave settings");
+
+        match message.transition_s {
+            Some(transition_s) if transition_s.is_finite() && transition_s > 0.0 => {
+                let easing = message.easing.unwrap_or_default();
+                let duration =
+                    Duration::from_secs_f32(transition_s.min(MAX_TRANSITION.as_secs_f32()));
+                info!(?duration, ?easing, "Transitioning wave settings");
+                transition.0 = Some(SettingsTransition {
+                    from: noise_generator_settings.clone(),
+                    to: settings.clone(),
+                    easing,
+                    timer: Timer::new(duration, TimerMode::Once),
+                });
             }
-            Visibility::Inherited => {}
-        }
-
-        let points = noise
-            .iter()
-            .enumerate()
-            .map(|(index, point)| {
-                Vec2::new(
-                    resolution.min.x + (index as f32) * noise_generator_settings.segment_width,
-                    (*point * noise_generator_settings.height_multiplier) as f32,
-                )
-            })
-            .collect();
+            _ => {
+                transition.0 = None;
+                noise_generator_settings.set_if_neq(settings.clone());
+            }
+        }
+
+        if message.persist {
+            let update = settings.to_update();
+            if let Err(error) = settings_file.persist(SETTINGS_FILE_SECTION, &update) {
+                error!(?error, "Failed to persist wave settings");
+                ack_sender.send(
+                    message.reply_to.as_deref(),
+                    CommandAck::rejected(format!("Applied but not persisted: {error:#}")),
+                );
+                continue;
+            }
+            disable_mood_schedule(mood_schedule.as_mut());
+        }
 
-        let shape = shapes::Polygon {
-            points,
-            closed: false,
+        ack_sender.send(
+            message.reply_to.as_deref(),
+            CommandAck::applied(settings.to_update()),
+        );
+    }
+}
+
+impl NoiseGeneratorSettings {
+    /// Settings `progress` of the way to `other`, eased progress may overshoot either end
+    ///
+    /// Counts and switches take the values of `other` right away
+    fn lerp(&self, other: &Self, progress: f32) -> Self {
+        let lerp = |from: f64, to: f64, range| {
+            clamp_setting(from + (to - from) * f64::from(progress), range)
+        };
+        let lerp_color = |from: Color, to: Color| {
+            let (from, to) = (from.as_rgba_f32(), to.as_rgba_f32());
+            let [red, green, blue, alpha] = std::array::from_fn(|index| {
+                (from[index] + (to[index] - from[index]) * progress).clamp(0.0, 1.0)
+            });
+            Color::rgba(red, green, blue, alpha)
         };
+        Self {
+            width_divider: lerp(self.width_divider, other.width_divider, WIDTH_DIVIDER_RANGE),
+            height_multiplier: lerp(
+                self.height_multiplier,
+                other.height_multiplier,
+                HEIGHT_MULTIPLIER_RANGE,
+            ),
+            segment_width: lerp(
+                self.segment_width.into(),
+                other.segment_width.into(),
+                SEGMENT_WIDTH_RANGE,
+            ) as f32,
+            line_width: lerp(
+                self.line_width.into(),
+                other.line_width.into(),
+                LINE_WIDTH_RANGE,
+            ) as f32,
+            frame_time_divider: lerp(
+                self.frame_time_divider,
+                other.frame_time_divider,
+                FRAME_TIME_DIVIDER_RANGE,
+            ),
+            color: lerp_color(self.color, other.color),
+            background: lerp_color(self.background, other.background),
+            ..other.clone()
+        }
+    }
 
-        *path = ShapePath::build_as(&shape);
+    /// Whether going to `other` changes the shape of the wave so much
+    /// that morphing between them would look broken
+    fn is_drastic_change(&self, other: &Self) -> bool {
+        let relative_change = |from: f64, to: f64| {
+            let largest = from.abs().max(to.abs());
+            largest > f64::EPSILON && (from - to).abs() / largest > CROSS_FADE_THRESHOLD
+        };
+        relative_change(self.width_divider, other.width_divider)
+            || relative_change(self.height_multiplier, other.height_multiplier)
+            || relative_change(self.segment_width.into(), other.segment_width.into())
+            || relative_change(self.frame_time_divider, other.frame_time_divider)
+            || self
+                .perlin_noise_octaves
+                .abs_diff(other.perlin_noise_octaves)
+                > 1
     }
 }
 
-#[derive(serde::Deserialize)]
-pub struct NoiseGeneratorSettingsUpdate {
-    #[serde(default)]
-    width_divider: Option<f64>,
-    #[serde(default)]
-    height_multiplier: Option<f64>,
-    #[serde(default)]
-    segment_width: Option<f32>,
-    #[serde(default)]
-    frame_time_divider: Option<f64>,
-    #[serde(default)]
-    perlin_noise_octaves: Option<usize>,
-    #[serde(default)]
-    hidden: Option<bool>,
+/// Fade of the frozen old wave into the new one
+#[derive(Resource)]
+struct WaveCrossFade {
+    timer: Timer,
 }
 
-fn process_noise_generator_update_messages(
-    mut receiver: ResMut<StreamReceiver>,
+/// Copy of the wave as it looked before a drastic settings change
+#[derive(Component)]
+struct FadingWave {
+    /// Alpha of the wave color when the fade started
+    alpha: f32,
+}
+
+/// Freeze the current wave and fade it out when the new settings would reshape it drastically
+///
+/// A wave that is still fading from an earlier change keeps fading along with the new one
+fn start_wave_cross_fade(
+    mut commands: Commands,
+    noise_generator_settings: Res<NoiseGeneratorSettings>,
+    night_mode: Res<NightMode>,
+    mut previous_settings: Local<Option<NoiseGeneratorSettings>>,
+    query: Query<(&Mesh2dHandle, &Transform, &Visibility), With<NoiseWave>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let previous = previous_settings.replace(noise_generator_settings.clone());
+    let Some(previous) = previous else {
+        return;
+    };
+    if !previous.is_drastic_change(&noise_generator_settings) {
+        return;
+    }
+
+    for (mesh, transform, visibility) in query.iter() {
+        if *visibility == Visibility::Hidden {
+            continue;
+        }
+        let Some(mesh) = meshes.get(&mesh.0).cloned() else {
+            continue;
+        };
+        let mut transform = *transform;
+        transform.translation.z += CROSS_FADE_Z;
+        let color = night_mode.apply(previous.color);
+        commands.spawn((
+            MaterialMesh2dBundle {
+                mesh: meshes.add(mesh).into(),
+                material: materials.add(ColorMaterial::from(color)),
+                transform,
+                ..default()
+            },
+            FadingWave { alpha: color.a() },
+        ));
+    }
+    commands.insert_resource(WaveCrossFade {
+        timer: Timer::new(CROSS_FADE_DURATION, TimerMode::Once),
+    });
+}
+
+/// Fade the old wave out and the new one in
+fn update_wave_cross_fade(
+    mut commands: Commands,
+    clock: Res<FaceClock>,
+    mut cross_fade: ResMut<WaveCrossFade>,
+    (noise_generator_settings, night_mode): (Res<NoiseGeneratorSettings>, Res<NightMode>),
+    waves: Query<&Handle<ColorMaterial>, With<NoiseWave>>,
+    fading: Query<(Entity, &Handle<ColorMaterial>, &FadingWave)>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let progress = cross_fade.timer.tick(clock.delta()).fraction();
+    let color = night_mode.apply(noise_generator_settings.color);
+    for material in waves.iter() {
+        if let Some(material) = materials.get_mut(material) {
+            material.color = color.with_a(color.a() * progress);
+        }
+    }
+    for (entity, material, fading) in fading.iter() {
+        if cross_fade.timer.finished() {
+            commands.entity(entity).despawn();
+        } else if let Some(material) = materials.get_mut(material) {
+            material.color.set_a(fading.alpha * (1.0 - progress));
+        }
+    }
+    if cross_fade.timer.finished() {
+        commands.remove_resource::<WaveCrossFade>();
+    }
+}
+
+/// Apply settings that don't take effect on their own, no matter how they were changed
+fn apply_noise_generator_settings(
+    noise_generator_settings: Res<NoiseGeneratorSettings>,
+    night_mode: Res<NightMode>,
     mut noise_generator: ResMut<NoiseGenerator>,
-    mut noise_generator_settings: ResMut<NoiseGeneratorSettings>,
+    query: Query<&Handle<ColorMaterial>, With<NoiseWave>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut clear_color: ResMut<ClearColor>,
 ) {
-    while let Ok(message) = receiver.try_recv() {
-        if let Some(width_divider) = message.width_divider {
-            info!(width_divider, "Updating width_divider");
-            noise_generator_settings.width_divider = width_divider;
+    for material in query.iter() {
+        if let Some(material) = materials.get_mut(material) {
+            material.color = night_mode.apply(noise_generator_settings.color);
         }
-        if let Some(height_multiplier) = message.height_multiplier {
-            info!(height_multiplier, "Updating height_multiplier");
-            noise_generator_settings.height_multiplier = height_multiplier;
+    }
+    let background = night_mode.apply(noise_generator_settings.background);
+    if clear_color.0 != background {
+        clear_color.0 = background;
+    }
+    if noise_generator.generator.octaves != noise_generator_settings.perlin_noise_octaves {
+        noise_generator.generator = noise_generator
+            .generator
+            .clone()
+            .set_octaves(noise_generator_settings.perlin_noise_octaves);
+    }
+}
+
+/// Clamp a setting into `range`, NaN falls back to the lower bound
+pub(crate) fn clamp_setting(value: f64, range: RangeInclusive<f64>) -> f64 {
+    if value.is_nan() {
+        *range.start()
+    } else {
+        value.clamp(*range.start(), *range.end())
+    }
+}
+
+/// Color as the hex string settings updates use
+pub fn hex_color(color: Color) -> String {
+    let [red, green, blue, alpha] = color.as_rgba_u8();
+    format!("{red:02x}{green:02x}{blue:02x}{alpha:02x}")
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::{
+        messaging::KeyPrefix, outbound::OutboundQueue, scene::SceneRequests,
+        startup::StartupPlugin, trace::CommandTraces,
+    };
+
+    proptest! {
+        #[test]
+        fn clamped_settings_stay_in_range(value in any::<f64>()) {
+            for range in [
+                WIDTH_DIVIDER_RANGE,
+                HEIGHT_MULTIPLIER_RANGE,
+                SEGMENT_WIDTH_RANGE,
+                LINE_WIDTH_RANGE,
+                FRAME_TIME_DIVIDER_RANGE,
+            ] {
+                let clamped = clamp_setting(value, range.clone());
+                prop_assert!(range.contains(&clamped));
+            }
         }
-        if let Some(segment_width) = message.segment_width {
-            info!(segment_width, "Updating segment_width");
-            noise_generator_settings.segment_width = segment_width;
+
+        #[test]
+        fn values_in_range_are_kept(value in 1.0..=500.0f64) {
+            prop_assert_eq!(clamp_setting(value, SEGMENT_WIDTH_RANGE), value);
         }
-        if let Some(frame_time_divider) = message.frame_time_divider {
-            info!(frame_time_divider, "Updating frame_time_divider");
-            noise_generator_settings.frame_time_divider = frame_time_divider;
+
+        #[test]
+        fn settings_update_parses_any_values(
+            width_divider in proptest::option::of(any::<f64>().prop_filter("finite", |v| v.is_finite())),
+            segment_width in proptest::option::of(any::<f32>().prop_filter("finite", |v| v.is_finite())),
+            perlin_noise_octaves in proptest::option::of(any::<usize>()),
+            hidden in proptest::option::of(any::<bool>()),
+        ) {
+            let json = serde_json::json!({
+                "width_divider": width_divider,
+                "segment_width": segment_width,
+                "perlin_noise_octaves": perlin_noise_octaves,
+                "hidden": hidden,
+            });
+            let update: NoiseGeneratorSettingsUpdate = serde_json::from_value(json).unwrap();
+            prop_assert_eq!(update.width_divider, width_divider);
+            prop_assert_eq!(update.segment_width, segment_width);
+            prop_assert_eq!(update.perlin_noise_octaves, perlin_noise_octaves);
+            prop_assert_eq!(update.hidden, hidden);
         }
-        if let Some(hidden) = message.hidden {
-            info!(hidden, "Updating hidden");
-            noise_generator_settings.hidden = hidden;
+
+        #[test]
+        fn settings_update_ignores_unknown_fields(key in "[a-z_]{1,16}", value in any::<i64>()) {
+            let json = format!(r#"{{"{key}_unknown": {value}, "hidden": true}}"#);
+            let update: NoiseGeneratorSettingsUpdate = serde_json::from_str(&json).unwrap();
+            prop_assert_eq!(update.hidden, Some(true));
         }
 
-        if let Some(perlin_noise_octaves) = message.perlin_noise_octaves {
-            info!(perlin_noise_octaves, "Updating perlin_noise_octaves");
-            noise_generator.generator = noise_generator
-                .generator
-                .clone()
-                .set_octaves(perlin_noise_octaves);
+        #[test]
+        fn settings_update_never_panics_on_arbitrary_input(json in ".*") {
+            _ = serde_json::from_str::<NoiseGeneratorSettingsUpdate>(&json);
         }
     }
+
+    #[test]
+    fn transitions_stay_in_range() {
+        let from = NoiseGeneratorSettings::default();
+        let to = NoiseGeneratorSettings {
+            height_multiplier: 0.0,
+            color: Color::BLACK,
+            perlin_noise_octaves: 4,
+            ..default()
+        };
+        let halfway = from.lerp(&to, 0.5);
+        assert_eq!(halfway.height_multiplier, HEIGHT_MULTIPLIER / 2.0);
+        assert_eq!(halfway.perlin_noise_octaves, 4);
+        // springy curves overshoot past the target
+        let overshoot = from.lerp(&to, 1.2);
+        assert_eq!(overshoot.height_multiplier, 0.0);
+        assert_eq!(overshoot.color, Color::rgba(0.0, 0.0, 0.0, 1.0));
+        assert!(from.lerp(&to, 1.0) == to);
+    }
+
+    #[test]
+    fn settings_snapshot_can_be_sent_back() {
+        let settings = NoiseGeneratorSettings {
+            height_multiplier: 250.0,
+            color: Color::rgba_u8(0x60, 0xc0, 0xff, 0x80),
+            smoothing_window: 3,
+            ..default()
+        };
+        let snapshot = WaveSettingsSnapshot::default();
+        snapshot.0.lock().unwrap().clone_from(&settings.to_update());
+
+        let json = serde_json::to_string(&snapshot.get()).unwrap();
+        let update: NoiseGeneratorSettingsUpdate = serde_json::from_str(&json).unwrap();
+        assert!(NoiseGeneratorSettings::default().updated(&update).unwrap() == settings);
+    }
+
+    #[test]
+    fn empty_settings_update_changes_nothing() {
+        let update: NoiseGeneratorSettingsUpdate = serde_json::from_str("{}").unwrap();
+        assert!(update.width_divider.is_none());
+        assert!(update.height_multiplier.is_none());
+        assert!(update.segment_width.is_none());
+        assert!(update.line_width.is_none());
+        assert!(update.frame_time_divider.is_none());
+        assert!(update.perlin_noise_octaves.is_none());
+        assert!(update.smoothing_window.is_none());
+        assert!(update.curve_subdivisions.is_none());
+        assert!(update.hidden.is_none());
+        assert!(update.color.is_none());
+        assert!(update.background.is_none());
+    }
+
+    #[test]
+    fn colors_are_applied_with_alpha() {
+        let update: NoiseGeneratorSettingsUpdate =
+            serde_json::from_str(r#"{"color": "ff000080", "background": "000000"}"#).unwrap();
+        let settings = NoiseGeneratorSettings::default().updated(&update).unwrap();
+        assert_eq!(hex_color(settings.color), "ff000080");
+        assert_eq!(settings.background, Color::rgb(0.0, 0.0, 0.0));
+
+        let invalid = NoiseGeneratorSettingsUpdate {
+            background: Some("black".to_owned()),
+            ..default()
+        };
+        assert!(NoiseGeneratorSettings::default().updated(&invalid).is_err());
+    }
+
+    #[test]
+    fn nan_falls_back_to_lower_bound() {
+        assert_eq!(clamp_setting(f64::NAN, SEGMENT_WIDTH_RANGE), 1.0);
+    }
+
+    #[test]
+    fn only_large_shape_changes_cross_fade() {
+        let settings = NoiseGeneratorSettings::default();
+        let mut nudged = settings.clone();
+        nudged.height_multiplier *= 1.1;
+        nudged.color = Color::RED;
+        assert!(!settings.is_drastic_change(&nudged));
+
+        let mut reshaped = settings.clone();
+        reshaped.width_divider *= 3.0;
+        assert!(settings.is_drastic_change(&reshaped));
+    }
+
+    #[test]
+    fn persisted_wave_switches_the_mood_schedule_off() {
+        let path =
+            std::env::temp_dir().join(format!("face-persisted-wave-{}.json", std::process::id()));
+        let settings_file = SettingsFile {
+            path: Some(path.clone()),
+        };
+        settings_file
+            .persist(
+                SETTINGS_FILE_SECTION,
+                &serde_json::json!({"color": "ff0000"}),
+            )
+            .unwrap();
+        let boot = |settings_file: &SettingsFile| {
+            let mut app = App::new();
+            app.insert_resource(settings_file.clone())
+                .insert_resource(MoodSchedule::default())
+                .init_resource::<SceneRequests>()
+                .add_event::<NoiseGeneratorSettingsUpdate>()
+                .add_plugins(StartupPlugin { scene: None })
+                .add_systems(Startup, load_persisted_settings);
+            app.update();
+            app
+        };
+
+        let app = boot(&settings_file);
+        assert!(!app.world.resource::<MoodSchedule>().enabled);
+        let updates = app.world.resource::<Events<NoiseGeneratorSettingsUpdate>>();
+        let colors: Vec<_> = updates
+            .get_reader()
+            .read(updates)
+            .map(|update| update.color.clone())
+            .collect();
+        assert_eq!(colors, [Some(String::from("ff0000"))]);
+
+        // asking for the mood schedule at startup keeps it on
+        settings_file
+            .persist("startup", &serde_json::json!({"mood": true}))
+            .unwrap();
+        let app = boot(&settings_file);
+        assert!(app.world.resource::<MoodSchedule>().enabled);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn slow_transitions_are_capped() {
+        let mut app = App::new();
+        app.init_resource::<NoiseGeneratorSettings>()
+            .init_resource::<WaveTransition>()
+            .init_resource::<SettingsFile>()
+            .insert_resource(AckSender::new(
+                OutboundQueue::new(16),
+                KeyPrefix::default(),
+                CommandTraces::default(),
+            ))
+            .add_event::<NoiseGeneratorSettingsUpdate>()
+            .add_systems(Update, process_noise_generator_update_messages);
+        app.world.send_event(NoiseGeneratorSettingsUpdate {
+            height_multiplier: Some(100.0),
+            transition_s: Some(1e30),
+            ..default()
+        });
+        app.update();
+
+        let transition = app.world.resource::<WaveTransition>();
+        let running = transition.0.as_ref().unwrap();
+        assert_eq!(running.timer.duration(), MAX_TRANSITION);
+        assert_eq!(running.to.height_multiplier, 100.0);
+    }
 }
@@ -0,0 +1,183 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{
+    messaging::ZenohConnection,
+    priority::Priority,
+    rotation::FaceRotation,
+    safe_area::SafeArea,
+    scene::{FaceScene, SceneRequests},
+};
+
+/// Mark the face offline when zenoh doesn't connect in time at boot
+///
+/// A face that never connected looks just like a working one while ignoring every command.
+/// Once `connect_timeout` passes without a session the offline scene is shown until zenoh
+/// connects, the worker keeps retrying in the background
+pub struct OfflinePlugin {
+    pub connect_timeout: Duration,
+}
+
+impl Plugin for OfflinePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(BootConnection {
+            timeout: self.connect_timeout,
+            state: BootState::Connecting,
+        })
+        .add_systems(OnEnter(FaceScene::Offline), spawn_offline_text)
+        .add_systems(OnExit(FaceScene::Offline), despawn_offline_text)
+        .add_systems(
+            Update,
+            (
+                watch_boot_connection,
+                update_offline_text.run_if(in_state(FaceScene::Offline)),
+            )
+                .chain(),
+        );
+    }
+}
+
+/// Default seconds zenoh has to connect at boot
+pub const DEFAULT_CONNECT_TIMEOUT_S: f32 = 10.0;
+const OFFLINE_COLOR: Color = Color::rgb(1.0, 0.6, 0.0);
+const OFFLINE_FONT_SIZE: f32 = 96.0;
+const DETAIL_FONT_SIZE: f32 = 24.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BootState {
+    Connecting,
+    /// The timeout passed and the offline scene is requested
+    Offline,
+    /// Connected at least once, later disconnects don't bring the offline scene back
+    Connected,
+}
+
+#[derive(Resource)]
+struct BootConnection {
+    timeout: Duration,
+    state: BootState,
+}
+
+impl BootConnection {
+    /// State once `elapsed` passed since boot
+    fn next(&self, connected: bool, elapsed: Duration) -> BootState {
+        match self.state {
+            BootState::Connected => BootState::Connected,
+            _ if connected => BootState::Connected,
+            BootState::Connecting if elapsed >= self.timeout => BootState::Offline,
+            state => state,
+        }
+    }
+}
+
+#[derive(Component)]
+struct OfflineText;
+
+fn watch_boot_connection(
+    mut boot: ResMut<BootConnection>,
+    zenoh_connection: Res<ZenohConnection>,
+    mut scene_requests: ResMut<SceneRequests>,
+    time: Res<Time<Real>>,
+) {
+    let next = boot.next(zenoh_connection.connected, time.elapsed());
+    if next == boot.state {
+        return;
+    }
+    match next {
+        BootState::Offline => {
+            warn!(timeout = ?boot.timeout, "Zenoh didn't connect in time, showing offline scene");
+            scene_requests.push(Priority::Error, FaceScene::Offline, None);
+        }
+        BootState::Connected if boot.state == BootState::Offline => {
+            info!("Zenoh connected, leaving offline scene");
+            scene_requests.remove(&FaceScene::Offline);
+        }
+        _ => {}
+    }
+    boot.state = next;
+}
+
+fn spawn_offline_text(mut commands: Commands) {
+    commands.spawn((
+        Text2dBundle {
+            text: Text::from_sections([
+                TextSection::new(
+                    "OFFLINE\n",
+                    TextStyle {
+                        font_size: OFFLINE_FONT_SIZE,
+                        color: OFFLINE_COLOR,
+                        ..default()
+                    },
+                ),
+                TextSection::new(
+                    "",
+                    TextStyle {
+                        font_size: DETAIL_FONT_SIZE,
+                        color: OFFLINE_COLOR,
+                        ..default()
+                    },
+                ),
+            ])
+            .with_justify(JustifyText::Center),
+            transform: Transform::from_xyz(0.0, 0.0, 3.0),
+            ..default()
+        },
+        OfflineText,
+    ));
+}
+
+fn despawn_offline_text(mut commands: Commands, query: Query<Entity, With<OfflineText>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Keep the label centered and count the time without a connection
+fn update_offline_text(
+    mut query: Query<(&mut Text, &mut Transform), With<OfflineText>>,
+    query_camera: Query<&OrthographicProjection>,
+    (safe_area, rotation): (Res<SafeArea>, Res<FaceRotation>),
+    time: Res<Time<Real>>,
+) {
+    let mut center = Vec2::ZERO;
+    for camera in query_camera.iter() {
+        center = safe_area.apply(rotation.area(camera.area)).center();
+    }
+    let detail = format!(
+        "no zenoh connection for {} s\nretrying",
+        time.elapsed().as_secs()
+    );
+    for (mut text, mut transform) in query.iter_mut() {
+        if text.sections[1].value != detail {
+            text.sections[1].value.clone_from(&detail);
+        }
+        transform.translation.x = center.x;
+        transform.translation.y = center.y;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offline_scene_is_only_entered_at_boot() {
+        let mut boot = BootConnection {
+            timeout: Duration::from_secs(10),
+            state: BootState::Connecting,
+        };
+        assert_eq!(
+            boot.next(false, Duration::from_secs(5)),
+            BootState::Connecting
+        );
+        boot.state = boot.next(false, Duration::from_secs(10));
+        assert_eq!(boot.state, BootState::Offline);
+        boot.state = boot.next(true, Duration::from_secs(30));
+        assert_eq!(boot.state, BootState::Connected);
+        assert_eq!(
+            boot.next(false, Duration::from_secs(60)),
+            BootState::Connected
+        );
+    }
+}
@@ -0,0 +1,113 @@
+//! Mirror of a compact status summary on a small SSD1306 OLED
+//!
+//! The display is driven from a dedicated thread since I2C writes block
+
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use bevy::prelude::*;
+use embedded_graphics::{
+    mono_font::{ascii::FONT_6X10, MonoTextStyle},
+    pixelcolor::BinaryColor,
+    prelude::*,
+    text::{Baseline, Text},
+};
+use linux_embedded_hal::I2cdev;
+use ssd1306::{prelude::*, I2CDisplayInterface, Ssd1306};
+
+use crate::{
+    battery::Battery, display::DisplayPower, expression::FaceExpression,
+    messaging::ZenohConnection, scene::FaceScene,
+};
+
+pub struct OledStatusPlugin {
+    /// I2C bus device the OLED is connected to such as `/dev/i2c-1`
+    pub i2c_bus: String,
+}
+
+impl Plugin for OledStatusPlugin {
+    fn build(&self, app: &mut App) {
+        let (tx, rx) = channel();
+        let i2c_bus = self.i2c_bus.clone();
+        std::thread::spawn(move || {
+            if let Err(error) = run_oled_display(&i2c_bus, rx) {
+                error!(?error, i2c_bus, "OLED status display failed");
+            }
+        });
+
+        app.insert_resource(OledStatusSender(tx))
+            .init_resource::<FaceExpression>()
+            .init_resource::<Battery>()
+            .add_systems(Update, update_oled_status);
+    }
+}
+
+const OLED_LINE_HEIGHT: i32 = 12;
+
+#[derive(Resource)]
+struct OledStatusSender(Sender<Vec<String>>);
+
+fn update_oled_status(
+    sender: Res<OledStatusSender>,
+    scene: Res<State<FaceScene>>,
+    expression: Res<FaceExpression>,
+    display_power: Res<DisplayPower>,
+    zenoh_connection: Res<ZenohConnection>,
+    battery: Res<Battery>,
+    mut shown_expression: Local<Option<String>>,
+) {
+    // the expression blend changes the resource every frame
+    let expression_changed = shown_expression.as_deref() != Some(expression.name.as_str());
+    if !scene.is_changed()
+        && !expression_changed
+        && !display_power.is_changed()
+        && !zenoh_connection.is_changed()
+        && !battery.is_changed()
+    {
+        return;
+    }
+    *shown_expression = Some(expression.name.clone());
+    let lines = vec![
+        format!("scene: {:?}", scene.get()),
+        format!("expression: {}", expression.name),
+        format!("display: {}", if display_power.on { "on" } else { "off" }),
+        format!("battery: {}", battery.summary()),
+        format!(
+            "zenoh: {}",
+            if zenoh_connection.connected {
+                "connected"
+            } else {
+                "offline"
+            }
+        ),
+    ];
+    _ = sender.0.send(lines);
+}
+
+fn run_oled_display(i2c_bus: &str, rx: Receiver<Vec<String>>) -> anyhow::Result<()> {
+    let i2c = I2cdev::new(i2c_bus)?;
+    let interface = I2CDisplayInterface::new(i2c);
+    let mut display = Ssd1306::new(interface, DisplaySize128x64, DisplayRotation::Rotate0)
+        .into_buffered_graphics_mode();
+    display
+        .init()
+        .map_err(|error| anyhow::anyhow!("Failed to initialize OLED {error:?}"))?;
+
+    let text_style = MonoTextStyle::new(&FONT_6X10, BinaryColor::On);
+    while let Ok(lines) = rx.recv() {
+        display.clear_buffer();
+        for (index, line) in lines.iter().enumerate() {
+            Text::with_baseline(
+                line,
+                Point::new(0, index as i32 * OLED_LINE_HEIGHT),
+                text_style,
+                Baseline::Top,
+            )
+            .draw(&mut display)
+            .map_err(|error| anyhow::anyhow!("Failed to draw OLED text {error:?}"))?;
+        }
+        display
+            .flush()
+            .map_err(|error| anyhow::anyhow!("Failed to flush OLED {error:?}"))?;
+    }
+    Ok(())
+}
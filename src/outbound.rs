@@ -0,0 +1,173 @@
+use std::{
+    collections::VecDeque,
+    ops::RangeInclusive,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::Duration,
+};
+
+use bevy::prelude::*;
+use tokio::{sync::Notify, time::Interval};
+
+/// Size and rate of the queue of acks, events and telemetry published by the face
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct OutboundSettings {
+    /// Messages kept before the oldest are dropped
+    pub capacity: usize,
+    /// Messages published per second at most
+    pub rate_hz: f64,
+}
+
+impl Default for OutboundSettings {
+    fn default() -> Self {
+        Self {
+            capacity: 64,
+            rate_hz: 100.0,
+        }
+    }
+}
+
+/// Publishing rates in messages per second the queue can be paced at
+pub const OUTBOUND_RATE_RANGE: RangeInclusive<f64> = 0.01..=100_000.0;
+
+impl OutboundSettings {
+    /// Interval between published messages, later ticks are delayed rather than bunched up
+    pub fn interval(&self) -> Interval {
+        let rate = if self.rate_hz.is_nan() {
+            *OUTBOUND_RATE_RANGE.start()
+        } else {
+            self.rate_hz
+                .clamp(*OUTBOUND_RATE_RANGE.start(), *OUTBOUND_RATE_RANGE.end())
+        };
+        let period = Duration::from_secs_f64(1.0 / rate);
+        let mut interval = tokio::time::interval(period);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        interval
+    }
+}
+
+/// Parse a publishing rate in messages per second
+pub fn parse_outbound_rate(rate: &str) -> Result<f64, String> {
+    let rate: f64 = rate.parse().map_err(|error| format!("{error}"))?;
+    if OUTBOUND_RATE_RANGE.contains(&rate) {
+        Ok(rate)
+    } else {
+        Err(format!(
+            "{rate} is not a rate between {} and {}",
+            OUTBOUND_RATE_RANGE.start(),
+            OUTBOUND_RATE_RANGE.end()
+        ))
+    }
+}
+
+#[derive(Default)]
+struct OutboundState {
+    capacity: usize,
+    messages: VecDeque<(String, serde_json::Value)>,
+    dropped: u64,
+}
+
+/// Messages waiting for the zenoh worker to publish them
+///
+/// Pushing never blocks, a full queue drops its oldest message so a saturated network
+/// only ever holds back `capacity` messages
+#[derive(Clone)]
+pub struct OutboundQueue {
+    state: Arc<Mutex<OutboundState>>,
+    pushed: Arc<Notify>,
+}
+
+impl OutboundQueue {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(OutboundState {
+                capacity: capacity.max(1),
+                messages: VecDeque::new(),
+                dropped: 0,
+            })),
+            pushed: Arc::new(Notify::new()),
+        }
+    }
+
+    pub fn push(&self, key_expr: String, json: serde_json::Value) {
+        let mut state = self.state();
+        if state.messages.len() >= state.capacity {
+            state.messages.pop_front();
+            state.dropped += 1;
+        }
+        state.messages.push_back((key_expr, json));
+        drop(state);
+        self.pushed.notify_one();
+    }
+
+    /// Wait for the next tick of `rate` and the oldest message
+    ///
+    /// Cancel safe, a message is only taken once it is returned
+    pub async fn next(&self, rate: &mut Interval) -> (String, serde_json::Value) {
+        rate.tick().await;
+        loop {
+            if let Some(message) = self.state().messages.pop_front() {
+                return message;
+            }
+            self.pushed.notified().await;
+        }
+    }
+
+    /// Number of messages waiting
+    pub fn len(&self) -> usize {
+        self.state().messages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of messages dropped because the queue was full
+    pub fn dropped(&self) -> u64 {
+        self.state().dropped
+    }
+
+    fn state(&self) -> MutexGuard<'_, OutboundState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn full_queues_drop_the_oldest_message() {
+        let queue = OutboundQueue::new(2);
+        for index in 0..3 {
+            queue.push(format!("face/event/{index}"), serde_json::json!(index));
+        }
+        assert_eq!(queue.len(), 2);
+        assert_eq!(queue.dropped(), 1);
+
+        let mut rate = OutboundSettings {
+            capacity: 2,
+            rate_hz: 1000.0,
+        }
+        .interval();
+        assert_eq!(queue.next(&mut rate).await.0, "face/event/1");
+        assert_eq!(queue.next(&mut rate).await.0, "face/event/2");
+        assert!(queue.is_empty());
+    }
+
+    #[tokio::test]
+    async fn rates_are_limited() {
+        assert_eq!(parse_outbound_rate("0.5"), Ok(0.5));
+        for rate in ["0", "-1", "NaN", "inf", "1e-300", "1e300"] {
+            assert!(parse_outbound_rate(rate).is_err(), "{rate}");
+        }
+        for rate_hz in [1e-300, 1e300, f64::NAN, 0.0] {
+            let interval = OutboundSettings {
+                capacity: 1,
+                rate_hz,
+            }
+            .interval();
+            assert!(interval.period() >= Duration::from_micros(10), "{rate_hz}");
+            assert!(interval.period() <= Duration::from_secs(100), "{rate_hz}");
+        }
+    }
+}
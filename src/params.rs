@@ -0,0 +1,246 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use anyhow::Context;
+use bevy::{
+    prelude::*,
+    reflect::{
+        serde::{TypedReflectDeserializer, TypedReflectSerializer},
+        GetTypeRegistration, TypeRegistry,
+    },
+};
+use serde::de::DeserializeSeed;
+use tokio::sync::oneshot;
+
+use crate::event_log::{EventKind, EventLog};
+
+/// Remote get and set of tunable resources over `face/param/<name>/<field>`
+///
+/// Resources registered with [`RegisterTunable::register_tunable`] are reflected,
+/// so new fields become adjustable without new messages or handlers
+pub struct ParamsPlugin;
+
+impl Plugin for ParamsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ParamSnapshot>()
+            .init_resource::<Tunables>()
+            .add_event::<ParamUpdate>()
+            .add_systems(PreUpdate, apply_param_updates);
+    }
+}
+
+/// Resource that can be read and changed remotely through reflection
+pub trait Tunable: Resource + Reflect + FromReflect + TypePath + GetTypeRegistration {
+    /// Bring values back into their valid range after a remote change
+    fn sanitize(&mut self) {}
+}
+
+pub trait RegisterTunable {
+    /// Expose `T` as `face/param/<name>`
+    fn register_tunable<T: Tunable>(&mut self, name: &'static str) -> &mut Self;
+}
+
+impl RegisterTunable for App {
+    fn register_tunable<T: Tunable>(&mut self, name: &'static str) -> &mut Self {
+        self.register_type::<T>()
+            .init_resource::<ParamSnapshot>()
+            .init_resource::<Tunables>()
+            .add_systems(
+                Last,
+                (move |tunable: Res<T>,
+                       registry: Res<AppTypeRegistry>,
+                       snapshot: Res<ParamSnapshot>| {
+                    match to_json(tunable.as_reflect(), &registry.read()) {
+                        Ok(json) => snapshot.set(name, json),
+                        Err(error) => error!(?error, name, "Failed to serialize tunable"),
+                    }
+                })
+                .run_if(resource_changed::<T>),
+            );
+        self.world
+            .resource_mut::<Tunables>()
+            .0
+            .insert(name, set_tunable::<T>);
+        self
+    }
+}
+
+/// Change of a tunable value
+#[derive(Event)]
+pub struct ParamUpdate {
+    /// Tunable name followed by the field path, such as `wave/height_multiplier`
+    pub path: String,
+    pub value: serde_json::Value,
+    /// Receives the resulting value or the reason the update was rejected
+    pub reply: Option<oneshot::Sender<Result<serde_json::Value, String>>>,
+}
+
+/// Value of a write to `face/param/**`
+///
+/// Either the bare value or `{"value": ..., "source": ...}` naming the sender for access control
+pub fn parse_param_value(payload: &[u8]) -> anyhow::Result<serde_json::Value> {
+    let value: serde_json::Value =
+        serde_json::from_slice(payload).context("Failed to parse json")?;
+    match value {
+        serde_json::Value::Object(mut object)
+            if object.len() == 2 && object.contains_key("source") =>
+        {
+            object
+                .remove("value")
+                .context("Parameter with a source needs a value")
+        }
+        value => Ok(value),
+    }
+}
+
+/// Latest values of all tunables, shared with the zenoh worker to answer queries
+#[derive(Resource, Clone, Default)]
+pub struct ParamSnapshot(Arc<Mutex<BTreeMap<&'static str, serde_json::Value>>>);
+
+impl ParamSnapshot {
+    fn set(&self, name: &'static str, value: serde_json::Value) {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(name, value);
+    }
+
+    /// Value at `path` such as `wave` or `wave/height_multiplier`
+    pub fn get(&self, path: &str) -> Option<serde_json::Value> {
+        let (name, field) = split_path(path);
+        let tunables = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        let tunable = tunables.get(name)?;
+        if field.is_empty() {
+            Some(tunable.clone())
+        } else {
+            tunable.pointer(&format!("/{field}")).cloned()
+        }
+    }
+
+    /// Every tunable with its name
+    pub fn all(&self) -> Vec<(&'static str, serde_json::Value)> {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .map(|(name, value)| (*name, value.clone()))
+            .collect()
+    }
+}
+
+type SetTunable = fn(&mut World, &str, serde_json::Value) -> anyhow::Result<serde_json::Value>;
+
+/// Setters of the registered tunables by name
+#[derive(Resource, Default)]
+struct Tunables(BTreeMap<&'static str, SetTunable>);
+
+fn split_path(path: &str) -> (&str, &str) {
+    let path = path.trim_matches('/');
+    path.split_once('/').unwrap_or((path, ""))
+}
+
+fn to_json(value: &dyn Reflect, registry: &TypeRegistry) -> anyhow::Result<serde_json::Value> {
+    let serializer = TypedReflectSerializer::new(value, registry);
+    Ok(serde_json::to_value(serializer)?)
+}
+
+/// Set the field at `path` of `T` and return its sanitized value
+fn set_tunable<T: Tunable>(
+    world: &mut World,
+    path: &str,
+    value: serde_json::Value,
+) -> anyhow::Result<serde_json::Value> {
+    let registry = world.resource::<AppTypeRegistry>().clone();
+    let registry = registry.read();
+    let mut tunable = T::from_reflect(world.get_resource::<T>().context("Tunable missing")?)
+        .context("Failed to copy tunable")?;
+
+    let field = if path.is_empty() {
+        tunable.as_reflect_mut()
+    } else {
+        tunable
+            .reflect_path_mut(path.replace('/', ".").as_str())
+            .map_err(|error| anyhow::anyhow!("Unknown field {path}: {error}"))?
+    };
+    let registration = field
+        .get_represented_type_info()
+        .and_then(|type_info| registry.get(type_info.type_id()))
+        .with_context(|| format!("Field {path} can't be changed"))?;
+    let value = TypedReflectDeserializer::new(registration, &registry)
+        .deserialize(value)
+        .with_context(|| format!("Invalid value for {path}"))?;
+    field
+        .set(value)
+        .map_err(|_| anyhow::anyhow!("Invalid value for {path}"))?;
+
+    tunable.sanitize();
+    let field = if path.is_empty() {
+        tunable.as_reflect()
+    } else {
+        tunable
+            .reflect_path(path.replace('/', ".").as_str())
+            .map_err(|error| anyhow::anyhow!("Unknown field {path}: {error}"))?
+    };
+    let result = to_json(field, &registry)?;
+    world.insert_resource(tunable);
+    Ok(result)
+}
+
+fn apply_param_updates(world: &mut World) {
+    let updates: Vec<_> = world
+        .resource_mut::<Events<ParamUpdate>>()
+        .drain()
+        .collect();
+    for update in updates {
+        let (name, field) = split_path(&update.path);
+        let set = world.resource::<Tunables>().0.get(name).copied();
+        let result = match set {
+            Some(set) => set(world, field, update.value),
+            None => Err(anyhow::anyhow!("Unknown tunable {name}")),
+        };
+        match &result {
+            Ok(value) => info!(path = update.path, %value, "Updated tunable"),
+            Err(error) => error!(?error, path = update.path, "Failed to update tunable"),
+        }
+        // accepted writes were already logged by the zenoh worker
+        if let (Err(error), Some(event_log)) = (&result, world.get_resource::<EventLog>()) {
+            event_log.record(
+                EventKind::Error,
+                format!("face/param/{}: {error:#}", update.path),
+            );
+        }
+        if let Some(reply) = update.reply {
+            _ = reply.send(result.map_err(|error| format!("{error:#}")));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::noise_plugin::NoiseGeneratorSettings;
+
+    #[test]
+    fn set_fields_are_sanitized_and_validated() {
+        let mut app = App::new();
+        app.insert_resource(NoiseGeneratorSettings::default())
+            .register_type::<NoiseGeneratorSettings>();
+        let world = &mut app.world;
+
+        let value =
+            set_tunable::<NoiseGeneratorSettings>(world, "height_multiplier", json!(1e9)).unwrap();
+        assert_eq!(value, json!(10_000.0));
+        assert_eq!(
+            world.resource::<NoiseGeneratorSettings>().height_multiplier,
+            10_000.0
+        );
+
+        assert!(set_tunable::<NoiseGeneratorSettings>(world, "missing", json!(1.0)).is_err());
+        assert!(set_tunable::<NoiseGeneratorSettings>(world, "hidden", json!("yes")).is_err());
+        assert!(!world.resource::<NoiseGeneratorSettings>().hidden);
+    }
+}
@@ -0,0 +1,129 @@
+use std::time::Duration;
+
+use bevy::{core::FrameCount, prelude::*};
+use tokio::sync::oneshot;
+
+use crate::{clock_sync::local_unix_ms, face_clock::FaceClock, rotation::FaceRotation};
+
+/// Answer latency queries on `face/ping`
+///
+/// The zenoh worker timestamps a query when it arrives and bevy when it handles it,
+/// so the reply splits the latency into the network and the message queue.
+/// A ping can also blink a square in the corner for measuring the time to the screen
+pub struct PingPlugin;
+
+impl Plugin for PingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PingRequest>()
+            .add_systems(Startup, spawn_ping_blink)
+            .add_systems(Update, (answer_pings, update_ping_blink).chain());
+    }
+}
+
+/// Key the face answers latency queries on
+pub const PING_KEY: &str = "face/ping";
+const BLINK_DURATION: Duration = Duration::from_millis(100);
+const BLINK_SIZE: f32 = 40.0;
+
+/// Optional value of a ping query
+#[derive(serde::Deserialize, Debug, Default)]
+pub struct PingQuery {
+    /// Blink a square in the top left corner once handled
+    #[serde(default)]
+    pub blink: bool,
+    /// Time the controller sent the query, echoed back
+    #[serde(default)]
+    pub sent_unix_ms: Option<f64>,
+}
+
+#[derive(serde::Serialize, Debug, Clone, PartialEq)]
+pub struct PingReply {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sent_unix_ms: Option<f64>,
+    /// Time the zenoh worker received the query
+    pub received_unix_ms: f64,
+    /// Time bevy handled the query
+    pub applied_unix_ms: f64,
+    /// Frame the query was handled in, a blink shows up in the next one
+    pub frame: u32,
+}
+
+/// Ping forwarded from the zenoh worker
+#[derive(Event)]
+pub struct PingRequest {
+    pub query: PingQuery,
+    pub received_unix_ms: f64,
+    pub reply: oneshot::Sender<PingReply>,
+}
+
+#[derive(Component)]
+struct PingBlink;
+
+/// Time the last blink started at
+#[derive(Resource)]
+struct PingBlinkStart(Duration);
+
+fn spawn_ping_blink(mut commands: Commands) {
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: Color::WHITE,
+                custom_size: Some(Vec2::splat(BLINK_SIZE)),
+                ..default()
+            },
+            transform: Transform::from_xyz(0.0, 0.0, 5.0),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        PingBlink,
+    ));
+}
+
+fn answer_pings(
+    mut commands: Commands,
+    mut requests: ResMut<Events<PingRequest>>,
+    frames: Res<FrameCount>,
+    clock: Res<FaceClock>,
+) {
+    // drained since the reply channel can't be cloned out of a reader
+    for request in requests.drain() {
+        let reply = PingReply {
+            sent_unix_ms: request.query.sent_unix_ms,
+            received_unix_ms: request.received_unix_ms,
+            applied_unix_ms: local_unix_ms(),
+            frame: frames.0,
+        };
+        debug!(?reply, "Answering ping");
+        if request.query.blink {
+            commands.insert_resource(PingBlinkStart(clock.elapsed()));
+        }
+        _ = request.reply.send(reply);
+    }
+}
+
+fn update_ping_blink(
+    blink_start: Option<Res<PingBlinkStart>>,
+    clock: Res<FaceClock>,
+    mut query: Query<(&mut Transform, &mut Visibility), With<PingBlink>>,
+    query_camera: Query<&OrthographicProjection>,
+    rotation: Res<FaceRotation>,
+) {
+    let blinking = blink_start.is_some_and(|start| clock.elapsed() - start.0 < BLINK_DURATION);
+    let mut corner = Vec2::ZERO;
+    for camera in query_camera.iter() {
+        let area = rotation.area(camera.area);
+        corner = Vec2::new(area.min.x, area.max.y) + Vec2::new(1.0, -1.0) * BLINK_SIZE / 2.0;
+    }
+    for (mut transform, mut visibility) in query.iter_mut() {
+        let target = if blinking {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        if *visibility != target {
+            *visibility = target;
+        }
+        transform.translation.x = corner.x;
+        transform.translation.y = corner.y;
+    }
+}
@@ -0,0 +1,124 @@
+use bevy::{prelude::*, window::PresentMode};
+
+use crate::{
+    messaging::{AckSender, CommandAck},
+    speech::SpeechEvent,
+    topics::RegisterTopic,
+};
+
+/// Switch between tear free and low latency presentation over `face/present_mode`
+///
+/// Vsync can hold a frame back for a refresh or two, which shows while lip-syncing
+/// but tearing is more noticeable on the slow idle wave
+pub struct PresentModePlugin;
+
+impl Plugin for PresentModePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_topic::<PresentModeMessage>("face/present_mode")
+            .add_event::<SpeechEvent>()
+            .init_resource::<Presentation>()
+            .add_systems(
+                Update,
+                (
+                    process_present_mode_messages,
+                    track_speech,
+                    apply_present_mode,
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[derive(
+    serde::Deserialize,
+    serde::Serialize,
+    clap::ValueEnum,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Default,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum LatencyMode {
+    /// Wait for vsync, no tearing
+    #[default]
+    Vsync,
+    /// Present right away, may tear
+    LowLatency,
+    /// Low latency while the robot speaks and vsync otherwise
+    WhileSpeaking,
+}
+
+#[derive(Event, serde::Deserialize, serde::Serialize, clap::Args, Debug)]
+pub struct PresentModeMessage {
+    #[arg(value_enum)]
+    pub mode: LatencyMode,
+    /// Key to publish an acknowledgement to once applied
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub reply_to: Option<String>,
+}
+
+#[derive(Resource, Default)]
+struct Presentation {
+    mode: LatencyMode,
+    speaking: bool,
+}
+
+impl Presentation {
+    fn present_mode(&self) -> PresentMode {
+        let low_latency = match self.mode {
+            LatencyMode::Vsync => false,
+            LatencyMode::LowLatency => true,
+            LatencyMode::WhileSpeaking => self.speaking,
+        };
+        if low_latency {
+            // immediate where supported, falling back to mailbox and vsync
+            PresentMode::AutoNoVsync
+        } else {
+            PresentMode::AutoVsync
+        }
+    }
+}
+
+fn process_present_mode_messages(
+    mut messages: EventReader<PresentModeMessage>,
+    mut presentation: ResMut<Presentation>,
+    ack_sender: Res<AckSender>,
+) {
+    for (message, id) in messages.read_with_id() {
+        let _trace = ack_sender.trace(id);
+        info!(mode = ?message.mode, "Updating latency mode");
+        presentation.mode = message.mode;
+        ack_sender.send(
+            message.reply_to.as_deref(),
+            CommandAck::applied(serde_json::json!({
+                "mode": presentation.mode,
+                "present_mode": format!("{:?}", presentation.present_mode()),
+            })),
+        );
+    }
+}
+
+fn track_speech(mut events: EventReader<SpeechEvent>, mut presentation: ResMut<Presentation>) {
+    if let Some(event) = events.read().last() {
+        if presentation.speaking != event.speaking {
+            presentation.speaking = event.speaking;
+        }
+    }
+}
+
+fn apply_present_mode(presentation: Res<Presentation>, mut windows: Query<&mut Window>) {
+    if !presentation.is_changed() {
+        return;
+    }
+    let present_mode = presentation.present_mode();
+    for mut window in windows.iter_mut() {
+        if window.present_mode != present_mode {
+            info!(?present_mode, "Switching present mode");
+            window.present_mode = present_mode;
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+/// Priority of a request competing for the same output
+///
+/// Higher priorities interrupt lower ones
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Default,
+    serde::Deserialize,
+    serde::Serialize,
+    clap::ValueEnum,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Priority {
+    /// Background behavior shown when nothing else is requested
+    Idle,
+    /// Explicitly commanded by a publisher
+    #[default]
+    Commanded,
+    /// Short reactions to the surroundings, shown over commanded requests which return after
+    Reaction,
+    /// Errors and warnings that must always be visible
+    Error,
+}
+
+struct PriorityEntry<T> {
+    priority: Priority,
+    value: T,
+    timer: Option<Timer>,
+}
+
+/// Active requests ordered by priority
+///
+/// Each priority level holds at most one request and newer requests
+/// replace older ones on the same level.
+/// A request on a lower level never interrupts a higher one, it is kept
+/// and becomes current once all higher requests end.
+pub struct PriorityStack<T> {
+    entries: Vec<PriorityEntry<T>>,
+}
+
+impl<T> Default for PriorityStack<T> {
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl<T: PartialEq> PriorityStack<T> {
+    /// Add a request that optionally ends after `duration`
+    pub fn push(&mut self, priority: Priority, value: T, duration: Option<Duration>) {
+        self.entries.retain(|entry| entry.priority != priority);
+        self.entries.push(PriorityEntry {
+            priority,
+            value,
+            timer: duration.map(|duration| Timer::new(duration, TimerMode::Once)),
+        });
+        self.entries.sort_by_key(|entry| entry.priority);
+    }
+
+    /// End all requests for `value`
+    pub fn remove(&mut self, value: &T) {
+        self.entries.retain(|entry| entry.value != *value);
+    }
+
+    /// End the request on `priority`
+    pub fn clear(&mut self, priority: Priority) {
+        self.entries.retain(|entry| entry.priority != priority);
+    }
+
+    /// Advance timers and drop requests that ran out
+    pub fn tick(&mut self, delta: Duration) {
+        self.entries.retain_mut(|entry| match &mut entry.timer {
+            Some(timer) => !timer.tick(delta).finished(),
+            None => true,
+        });
+    }
+
+    /// Highest priority request
+    pub fn current(&self) -> Option<&T> {
+        self.entries.last().map(|entry| &entry.value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commanded_request_returns_after_reaction() {
+        let mut stack = PriorityStack::default();
+        stack.push(Priority::Commanded, "calibration", None);
+        stack.push(Priority::Reaction, "dizzy", Some(Duration::from_secs(3)));
+        assert_eq!(stack.current(), Some(&"dizzy"));
+
+        stack.tick(Duration::from_secs(3));
+        assert_eq!(stack.current(), Some(&"calibration"));
+    }
+}
@@ -0,0 +1,393 @@
+use std::{collections::VecDeque, f32::consts::PI, time::Duration};
+
+use bevy::prelude::*;
+
+use crate::{
+    dnd::DoNotDisturb,
+    face_clock::FaceClock,
+    messaging::{AckSender, CommandAck},
+    noise_plugin::NoiseWave,
+    params::{RegisterTunable, Tunable},
+    sound::PlaySound,
+    topics::RegisterTopic,
+};
+
+/// Short canned reactions started with `face/react`
+///
+/// Reactions end by themselves and are layered over whatever scene the face shows.
+/// Up to `max_concurrent` different reactions play at once and further ones wait
+/// in a queue of `max_queued`, both set with the `reactions` tunable
+pub struct ReactionPlugin;
+
+impl Plugin for ReactionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_topic::<ReactionMessage>("face/react")
+            .init_resource::<ReactionLimits>()
+            .init_resource::<Reactions>()
+            .init_resource::<DoNotDisturb>()
+            .init_resource::<ReactionPose>()
+            .register_tunable::<ReactionLimits>("reactions")
+            .add_systems(
+                Update,
+                (
+                    process_reaction_messages,
+                    start_queued_reactions,
+                    advance_reactions,
+                    float_question_marks,
+                )
+                    .chain(),
+            );
+    }
+}
+
+pub const DEFAULT_CONFUSED_SOUND: &str = "sounds/confused.ogg";
+/// Wave tilt in radians at the height of the confused reaction
+const CONFUSED_TILT: f32 = 0.2;
+const QUESTION_MARK_FONT_SIZE: f32 = 120.0;
+/// Start of the question mark relative to the center of the screen
+const QUESTION_MARK_OFFSET: Vec2 = Vec2::new(120.0, 80.0);
+/// Pixels the question mark floats up over the reaction
+const QUESTION_MARK_RISE: f32 = 100.0;
+/// Pixels the wave dips down per nod
+const NOD_DEPTH: f32 = 40.0;
+const NOD_COUNT: f32 = 2.0;
+/// Sideways shake and bounce of the wave in pixels while laughing
+const LAUGH_SHAKE: f32 = 25.0;
+const LAUGH_BOUNCE: f32 = 15.0;
+const LAUGH_SHAKES: f32 = 8.0;
+/// Height the wave jumps to when gasping
+const GASP_SCALE: f32 = 1.8;
+/// Share of the gasp spent jumping up, the rest settles back
+const GASP_ATTACK: f32 = 0.15;
+/// Upper bounds for the tunable limits
+const MAX_CONCURRENT_LIMIT: usize = 4;
+const MAX_QUEUED_LIMIT: usize = 16;
+
+#[derive(
+    serde::Deserialize, serde::Serialize, clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Reaction {
+    /// Tilted wave, a floating question mark and a chirp, such as after a voice command that wasn't understood
+    Confused,
+    /// Wave dips down twice
+    Nod,
+    /// Wave shakes and bounces
+    Laugh,
+    /// Wave jumps up and settles
+    Gasp,
+}
+
+impl Reaction {
+    fn duration(self) -> Duration {
+        match self {
+            Reaction::Confused => Duration::from_secs(2),
+            Reaction::Nod => Duration::from_millis(1000),
+            Reaction::Laugh => Duration::from_millis(1500),
+            Reaction::Gasp => Duration::from_millis(800),
+        }
+    }
+
+    fn default_sound(self) -> Option<&'static str> {
+        match self {
+            Reaction::Confused => Some(DEFAULT_CONFUSED_SOUND),
+            Reaction::Nod | Reaction::Laugh | Reaction::Gasp => None,
+        }
+    }
+
+    /// Contribution to the wave pose at `progress` between 0.0 and 1.0
+    fn pose(self, progress: f32) -> ReactionPose {
+        let mut pose = ReactionPose::default();
+        match self {
+            Reaction::Confused => pose.tilt = CONFUSED_TILT * (progress * PI).sin(),
+            Reaction::Nod => pose.offset.y = -NOD_DEPTH * (progress * NOD_COUNT * PI).sin().abs(),
+            Reaction::Laugh => {
+                let fade = 1.0 - progress;
+                pose.offset.x = LAUGH_SHAKE * (progress * LAUGH_SHAKES * 2.0 * PI).sin() * fade;
+                pose.offset.y = LAUGH_BOUNCE * (progress * LAUGH_SHAKES * PI).sin().abs() * fade;
+            }
+            Reaction::Gasp => {
+                let envelope = if progress < GASP_ATTACK {
+                    progress / GASP_ATTACK
+                } else {
+                    1.0 - (progress - GASP_ATTACK) / (1.0 - GASP_ATTACK)
+                };
+                pose.scale.y = 1.0 + (GASP_SCALE - 1.0) * envelope;
+            }
+        }
+        pose
+    }
+}
+
+#[derive(Event, serde::Deserialize, serde::Serialize, clap::Args, Debug)]
+pub struct ReactionMessage {
+    #[arg(value_enum)]
+    pub reaction: Reaction,
+    /// Asset path of the sound played with the reaction instead of the default one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub sound: Option<String>,
+    /// Key to publish an acknowledgement to once started or queued
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub reply_to: Option<String>,
+}
+
+/// How many reactions play at once and wait
+#[derive(Resource, Reflect, Clone, Debug)]
+#[reflect(Resource)]
+pub struct ReactionLimits {
+    /// Different reactions playing at the same time
+    pub max_concurrent: usize,
+    /// Reactions waiting to play, further ones are rejected
+    pub max_queued: usize,
+}
+
+impl Default for ReactionLimits {
+    fn default() -> Self {
+        Self {
+            max_concurrent: 2,
+            max_queued: 4,
+        }
+    }
+}
+
+impl Tunable for ReactionLimits {
+    fn sanitize(&mut self) {
+        self.max_concurrent = self.max_concurrent.clamp(1, MAX_CONCURRENT_LIMIT);
+        self.max_queued = self.max_queued.min(MAX_QUEUED_LIMIT);
+    }
+}
+
+/// Offset, scale and tilt of the wave from all playing reactions
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct ReactionPose {
+    /// Pixels on top of where the wave is placed
+    pub offset: Vec2,
+    pub scale: Vec2,
+    /// Radians
+    pub tilt: f32,
+}
+
+impl Default for ReactionPose {
+    fn default() -> Self {
+        Self {
+            offset: Vec2::ZERO,
+            scale: Vec2::ONE,
+            tilt: 0.0,
+        }
+    }
+}
+
+impl ReactionPose {
+    fn combine(self, other: Self) -> Self {
+        Self {
+            offset: self.offset + other.offset,
+            scale: self.scale * other.scale,
+            tilt: self.tilt + other.tilt,
+        }
+    }
+}
+
+struct PendingReaction {
+    reaction: Reaction,
+    sound: Option<String>,
+}
+
+struct PlayingReaction {
+    reaction: Reaction,
+    elapsed: Duration,
+}
+
+#[derive(Resource, Default)]
+struct Reactions {
+    playing: Vec<PlayingReaction>,
+    queued: VecDeque<PendingReaction>,
+}
+
+/// Question mark floating up during the confused reaction
+#[derive(Component)]
+struct QuestionMark;
+
+fn process_reaction_messages(
+    mut messages: EventReader<ReactionMessage>,
+    mut reactions: ResMut<Reactions>,
+    limits: Res<ReactionLimits>,
+    ack_sender: Res<AckSender>,
+    do_not_disturb: Res<DoNotDisturb>,
+) {
+    for (message, id) in messages.read_with_id() {
+        let _trace = ack_sender.trace(id);
+        if do_not_disturb.active() {
+            debug!(reaction = ?message.reaction, "Skipping reaction, do-not-disturb is on");
+            ack_sender.send(
+                message.reply_to.as_deref(),
+                CommandAck::rejected("Do-not-disturb is on"),
+            );
+            continue;
+        }
+        // reactions queued into free slots start this frame and don't count as waiting
+        let free = limits
+            .max_concurrent
+            .saturating_sub(reactions.playing.len());
+        if reactions.queued.len() >= limits.max_queued + free {
+            warn!(reaction = ?message.reaction, "Reaction queue full");
+            ack_sender.send(
+                message.reply_to.as_deref(),
+                CommandAck::rejected("Reaction queue full"),
+            );
+            continue;
+        }
+        debug!(reaction = ?message.reaction, "Queueing reaction");
+        reactions.queued.push_back(PendingReaction {
+            reaction: message.reaction,
+            sound: message.sound.clone(),
+        });
+        ack_sender.send(
+            message.reply_to.as_deref(),
+            CommandAck::applied(serde_json::json!({
+                "reaction": message.reaction,
+                "position": reactions.queued.len() - 1,
+            })),
+        );
+    }
+}
+
+/// Start waiting reactions in order while there is room, the same reaction never plays twice at once
+fn start_queued_reactions(
+    mut commands: Commands,
+    mut reactions: ResMut<Reactions>,
+    mut play_sound: EventWriter<PlaySound>,
+    limits: Res<ReactionLimits>,
+) {
+    let reactions = &mut *reactions;
+    while reactions.playing.len() < limits.max_concurrent {
+        let Some(next) = reactions.queued.front() else {
+            break;
+        };
+        if reactions
+            .playing
+            .iter()
+            .any(|playing| playing.reaction == next.reaction)
+        {
+            break;
+        }
+        let Some(PendingReaction { reaction, sound }) = reactions.queued.pop_front() else {
+            break;
+        };
+
+        info!(?reaction, "Reacting");
+        if reaction == Reaction::Confused {
+            commands.spawn((
+                Text2dBundle {
+                    text: Text::from_section(
+                        "?",
+                        TextStyle {
+                            font_size: QUESTION_MARK_FONT_SIZE,
+                            color: Color::WHITE,
+                            ..default()
+                        },
+                    ),
+                    transform: Transform::from_translation(QUESTION_MARK_OFFSET.extend(2.0)),
+                    ..default()
+                },
+                QuestionMark,
+            ));
+        }
+        if let Some(sound) = sound.as_deref().or(reaction.default_sound()) {
+            play_sound.send(PlaySound(sound.to_owned()));
+        }
+        reactions.playing.push(PlayingReaction {
+            reaction,
+            elapsed: Duration::ZERO,
+        });
+    }
+}
+
+fn advance_reactions(
+    mut reactions: ResMut<Reactions>,
+    mut pose: ResMut<ReactionPose>,
+    clock: Res<FaceClock>,
+    mut waves: Query<&mut Transform, With<NoiseWave>>,
+) {
+    if reactions.playing.is_empty() && *pose == ReactionPose::default() {
+        return;
+    }
+    for playing in &mut reactions.playing {
+        playing.elapsed += clock.delta();
+    }
+    reactions
+        .playing
+        .retain(|playing| playing.elapsed < playing.reaction.duration());
+
+    let combined = reactions
+        .playing
+        .iter()
+        .map(|playing| {
+            let progress =
+                playing.elapsed.as_secs_f32() / playing.reaction.duration().as_secs_f32();
+            playing.reaction.pose(progress)
+        })
+        .fold(ReactionPose::default(), ReactionPose::combine);
+    // the noise plugin places the wave with the offset and scale, tilting is left to reactions
+    // so the dizzy wobble isn't overridden while no reaction tilts
+    if combined.tilt != pose.tilt {
+        for mut wave in waves.iter_mut() {
+            wave.rotation = Quat::from_rotation_z(combined.tilt);
+        }
+    }
+    *pose = combined;
+}
+
+fn float_question_marks(
+    mut commands: Commands,
+    reactions: Res<Reactions>,
+    mut question_marks: Query<(Entity, &mut Transform, &mut Text), With<QuestionMark>>,
+) {
+    let confused = reactions
+        .playing
+        .iter()
+        .find(|playing| playing.reaction == Reaction::Confused);
+    for (entity, mut transform, mut text) in question_marks.iter_mut() {
+        let Some(confused) = confused else {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        };
+        let progress = confused.elapsed.as_secs_f32() / Reaction::Confused.duration().as_secs_f32();
+        transform.translation.y = QUESTION_MARK_OFFSET.y + QUESTION_MARK_RISE * progress;
+        // fade out over the last third
+        let alpha = ((1.0 - progress) * 3.0).min(1.0);
+        text.sections[0].style.color.set_a(alpha);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reactions_start_and_end_at_rest() {
+        for reaction in [
+            Reaction::Confused,
+            Reaction::Nod,
+            Reaction::Laugh,
+            Reaction::Gasp,
+        ] {
+            for progress in [0.0, 1.0] {
+                let pose = reaction.pose(progress);
+                assert!(pose.offset.length() < 1e-3, "{reaction:?} at {progress}");
+                assert!((pose.scale - Vec2::ONE).length() < 1e-3);
+                assert!(pose.tilt.abs() < 1e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn poses_layer() {
+        let pose = Reaction::Gasp
+            .pose(GASP_ATTACK)
+            .combine(Reaction::Nod.pose(0.25));
+        assert_eq!(pose.scale.y, GASP_SCALE);
+        assert_eq!(pose.offset.y, -NOD_DEPTH);
+    }
+}
@@ -0,0 +1,96 @@
+use std::{ops::RangeInclusive, path::PathBuf, time::Duration};
+
+use bevy::{
+    app::AppExit, core::FrameCount, prelude::*, render::view::screenshot::ScreenshotManager,
+    time::TimeUpdateStrategy, window::PrimaryWindow,
+};
+
+use crate::{
+    face_clock::{ClockSource, FaceClock},
+    noise_plugin::NoiseSeed,
+};
+
+/// Deterministic mode for golden image comparisons and reproducible bug reports
+///
+/// Time advances by a fixed step every frame instead of following the wall clock
+/// so the same seed and inputs always render the same frames
+pub struct ReplayPlugin {
+    pub settings: ReplaySettings,
+}
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        if !self.settings.screenshot_frames.is_empty() {
+            if let Err(error) = std::fs::create_dir_all(&self.settings.screenshot_dir) {
+                error!(?error, "Failed to create screenshot directory");
+            }
+        }
+
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(self.settings.step))
+            .insert_resource(NoiseSeed(self.settings.seed))
+            .insert_resource(FaceClock::new(ClockSource::simulated_from_hour(
+                self.settings.start_hour,
+            )))
+            .insert_resource(self.settings.clone())
+            .add_systems(Last, capture_replay_frames);
+    }
+}
+
+/// Frames to wait for the last screenshot to be written before exiting
+const SCREENSHOT_EXIT_DELAY: u32 = 10;
+/// Frame rates a replay can simulate
+const REPLAY_FPS_RANGE: RangeInclusive<f64> = 1.0..=1000.0;
+
+/// Parse the frame rate simulated in replay mode
+pub fn parse_replay_fps(fps: &str) -> Result<f64, String> {
+    let fps: f64 = fps.parse().map_err(|error| format!("{error}"))?;
+    if REPLAY_FPS_RANGE.contains(&fps) {
+        Ok(fps)
+    } else {
+        Err(format!(
+            "{fps} is not a frame rate between {} and {}",
+            REPLAY_FPS_RANGE.start(),
+            REPLAY_FPS_RANGE.end()
+        ))
+    }
+}
+
+#[derive(Resource, Clone, Debug)]
+pub struct ReplaySettings {
+    /// Time added every frame
+    pub step: Duration,
+    /// Seed of the noise generator
+    pub seed: u32,
+    /// Local hour the replay pretends to start at
+    pub start_hour: u32,
+    /// Frames to save as screenshots, the app exits after the last one
+    pub screenshot_frames: Vec<u32>,
+    pub screenshot_dir: PathBuf,
+}
+
+fn capture_replay_frames(
+    settings: Res<ReplaySettings>,
+    frames: Res<FrameCount>,
+    window: Query<Entity, With<PrimaryWindow>>,
+    mut screenshot_manager: ResMut<ScreenshotManager>,
+    mut exit: EventWriter<AppExit>,
+) {
+    let Some(last_frame) = settings.screenshot_frames.iter().max() else {
+        return;
+    };
+    if settings.screenshot_frames.contains(&frames.0) {
+        let path = settings
+            .screenshot_dir
+            .join(format!("frame_{:05}.png", frames.0));
+        info!(?path, "Saving replay frame");
+        if let Ok(window) = window.get_single() {
+            if let Err(error) = screenshot_manager.save_screenshot_to_disk(window, path) {
+                error!(?error, "Failed to save replay frame");
+            }
+        }
+    }
+    if frames.0 >= last_frame + SCREENSHOT_EXIT_DELAY {
+        info!("Replay finished");
+        exit.send(AppExit);
+    }
+}
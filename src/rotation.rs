@@ -0,0 +1,160 @@
+use std::f32::consts::FRAC_PI_2;
+
+use bevy::prelude::*;
+
+use crate::display::DisplayControl;
+
+/// Rotate the face inside the window instead of relying on the compositor transform
+///
+/// Cameras are rotated so scenes keep laying themselves out in portrait
+/// through [`FaceRotation::area`] whichever way the panel is mounted
+pub struct RotationPlugin {
+    pub rotation: FaceRotation,
+}
+
+impl Plugin for RotationPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.rotation)
+            .add_systems(PostUpdate, rotate_cameras);
+    }
+}
+
+/// Requested rotation of the face
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RotationSetting {
+    /// Rotate landscape panels by `--display-transform` unless the compositor already rotates them
+    Auto,
+    /// Leave rotating to the compositor
+    #[default]
+    Normal,
+    #[value(name = "90")]
+    Rotate90,
+    #[value(name = "180")]
+    Rotate180,
+    #[value(name = "270")]
+    Rotate270,
+}
+
+/// Counter clockwise rotation of the face inside the window, same as wayland output transforms
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FaceRotation {
+    #[default]
+    Normal,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+}
+
+impl FaceRotation {
+    fn quarter_turns(self) -> u8 {
+        match self {
+            FaceRotation::Normal => 0,
+            FaceRotation::Rotate90 => 1,
+            FaceRotation::Rotate180 => 2,
+            FaceRotation::Rotate270 => 3,
+        }
+    }
+
+    /// Whether width and height of the window are swapped for the face
+    pub fn is_sideways(self) -> bool {
+        self.quarter_turns() % 2 == 1
+    }
+
+    /// Part of the world visible through a camera with the projection `area`
+    pub fn area(self, area: Rect) -> Rect {
+        if self.is_sideways() {
+            Rect::from_center_size(area.center(), area.size().yx())
+        } else {
+            area
+        }
+    }
+
+    /// Window size showing the face at `width` by `height`
+    pub fn window_size(self, width: f32, height: f32) -> (f32, f32) {
+        if self.is_sideways() {
+            (height, width)
+        } else {
+            (width, height)
+        }
+    }
+
+    fn camera_rotation(self) -> Quat {
+        // the camera turns the opposite way of the content
+        Quat::from_rotation_z(-FRAC_PI_2 * self.quarter_turns() as f32)
+    }
+
+    fn from_transform(transform: &str) -> Option<Self> {
+        match transform {
+            "normal" | "0" => Some(FaceRotation::Normal),
+            "90" => Some(FaceRotation::Rotate90),
+            "180" => Some(FaceRotation::Rotate180),
+            "270" => Some(FaceRotation::Rotate270),
+            _ => None,
+        }
+    }
+}
+
+impl RotationSetting {
+    /// Decide on the rotation at startup
+    ///
+    /// Once the face rotates itself the compositor transform of `display_control` is reset
+    /// so turning the display on doesn't rotate it a second time
+    pub fn resolve(self, display_control: &mut DisplayControl) -> FaceRotation {
+        let rotation = match self {
+            RotationSetting::Auto => detect_rotation(display_control),
+            RotationSetting::Normal => FaceRotation::Normal,
+            RotationSetting::Rotate90 => FaceRotation::Rotate90,
+            RotationSetting::Rotate180 => FaceRotation::Rotate180,
+            RotationSetting::Rotate270 => FaceRotation::Rotate270,
+        };
+        if rotation != FaceRotation::Normal {
+            display_control.transform = "normal".to_owned();
+        }
+        rotation
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn detect_rotation(display_control: &DisplayControl) -> FaceRotation {
+    use wayland_client::protocol::wl_output::Transform;
+
+    let info = match crate::wlr_output::output_info(&display_control.output) {
+        Ok(info) => info,
+        Err(error) => {
+            warn!(?error, "Failed to detect panel orientation, not rotating");
+            return FaceRotation::Normal;
+        }
+    };
+    let rotation = if info.transform != Transform::Normal || info.width <= info.height {
+        FaceRotation::Normal
+    } else {
+        FaceRotation::from_transform(&display_control.transform).unwrap_or_else(|| {
+            warn!(
+                transform = display_control.transform,
+                "Display transform is not a rotation, not rotating"
+            );
+            FaceRotation::Normal
+        })
+    };
+    info!(
+        output = display_control.output,
+        width = info.width,
+        height = info.height,
+        transform = ?info.transform,
+        ?rotation,
+        "Detected panel orientation"
+    );
+    rotation
+}
+
+#[cfg(not(target_os = "linux"))]
+fn detect_rotation(_display_control: &DisplayControl) -> FaceRotation {
+    info!("Panel orientation can't be detected on this platform, not rotating");
+    FaceRotation::Normal
+}
+
+fn rotate_cameras(rotation: Res<FaceRotation>, mut query: Query<&mut Transform, Added<Camera2d>>) {
+    for mut transform in query.iter_mut() {
+        transform.rotation = rotation.camera_rotation();
+    }
+}
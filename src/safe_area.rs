@@ -0,0 +1,120 @@
+use bevy::prelude::*;
+
+use crate::params::{RegisterTunable, Tunable};
+
+/// Margins along the edges of the panel that are covered by the face plate
+///
+/// Scenes lay themselves out inside [`SafeArea::apply`] and overlays marked with
+/// [`SafeAreaNode`] are pushed in by the margins. Adjustable at runtime as the `safe_area` tunable
+pub struct SafeAreaPlugin {
+    pub safe_area: SafeArea,
+}
+
+impl Plugin for SafeAreaPlugin {
+    fn build(&self, app: &mut App) {
+        let mut safe_area = self.safe_area.clone();
+        safe_area.sanitize();
+        app.insert_resource(safe_area)
+            .register_tunable::<SafeArea>("safe_area")
+            .add_systems(Update, position_safe_area_nodes);
+    }
+}
+
+/// Largest accepted margin in pixels
+const MAX_SAFE_AREA_MARGIN: f32 = 10_000.0;
+
+/// Covered margin of each edge in pixels
+#[derive(Resource, Reflect, Clone, Debug, Default, PartialEq)]
+#[reflect(Resource)]
+pub struct SafeArea {
+    pub top: f32,
+    pub bottom: f32,
+    pub left: f32,
+    pub right: f32,
+}
+
+impl SafeArea {
+    /// Visible part of the camera `area`, collapses to its center line when the margins overlap
+    pub fn apply(&self, area: Rect) -> Rect {
+        let min = Vec2::new(area.min.x + self.left, area.min.y + self.bottom);
+        let max = Vec2::new(area.max.x - self.right, area.max.y - self.top);
+        let center = (min + max) * 0.5;
+        Rect::from_corners(min.min(center), max.max(center))
+    }
+}
+
+impl Tunable for SafeArea {
+    fn sanitize(&mut self) {
+        for margin in [
+            &mut self.top,
+            &mut self.bottom,
+            &mut self.left,
+            &mut self.right,
+        ] {
+            *margin = if margin.is_finite() {
+                margin.clamp(0.0, MAX_SAFE_AREA_MARGIN)
+            } else {
+                0.0
+            };
+        }
+    }
+}
+
+/// UI node kept `offset` pixels inside the safe area
+///
+/// Only the sides the node is positioned from are adjusted, the others are left at `Val::Auto`
+#[derive(Component)]
+pub struct SafeAreaNode {
+    pub offset: f32,
+}
+
+fn position_safe_area_nodes(
+    safe_area: Res<SafeArea>,
+    mut query: Query<(Ref<SafeAreaNode>, &mut Style)>,
+) {
+    for (node, mut style) in query.iter_mut() {
+        if !safe_area.is_changed() && !node.is_changed() {
+            continue;
+        }
+        let style = &mut *style;
+        for (position, margin) in [
+            (&mut style.top, safe_area.top),
+            (&mut style.bottom, safe_area.bottom),
+            (&mut style.left, safe_area.left),
+            (&mut style.right, safe_area.right),
+        ] {
+            if *position != Val::Auto {
+                *position = Val::Px(node.offset + margin);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn margins_shrink_the_area_until_it_collapses() {
+        let area = Rect::new(-240.0, -400.0, 240.0, 400.0);
+        let safe_area = SafeArea {
+            top: 20.0,
+            bottom: 10.0,
+            left: 20.0,
+            right: 20.0,
+        };
+        assert_eq!(
+            safe_area.apply(area),
+            Rect::new(-220.0, -390.0, 220.0, 380.0)
+        );
+
+        let safe_area = SafeArea {
+            left: 400.0,
+            right: 200.0,
+            ..default()
+        };
+        let collapsed = safe_area.apply(area);
+        assert_eq!(collapsed.width(), 0.0);
+        assert_eq!(collapsed.min.x, 100.0);
+    }
+}
@@ -0,0 +1,63 @@
+use bevy::prelude::*;
+
+use crate::{face_clock::FaceClock, priority::PriorityStack};
+
+pub struct ScenePlugin;
+
+impl Plugin for ScenePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SceneRequests>()
+            .add_systems(Update, apply_scene_requests);
+    }
+}
+
+/// Top level scene currently shown on the face
+#[derive(
+    States,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    Default,
+    serde::Deserialize,
+    serde::Serialize,
+    clap::ValueEnum,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum FaceScene {
+    /// Regular animated noise wave
+    #[default]
+    Wave,
+    /// Static alignment pattern used while assembling the head
+    Calibration,
+    /// Spiral eyes over a wobbling wave after being shaken
+    Dizzy,
+    /// Happy eyes looking at a person who approached
+    Greeting,
+    /// Test pattern sequence run after assembling the head
+    SelfTest,
+    /// Marks the face uncontrollable while zenoh hasn't connected since boot
+    Offline,
+}
+
+/// Scenes requested by publishers
+///
+/// The highest priority request is shown, falling back to the wave
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct SceneRequests(PriorityStack<FaceScene>);
+
+fn apply_scene_requests(
+    clock: Res<FaceClock>,
+    mut requests: ResMut<SceneRequests>,
+    scene: Res<State<FaceScene>>,
+    mut next_scene: ResMut<NextState<FaceScene>>,
+) {
+    requests.tick(clock.delta());
+    let requested = requests.current().copied().unwrap_or_default();
+    if requested != *scene.get() {
+        info!(?requested, "Switching scene");
+        next_scene.set(requested);
+    }
+}
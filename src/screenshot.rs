@@ -0,0 +1,146 @@
+use std::path::PathBuf;
+
+use bevy::{prelude::*, render::view::screenshot::ScreenshotManager, window::PrimaryWindow};
+
+/// Save the current frame as a timestamped PNG with a hotkey during tuning sessions
+pub struct ScreenshotHotkeyPlugin {
+    pub key: KeyCode,
+    pub dir: PathBuf,
+}
+
+impl Plugin for ScreenshotHotkeyPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ScreenshotHotkey {
+            key: self.key,
+            dir: self.dir.clone(),
+        })
+        .add_systems(Update, save_screenshot_on_hotkey);
+    }
+}
+
+pub const DEFAULT_SCREENSHOT_KEY: &str = "F12";
+
+#[derive(Resource)]
+struct ScreenshotHotkey {
+    key: KeyCode,
+    dir: PathBuf,
+}
+
+/// Parse a hotkey argument, function keys such as "F12" or a single letter or digit
+pub fn parse_key_code(key: &str) -> Result<KeyCode, String> {
+    const FUNCTION_KEYS: [KeyCode; 12] = [
+        KeyCode::F1,
+        KeyCode::F2,
+        KeyCode::F3,
+        KeyCode::F4,
+        KeyCode::F5,
+        KeyCode::F6,
+        KeyCode::F7,
+        KeyCode::F8,
+        KeyCode::F9,
+        KeyCode::F10,
+        KeyCode::F11,
+        KeyCode::F12,
+    ];
+    const LETTER_KEYS: [KeyCode; 26] = [
+        KeyCode::KeyA,
+        KeyCode::KeyB,
+        KeyCode::KeyC,
+        KeyCode::KeyD,
+        KeyCode::KeyE,
+        KeyCode::KeyF,
+        KeyCode::KeyG,
+        KeyCode::KeyH,
+        KeyCode::KeyI,
+        KeyCode::KeyJ,
+        KeyCode::KeyK,
+        KeyCode::KeyL,
+        KeyCode::KeyM,
+        KeyCode::KeyN,
+        KeyCode::KeyO,
+        KeyCode::KeyP,
+        KeyCode::KeyQ,
+        KeyCode::KeyR,
+        KeyCode::KeyS,
+        KeyCode::KeyT,
+        KeyCode::KeyU,
+        KeyCode::KeyV,
+        KeyCode::KeyW,
+        KeyCode::KeyX,
+        KeyCode::KeyY,
+        KeyCode::KeyZ,
+    ];
+    const DIGIT_KEYS: [KeyCode; 10] = [
+        KeyCode::Digit0,
+        KeyCode::Digit1,
+        KeyCode::Digit2,
+        KeyCode::Digit3,
+        KeyCode::Digit4,
+        KeyCode::Digit5,
+        KeyCode::Digit6,
+        KeyCode::Digit7,
+        KeyCode::Digit8,
+        KeyCode::Digit9,
+    ];
+
+    let key = key.trim().to_ascii_uppercase();
+    if let Some(number) = key.strip_prefix('F').filter(|number| !number.is_empty()) {
+        if let Some(key_code) = number
+            .parse::<usize>()
+            .ok()
+            .and_then(|number| FUNCTION_KEYS.get(number.checked_sub(1)?))
+        {
+            return Ok(*key_code);
+        }
+    }
+    match key.as_bytes() {
+        [letter @ b'A'..=b'Z'] => Ok(LETTER_KEYS[(letter - b'A') as usize]),
+        [digit @ b'0'..=b'9'] => Ok(DIGIT_KEYS[(digit - b'0') as usize]),
+        _ => Err(format!(
+            "Unsupported key {key:?}, expected F1 to F12, a letter or a digit"
+        )),
+    }
+}
+
+fn save_screenshot_on_hotkey(
+    input: Res<ButtonInput<KeyCode>>,
+    hotkey: Res<ScreenshotHotkey>,
+    window: Query<Entity, With<PrimaryWindow>>,
+    mut screenshot_manager: ResMut<ScreenshotManager>,
+) {
+    if !input.just_pressed(hotkey.key) {
+        return;
+    }
+    let Ok(window) = window.get_single() else {
+        return;
+    };
+    if let Err(error) = std::fs::create_dir_all(&hotkey.dir) {
+        error!(?error, "Failed to create screenshot directory");
+        return;
+    }
+    let path = hotkey.dir.join(format!(
+        "face_{}.png",
+        chrono::Local::now().format("%Y%m%d_%H%M%S%.3f")
+    ));
+    info!(?path, "Saving screenshot");
+    if let Err(error) = screenshot_manager.save_screenshot_to_disk(window, path) {
+        error!(?error, "Failed to save screenshot");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hotkeys_parse() {
+        assert_eq!(parse_key_code("F12"), Ok(KeyCode::F12));
+        assert_eq!(parse_key_code("f1"), Ok(KeyCode::F1));
+        assert_eq!(parse_key_code("s"), Ok(KeyCode::KeyS));
+        assert_eq!(parse_key_code("F"), Ok(KeyCode::KeyF));
+        assert_eq!(parse_key_code("7"), Ok(KeyCode::Digit7));
+        assert!(parse_key_code("F13").is_err());
+        assert!(parse_key_code("F0").is_err());
+        assert!(parse_key_code("space").is_err());
+    }
+}
@@ -0,0 +1,661 @@
+use std::{f32::consts::PI, time::Duration};
+
+use bevy::{prelude::*, window::PrimaryWindow};
+use bevy_prototype_lyon::prelude::*;
+
+use crate::{
+    face_clock::FaceClock,
+    messaging::{AckSender, CommandAck},
+    priority::Priority,
+    rotation::FaceRotation,
+    safe_area::SafeArea,
+    scene::{FaceScene, SceneRequests},
+    topics::RegisterTopic,
+    trace::{CommandTrace, TraceGuard},
+};
+
+/// Checks for a freshly assembled head, started with `--self-test` or over `face/self_test`
+///
+/// Shows color bars, a white and a black frame, asks for taps on touch targets
+/// and sweeps a wave over the full height. Every step is published on `face/self_test/step`
+/// and the summary on `face/self_test/result`
+pub struct SelfTestPlugin {
+    pub run_at_startup: bool,
+}
+
+impl Plugin for SelfTestPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_topic::<SelfTestMessage>("face/self_test")
+            .add_systems(
+                OnExit(FaceScene::SelfTest),
+                (despawn_self_test_step, abort_self_test),
+            )
+            .add_systems(
+                Update,
+                (
+                    process_self_test_messages,
+                    (
+                        spawn_self_test_step,
+                        check_touch_targets,
+                        sweep_wave,
+                        advance_self_test,
+                    )
+                        .chain()
+                        .run_if(
+                            resource_exists::<SelfTest>.and_then(in_state(FaceScene::SelfTest)),
+                        ),
+                )
+                    .chain(),
+            );
+        if self.run_at_startup {
+            app.add_systems(Startup, |mut messages: EventWriter<SelfTestMessage>| {
+                messages.send(SelfTestMessage::default());
+            });
+        }
+    }
+}
+
+const SELF_TEST_STEP_KEY: &str = "face/self_test/step";
+const SELF_TEST_RESULT_KEY: &str = "face/self_test/result";
+
+/// Vertical bars of the color bar step, left to right
+const COLOR_BARS: [Color; 8] = [
+    Color::WHITE,
+    Color::YELLOW,
+    Color::CYAN,
+    Color::GREEN,
+    Color::FUCHSIA,
+    Color::RED,
+    Color::BLUE,
+    Color::BLACK,
+];
+/// Touch targets are this far inside of the safe area
+const TOUCH_TARGET_INSET: f32 = 40.0;
+const TOUCH_TARGET_RADIUS: f32 = 24.0;
+/// Taps this far from the center of a target still count
+const TOUCH_TARGET_TOLERANCE: f32 = 48.0;
+const TOUCH_TARGET_COLOR: Color = Color::WHITE;
+const TOUCH_TARGET_HIT_COLOR: Color = Color::GREEN;
+const SWEEP_LINE_WIDTH: f32 = 2.0;
+const SWEEP_SEGMENTS: usize = 120;
+/// Wavelengths across the width while sweeping
+const SWEEP_CYCLES: f32 = 2.0;
+/// The sweep fails if a single frame takes longer than this
+const SWEEP_MAX_FRAME: Duration = Duration::from_millis(100);
+
+/// Start the self test
+#[derive(Event, serde::Deserialize, serde::Serialize, clap::Args, Debug, Default)]
+pub struct SelfTestMessage {
+    /// Don't wait for taps, for heads without a touch panel
+    #[serde(default)]
+    #[arg(long)]
+    pub skip_touch: bool,
+    /// Key to publish the summary to once done
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub reply_to: Option<String>,
+}
+
+#[derive(serde::Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SelfTestStep {
+    ColorBars,
+    White,
+    Black,
+    Touch,
+    WaveSweep,
+}
+
+impl SelfTestStep {
+    const ALL: [SelfTestStep; 5] = [
+        SelfTestStep::ColorBars,
+        SelfTestStep::White,
+        SelfTestStep::Black,
+        SelfTestStep::Touch,
+        SelfTestStep::WaveSweep,
+    ];
+
+    /// Longest time the step is shown, touch ends early once every target was hit
+    fn duration(self) -> Duration {
+        match self {
+            SelfTestStep::ColorBars => Duration::from_secs(5),
+            SelfTestStep::White | SelfTestStep::Black => Duration::from_secs(3),
+            SelfTestStep::Touch => Duration::from_secs(20),
+            SelfTestStep::WaveSweep => Duration::from_secs(6),
+        }
+    }
+}
+
+#[derive(serde::Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    /// Shown for the assembler to check by eye
+    Shown,
+    Passed,
+    Failed,
+    Skipped,
+}
+
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct StepResult {
+    pub step: SelfTestStep,
+    pub status: StepStatus,
+    #[serde(skip_serializing_if = "serde_json::Value::is_null")]
+    pub details: serde_json::Value,
+}
+
+/// Touch target corners and center
+#[derive(Debug, Clone, Copy)]
+enum TouchTarget {
+    TopLeft,
+    TopRight,
+    Center,
+    BottomLeft,
+    BottomRight,
+}
+
+impl TouchTarget {
+    const ALL: [TouchTarget; 5] = [
+        TouchTarget::TopLeft,
+        TouchTarget::TopRight,
+        TouchTarget::Center,
+        TouchTarget::BottomLeft,
+        TouchTarget::BottomRight,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            TouchTarget::TopLeft => "top_left",
+            TouchTarget::TopRight => "top_right",
+            TouchTarget::Center => "center",
+            TouchTarget::BottomLeft => "bottom_left",
+            TouchTarget::BottomRight => "bottom_right",
+        }
+    }
+
+    fn position(self, area: Rect) -> Vec2 {
+        let area = Rect::from_center_size(
+            area.center(),
+            (area.size() - Vec2::splat(TOUCH_TARGET_INSET * 2.0)).max(Vec2::ZERO),
+        );
+        match self {
+            TouchTarget::TopLeft => Vec2::new(area.min.x, area.max.y),
+            TouchTarget::TopRight => area.max,
+            TouchTarget::Center => area.center(),
+            TouchTarget::BottomLeft => area.min,
+            TouchTarget::BottomRight => Vec2::new(area.max.x, area.min.y),
+        }
+    }
+}
+
+/// Running self test
+#[derive(Resource)]
+struct SelfTest {
+    step: usize,
+    timer: Timer,
+    skip_touch: bool,
+    reply_to: Option<String>,
+    /// Steps and the result are published in later frames under the trace of the command
+    trace: Option<CommandTrace>,
+    results: Vec<StepResult>,
+    /// Visuals of the current step were spawned
+    spawned: bool,
+    touched: [bool; TouchTarget::ALL.len()],
+    frames: u32,
+    longest_frame: Duration,
+}
+
+impl SelfTest {
+    fn current(&self) -> SelfTestStep {
+        SelfTestStep::ALL[self.step]
+    }
+}
+
+/// Everything drawn by the current step
+#[derive(Component)]
+struct SelfTestElement;
+
+#[derive(Component)]
+struct TouchTargetMarker(usize);
+
+#[derive(Component)]
+struct SweepLine;
+
+fn process_self_test_messages(
+    mut commands: Commands,
+    mut messages: EventReader<SelfTestMessage>,
+    self_test: Option<Res<SelfTest>>,
+    mut scene_requests: ResMut<SceneRequests>,
+    ack_sender: Res<AckSender>,
+) {
+    let mut running = self_test.is_some();
+    for (message, id) in messages.read_with_id() {
+        let _trace = ack_sender.trace(id);
+        if running {
+            ack_sender.send(
+                message.reply_to.as_deref(),
+                CommandAck::rejected("Self test already running"),
+            );
+            continue;
+        }
+        running = true;
+        info!(skip_touch = message.skip_touch, "Starting self test");
+        commands.insert_resource(SelfTest {
+            step: 0,
+            timer: Timer::new(SelfTestStep::ALL[0].duration(), TimerMode::Once),
+            skip_touch: message.skip_touch,
+            reply_to: message.reply_to.clone(),
+            trace: TraceGuard::current(),
+            results: Vec::new(),
+            spawned: false,
+            touched: default(),
+            frames: 0,
+            longest_frame: Duration::ZERO,
+        });
+        scene_requests.push(Priority::Commanded, FaceScene::SelfTest, None);
+    }
+}
+
+fn spawn_self_test_step(
+    mut commands: Commands,
+    mut self_test: ResMut<SelfTest>,
+    query_camera: Query<&OrthographicProjection>,
+    (safe_area, rotation): (Res<SafeArea>, Res<FaceRotation>),
+) {
+    if self_test.spawned {
+        return;
+    }
+    self_test.spawned = true;
+    let mut area = Rect::default();
+    for camera in query_camera.iter() {
+        area = rotation.area(camera.area);
+    }
+
+    let fill = |commands: &mut Commands, color: Color, center: Vec2, size: Vec2| {
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color,
+                    custom_size: Some(size),
+                    ..default()
+                },
+                transform: Transform::from_translation(center.extend(0.0)),
+                ..default()
+            },
+            SelfTestElement,
+        ));
+    };
+    match self_test.current() {
+        SelfTestStep::ColorBars => {
+            let width = area.width() / COLOR_BARS.len() as f32;
+            for (index, color) in COLOR_BARS.into_iter().enumerate() {
+                let x = area.min.x + width * (index as f32 + 0.5);
+                let center = Vec2::new(x, area.center().y);
+                fill(
+                    &mut commands,
+                    color,
+                    center,
+                    Vec2::new(width, area.height()),
+                );
+            }
+        }
+        SelfTestStep::White => fill(&mut commands, Color::WHITE, area.center(), area.size()),
+        SelfTestStep::Black => fill(&mut commands, Color::BLACK, area.center(), area.size()),
+        SelfTestStep::Touch => {
+            let area = safe_area.apply(area);
+            for (index, target) in TouchTarget::ALL.into_iter().enumerate() {
+                let color = if self_test.touched[index] {
+                    TOUCH_TARGET_HIT_COLOR
+                } else {
+                    TOUCH_TARGET_COLOR
+                };
+                commands.spawn((
+                    ShapeBundle {
+                        path: GeometryBuilder::build_as(&shapes::Circle {
+                            radius: TOUCH_TARGET_RADIUS,
+                            center: target.position(area),
+                        }),
+                        ..default()
+                    },
+                    Fill::color(color),
+                    TouchTargetMarker(index),
+                    SelfTestElement,
+                ));
+            }
+        }
+        SelfTestStep::WaveSweep => {
+            commands.spawn((
+                ShapeBundle::default(),
+                Stroke::new(Color::WHITE, SWEEP_LINE_WIDTH),
+                Fill::color(Color::NONE),
+                SweepLine,
+                SelfTestElement,
+            ));
+        }
+    }
+}
+
+fn despawn_self_test_step(mut commands: Commands, query: Query<Entity, With<SelfTestElement>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Mark targets hit by a tap or a click
+fn check_touch_targets(
+    mut self_test: ResMut<SelfTest>,
+    touches: Res<Touches>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform, &OrthographicProjection)>,
+    mut targets: Query<(&TouchTargetMarker, &mut Fill)>,
+    (safe_area, rotation): (Res<SafeArea>, Res<FaceRotation>),
+) {
+    if self_test.current() != SelfTestStep::Touch {
+        return;
+    }
+    let Ok((camera, camera_transform, projection)) = cameras.get_single() else {
+        return;
+    };
+    let mut taps: Vec<Vec2> = touches
+        .iter_just_pressed()
+        .map(|touch| touch.position())
+        .collect();
+    if mouse.just_pressed(MouseButton::Left) {
+        taps.extend(windows.iter().filter_map(Window::cursor_position));
+    }
+
+    let area = safe_area.apply(rotation.area(projection.area));
+    for tap in taps {
+        let Some(tap) = camera.viewport_to_world_2d(camera_transform, tap) else {
+            continue;
+        };
+        for (index, target) in TouchTarget::ALL.into_iter().enumerate() {
+            if tap.distance(target.position(area)) <= TOUCH_TARGET_TOLERANCE {
+                info!(target = target.name(), "Touch target hit");
+                self_test.touched[index] = true;
+            }
+        }
+    }
+    for (marker, mut fill) in targets.iter_mut() {
+        if self_test.touched[marker.0] && fill.color != TOUCH_TARGET_HIT_COLOR {
+            fill.color = TOUCH_TARGET_HIT_COLOR;
+        }
+    }
+}
+
+/// Grow and shrink a sine wave over the full height of the safe area
+fn sweep_wave(
+    mut self_test: ResMut<SelfTest>,
+    time: Res<Time<Real>>,
+    mut lines: Query<&mut Path, With<SweepLine>>,
+    query_camera: Query<&OrthographicProjection>,
+    (safe_area, rotation): (Res<SafeArea>, Res<FaceRotation>),
+) {
+    if self_test.current() != SelfTestStep::WaveSweep {
+        return;
+    }
+    self_test.frames += 1;
+    self_test.longest_frame = self_test.longest_frame.max(time.delta());
+
+    let Some(camera) = query_camera.iter().next() else {
+        return;
+    };
+    let area = safe_area.apply(rotation.area(camera.area));
+    let amplitude = (self_test.timer.fraction() * PI).sin() * area.height() / 2.0;
+    let points: Vec<Vec2> = (0..=SWEEP_SEGMENTS)
+        .map(|index| {
+            let position = index as f32 / SWEEP_SEGMENTS as f32;
+            Vec2::new(
+                area.min.x + position * area.width(),
+                area.center().y + (position * SWEEP_CYCLES * 2.0 * PI).sin() * amplitude,
+            )
+        })
+        .collect();
+    for mut path in lines.iter_mut() {
+        *path = GeometryBuilder::build_as(&shapes::Polygon {
+            points: points.clone(),
+            closed: false,
+        });
+    }
+}
+
+/// Finish the current step once its time is up and move on to the next one
+fn advance_self_test(
+    mut commands: Commands,
+    mut self_test: ResMut<SelfTest>,
+    clock: Res<FaceClock>,
+    mut scene_requests: ResMut<SceneRequests>,
+    ack_sender: Res<AckSender>,
+    elements: Query<Entity, With<SelfTestElement>>,
+) {
+    let step = self_test.current();
+    let skipped = step == SelfTestStep::Touch && self_test.skip_touch;
+    let all_touched =
+        step == SelfTestStep::Touch && self_test.touched.iter().all(|touched| *touched);
+    let finished = self_test.timer.tick(clock.delta()).finished();
+    if !(finished || skipped || all_touched) {
+        return;
+    }
+
+    let _trace = TraceGuard::enter(self_test.trace.clone());
+    let result = step_result(&self_test, step, skipped);
+    info!(?result, "Self test step done");
+    ack_sender.publish(SELF_TEST_STEP_KEY, &result);
+    self_test.results.push(result);
+
+    for entity in elements.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    self_test.step += 1;
+    if let Some(next) = SelfTestStep::ALL.get(self_test.step) {
+        self_test.timer = Timer::new(next.duration(), TimerMode::Once);
+        self_test.spawned = false;
+        return;
+    }
+
+    let passed = !self_test
+        .results
+        .iter()
+        .any(|result| result.status == StepStatus::Failed);
+    let summary = serde_json::json!({
+        "passed": passed,
+        "steps": self_test.results,
+    });
+    info!(passed, "Self test finished");
+    ack_sender.publish(SELF_TEST_RESULT_KEY, &summary);
+    ack_sender.send(self_test.reply_to.as_deref(), CommandAck::applied(&summary));
+    commands.remove_resource::<SelfTest>();
+    scene_requests.remove(&FaceScene::SelfTest);
+}
+
+/// End a self test whose scene was replaced by another request before it finished
+///
+/// Its systems only run in the self test scene, the test would never finish otherwise
+fn abort_self_test(
+    mut commands: Commands,
+    self_test: Option<Res<SelfTest>>,
+    mut scene_requests: ResMut<SceneRequests>,
+    ack_sender: Res<AckSender>,
+) {
+    let Some(self_test) = self_test else {
+        return;
+    };
+    let _trace = TraceGuard::enter(self_test.trace.clone());
+    let step = SelfTestStep::ALL.get(self_test.step).copied();
+    warn!(?step, "Self test interrupted by another scene");
+    let summary = serde_json::json!({
+        "passed": false,
+        "interrupted_at": step,
+        "steps": self_test.results,
+    });
+    ack_sender.publish(SELF_TEST_RESULT_KEY, &summary);
+    ack_sender.send(
+        self_test.reply_to.as_deref(),
+        CommandAck::rejected("Self test interrupted by another scene"),
+    );
+    commands.remove_resource::<SelfTest>();
+    scene_requests.remove(&FaceScene::SelfTest);
+}
+
+fn step_result(self_test: &SelfTest, step: SelfTestStep, skipped: bool) -> StepResult {
+    let (status, details) = match step {
+        _ if skipped => (StepStatus::Skipped, serde_json::Value::Null),
+        SelfTestStep::ColorBars | SelfTestStep::White | SelfTestStep::Black => {
+            (StepStatus::Shown, serde_json::Value::Null)
+        }
+        SelfTestStep::Touch => {
+            let missed: Vec<_> = TouchTarget::ALL
+                .into_iter()
+                .zip(self_test.touched)
+                .filter(|(_, touched)| !touched)
+                .map(|(target, _)| target.name())
+                .collect();
+            let status = if missed.is_empty() {
+                StepStatus::Passed
+            } else {
+                StepStatus::Failed
+            };
+            (status, serde_json::json!({ "missed": missed }))
+        }
+        SelfTestStep::WaveSweep => {
+            let status = if self_test.longest_frame <= SWEEP_MAX_FRAME {
+                StepStatus::Passed
+            } else {
+                StepStatus::Failed
+            };
+            (
+                status,
+                serde_json::json!({
+                    "frames": self_test.frames,
+                    "longest_frame_ms": self_test.longest_frame.as_secs_f64() * 1000.0,
+                }),
+            )
+        }
+    };
+    StepResult {
+        step,
+        status,
+        details,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        messaging::KeyPrefix,
+        outbound::{OutboundQueue, OutboundSettings},
+        scene::ScenePlugin,
+        trace::CommandTraces,
+    };
+
+    fn self_test() -> SelfTest {
+        SelfTest {
+            step: 3,
+            timer: Timer::new(SelfTestStep::Touch.duration(), TimerMode::Once),
+            skip_touch: false,
+            reply_to: None,
+            trace: None,
+            results: Vec::new(),
+            spawned: true,
+            touched: [true, false, true, true, false],
+            frames: 0,
+            longest_frame: Duration::ZERO,
+        }
+    }
+
+    #[test]
+    fn touch_step_lists_missed_targets() {
+        let result = step_result(&self_test(), SelfTestStep::Touch, false);
+        assert_eq!(result.status, StepStatus::Failed);
+        assert_eq!(
+            result.details,
+            serde_json::json!({ "missed": ["top_right", "bottom_right"] })
+        );
+
+        let result = step_result(&self_test(), SelfTestStep::Touch, true);
+        assert_eq!(result.status, StepStatus::Skipped);
+    }
+
+    #[test]
+    fn touch_targets_stay_inside_area() {
+        let area = Rect::new(-100.0, -50.0, 100.0, 50.0);
+        for target in TouchTarget::ALL {
+            let position = target.position(area);
+            assert!(area.contains(position), "{} outside", target.name());
+        }
+        assert_eq!(TouchTarget::TopLeft.position(area), Vec2::new(-60.0, 10.0));
+    }
+
+    #[tokio::test]
+    async fn evicted_self_test_is_aborted() {
+        let outbound = OutboundQueue::new(16);
+        let traces = CommandTraces::default();
+        let mut app = App::new();
+        app.init_resource::<FaceClock>()
+            .insert_state(FaceScene::Wave)
+            .add_plugins(ScenePlugin)
+            .insert_resource(AckSender::new(
+                outbound.clone(),
+                KeyPrefix::default(),
+                traces.clone(),
+            ))
+            .add_event::<SelfTestMessage>()
+            .add_systems(OnExit(FaceScene::SelfTest), abort_self_test)
+            .add_systems(Update, process_self_test_messages);
+        let start = |app: &mut App, trace: Option<CommandTrace>| {
+            let id = app.world.send_event(SelfTestMessage {
+                skip_touch: true,
+                reply_to: Some(String::from("test/reply")),
+            });
+            traces.dispatched(id.unwrap(), trace);
+            app.update();
+            app.update();
+        };
+
+        start(
+            &mut app,
+            Some(CommandTrace {
+                trace_id: String::from("4bf92f3577b34da6"),
+                key_expr: String::from("face/self_test"),
+            }),
+        );
+        assert_eq!(
+            *app.world.resource::<State<FaceScene>>().get(),
+            FaceScene::SelfTest
+        );
+
+        app.world.resource_mut::<SceneRequests>().push(
+            Priority::Commanded,
+            FaceScene::Calibration,
+            None,
+        );
+        app.update();
+        app.update();
+        assert_eq!(
+            *app.world.resource::<State<FaceScene>>().get(),
+            FaceScene::Calibration
+        );
+        assert!(!app.world.contains_resource::<SelfTest>());
+
+        let mut rate = OutboundSettings::default().interval();
+        let (key, result) = outbound.next(&mut rate).await;
+        assert_eq!(key, SELF_TEST_RESULT_KEY);
+        assert_eq!(result["passed"], false);
+        // the abort answers the command in a later frame under its trace
+        assert_eq!(result["trace_id"], "4bf92f3577b34da6");
+        let (key, ack) = outbound.next(&mut rate).await;
+        assert_eq!(key, "test/reply");
+        assert_eq!(ack["status"], "rejected");
+        assert_eq!(ack["trace_id"], "4bf92f3577b34da6");
+
+        // a new self test is accepted instead of being rejected as already running
+        app.world
+            .resource_mut::<SceneRequests>()
+            .clear(Priority::Commanded);
+        start(&mut app, None);
+        assert!(app.world.contains_resource::<SelfTest>());
+        assert!(outbound.is_empty());
+    }
+}
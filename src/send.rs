@@ -0,0 +1,266 @@
+use anyhow::Context;
+use zenoh::prelude::r#async::*;
+
+use crate::{
+    asset_pack::AssetPackMessage,
+    calibration::CalibrationMessage,
+    countdown::TimerMessage,
+    display::{DisplayControlMessage, DisplayMode},
+    dnd::DoNotDisturbMessage,
+    expression::ExpressionMessage,
+    gaze::GazeMessage,
+    glitch::GlitchMessage,
+    messaging::{ErrorWrapper, ZenohSettings},
+    noise_plugin::NoiseGeneratorSettingsUpdate,
+    present_mode::PresentModeMessage,
+    reaction::ReactionMessage,
+    self_test::SelfTestMessage,
+    shake::ShakeMessage,
+    shutdown::FaceCommandMessage,
+    speech::SayMessage,
+    weather::WeatherMessage,
+};
+
+/// Command published to a running face
+#[derive(clap::Subcommand, Debug)]
+pub enum SendCommand {
+    /// Change the wave settings
+    Settings(NoiseGeneratorSettingsUpdate),
+    /// Turn the display on or off, rotate it or change its mode
+    Display(DisplayArgs),
+    /// Show or hide the calibration pattern
+    Calibration(CalibrationMessage),
+    /// Show the current weather
+    Weather(WeatherMessage),
+    /// Look at a point on the screen
+    Gaze(GazeMessage),
+    /// Shut down or restart the face
+    Command(FaceCommandMessage),
+    /// Download and install an asset pack
+    AssetPack(AssetPackMessage),
+    /// Run the self test
+    SelfTest(SelfTestMessage),
+    /// Show text in the speech bubble
+    Say(SayMessage),
+    /// Start or cancel the countdown
+    Timer(TimerMessage),
+    /// Glitch the wave for a while
+    Glitch(GlitchMessage),
+    /// Play a short reaction
+    React(ReactionMessage),
+    /// Turn do-not-disturb on or off
+    Dnd(DoNotDisturbMessage),
+    /// Shake the screen
+    Shake(ShakeMessage),
+    /// Trade tear free presentation for lower latency
+    PresentMode(PresentModeMessage),
+    /// Change the expression of the face
+    Expression(ExpressionMessage),
+}
+
+/// Command published to a running face along with who sends it
+#[derive(clap::Args, Debug)]
+pub struct SendArgs {
+    /// Name of the sender, checked against the access control rules of the face
+    #[arg(long)]
+    pub source: Option<String>,
+    /// Trace id to follow the command through the face's logs, acks and events
+    #[arg(long)]
+    pub trace_id: Option<String>,
+    #[command(subcommand)]
+    pub command: SendCommand,
+}
+
+impl SendArgs {
+    /// Key expression and json payload of the command including the source and trace id
+    pub fn to_message(&self) -> serde_json::Result<(&'static str, String)> {
+        let (key_expr, json) = self.command.to_message()?;
+        if self.source.is_none() && self.trace_id.is_none() {
+            return Ok((key_expr, json));
+        }
+        let mut message: serde_json::Map<String, serde_json::Value> = serde_json::from_str(&json)?;
+        if let Some(source) = &self.source {
+            message.insert("source".to_owned(), source.clone().into());
+        }
+        if let Some(trace_id) = &self.trace_id {
+            message.insert("trace_id".to_owned(), trace_id.clone().into());
+        }
+        Ok((key_expr, serde_json::to_string(&message)?))
+    }
+}
+
+#[derive(clap::Args, Debug)]
+pub struct DisplayArgs {
+    /// Turn the display on
+    #[arg(long, conflicts_with = "off")]
+    on: bool,
+    /// Turn the display off
+    #[arg(long)]
+    off: bool,
+    /// Output transform such as "normal", "90" or "270"
+    #[arg(long)]
+    transform: Option<String>,
+    /// Output mode such as 1920x1080 or 1920x1080@60
+    #[arg(long)]
+    mode: Option<DisplayMode>,
+    /// Switch night mode on or off
+    #[arg(long)]
+    night_mode: Option<bool>,
+    /// Key to publish an acknowledgement to once applied
+    #[arg(long)]
+    reply_to: Option<String>,
+}
+
+impl SendCommand {
+    /// Key expression and json payload of the command
+    pub fn to_message(&self) -> serde_json::Result<(&'static str, String)> {
+        match self {
+            SendCommand::Settings(update) => Ok(("face/settings", serde_json::to_string(update)?)),
+            SendCommand::Display(args) => Ok((
+                "face/display",
+                serde_json::to_string(&DisplayControlMessage {
+                    display_on: (args.on || args.off).then_some(args.on),
+                    transform: args.transform.clone(),
+                    mode: args.mode,
+                    night_mode: args.night_mode,
+                    reply_to: args.reply_to.clone(),
+                })?,
+            )),
+            SendCommand::Calibration(message) => {
+                Ok(("face/calibration", serde_json::to_string(message)?))
+            }
+            SendCommand::Weather(message) => Ok(("face/weather", serde_json::to_string(message)?)),
+            SendCommand::Gaze(message) => Ok(("face/gaze", serde_json::to_string(message)?)),
+            SendCommand::Command(message) => Ok(("face/command", serde_json::to_string(message)?)),
+            SendCommand::AssetPack(message) => {
+                Ok(("face/assets/install", serde_json::to_string(message)?))
+            }
+            SendCommand::Say(message) => Ok(("face/say", serde_json::to_string(message)?)),
+            SendCommand::Glitch(message) => Ok(("face/glitch", serde_json::to_string(message)?)),
+            SendCommand::React(message) => Ok(("face/react", serde_json::to_string(message)?)),
+            SendCommand::PresentMode(message) => {
+                Ok(("face/present_mode", serde_json::to_string(message)?))
+            }
+            SendCommand::Expression(message) => {
+                Ok(("face/expression", serde_json::to_string(message)?))
+            }
+            SendCommand::Shake(message) => Ok(("face/shake", serde_json::to_string(message)?)),
+            SendCommand::Dnd(message) => Ok(("face/dnd", serde_json::to_string(message)?)),
+            SendCommand::Timer(message) => Ok(("face/timer", serde_json::to_string(message)?)),
+            SendCommand::SelfTest(message) => {
+                Ok(("face/self_test", serde_json::to_string(message)?))
+            }
+        }
+    }
+}
+
+/// Open a short lived zenoh session and publish `command`
+pub async fn send_command(
+    zenoh_settings: &ZenohSettings,
+    command: &SendArgs,
+) -> anyhow::Result<()> {
+    let (key_expr, json) = command
+        .to_message()
+        .context("Failed to serialize command")?;
+
+    let session = zenoh::open(zenoh_settings.to_config()?)
+        .res()
+        .await
+        .map_err(ErrorWrapper::ZenohError)
+        .context("Failed to create zenoh session")?;
+    session
+        .put(zenoh_settings.key_prefix.apply(key_expr), json.clone())
+        .encoding(Encoding::APP_JSON)
+        .res()
+        .await
+        .map_err(ErrorWrapper::ZenohError)
+        .with_context(|| format!("Failed to publish to {key_expr}"))?;
+    session
+        .close()
+        .res()
+        .await
+        .map_err(ErrorWrapper::ZenohError)
+        .context("Failed to close zenoh session")?;
+
+    println!("{key_expr} {json}");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        send: SendArgs,
+    }
+
+    fn message(args: &[&str]) -> (&'static str, serde_json::Value) {
+        let cli = Cli::try_parse_from(std::iter::once("send").chain(args.iter().copied())).unwrap();
+        let (key_expr, json) = cli.send.to_message().unwrap();
+        (key_expr, serde_json::from_str(&json).unwrap())
+    }
+
+    #[test]
+    fn settings_only_contain_given_values() {
+        assert_eq!(
+            message(&["settings", "--height-multiplier", "600"]),
+            (
+                "face/settings",
+                serde_json::json!({ "height_multiplier": 600.0 })
+            )
+        );
+    }
+
+    #[test]
+    fn source_is_added_to_the_command() {
+        assert_eq!(
+            message(&["--source", "supervisor", "display", "--off"]),
+            (
+                "face/display",
+                serde_json::json!({ "display_on": false, "source": "supervisor" })
+            )
+        );
+        assert_eq!(
+            message(&["--trace-id", "4bf92f35", "display", "--off"]),
+            (
+                "face/display",
+                serde_json::json!({ "display_on": false, "trace_id": "4bf92f35" })
+            )
+        );
+    }
+
+    #[test]
+    fn present_mode_is_positional() {
+        assert_eq!(
+            message(&["present-mode", "while-speaking"]),
+            (
+                "face/present_mode",
+                serde_json::json!({ "mode": "while_speaking" })
+            )
+        );
+    }
+
+    #[test]
+    fn display_commands_are_well_formed() {
+        assert_eq!(
+            message(&["display", "--off"]),
+            ("face/display", serde_json::json!({ "display_on": false }))
+        );
+        assert_eq!(
+            message(&["display", "--on", "--mode", "800x480@60Hz"]),
+            (
+                "face/display",
+                serde_json::json!({
+                    "display_on": true,
+                    "mode": { "width": 800, "height": 480, "refresh": 60.0 },
+                })
+            )
+        );
+        assert!(Cli::try_parse_from(["send", "display", "--on", "--off"]).is_err());
+        assert!(Cli::try_parse_from(["send", "display", "--mode", "0x480"]).is_err());
+    }
+}
@@ -0,0 +1,95 @@
+use std::path::PathBuf;
+
+use anyhow::Context;
+use bevy::prelude::*;
+
+/// JSON file commands with `"persist": true` write their resulting values to
+///
+/// Each persisting command owns a section of the file, loaded again at startup
+#[derive(Resource, Clone, Debug, Default)]
+pub struct SettingsFile {
+    /// Persisting is rejected when no file is configured
+    pub path: Option<PathBuf>,
+}
+
+impl SettingsFile {
+    /// Values of `section`, `None` if the file or the section doesn't exist
+    pub fn load<T: serde::de::DeserializeOwned>(&self, section: &str) -> anyhow::Result<Option<T>> {
+        let Some(path) = &self.path else {
+            return Ok(None);
+        };
+        if !path.exists() {
+            return Ok(None);
+        }
+        let mut sections = read_sections(path)?;
+        sections
+            .remove(section)
+            .map(serde_json::from_value)
+            .transpose()
+            .with_context(|| format!("Invalid {section} section in {}", path.display()))
+    }
+
+    /// Replace `section` while keeping the other sections
+    ///
+    /// The file is written next to the old one and renamed over it,
+    /// so a power loss leaves either the old or the new file behind
+    pub fn persist(&self, section: &str, values: &impl serde::Serialize) -> anyhow::Result<()> {
+        let path = self.path.as_ref().context("No settings file configured")?;
+        let mut sections = if path.exists() {
+            read_sections(path)?
+        } else {
+            serde_json::Map::new()
+        };
+        sections.insert(section.to_owned(), serde_json::to_value(values)?);
+
+        let mut temporary = path.clone().into_os_string();
+        temporary.push(".tmp");
+        let temporary = PathBuf::from(temporary);
+        let json = serde_json::to_vec_pretty(&sections)?;
+        {
+            use std::io::Write;
+            let mut file = std::fs::File::create(&temporary)
+                .with_context(|| format!("Failed to create {}", temporary.display()))?;
+            file.write_all(&json)?;
+            file.sync_all()?;
+        }
+        std::fs::rename(&temporary, path)
+            .with_context(|| format!("Failed to replace {}", path.display()))?;
+        info!(section, path = %path.display(), "Persisted settings");
+        Ok(())
+    }
+}
+
+fn read_sections(
+    path: &std::path::Path,
+) -> anyhow::Result<serde_json::Map<String, serde_json::Value>> {
+    let data = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_json::from_slice(&data)
+        .with_context(|| format!("Invalid settings file {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sections_are_replaced_independently() {
+        let path = std::env::temp_dir().join(format!("face-settings-{}.json", std::process::id()));
+        let settings_file = SettingsFile {
+            path: Some(path.clone()),
+        };
+        settings_file
+            .persist("wave", &serde_json::json!({"hidden": true}))
+            .unwrap();
+        settings_file.persist("other", &1).unwrap();
+        settings_file
+            .persist("wave", &serde_json::json!({"hidden": false}))
+            .unwrap();
+
+        let wave: serde_json::Value = settings_file.load("wave").unwrap().unwrap();
+        assert_eq!(wave, serde_json::json!({"hidden": false}));
+        assert_eq!(settings_file.load::<u32>("other").unwrap(), Some(1));
+        assert_eq!(settings_file.load::<u32>("missing").unwrap(), None);
+        std::fs::remove_file(path).unwrap();
+    }
+}
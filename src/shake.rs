@@ -0,0 +1,244 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+
+use crate::{
+    dnd::DoNotDisturb,
+    face_clock::FaceClock,
+    messaging::{AckSender, CommandAck},
+    params::{RegisterTunable, Tunable},
+    topics::RegisterTopic,
+};
+
+/// Whole screen shake for impacts, started over `face/shake`
+///
+/// Collisions reported on `face/collision` shake the screen as well, scaled by their intensity.
+/// The defaults for both are the `screen_shake` tunable
+pub struct ScreenShakePlugin;
+
+impl Plugin for ScreenShakePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_topic::<ShakeMessage>("face/shake")
+            .add_topic::<CollisionMessage>("face/collision")
+            .init_resource::<ScreenShake>()
+            .init_resource::<Shakes>()
+            .init_resource::<DoNotDisturb>()
+            .register_tunable::<ScreenShake>("screen_shake")
+            .add_systems(
+                Update,
+                (process_shake_messages, process_collisions, shake_cameras).chain(),
+            );
+    }
+}
+
+/// Shakes are dropped once they move the screen less than this
+const MIN_SHAKE_PX: f32 = 0.5;
+/// Shakes past this many only add up to the oldest ones
+const MAX_SHAKES: usize = 8;
+
+/// Default shake, also used for collisions
+#[derive(Resource, Reflect, Clone, Debug)]
+#[reflect(Resource)]
+pub struct ScreenShake {
+    /// Largest offset of the screen in pixels
+    pub magnitude_px: f32,
+    /// Back and forth movements per second
+    pub frequency_hz: f32,
+    /// Seconds for the shake to die down to about a third
+    pub decay_s: f32,
+    /// Shake on collisions reported on `face/collision`
+    pub on_collision: bool,
+}
+
+impl Default for ScreenShake {
+    fn default() -> Self {
+        Self {
+            magnitude_px: 20.0,
+            frequency_hz: 25.0,
+            decay_s: 0.15,
+            on_collision: true,
+        }
+    }
+}
+
+impl Tunable for ScreenShake {
+    fn sanitize(&mut self) {
+        let defaults = Self::default();
+        self.magnitude_px = valid_or(self.magnitude_px, defaults.magnitude_px).min(500.0);
+        self.frequency_hz = valid_or(self.frequency_hz, defaults.frequency_hz).min(120.0);
+        self.decay_s = valid_or(self.decay_s, defaults.decay_s).min(10.0);
+    }
+}
+
+fn valid_or(value: f32, default: f32) -> f32 {
+    if value.is_finite() && value > 0.0 {
+        value
+    } else {
+        default
+    }
+}
+
+/// Shake the screen, left out values come from the `screen_shake` tunable
+#[derive(Event, serde::Deserialize, serde::Serialize, clap::Args, Debug)]
+pub struct ShakeMessage {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub magnitude_px: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub frequency_hz: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub decay_s: Option<f32>,
+    /// Key to publish an acknowledgement to once applied
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub reply_to: Option<String>,
+}
+
+/// Collision reported by the robot, such as from a bumper
+#[derive(Event, serde::Deserialize, Debug)]
+pub struct CollisionMessage {
+    /// Between 0.0 for a light touch and 1.0 for a full impact
+    #[serde(default = "full_intensity")]
+    pub intensity: f32,
+}
+
+fn full_intensity() -> f32 {
+    1.0
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+struct Shake {
+    magnitude_px: f32,
+    frequency_hz: f32,
+    decay_s: f32,
+    #[serde(skip)]
+    started: f32,
+}
+
+impl Shake {
+    /// Offset of the screen `elapsed` seconds into the shake
+    fn offset(&self, elapsed: f32) -> Vec2 {
+        let amplitude = self.amplitude(elapsed);
+        let phase = elapsed * self.frequency_hz * TAU;
+        // incommensurate frequencies so the screen doesn't just move along a line
+        Vec2::new(phase.sin(), (phase * 1.37 + 1.0).sin()) * amplitude
+    }
+
+    fn amplitude(&self, elapsed: f32) -> f32 {
+        self.magnitude_px * (-elapsed / self.decay_s).exp()
+    }
+}
+
+#[derive(Resource, Default)]
+struct Shakes(Vec<Shake>);
+
+impl Shakes {
+    fn start(&mut self, shake: Shake) {
+        if self.0.len() < MAX_SHAKES {
+            self.0.push(shake);
+        }
+    }
+}
+
+fn process_shake_messages(
+    mut messages: EventReader<ShakeMessage>,
+    mut shakes: ResMut<Shakes>,
+    (defaults, do_not_disturb): (Res<ScreenShake>, Res<DoNotDisturb>),
+    ack_sender: Res<AckSender>,
+    clock: Res<FaceClock>,
+) {
+    for (message, id) in messages.read_with_id() {
+        let _trace = ack_sender.trace(id);
+        if do_not_disturb.active() {
+            ack_sender.send(
+                message.reply_to.as_deref(),
+                CommandAck::rejected("Do-not-disturb is on"),
+            );
+            continue;
+        }
+        let mut settings = ScreenShake {
+            magnitude_px: message.magnitude_px.unwrap_or(defaults.magnitude_px),
+            frequency_hz: message.frequency_hz.unwrap_or(defaults.frequency_hz),
+            decay_s: message.decay_s.unwrap_or(defaults.decay_s),
+            ..defaults.clone()
+        };
+        settings.sanitize();
+        let shake = Shake {
+            magnitude_px: settings.magnitude_px,
+            frequency_hz: settings.frequency_hz,
+            decay_s: settings.decay_s,
+            started: clock.elapsed_seconds(),
+        };
+        info!(?shake, "Shaking screen");
+        shakes.start(shake);
+        ack_sender.send(message.reply_to.as_deref(), CommandAck::applied(shake));
+    }
+}
+
+fn process_collisions(
+    mut messages: EventReader<CollisionMessage>,
+    mut shakes: ResMut<Shakes>,
+    (defaults, do_not_disturb): (Res<ScreenShake>, Res<DoNotDisturb>),
+    clock: Res<FaceClock>,
+) {
+    for message in messages.read() {
+        if !defaults.on_collision || do_not_disturb.active() || !message.intensity.is_finite() {
+            continue;
+        }
+        debug!(intensity = message.intensity, "Collision");
+        shakes.start(Shake {
+            magnitude_px: defaults.magnitude_px * message.intensity.clamp(0.0, 1.0),
+            frequency_hz: defaults.frequency_hz,
+            decay_s: defaults.decay_s,
+            started: clock.elapsed_seconds(),
+        });
+    }
+}
+
+/// Move the cameras by the change in offset, so the shake adds onto where they are
+fn shake_cameras(
+    mut shakes: ResMut<Shakes>,
+    mut cameras: Query<&mut Transform, With<Camera2d>>,
+    mut applied_offset: Local<Vec2>,
+    clock: Res<FaceClock>,
+) {
+    let now = clock.elapsed_seconds();
+    if shakes.0.is_empty() && *applied_offset == Vec2::ZERO {
+        return;
+    }
+    shakes
+        .0
+        .retain(|shake| shake.amplitude(now - shake.started) >= MIN_SHAKE_PX);
+    let offset: Vec2 = shakes
+        .0
+        .iter()
+        .map(|shake| shake.offset(now - shake.started))
+        .sum();
+    let moved = offset - *applied_offset;
+    *applied_offset = offset;
+    for mut transform in cameras.iter_mut() {
+        transform.translation += moved.extend(0.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shake_dies_down() {
+        let shake = Shake {
+            magnitude_px: 20.0,
+            frequency_hz: 25.0,
+            decay_s: 0.15,
+            started: 0.0,
+        };
+        let peak = (0..20)
+            .map(|step| shake.offset(step as f32 * 0.005).length())
+            .fold(0.0, f32::max);
+        assert!(peak > 10.0 && peak <= 20.0 * std::f32::consts::SQRT_2);
+        assert!(shake.amplitude(1.0) < MIN_SHAKE_PX);
+    }
+}
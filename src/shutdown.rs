@@ -0,0 +1,338 @@
+use std::{
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+    time::Duration,
+};
+
+use bevy::{app::AppExit, ecs::system::SystemParam, prelude::*};
+
+use crate::{
+    display::{DisplayControl, DisplayPower, DisplayState},
+    face_clock::FaceClock,
+    messaging::{AckSender, CommandAck},
+    rotation::FaceRotation,
+    topics::RegisterTopic,
+};
+
+/// Orderly exit requested over `face/command` or with SIGTERM or SIGINT
+///
+/// Plays a power down animation, restores the configured display power and transform,
+/// publishes `face/offline` and exits with the requested code so systemd can decide whether to restart.
+/// A second signal exits right away
+pub struct ShutdownPlugin;
+
+impl Plugin for ShutdownPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_topic::<FaceCommandMessage>("face/command")
+            .init_resource::<RequestedExitCode>()
+            .init_resource::<ShutdownSignal>()
+            .add_systems(Startup, listen_for_signals)
+            .add_systems(
+                Update,
+                (
+                    (process_face_commands, process_shutdown_signal),
+                    power_down.run_if(resource_exists::<ShuttingDown>),
+                )
+                    .chain(),
+            );
+    }
+}
+
+const POWER_DOWN_DURATION: Duration = Duration::from_secs(1);
+/// Part of the power down spent fading to black, the rest collapses the line left behind
+const POWER_DOWN_FADE: f32 = 0.5;
+const POWER_DOWN_LINE_HEIGHT: f32 = 3.0;
+/// Draw the power down in front of everything else
+const POWER_DOWN_Z: f32 = 100.0;
+/// EX_TEMPFAIL, restart with `RestartForceExitStatus=75`
+const RESTART_EXIT_CODE: u8 = 75;
+/// Exit code of shells for processes ended with SIGINT
+const INTERRUPTED_EXIT_CODE: u8 = 130;
+
+#[derive(serde::Deserialize, serde::Serialize, clap::ValueEnum, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FaceCommand {
+    /// Exit with code 0
+    Shutdown,
+    /// Exit with code 75
+    Restart,
+}
+
+impl FaceCommand {
+    fn exit_code(self) -> u8 {
+        match self {
+            FaceCommand::Shutdown => 0,
+            FaceCommand::Restart => RESTART_EXIT_CODE,
+        }
+    }
+}
+
+#[derive(Event, serde::Deserialize, serde::Serialize, clap::Args, Debug)]
+pub struct FaceCommandMessage {
+    #[arg(value_enum)]
+    pub command: FaceCommand,
+    /// Overrides the exit code of the command
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub exit_code: Option<u8>,
+    /// Key to publish an acknowledgement to once applied
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub reply_to: Option<String>,
+}
+
+/// Exit code of the process once the app exits
+///
+/// Shared with `main` which exits with it after the app stopped running
+#[derive(Resource, Clone, Default)]
+pub struct RequestedExitCode(Arc<AtomicU8>);
+
+impl RequestedExitCode {
+    pub fn get(&self) -> u8 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    fn set(&self, exit_code: u8) {
+        self.0.store(exit_code, Ordering::Relaxed);
+    }
+}
+
+/// Exit code requested by a signal, set from the signal thread
+#[derive(Resource, Clone, Default)]
+struct ShutdownSignal(Arc<Mutex<Option<u8>>>);
+
+impl ShutdownSignal {
+    fn set(&self, exit_code: u8) {
+        *self.0.lock().unwrap_or_else(PoisonError::into_inner) = Some(exit_code);
+    }
+
+    fn take(&self) -> Option<u8> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner).take()
+    }
+}
+
+#[derive(Resource)]
+struct ShuttingDown {
+    exit_code: u8,
+    timer: Timer,
+    overlay: Entity,
+    line: Entity,
+}
+
+/// Wait for SIGTERM and SIGINT on a thread of their own
+fn listen_for_signals(signal: Res<ShutdownSignal>) {
+    let signal = signal.clone();
+    std::thread::spawn(move || {
+        let runtime = match tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+        {
+            Ok(runtime) => runtime,
+            Err(error) => {
+                error!(?error, "Failed to build signal runtime");
+                return;
+            }
+        };
+        runtime.block_on(async {
+            let mut received = false;
+            loop {
+                let exit_code = match wait_for_signal().await {
+                    Ok(exit_code) => exit_code,
+                    Err(error) => {
+                        error!(?error, "Failed to listen for signals");
+                        return;
+                    }
+                };
+                if received {
+                    warn!("Received a second signal, exiting right away");
+                    std::process::exit(exit_code.into());
+                }
+                received = true;
+                signal.set(exit_code);
+            }
+        });
+    });
+}
+
+/// Exit code for the next termination signal
+#[cfg(unix)]
+async fn wait_for_signal() -> std::io::Result<u8> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut terminate = signal(SignalKind::terminate())?;
+    let mut interrupt = signal(SignalKind::interrupt())?;
+    tokio::select! {
+        _ = terminate.recv() => Ok(0),
+        _ = interrupt.recv() => Ok(INTERRUPTED_EXIT_CODE),
+    }
+}
+
+/// Exit code for the next termination signal
+#[cfg(not(unix))]
+async fn wait_for_signal() -> std::io::Result<u8> {
+    tokio::signal::ctrl_c().await?;
+    Ok(INTERRUPTED_EXIT_CODE)
+}
+
+/// What's needed to start shutting down
+#[derive(SystemParam)]
+struct Shutdown<'w, 's> {
+    commands: Commands<'w, 's>,
+    shutting_down: Option<Res<'w, ShuttingDown>>,
+    ack_sender: Res<'w, AckSender>,
+    rotation: Res<'w, FaceRotation>,
+    cameras: Query<'w, 's, &'static OrthographicProjection>,
+}
+
+impl Shutdown<'_, '_> {
+    fn is_shutting_down(&self) -> bool {
+        self.shutting_down.is_some()
+    }
+
+    /// Start the power down, `reason` is published on `face/offline`
+    fn start(&mut self, reason: impl serde::Serialize, exit_code: u8) {
+        let offline = serde_json::json!({
+            "reason": reason,
+            "exit_code": exit_code,
+        });
+        // published right away so the worker has the animation to deliver it
+        self.ack_sender.publish("face/offline", &offline);
+
+        let width = self
+            .cameras
+            .iter()
+            .next()
+            .map_or(480.0, |camera| self.rotation.area(camera.area).width());
+        let overlay = self
+            .commands
+            .spawn(SpriteBundle {
+                sprite: Sprite {
+                    color: Color::BLACK.with_a(0.0),
+                    custom_size: Some(Vec2::splat(100_000.0)),
+                    ..default()
+                },
+                transform: Transform::from_xyz(0.0, 0.0, POWER_DOWN_Z),
+                ..default()
+            })
+            .id();
+        let line = self
+            .commands
+            .spawn(SpriteBundle {
+                sprite: Sprite {
+                    color: Color::WHITE,
+                    custom_size: Some(Vec2::new(width, POWER_DOWN_LINE_HEIGHT)),
+                    ..default()
+                },
+                transform: Transform::from_xyz(0.0, 0.0, POWER_DOWN_Z + 1.0),
+                visibility: Visibility::Hidden,
+                ..default()
+            })
+            .id();
+        self.commands.insert_resource(ShuttingDown {
+            exit_code,
+            timer: Timer::new(POWER_DOWN_DURATION, TimerMode::Once),
+            overlay,
+            line,
+        });
+    }
+}
+
+fn process_face_commands(mut messages: EventReader<FaceCommandMessage>, mut shutdown: Shutdown) {
+    let mut shutting_down = shutdown.is_shutting_down();
+    for (message, id) in messages.read_with_id() {
+        let _trace = shutdown.ack_sender.trace(id);
+        let reply_to = message.reply_to.as_deref();
+        if shutting_down {
+            shutdown
+                .ack_sender
+                .send(reply_to, CommandAck::rejected("Already shutting down"));
+            continue;
+        }
+        shutting_down = true;
+
+        let exit_code = message
+            .exit_code
+            .unwrap_or_else(|| message.command.exit_code());
+        info!(command = ?message.command, exit_code, "Shutting down");
+        let goodbye = serde_json::json!({
+            "command": message.command,
+            "exit_code": exit_code,
+        });
+        shutdown.ack_sender.publish("face/event/goodbye", &goodbye);
+        shutdown
+            .ack_sender
+            .send(reply_to, CommandAck::applied(&goodbye));
+        shutdown.start(message.command, exit_code);
+    }
+}
+
+fn process_shutdown_signal(signal: Res<ShutdownSignal>, mut shutdown: Shutdown) {
+    let Some(exit_code) = signal.take() else {
+        return;
+    };
+    if shutdown.is_shutting_down() {
+        return;
+    }
+    info!(exit_code, "Shutting down after signal");
+    shutdown.start("signal", exit_code);
+}
+
+/// Fade to black, collapse the remaining line like an old tube and exit
+fn power_down(
+    mut shutting_down: ResMut<ShuttingDown>,
+    mut sprites: Query<(&mut Sprite, &mut Transform, &mut Visibility)>,
+    clock: Res<FaceClock>,
+    (display_power, display_state, display_control): (
+        Res<DisplayPower>,
+        Res<DisplayState>,
+        Res<DisplayControl>,
+    ),
+    requested_exit_code: Res<RequestedExitCode>,
+    mut app_exit: EventWriter<AppExit>,
+) {
+    let progress = shutting_down.timer.tick(clock.delta()).fraction();
+    let fade = (progress / POWER_DOWN_FADE).min(1.0);
+    if let Ok((mut sprite, _, _)) = sprites.get_mut(shutting_down.overlay) {
+        sprite.color.set_a(fade);
+    }
+    if let Ok((_, mut transform, mut visibility)) = sprites.get_mut(shutting_down.line) {
+        let collapse = ((progress - POWER_DOWN_FADE) / (1.0 - POWER_DOWN_FADE)).clamp(0.0, 1.0);
+        *visibility = if fade >= 1.0 && collapse < 1.0 {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        };
+        transform.scale.x = 1.0 - collapse;
+    }
+    if !shutting_down.timer.just_finished() {
+        return;
+    }
+
+    // a face/display command may have rotated the output away from the configured transform
+    let transform_changed = display_state
+        .transform
+        .as_ref()
+        .is_some_and(|transform| *transform != display_control.transform);
+    if !display_power.on || transform_changed {
+        info!("Restoring display power and transform before exiting");
+        if let Err(error) = restore_display(display_control.clone()) {
+            error!(?error, "Failed to restore display");
+        }
+    }
+    requested_exit_code.set(shutting_down.exit_code);
+    app_exit.send(AppExit);
+}
+
+/// Turn the display on from outside of the zenoh worker's runtime
+fn restore_display(display_control: DisplayControl) -> anyhow::Result<()> {
+    std::thread::spawn(move || {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?
+            .block_on(display_control.turn_on_display())
+    })
+    .join()
+    .map_err(|_| anyhow::anyhow!("Display thread panicked"))?
+}
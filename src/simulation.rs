@@ -0,0 +1,181 @@
+use std::{f32::consts::TAU, ops::Range, time::Duration};
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+use crate::{
+    battery::BatteryMessage, dizzy::ImuMessage, face_clock::FaceClock, greeting::ProximityMessage,
+    noise_plugin::RawWaveSamples, speech::SpeechEvent, velocity::RobotVelocity,
+    wave::envelope_wave,
+};
+
+/// Synthetic sensor data for developing and demoing without a robot
+///
+/// A scripted minute repeats forever: a person walks up and leaves, the robot talks,
+/// drives around, gets shaken once and recharges its battery. The readings are sent
+/// as the same events the zenoh topics are forwarded as
+pub struct SimulationPlugin;
+
+impl Plugin for SimulationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PreUpdate, simulate_sensors);
+    }
+}
+
+/// Length of the scripted loop in seconds
+const SIMULATION_CYCLE: f32 = 60.0;
+/// Sensors other than the audio envelope report at this rate
+const SENSOR_INTERVAL: Duration = Duration::from_millis(100);
+const PERSON_ID: &str = "simulated-person";
+/// Seconds of the loop the person is around, walking up, standing and walking away
+const PERSON_VISIT: Range<f32> = 5.0..20.0;
+const PERSON_WALK: f32 = 5.0;
+const PERSON_FAR_M: f32 = 3.0;
+const PERSON_NEAR_M: f32 = 0.8;
+const SPEECH: Range<f32> = 22.0..30.0;
+/// Syllables per second while talking
+const SYLLABLE_RATE: f32 = 4.0;
+const ENVELOPE_SAMPLES: usize = 64;
+const DRIVE: Range<f32> = 35.0..45.0;
+const DRIVE_SPEED: f64 = 0.6;
+const DRIVE_TURN_RATE: f64 = 0.8;
+/// Second of the loop the robot is shaken at
+const SHAKE_AT: f32 = 50.0;
+const SHAKE_ACCELERATION: f32 = 35.0;
+/// Seconds of the loop the robot spends docked, recharging what it used up until then
+const CHARGE: Range<f32> = 52.0..SIMULATION_CYCLE;
+const BATTERY_FULL: f32 = 90.0;
+const BATTERY_LOW: f32 = 70.0;
+
+#[derive(Default)]
+struct SimulationState {
+    since_sensors: Duration,
+    speaking: bool,
+    /// Position in the loop last frame
+    previous: f32,
+}
+
+/// Distance of the simulated person at `t` seconds into the loop, none while away
+fn person_distance(t: f32) -> Option<f32> {
+    if !PERSON_VISIT.contains(&t) {
+        return None;
+    }
+    let arriving = ((t - PERSON_VISIT.start) / PERSON_WALK).min(1.0);
+    let leaving = ((PERSON_VISIT.end - t) / PERSON_WALK).min(1.0);
+    let closeness = arriving.min(leaving);
+    Some(PERSON_FAR_M + (PERSON_NEAR_M - PERSON_FAR_M) * closeness)
+}
+
+/// Loudness between 0.0 and 1.0 of the simulated voice, pulsing with syllables
+fn speech_envelope(t: f32) -> f32 {
+    if !SPEECH.contains(&t) {
+        return 0.0;
+    }
+    let syllable = (t * SYLLABLE_RATE * TAU).sin().max(0.0);
+    let phrase = 0.6 + 0.4 * (t * 0.7 * TAU).sin();
+    syllable * phrase
+}
+
+/// Battery charge in percent at `t` seconds into the loop, draining until docked
+fn battery_percent(t: f32) -> f32 {
+    let used = if t < CHARGE.start {
+        t / CHARGE.start
+    } else {
+        (CHARGE.end - t) / (CHARGE.end - CHARGE.start)
+    };
+    BATTERY_FULL + (BATTERY_LOW - BATTERY_FULL) * used
+}
+
+/// Event writers for every simulated sensor
+#[derive(SystemParam)]
+struct SensorWriters<'w> {
+    proximity: EventWriter<'w, ProximityMessage>,
+    velocity: EventWriter<'w, RobotVelocity>,
+    imu: EventWriter<'w, ImuMessage>,
+    raw_wave: EventWriter<'w, RawWaveSamples>,
+    speech: EventWriter<'w, SpeechEvent>,
+    battery: EventWriter<'w, BatteryMessage>,
+}
+
+fn simulate_sensors(
+    clock: Res<FaceClock>,
+    mut state: Local<SimulationState>,
+    mut sensors: SensorWriters,
+) {
+    let t = clock.elapsed_seconds() % SIMULATION_CYCLE;
+    let previous = std::mem::replace(&mut state.previous, t);
+
+    let speaking = SPEECH.contains(&t);
+    if speaking != state.speaking {
+        state.speaking = speaking;
+        sensors.speech.send(SpeechEvent { speaking });
+    }
+    if speaking {
+        sensors.raw_wave.send(RawWaveSamples(envelope_wave(
+            t,
+            speech_envelope(t),
+            ENVELOPE_SAMPLES,
+        )));
+    }
+    if previous < SHAKE_AT && t >= SHAKE_AT {
+        sensors.imu.send(ImuMessage {
+            linear_acceleration: [SHAKE_ACCELERATION, 0.0, 9.81],
+        });
+    }
+
+    state.since_sensors += clock.delta();
+    if state.since_sensors < SENSOR_INTERVAL {
+        return;
+    }
+    state.since_sensors = Duration::ZERO;
+    sensors.battery.send(BatteryMessage {
+        percent: battery_percent(t),
+        charging: CHARGE.contains(&t),
+    });
+    if let Some(distance_m) = person_distance(t) {
+        sensors.proximity.send(ProximityMessage {
+            person_id: Some(PERSON_ID.to_owned()),
+            distance_m,
+            bearing_rad: 0.4 * (t * 0.5).sin(),
+        });
+    }
+    if DRIVE.contains(&t) {
+        let progress = f64::from((t - DRIVE.start) / (DRIVE.end - DRIVE.start));
+        let ramp = (progress * std::f64::consts::PI).sin();
+        sensors.velocity.send(RobotVelocity {
+            linear: DRIVE_SPEED * ramp,
+            angular: DRIVE_TURN_RATE * ramp * (progress * std::f64::consts::TAU).sin(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn person_walks_up_and_leaves() {
+        assert_eq!(person_distance(0.0), None);
+        assert_eq!(person_distance(PERSON_VISIT.start), Some(PERSON_FAR_M));
+        assert!((person_distance(12.0).unwrap() - PERSON_NEAR_M).abs() < 1e-5);
+        assert!(person_distance(PERSON_VISIT.end - 0.1).unwrap() > 2.5);
+        assert_eq!(person_distance(PERSON_VISIT.end), None);
+    }
+
+    #[test]
+    fn battery_drains_until_docked() {
+        assert_eq!(battery_percent(0.0), BATTERY_FULL);
+        assert!(battery_percent(30.0) < battery_percent(10.0));
+        assert_eq!(battery_percent(CHARGE.start), BATTERY_LOW);
+        assert!(battery_percent(56.0) > BATTERY_LOW);
+        assert!((battery_percent(SIMULATION_CYCLE - 0.01) - BATTERY_FULL).abs() < 0.1);
+    }
+
+    #[test]
+    fn voice_is_only_heard_while_speaking() {
+        assert_eq!(speech_envelope(SPEECH.start - 1.0), 0.0);
+        let loudest = (0..80)
+            .map(|step| speech_envelope(SPEECH.start + step as f32 * 0.1))
+            .fold(0.0, f32::max);
+        assert!(loudest > 0.5 && loudest <= 1.0);
+    }
+}
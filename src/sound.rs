@@ -0,0 +1,267 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use bevy::{
+    audio::{Decodable, Source},
+    prelude::*,
+};
+
+use crate::{
+    dnd::DoNotDisturb,
+    face_clock::FaceClock,
+    messaging::{AckSender, CommandAck},
+    noise_plugin::RawWaveSamples,
+    scene::FaceScene,
+    wave::envelope_wave,
+};
+
+pub struct SoundPlugin;
+
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SoundHooks::default())
+            .init_resource::<SoundEnvelopes>()
+            .init_resource::<DoNotDisturb>()
+            .add_event::<PlaySound>()
+            .add_event::<SoundSettingsUpdate>()
+            .add_systems(
+                Update,
+                (
+                    process_sound_settings_update_messages,
+                    trigger_scene_sounds,
+                    play_sounds,
+                )
+                    .chain(),
+            )
+            .add_systems(Update, (track_sound_envelopes, lip_sync_sounds).chain());
+    }
+}
+
+/// Sounds played when entering or leaving a preset
+#[derive(Clone, Default, Debug, serde::Deserialize, serde::Serialize)]
+pub struct SoundHook {
+    /// Asset path of the sound played on entry
+    #[serde(default)]
+    pub on_enter: Option<String>,
+    /// Asset path of the sound played on exit
+    #[serde(default)]
+    pub on_exit: Option<String>,
+}
+
+#[derive(Resource, serde::Serialize)]
+pub struct SoundHooks {
+    /// Global switch for all sound hooks
+    pub enabled: bool,
+    pub scenes: HashMap<FaceScene, SoundHook>,
+}
+
+impl Default for SoundHooks {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            scenes: HashMap::new(),
+        }
+    }
+}
+
+/// Request to play a sound asset
+///
+/// Ignored while sound hooks are disabled or do-not-disturb is on
+#[derive(Event)]
+pub struct PlaySound(pub String);
+
+#[derive(Event, serde::Deserialize)]
+pub struct SoundSettingsUpdate {
+    #[serde(default)]
+    enabled: Option<bool>,
+    /// Replaces hooks of the listed scenes
+    #[serde(default)]
+    scenes: Option<HashMap<FaceScene, SoundHook>>,
+    /// Key to publish an acknowledgement to once applied
+    #[serde(default)]
+    reply_to: Option<String>,
+}
+
+fn process_sound_settings_update_messages(
+    mut messages: EventReader<SoundSettingsUpdate>,
+    mut sound_hooks: ResMut<SoundHooks>,
+    ack_sender: Res<AckSender>,
+) {
+    for (message, id) in messages.read_with_id() {
+        let _trace = ack_sender.trace(id);
+        if let Some(enabled) = message.enabled {
+            info!(enabled, "Updating sound hooks enabled");
+            sound_hooks.enabled = enabled;
+        }
+        if let Some(scenes) = &message.scenes {
+            for (scene, hook) in scenes {
+                info!(?scene, ?hook, "Updating scene sound hook");
+                sound_hooks.scenes.insert(*scene, hook.clone());
+            }
+        }
+        ack_sender.send(
+            message.reply_to.as_deref(),
+            CommandAck::applied(&*sound_hooks),
+        );
+    }
+}
+
+fn trigger_scene_sounds(
+    mut transitions: EventReader<StateTransitionEvent<FaceScene>>,
+    sound_hooks: Res<SoundHooks>,
+    mut play_sound: EventWriter<PlaySound>,
+) {
+    for transition in transitions.read() {
+        let on_exit = sound_hooks
+            .scenes
+            .get(&transition.before)
+            .and_then(|hook| hook.on_exit.clone());
+        let on_enter = sound_hooks
+            .scenes
+            .get(&transition.after)
+            .and_then(|hook| hook.on_enter.clone());
+        for sound in on_exit.into_iter().chain(on_enter) {
+            play_sound.send(PlaySound(sound));
+        }
+    }
+}
+
+fn play_sounds(
+    mut commands: Commands,
+    mut requests: EventReader<PlaySound>,
+    sound_hooks: Res<SoundHooks>,
+    asset_server: Res<AssetServer>,
+    do_not_disturb: Res<DoNotDisturb>,
+) {
+    for PlaySound(path) in requests.read() {
+        if !sound_hooks.enabled {
+            continue;
+        }
+        if do_not_disturb.active() {
+            debug!(path, "Not playing sound, do-not-disturb is on");
+            continue;
+        }
+        info!(path, "Playing sound");
+        commands.spawn(AudioBundle {
+            source: asset_server.load(path.clone()),
+            settings: PlaybackSettings::DESPAWN,
+        });
+    }
+}
+
+/// Loudness of played sounds is measured over windows this long
+const ENVELOPE_WINDOW: Duration = Duration::from_millis(20);
+const ENVELOPE_WAVE_SAMPLES: usize = 64;
+
+/// Loudness envelopes of sound assets played so far, decoded once per asset
+#[derive(Resource, Default)]
+struct SoundEnvelopes(HashMap<AssetId<AudioSource>, Arc<[f32]>>);
+
+/// Loudness of a playing sound, moves the mouth while it plays
+#[derive(Component)]
+struct PlayingEnvelope {
+    started: Duration,
+    envelope: Arc<[f32]>,
+}
+
+/// Loudness between 0.0 and 1.0 for every `ENVELOPE_WINDOW` of interleaved samples
+///
+/// Normalized to the loudest window so quiet sounds move the mouth as much as loud ones
+fn sound_envelope(samples: impl Iterator<Item = i16>, channels: u16, sample_rate: u32) -> Vec<f32> {
+    let window_length =
+        ((sample_rate as f32 * ENVELOPE_WINDOW.as_secs_f32()) as usize * channels as usize).max(1);
+    let mut envelope = vec![];
+    let mut sum_of_squares = 0.0;
+    let mut count = 0;
+    for sample in samples {
+        let sample = f32::from(sample) / f32::from(i16::MAX);
+        sum_of_squares += sample * sample;
+        count += 1;
+        if count == window_length {
+            envelope.push((sum_of_squares / count as f32).sqrt());
+            sum_of_squares = 0.0;
+            count = 0;
+        }
+    }
+    if count > 0 {
+        envelope.push((sum_of_squares / count as f32).sqrt());
+    }
+    let loudest = envelope.iter().copied().fold(0.0, f32::max);
+    if loudest > 0.0 {
+        envelope.iter_mut().for_each(|value| *value /= loudest);
+    }
+    envelope
+}
+
+/// Measure sounds once they start playing, bevy adds the sink once the asset loaded
+fn track_sound_envelopes(
+    mut commands: Commands,
+    started: Query<(Entity, &Handle<AudioSource>), Added<AudioSink>>,
+    sources: Res<Assets<AudioSource>>,
+    mut envelopes: ResMut<SoundEnvelopes>,
+    clock: Res<FaceClock>,
+) {
+    for (entity, handle) in started.iter() {
+        let envelope = match envelopes.0.get(&handle.id()) {
+            Some(envelope) => envelope.clone(),
+            None => {
+                let Some(source) = sources.get(handle) else {
+                    continue;
+                };
+                let decoder = source.decoder();
+                let (channels, sample_rate) = (decoder.channels(), decoder.sample_rate());
+                let envelope: Arc<[f32]> = sound_envelope(decoder, channels, sample_rate).into();
+                debug!(windows = envelope.len(), "Measured sound envelope");
+                envelopes.0.insert(handle.id(), envelope.clone());
+                envelope
+            }
+        };
+        commands.entity(entity).insert(PlayingEnvelope {
+            started: clock.elapsed(),
+            envelope,
+        });
+    }
+}
+
+/// Swing the wave with the loudest sound playing
+fn lip_sync_sounds(
+    playing: Query<(&PlayingEnvelope, &AudioSink)>,
+    clock: Res<FaceClock>,
+    mut raw_wave: EventWriter<RawWaveSamples>,
+) {
+    let mut loudness = None;
+    for (playing, sink) in playing.iter() {
+        if sink.is_paused() {
+            continue;
+        }
+        let window = ((clock.elapsed() - playing.started).as_secs_f32()
+            / ENVELOPE_WINDOW.as_secs_f32()) as usize;
+        if let Some(value) = playing.envelope.get(window) {
+            loudness = Some(loudness.unwrap_or(0.0_f32).max(*value));
+        }
+    }
+    if let Some(loudness) = loudness {
+        raw_wave.send(RawWaveSamples(envelope_wave(
+            clock.elapsed_seconds_wrapped(),
+            loudness,
+            ENVELOPE_WAVE_SAMPLES,
+        )));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn envelope_follows_loudness() {
+        // 20ms of silence, 20ms loud and a quiet 10ms of stereo at 1kHz
+        let samples = [vec![0; 40], vec![16000; 40], vec![-4000; 20]].concat();
+        let envelope = sound_envelope(samples.into_iter(), 2, 1000);
+        assert_eq!(envelope.len(), 3);
+        assert_eq!(envelope[0], 0.0);
+        assert_eq!(envelope[1], 1.0);
+        assert!((envelope[2] - 0.25).abs() < 1e-5);
+
+        assert_eq!(sound_envelope(vec![0; 10].into_iter(), 1, 1000), vec![0.0]);
+    }
+}
@@ -0,0 +1,360 @@
+use std::time::Duration;
+
+use bevy::{prelude::*, text::Text2dBounds};
+use bevy_prototype_lyon::prelude::*;
+
+use crate::{
+    dnd::DoNotDisturb,
+    face_clock::FaceClock,
+    messaging::{parse_json_payload, AckSender, CommandAck},
+    mouth::{VisemeSequence, VisemeTiming, MAX_VISEMES},
+    rotation::FaceRotation,
+    safe_area::SafeArea,
+    scene::FaceScene,
+    topics::RegisterTopic,
+};
+
+/// Subtitles of what the robot says, shown in a speech bubble above the wave
+///
+/// `face/say` sets the text and `face/speech` reports when the voice starts and stops,
+/// the bubble stays up while speaking and disappears shortly after.
+/// Viseme timings on `face/speech` are passed on to the mouth
+pub struct SpeechBubblePlugin;
+
+impl Plugin for SpeechBubblePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_topic::<SayMessage>("face/say")
+            .add_topic_with::<SpeechMessage>("face/speech", None, |payload, _| {
+                parse_speech_message(payload)
+            })
+            .add_event::<SpeechEvent>()
+            .add_event::<VisemeSequence>()
+            .init_resource::<Subtitle>()
+            .init_resource::<DoNotDisturb>()
+            .add_systems(Startup, spawn_speech_bubble)
+            .add_systems(
+                Update,
+                (
+                    process_say_messages,
+                    split_speech_messages,
+                    process_speech_events,
+                    update_speech_bubble,
+                    position_speech_bubble,
+                    fit_speech_bubble,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Keep the bubble up this long after the voice stopped
+const SPEECH_LINGER: Duration = Duration::from_millis(1500);
+/// Reading time per word when no speech events arrive
+const READING_TIME_PER_WORD: Duration = Duration::from_millis(400);
+const MIN_READING_TIME: Duration = Duration::from_secs(2);
+/// Longest time a bubble is shown for
+const MAX_BUBBLE_DURATION: Duration = Duration::from_secs(60 * 60);
+/// Texts longer than this are cut off
+const MAX_TEXT_LENGTH: usize = 280;
+const BUBBLE_FONT_SIZE: f32 = 36.0;
+const BUBBLE_PADDING: f32 = 20.0;
+const BUBBLE_CORNER_RADIUS: f32 = 24.0;
+const BUBBLE_MARGIN: f32 = 20.0;
+/// Share of the safe area width the bubble may take up
+const BUBBLE_MAX_WIDTH: f32 = 0.8;
+const BUBBLE_TAIL_WIDTH: f32 = 30.0;
+const BUBBLE_TAIL_HEIGHT: f32 = 30.0;
+const BUBBLE_LINE_WIDTH: f32 = 4.0;
+const BUBBLE_FILL_COLOR: Color = Color::WHITE;
+const BUBBLE_TEXT_COLOR: Color = Color::BLACK;
+const BUBBLE_OUTLINE_COLOR: Color = Color::GRAY;
+
+/// Text for the speech bubble
+#[derive(Event, serde::Deserialize, serde::Serialize, clap::Args, Debug)]
+pub struct SayMessage {
+    /// Text shown in the bubble, empty hides it
+    pub text: String,
+    /// Hide the bubble after this many seconds unless speech events keep it up,
+    /// defaults to the time it takes to read the text
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub duration_s: Option<f32>,
+    /// Key to publish an acknowledgement to once applied
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub reply_to: Option<String>,
+}
+
+/// Voice output started or stopped
+#[derive(Event, serde::Deserialize, Debug)]
+pub struct SpeechEvent {
+    pub speaking: bool,
+}
+
+/// Payload of `face/speech`, either a voice start or stop or the visemes being spoken
+#[derive(Event, serde::Deserialize, Debug)]
+#[serde(untagged)]
+pub enum SpeechMessage {
+    Event(SpeechEvent),
+    Visemes(Vec<VisemeTiming>),
+}
+
+fn parse_speech_message(payload: &[u8]) -> anyhow::Result<SpeechMessage> {
+    let message = parse_json_payload(payload)?;
+    if let SpeechMessage::Visemes(visemes) = &message {
+        anyhow::ensure!(
+            visemes.len() <= MAX_VISEMES,
+            "{} visemes are more than the {MAX_VISEMES} allowed",
+            visemes.len()
+        );
+    }
+    Ok(message)
+}
+
+#[derive(Resource, Default)]
+struct Subtitle {
+    text: Option<String>,
+    speaking: bool,
+    hide_at: Option<Duration>,
+}
+
+#[derive(Component)]
+struct SpeechBubble;
+
+#[derive(Component)]
+struct SpeechBubbleShape;
+
+#[derive(Component)]
+struct SpeechBubbleText;
+
+fn reading_time(text: &str) -> Duration {
+    let words = text.split_whitespace().count() as u32;
+    (READING_TIME_PER_WORD * words).max(MIN_READING_TIME)
+}
+
+fn spawn_speech_bubble(mut commands: Commands) {
+    commands
+        .spawn((
+            SpatialBundle {
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            SpeechBubble,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                ShapeBundle::default(),
+                Fill::color(BUBBLE_FILL_COLOR),
+                Stroke::new(BUBBLE_OUTLINE_COLOR, BUBBLE_LINE_WIDTH),
+                SpeechBubbleShape,
+            ));
+            parent.spawn((
+                Text2dBundle {
+                    text: Text::from_section(
+                        "",
+                        TextStyle {
+                            font_size: BUBBLE_FONT_SIZE,
+                            color: BUBBLE_TEXT_COLOR,
+                            ..default()
+                        },
+                    )
+                    .with_justify(JustifyText::Center),
+                    transform: Transform::from_xyz(0.0, 0.0, 0.1),
+                    ..default()
+                },
+                SpeechBubbleText,
+            ));
+        });
+}
+
+fn process_say_messages(
+    mut messages: EventReader<SayMessage>,
+    mut subtitle: ResMut<Subtitle>,
+    ack_sender: Res<AckSender>,
+    clock: Res<FaceClock>,
+) {
+    for (message, id) in messages.read_with_id() {
+        let _trace = ack_sender.trace(id);
+        let text: String = message.text.trim().chars().take(MAX_TEXT_LENGTH).collect();
+        if text.is_empty() {
+            info!("Hiding speech bubble");
+            subtitle.text = None;
+            subtitle.hide_at = None;
+        } else {
+            info!(text, "Showing speech bubble");
+            let duration = match message.duration_s {
+                Some(duration_s) if duration_s.is_finite() && duration_s > 0.0 => {
+                    Duration::from_secs_f32(duration_s.min(MAX_BUBBLE_DURATION.as_secs_f32()))
+                }
+                _ => reading_time(&text),
+            };
+            // the stop event takes care of hiding while the voice is still going
+            subtitle.hide_at = (!subtitle.speaking).then(|| clock.elapsed() + duration);
+            subtitle.text = Some(text);
+        }
+        ack_sender.send(
+            message.reply_to.as_deref(),
+            CommandAck::applied(serde_json::json!({ "text": subtitle.text })),
+        );
+    }
+}
+
+fn split_speech_messages(
+    mut messages: EventReader<SpeechMessage>,
+    mut speech: EventWriter<SpeechEvent>,
+    mut visemes: EventWriter<VisemeSequence>,
+) {
+    for message in messages.read() {
+        match message {
+            SpeechMessage::Event(event) => {
+                speech.send(SpeechEvent {
+                    speaking: event.speaking,
+                });
+            }
+            SpeechMessage::Visemes(timings) => {
+                visemes.send(VisemeSequence(timings.clone()));
+            }
+        }
+    }
+}
+
+fn process_speech_events(
+    mut events: EventReader<SpeechEvent>,
+    mut subtitle: ResMut<Subtitle>,
+    clock: Res<FaceClock>,
+) {
+    for event in events.read() {
+        debug!(speaking = event.speaking, "Speech event");
+        subtitle.speaking = event.speaking;
+        subtitle.hide_at = if event.speaking {
+            None
+        } else {
+            Some(clock.elapsed() + SPEECH_LINGER)
+        };
+    }
+}
+
+fn update_speech_bubble(
+    mut subtitle: ResMut<Subtitle>,
+    do_not_disturb: Res<DoNotDisturb>,
+    scene: Res<State<FaceScene>>,
+    clock: Res<FaceClock>,
+    mut bubble: Query<&mut Visibility, With<SpeechBubble>>,
+    mut texts: Query<&mut Text, With<SpeechBubbleText>>,
+) {
+    if subtitle
+        .hide_at
+        .is_some_and(|hide_at| clock.elapsed() >= hide_at)
+    {
+        subtitle.text = None;
+        subtitle.hide_at = None;
+    }
+
+    // test patterns need the whole screen
+    let covered = matches!(scene.get(), FaceScene::Calibration | FaceScene::SelfTest);
+    let visible = subtitle.text.is_some() && !covered && !do_not_disturb.active();
+    for mut visibility in bubble.iter_mut() {
+        let target = if visible {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        if *visibility != target {
+            *visibility = target;
+        }
+    }
+
+    if !subtitle.is_changed() {
+        return;
+    }
+    for mut text in texts.iter_mut() {
+        let value = subtitle.text.as_deref().unwrap_or_default();
+        if text.sections[0].value != value {
+            text.sections[0].value = value.to_owned();
+        }
+    }
+}
+
+/// Keep the bubble at the top of the safe area and wrap the text to its width
+fn position_speech_bubble(
+    mut bubble: Query<&mut Transform, With<SpeechBubble>>,
+    mut bounds: Query<&mut Text2dBounds, With<SpeechBubbleText>>,
+    query_camera: Query<Ref<OrthographicProjection>>,
+    safe_area: Res<SafeArea>,
+    rotation: Res<FaceRotation>,
+) {
+    for camera in query_camera.iter() {
+        if !camera.is_changed() && !safe_area.is_changed() {
+            continue;
+        }
+        let area = safe_area.apply(rotation.area(camera.area));
+        for mut transform in bubble.iter_mut() {
+            transform.translation = Vec3::new(area.center().x, area.max.y - BUBBLE_MARGIN, 2.0);
+        }
+        for mut bounds in bounds.iter_mut() {
+            bounds.size.x = (area.width() * BUBBLE_MAX_WIDTH - BUBBLE_PADDING * 2.0).max(0.0);
+        }
+    }
+}
+
+/// Size the bubble around the laid out text, hanging down from the top of the safe area
+fn fit_speech_bubble(
+    texts: Query<(Ref<bevy::text::TextLayoutInfo>, &Parent), With<SpeechBubbleText>>,
+    mut text_transforms: Query<&mut Transform, With<SpeechBubbleText>>,
+    mut shapes: Query<(&mut Path, &Parent), With<SpeechBubbleShape>>,
+) {
+    for (layout, text_parent) in texts.iter() {
+        if !layout.is_changed() {
+            continue;
+        }
+        let size = layout.logical_size + Vec2::splat(BUBBLE_PADDING * 2.0);
+        for (mut path, shape_parent) in shapes.iter_mut() {
+            if shape_parent.get() == text_parent.get() {
+                *path = build_bubble_path(size);
+            }
+        }
+        for mut transform in text_transforms.iter_mut() {
+            transform.translation.y = -size.y / 2.0;
+        }
+    }
+}
+
+/// Rounded box below the origin with a tail pointing down toward the wave
+fn build_bubble_path(size: Vec2) -> Path {
+    let radius = BUBBLE_CORNER_RADIUS.min(size.x / 2.0).min(size.y / 2.0);
+    let (left, right, top, bottom) = (-size.x / 2.0, size.x / 2.0, 0.0, -size.y);
+    let tail = (BUBBLE_TAIL_WIDTH / 2.0)
+        .min(size.x / 2.0 - radius)
+        .max(0.0);
+
+    let mut builder = PathBuilder::new();
+    builder.move_to(Vec2::new(left + radius, top));
+    builder.line_to(Vec2::new(right - radius, top));
+    builder.quadratic_bezier_to(Vec2::new(right, top), Vec2::new(right, top - radius));
+    builder.line_to(Vec2::new(right, bottom + radius));
+    builder.quadratic_bezier_to(Vec2::new(right, bottom), Vec2::new(right - radius, bottom));
+    builder.line_to(Vec2::new(tail, bottom));
+    builder.line_to(Vec2::new(-tail, bottom - BUBBLE_TAIL_HEIGHT));
+    builder.line_to(Vec2::new(-tail, bottom));
+    builder.line_to(Vec2::new(left + radius, bottom));
+    builder.quadratic_bezier_to(Vec2::new(left, bottom), Vec2::new(left, bottom + radius));
+    builder.line_to(Vec2::new(left, top - radius));
+    builder.quadratic_bezier_to(Vec2::new(left, top), Vec2::new(left + radius, top));
+    builder.close();
+    builder.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reading_time_grows_with_words() {
+        assert_eq!(reading_time(""), MIN_READING_TIME);
+        assert_eq!(reading_time("hi there"), MIN_READING_TIME);
+        assert_eq!(
+            reading_time("the quick brown fox jumps over the lazy dog"),
+            READING_TIME_PER_WORD * 9
+        );
+    }
+}
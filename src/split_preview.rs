@@ -0,0 +1,153 @@
+use bevy::{
+    prelude::*,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+};
+use noise::{BasicMulti, MultiFractal, Perlin};
+
+use crate::{
+    face_clock::FaceClock,
+    noise_plugin::{
+        NoiseGenerator, NoiseGeneratorSettings, NoiseGeneratorSettingsUpdate, NoiseSeed, WaveClip,
+    },
+    rotation::FaceRotation,
+    safe_area::SafeArea,
+    settings_file::SettingsFile,
+    wave::{
+        build_line_mesh, catmull_rom, sample_noise, smooth_samples, wave_points, write_line_mesh,
+    },
+};
+
+/// Compare edited wave settings with the deployed ones side by side in dev mode
+///
+/// B splits the wave, the left half shows the settings persisted to the settings file
+/// that the face comes back to after a restart and the right half the live settings.
+/// Both halves move with the same clock
+pub struct SplitPreviewPlugin;
+
+impl Plugin for SplitPreviewPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WaveClip>()
+            .init_resource::<SettingsFile>()
+            .add_systems(
+                Update,
+                (toggle_split_preview, update_reference_wave).chain(),
+            );
+    }
+}
+
+const TOGGLE_KEY: KeyCode = KeyCode::KeyB;
+/// Settings file section the production wave is read from
+const SETTINGS_FILE_SECTION: &str = "wave";
+
+/// Wave drawn with the production settings on the left half
+#[derive(Component)]
+struct ReferenceWave {
+    settings: NoiseGeneratorSettings,
+    generator: BasicMulti<Perlin>,
+    elapsed_step: f64,
+}
+
+/// Settings the face starts with after a restart
+fn production_settings(settings_file: &SettingsFile) -> NoiseGeneratorSettings {
+    let update = settings_file
+        .load::<NoiseGeneratorSettingsUpdate>(SETTINGS_FILE_SECTION)
+        .unwrap_or_else(|error| {
+            error!(?error, "Failed to load persisted wave settings");
+            None
+        })
+        .unwrap_or_default();
+    NoiseGeneratorSettings::default()
+        .updated(&update)
+        .unwrap_or_else(|error| {
+            error!(?error, "Invalid persisted wave color");
+            NoiseGeneratorSettings::default()
+        })
+}
+
+fn toggle_split_preview(
+    mut commands: Commands,
+    input: Res<ButtonInput<KeyCode>>,
+    references: Query<Entity, With<ReferenceWave>>,
+    mut clip: ResMut<WaveClip>,
+    (settings_file, seed, noise_generator): (
+        Res<SettingsFile>,
+        Res<NoiseSeed>,
+        Option<Res<NoiseGenerator>>,
+    ),
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    if !input.just_pressed(TOGGLE_KEY) {
+        return;
+    }
+    if !references.is_empty() {
+        info!("Closing split preview");
+        for entity in references.iter() {
+            commands.entity(entity).despawn();
+        }
+        clip.0 = None;
+        return;
+    }
+
+    let settings = production_settings(&settings_file);
+    info!(
+        update = ?settings.to_update(),
+        "Comparing the live wave with the persisted one"
+    );
+    let generator = BasicMulti::<Perlin>::new(seed.0).set_octaves(settings.perlin_noise_octaves);
+    commands.spawn((
+        MaterialMesh2dBundle {
+            mesh: meshes.add(build_line_mesh(&[], settings.line_width)).into(),
+            material: materials.add(ColorMaterial::from(settings.color)),
+            ..default()
+        },
+        ReferenceWave {
+            settings,
+            generator,
+            // start in step with the live wave
+            elapsed_step: noise_generator.map_or(0.0, |generator| generator.elapsed_step),
+        },
+    ));
+}
+
+fn update_reference_wave(
+    mut references: Query<(&mut ReferenceWave, &Mesh2dHandle, &mut Transform)>,
+    mut clip: ResMut<WaveClip>,
+    query_camera: Query<&OrthographicProjection>,
+    (safe_area, rotation): (Res<SafeArea>, Res<FaceRotation>),
+    mut meshes: ResMut<Assets<Mesh>>,
+    clock: Res<FaceClock>,
+) {
+    let Some(camera) = query_camera.iter().next() else {
+        return;
+    };
+    let resolution = safe_area.apply(rotation.area(camera.area));
+    let split_x = resolution.center().x;
+    for (mut reference, mesh, mut transform) in references.iter_mut() {
+        let reference = &mut *reference;
+        let settings = &reference.settings;
+        reference.elapsed_step += clock.delta_seconds_f64() / settings.frame_time_divider;
+
+        let width = (resolution.width() / settings.segment_width) as usize;
+        let samples = sample_noise(
+            &reference.generator,
+            reference.elapsed_step,
+            settings.width_divider,
+            width + 2,
+        );
+        let samples = smooth_samples(&samples, settings.smoothing_window);
+        let points = wave_points(
+            samples.into_iter(),
+            resolution.min.x,
+            settings.segment_width,
+            settings.height_multiplier,
+        );
+        let mut points = catmull_rom(&points, settings.curve_subdivisions);
+        points.retain(|point| point.x < split_x);
+        if let Some(mesh) = meshes.get_mut(&mesh.0) {
+            write_line_mesh(mesh, &points, settings.line_width);
+        }
+        transform.translation.y = resolution.center().y;
+        clip.0 = Some(split_x);
+    }
+}
@@ -0,0 +1,102 @@
+use bevy::prelude::*;
+
+/// Stiffness range that stays stable at the simulation step
+pub const STIFFNESS_RANGE: std::ops::RangeInclusive<f32> = 1.0..=2_000.0;
+/// Damping range, 2 * sqrt(stiffness) is critically damped and settles without overshooting
+pub const DAMPING_RANGE: std::ops::RangeInclusive<f32> = 0.0..=200.0;
+/// Longest simulation step, longer frames are split up
+const MAX_STEP_S: f32 = 1.0 / 240.0;
+/// Springs closer and slower than these have come to rest
+const SETTLED_DISTANCE: f32 = 1e-3;
+const SETTLED_SPEED: f32 = 1e-2;
+/// Frames after a long hitch are simulated in at most this many steps
+const MAX_STEPS: u32 = 64;
+
+/// Spring-damper pulling a point toward a target
+///
+/// Unlike a tween with a fixed duration a spring keeps its velocity when the target moves,
+/// so continuously changing targets are followed without restarts
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Spring {
+    pub position: Vec2,
+    pub velocity: Vec2,
+}
+
+impl Spring {
+    pub fn new(position: Vec2) -> Self {
+        Self {
+            position,
+            velocity: Vec2::ZERO,
+        }
+    }
+
+    /// Advance by `delta` seconds toward `target`, returns the new position
+    pub fn step(&mut self, target: Vec2, stiffness: f32, damping: f32, delta: f32) -> Vec2 {
+        if !delta.is_finite() || delta <= 0.0 {
+            return self.position;
+        }
+        let steps = ((delta / MAX_STEP_S).ceil() as u32).clamp(1, MAX_STEPS);
+        let step = delta / steps as f32;
+        for _ in 0..steps {
+            let acceleration = (target - self.position) * stiffness - self.velocity * damping;
+            // semi-implicit euler stays stable where the explicit one blows up
+            self.velocity += acceleration * step;
+            self.position += self.velocity * step;
+        }
+        self.position
+    }
+
+    /// Whether the spring came to rest at `target`
+    pub fn settled(&self, target: Vec2) -> bool {
+        self.position.distance(target) < SETTLED_DISTANCE && self.velocity.length() < SETTLED_SPEED
+    }
+}
+
+/// Clamp spring constants into their ranges, invalid values fall back to `defaults`
+pub fn sanitize_spring(stiffness: &mut f32, damping: &mut f32, defaults: (f32, f32)) {
+    let sanitize = |value: f32, range: std::ops::RangeInclusive<f32>, default: f32| {
+        if value.is_finite() {
+            value.clamp(*range.start(), *range.end())
+        } else {
+            default
+        }
+    };
+    *stiffness = sanitize(*stiffness, STIFFNESS_RANGE, defaults.0);
+    *damping = sanitize(*damping, DAMPING_RANGE, defaults.1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settle(stiffness: f32, damping: f32) -> (f32, Vec2) {
+        let mut spring = Spring::new(Vec2::ZERO);
+        let mut furthest = 0.0f32;
+        for _ in 0..300 {
+            spring.step(Vec2::X, stiffness, damping, 1.0 / 60.0);
+            furthest = furthest.max(spring.position.x);
+        }
+        (furthest, spring.position)
+    }
+
+    #[test]
+    fn spring_settles_on_target() {
+        let (furthest, position) = settle(150.0, 20.0);
+        assert!(furthest > 1.0, "underdamped springs overshoot");
+        assert!(position.distance(Vec2::X) < 1e-3);
+
+        let (furthest, position) = settle(100.0, 20.0);
+        assert!(
+            furthest <= 1.0 + 1e-4,
+            "critically damped springs don't overshoot"
+        );
+        assert!(position.distance(Vec2::X) < 1e-3);
+    }
+
+    #[test]
+    fn long_frames_stay_stable() {
+        let mut spring = Spring::new(Vec2::ZERO);
+        spring.step(Vec2::ONE, *STIFFNESS_RANGE.end(), 0.0, 0.5);
+        assert!(spring.position.length() < 10.0);
+    }
+}
@@ -0,0 +1,92 @@
+use bevy::prelude::*;
+
+use crate::{
+    mood::MoodSchedule,
+    noise_plugin::load_persisted_settings,
+    priority::Priority,
+    scene::{FaceScene, SceneRequests},
+    settings_file::SettingsFile,
+};
+
+/// Look the face boots into
+///
+/// Read from the `startup` section of the settings file, `--startup-scene` overrides the scene.
+/// The wave itself comes from the persisted `wave` section
+pub struct StartupPlugin {
+    pub scene: Option<FaceScene>,
+}
+
+impl Plugin for StartupPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(StartupOverride { scene: self.scene })
+            .init_resource::<SettingsFile>()
+            .init_resource::<StartupScene>()
+            // an explicit `mood` wins over the persisted wave switching the schedule off
+            .add_systems(
+                Startup,
+                apply_startup_settings.after(load_persisted_settings),
+            );
+    }
+}
+
+const SETTINGS_FILE_SECTION: &str = "startup";
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Default)]
+pub struct StartupSettings {
+    /// Idle scene shown from boot until something else is requested
+    #[serde(default)]
+    pub scene: Option<FaceScene>,
+    /// Set false to keep the persisted wave look instead of following the mood schedule
+    #[serde(default)]
+    pub mood: Option<bool>,
+}
+
+/// Idle scene the face booted into, restored once a mood period's own scene ends
+///
+/// Plugins replacing the idle scene init this resource themselves
+/// so they keep working without the startup plugin
+#[derive(Resource, Default, Debug, Clone, Copy)]
+pub struct StartupScene(pub Option<FaceScene>);
+
+impl StartupScene {
+    /// Show the startup scene on the idle level, or nothing if there is none
+    pub fn restore(&self, scene_requests: &mut SceneRequests) {
+        match self.0 {
+            Some(scene) => scene_requests.push(Priority::Idle, scene, None),
+            None => scene_requests.clear(Priority::Idle),
+        }
+    }
+}
+
+#[derive(Resource)]
+struct StartupOverride {
+    scene: Option<FaceScene>,
+}
+
+fn apply_startup_settings(
+    settings_file: Res<SettingsFile>,
+    startup_override: Res<StartupOverride>,
+    mut scene_requests: ResMut<SceneRequests>,
+    mut startup_scene: ResMut<StartupScene>,
+    mut mood_schedule: Option<ResMut<MoodSchedule>>,
+) {
+    let mut settings = match settings_file.load::<StartupSettings>(SETTINGS_FILE_SECTION) {
+        Ok(settings) => settings.unwrap_or_default(),
+        Err(error) => {
+            error!(?error, "Failed to load startup settings");
+            StartupSettings::default()
+        }
+    };
+    if startup_override.scene.is_some() {
+        settings.scene = startup_override.scene;
+    }
+    info!(?settings, "Applying startup settings");
+
+    *startup_scene = StartupScene(settings.scene);
+    if let Some(scene) = settings.scene {
+        scene_requests.push(Priority::Idle, scene, None);
+    }
+    if let (Some(mood), Some(schedule)) = (settings.mood, mood_schedule.as_deref_mut()) {
+        schedule.enabled = mood;
+    }
+}
@@ -0,0 +1,303 @@
+use std::ops::RangeInclusive;
+
+use bevy::prelude::*;
+
+use crate::{
+    event_log::EventLog,
+    face_clock::FaceClock,
+    messaging::{AckSender, CommandAck},
+    params::{RegisterTunable, Tunable},
+    scene::FaceScene,
+    topics::RegisterTopic,
+};
+
+/// Slowly changing mood built up from how the robot was treated recently
+///
+/// Taps on the screen, shaking and errors make the face annoyed while greetings
+/// make it content. Both decay over time and bias the idle wave, the response is
+/// adjustable as the `temperament` tunable. Other nodes report interactions on `face/interaction`
+pub struct TemperamentPlugin;
+
+impl Plugin for TemperamentPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TemperamentResponse>()
+            .init_resource::<Temperament>()
+            .register_tunable::<TemperamentResponse>("temperament")
+            .add_topic::<InteractionMessage>("face/interaction")
+            .add_systems(
+                OnEnter(FaceScene::Greeting),
+                |mut events: EventWriter<InteractionMessage>| {
+                    events.send(Interaction::Greeting.into());
+                },
+            )
+            .add_systems(
+                OnEnter(FaceScene::Dizzy),
+                |mut events: EventWriter<InteractionMessage>| {
+                    events.send(Interaction::Shake.into());
+                },
+            )
+            .add_systems(
+                Update,
+                (
+                    (detect_screen_touches, detect_errors),
+                    record_interactions,
+                    update_temperament,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Levels never go past this, so a long abuse session is forgotten in reasonable time
+const LEVEL_RANGE: RangeInclusive<f64> = 0.0..=1.0;
+pub const GAIN_RANGE: RangeInclusive<f64> = 0.1..=10.0;
+
+/// How strongly interactions change the mood and the mood changes the wave
+#[derive(Resource, Reflect, Clone, Debug)]
+#[reflect(Resource)]
+pub struct TemperamentResponse {
+    /// Seconds for a mood to fade to half its level
+    pub half_life_s: f64,
+    pub touch_annoyance: f64,
+    pub shake_annoyance: f64,
+    pub error_annoyance: f64,
+    pub greeting_contentment: f64,
+    /// Scroll speed multiplier when fully annoyed
+    pub annoyed_speed_gain: f64,
+    /// Height multiplier when fully annoyed
+    pub annoyed_amplitude_gain: f64,
+    /// Scroll speed multiplier when fully content
+    pub content_speed_gain: f64,
+    /// Height multiplier when fully content
+    pub content_amplitude_gain: f64,
+}
+
+impl Default for TemperamentResponse {
+    fn default() -> Self {
+        Self {
+            half_life_s: 120.0,
+            touch_annoyance: 0.1,
+            shake_annoyance: 0.3,
+            error_annoyance: 0.05,
+            greeting_contentment: 0.3,
+            annoyed_speed_gain: 2.5,
+            annoyed_amplitude_gain: 1.4,
+            content_speed_gain: 0.7,
+            content_amplitude_gain: 0.9,
+        }
+    }
+}
+
+impl Tunable for TemperamentResponse {
+    fn sanitize(&mut self) {
+        let defaults = Self::default();
+        self.half_life_s = if self.half_life_s.is_finite() && self.half_life_s > 0.0 {
+            self.half_life_s
+        } else {
+            defaults.half_life_s
+        };
+        for amount in [
+            &mut self.touch_annoyance,
+            &mut self.shake_annoyance,
+            &mut self.error_annoyance,
+            &mut self.greeting_contentment,
+        ] {
+            *amount = if amount.is_nan() {
+                0.0
+            } else {
+                amount.clamp(*LEVEL_RANGE.start(), *LEVEL_RANGE.end())
+            };
+        }
+        for gain in [
+            &mut self.annoyed_speed_gain,
+            &mut self.annoyed_amplitude_gain,
+            &mut self.content_speed_gain,
+            &mut self.content_amplitude_gain,
+        ] {
+            *gain = if gain.is_nan() {
+                1.0
+            } else {
+                gain.clamp(*GAIN_RANGE.start(), *GAIN_RANGE.end())
+            };
+        }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Interaction {
+    Touch,
+    Shake,
+    Error,
+    Greeting,
+}
+
+/// Interaction with the robot, also sent for taps, shakes, errors and greetings the face notices itself
+#[derive(Event, serde::Deserialize, Debug)]
+pub struct InteractionMessage {
+    pub interaction: Option<Interaction>,
+    /// Forget the history and go back to neutral
+    #[serde(default)]
+    pub reset: bool,
+    /// Key to publish the resulting mood to once applied
+    #[serde(default)]
+    pub reply_to: Option<String>,
+}
+
+impl From<Interaction> for InteractionMessage {
+    fn from(interaction: Interaction) -> Self {
+        Self {
+            interaction: Some(interaction),
+            reset: false,
+            reply_to: None,
+        }
+    }
+}
+
+/// Decaying mood levels and the multipliers they apply to the wave
+#[derive(Resource, Debug, serde::Serialize)]
+pub struct Temperament {
+    /// Between 0.0 for calm and 1.0 for fed up
+    pub annoyance: f64,
+    /// Between 0.0 for indifferent and 1.0 for delighted
+    pub contentment: f64,
+    #[serde(skip)]
+    pub speed_scale: f64,
+    #[serde(skip)]
+    pub amplitude_scale: f64,
+}
+
+impl Default for Temperament {
+    fn default() -> Self {
+        Self {
+            annoyance: 0.0,
+            contentment: 0.0,
+            speed_scale: 1.0,
+            amplitude_scale: 1.0,
+        }
+    }
+}
+
+impl Temperament {
+    fn record(&mut self, interaction: Interaction, response: &TemperamentResponse) {
+        let (level, amount) = match interaction {
+            Interaction::Touch => (&mut self.annoyance, response.touch_annoyance),
+            Interaction::Shake => (&mut self.annoyance, response.shake_annoyance),
+            Interaction::Error => (&mut self.annoyance, response.error_annoyance),
+            Interaction::Greeting => (&mut self.contentment, response.greeting_contentment),
+        };
+        *level = (*level + amount).clamp(*LEVEL_RANGE.start(), *LEVEL_RANGE.end());
+    }
+
+    fn decay(&mut self, elapsed_s: f64, half_life_s: f64) {
+        let factor = 0.5f64.powf(elapsed_s / half_life_s);
+        self.annoyance *= factor;
+        self.contentment *= factor;
+    }
+
+    fn update_scales(&mut self, response: &TemperamentResponse) {
+        let gain = |annoyed: f64, content: f64| {
+            (1.0 + (annoyed - 1.0) * self.annoyance) * (1.0 + (content - 1.0) * self.contentment)
+        };
+        self.speed_scale = gain(response.annoyed_speed_gain, response.content_speed_gain);
+        self.amplitude_scale = gain(
+            response.annoyed_amplitude_gain,
+            response.content_amplitude_gain,
+        );
+    }
+}
+
+/// Taps and clicks on the face, ignored while the self test asks for them
+fn detect_screen_touches(
+    touches: Res<Touches>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    scene: Res<State<FaceScene>>,
+    mut events: EventWriter<InteractionMessage>,
+) {
+    if *scene.get() == FaceScene::SelfTest {
+        return;
+    }
+    let taps =
+        touches.iter_just_pressed().count() + usize::from(mouse.just_pressed(MouseButton::Left));
+    for _ in 0..taps {
+        events.send(Interaction::Touch.into());
+    }
+}
+
+/// Errors that made it into the event log since the last frame
+fn detect_errors(
+    event_log: Res<EventLog>,
+    mut seen_errors: Local<u64>,
+    mut events: EventWriter<InteractionMessage>,
+) {
+    let errors = event_log.error_count();
+    for _ in *seen_errors..errors {
+        events.send(Interaction::Error.into());
+    }
+    *seen_errors = errors;
+}
+
+fn record_interactions(
+    mut messages: EventReader<InteractionMessage>,
+    mut temperament: ResMut<Temperament>,
+    response: Res<TemperamentResponse>,
+    ack_sender: Res<AckSender>,
+) {
+    for (message, id) in messages.read_with_id() {
+        let _trace = ack_sender.trace(id);
+        if message.reset {
+            info!("Resetting temperament");
+            temperament.annoyance = 0.0;
+            temperament.contentment = 0.0;
+        }
+        if let Some(interaction) = message.interaction {
+            debug!(?interaction, "Recording interaction");
+            temperament.record(interaction, &response);
+        }
+        ack_sender.send(
+            message.reply_to.as_deref(),
+            CommandAck::applied(&*temperament),
+        );
+    }
+}
+
+fn update_temperament(
+    mut temperament: ResMut<Temperament>,
+    response: Res<TemperamentResponse>,
+    clock: Res<FaceClock>,
+) {
+    if temperament.annoyance == 0.0 && temperament.contentment == 0.0 {
+        return;
+    }
+    temperament.decay(clock.delta_seconds_f64(), response.half_life_s);
+    // settle instead of creeping toward neutral forever
+    if temperament.annoyance < 1e-3 {
+        temperament.annoyance = 0.0;
+    }
+    if temperament.contentment < 1e-3 {
+        temperament.contentment = 0.0;
+    }
+    temperament.update_scales(&response);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeated_pokes_build_up_and_fade() {
+        let response = TemperamentResponse::default();
+        let mut temperament = Temperament::default();
+        for _ in 0..20 {
+            temperament.record(Interaction::Touch, &response);
+        }
+        assert_eq!(temperament.annoyance, 1.0);
+        temperament.update_scales(&response);
+        assert_eq!(temperament.speed_scale, response.annoyed_speed_gain);
+
+        temperament.decay(response.half_life_s, response.half_life_s);
+        assert!((temperament.annoyance - 0.5).abs() < 1e-9);
+        temperament.update_scales(&response);
+        assert!(temperament.speed_scale > 1.0 && temperament.speed_scale < 2.5);
+    }
+}
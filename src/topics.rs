@@ -0,0 +1,105 @@
+use std::sync::Arc;
+
+use bevy::prelude::*;
+
+use crate::{
+    messaging::{parse_json_payload, FaceMessage, OverflowPolicy},
+    trace::{CommandTrace, CommandTraces},
+};
+
+/// Parser turning a payload into a message for bevy, `binary` is set for octet stream payloads
+type ParseTopic = dyn Fn(&[u8], bool) -> anyhow::Result<FaceMessage> + Send + Sync;
+
+/// Zenoh topic forwarded to bevy as events
+#[derive(Clone)]
+pub struct Topic {
+    pub key_expr: &'static str,
+    /// Uses `--command-overflow-policy` when not set
+    pub overflow: Option<OverflowPolicy>,
+    parse: Arc<ParseTopic>,
+}
+
+impl Topic {
+    pub fn parse(&self, payload: &[u8], binary: bool) -> anyhow::Result<FaceMessage> {
+        (self.parse)(payload, binary)
+    }
+}
+
+/// Topics declared by plugins, subscribed to by the zenoh worker
+#[derive(Resource, Clone, Default)]
+pub struct TopicRegistry(Vec<Topic>);
+
+impl TopicRegistry {
+    pub fn iter(&self) -> impl Iterator<Item = &Topic> {
+        self.0.iter()
+    }
+
+    pub fn get(&self, key_expr: &str) -> Option<&Topic> {
+        self.0.iter().find(|topic| topic.key_expr == key_expr)
+    }
+}
+
+/// Sends a parsed event to the world along with the trace of its command
+type SendTopicEvent = dyn FnOnce(&mut World, Option<CommandTrace>) + Send + Sync;
+
+/// Event parsed from a registered topic, sent once bevy dispatches it
+pub struct TopicEvent(Box<SendTopicEvent>);
+
+impl TopicEvent {
+    pub fn new<E: Event>(event: E) -> Self {
+        Self(Box::new(move |world, trace| {
+            let id = world.send_event(event);
+            if let (Some(id), Some(traces)) = (id, world.get_resource::<CommandTraces>()) {
+                traces.dispatched(id, trace);
+            }
+        }))
+    }
+
+    /// Send the event, `trace` is the trace of the command it was parsed from
+    pub fn send(self, world: &mut World, trace: Option<CommandTrace>) {
+        (self.0)(world, trace)
+    }
+}
+
+pub trait RegisterTopic {
+    /// Forward json messages on `key_expr` as `E` events
+    fn add_topic<E: Event + serde::de::DeserializeOwned>(
+        &mut self,
+        key_expr: &'static str,
+    ) -> &mut Self {
+        self.add_topic_with::<E>(key_expr, None, |payload, _| parse_json_payload(payload))
+    }
+
+    /// Forward messages on `key_expr` parsed with `parse` as `E` events
+    fn add_topic_with<E: Event>(
+        &mut self,
+        key_expr: &'static str,
+        overflow: Option<OverflowPolicy>,
+        parse: impl Fn(&[u8], bool) -> anyhow::Result<E> + Send + Sync + 'static,
+    ) -> &mut Self;
+}
+
+impl RegisterTopic for App {
+    fn add_topic_with<E: Event>(
+        &mut self,
+        key_expr: &'static str,
+        overflow: Option<OverflowPolicy>,
+        parse: impl Fn(&[u8], bool) -> anyhow::Result<E> + Send + Sync + 'static,
+    ) -> &mut Self {
+        let parse = move |payload: &[u8], binary| {
+            let event = parse(payload, binary)?;
+            Ok(FaceMessage::Topic(TopicEvent::new(event)))
+        };
+        self.add_event::<E>()
+            .init_resource::<TopicRegistry>()
+            .world
+            .resource_mut::<TopicRegistry>()
+            .0
+            .push(Topic {
+                key_expr,
+                overflow,
+                parse: Arc::new(parse),
+            });
+        self
+    }
+}
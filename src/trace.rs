@@ -0,0 +1,206 @@
+use std::{
+    any::TypeId,
+    cell::RefCell,
+    collections::HashMap,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+use bevy::{ecs::event::EventId, prelude::*, utils::tracing::span::EnteredSpan};
+
+/// Longer trace ids are ignored
+const MAX_TRACE_ID_LENGTH: usize = 128;
+
+/// Trace a command was sent under, read from its optional `trace_id` field
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandTrace {
+    pub trace_id: String,
+    pub key_expr: String,
+}
+
+impl CommandTrace {
+    /// Read the trace of a json command, payloads without a valid `trace_id` aren't traced
+    pub fn parse(key_expr: &str, payload: &[u8]) -> Option<Self> {
+        // skip parsing the payload a second time for the bulk of untraced commands
+        if !payload.windows(8).any(|window| window == b"trace_id") {
+            return None;
+        }
+        let value = serde_json::from_slice::<serde_json::Value>(payload).ok()?;
+        let trace_id = value.get("trace_id")?.as_str()?;
+        if trace_id.is_empty()
+            || trace_id.len() > MAX_TRACE_ID_LENGTH
+            || !trace_id.chars().all(|char| char.is_ascii_graphic())
+        {
+            warn!(trace_id, key_expr, "Ignoring invalid trace id");
+            return None;
+        }
+        Some(Self {
+            trace_id: trace_id.to_owned(),
+            key_expr: key_expr.to_owned(),
+        })
+    }
+}
+
+/// Frames a dispatched event can still be read in, bevy drops events after two updates
+const TRACE_FRAMES: u64 = 2;
+
+/// Traces of the events dispatched for traced commands
+///
+/// Shared with the ack sender, handlers enter the trace of each event they read with
+/// [`crate::messaging::AckSender::trace`] so the acks and events they send carry its `trace_id`
+#[derive(Resource, Clone, Default)]
+pub struct CommandTraces(Arc<Mutex<DispatchedTraces>>);
+
+#[derive(Default)]
+struct DispatchedTraces {
+    frame: u64,
+    /// Trace and dispatch frame by event type and id
+    traces: HashMap<(TypeId, usize), (u64, CommandTrace)>,
+}
+
+impl CommandTraces {
+    /// Start a new frame, forgetting the traces of events bevy already dropped
+    pub fn start_frame(&self) {
+        let mut dispatched = self.lock();
+        dispatched.frame += 1;
+        let frame = dispatched.frame;
+        dispatched
+            .traces
+            .retain(|_, (dispatched_in, _)| frame - *dispatched_in <= TRACE_FRAMES);
+    }
+
+    /// Remember the trace of the command the event `id` was sent for, if it was traced
+    pub fn dispatched<E: Event>(&self, id: EventId<E>, trace: Option<CommandTrace>) {
+        let Some(trace) = trace else {
+            return;
+        };
+        let mut dispatched = self.lock();
+        let frame = dispatched.frame;
+        dispatched
+            .traces
+            .insert((TypeId::of::<E>(), id.id), (frame, trace));
+    }
+
+    /// Trace of the command the event `id` was sent for
+    pub fn get<E: Event>(&self, id: EventId<E>) -> Option<CommandTrace> {
+        self.lock()
+            .traces
+            .get(&(TypeId::of::<E>(), id.id))
+            .map(|(_, trace)| trace.clone())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, DispatchedTraces> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+thread_local! {
+    static CURRENT_TRACE: RefCell<Option<CommandTrace>> = const { RefCell::new(None) };
+}
+
+/// Handles the command of a trace on this thread until dropped
+///
+/// Inside a `command` span with its `trace_id`, the previous trace is restored on drop
+pub struct TraceGuard {
+    previous: Option<CommandTrace>,
+    _span: Option<EnteredSpan>,
+}
+
+impl TraceGuard {
+    /// Enter `trace`, `None` for commands that weren't traced
+    ///
+    /// Handlers that answer a command in a later frame keep [`TraceGuard::current`] and enter it again
+    pub fn enter(trace: Option<CommandTrace>) -> Self {
+        let span = trace
+            .as_ref()
+            .map(|trace| info_span!("command", trace_id = trace.trace_id).entered());
+        Self {
+            previous: CURRENT_TRACE.with(|current| current.replace(trace)),
+            _span: span,
+        }
+    }
+
+    /// Trace of the command handled on this thread
+    pub fn current() -> Option<CommandTrace> {
+        CURRENT_TRACE.with(|current| current.borrow().clone())
+    }
+}
+
+impl Drop for TraceGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        CURRENT_TRACE.with(|current| *current.borrow_mut() = previous);
+    }
+}
+
+/// Add `field` to a json object, other json values can't carry it
+pub fn annotate(json: &mut serde_json::Value, field: &str, value: impl Into<serde_json::Value>) {
+    if let serde_json::Value::Object(object) = json {
+        object.insert(field.to_owned(), value.into());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn traces_are_read_from_json_commands() {
+        let trace = CommandTrace::parse(
+            "face/expression",
+            br#"{"expression": "happy", "trace_id": "4bf92f3577b34da6", "reply_to": "robot/ack"}"#,
+        )
+        .unwrap();
+        assert_eq!(trace.trace_id, "4bf92f3577b34da6");
+        assert_eq!(trace.key_expr, "face/expression");
+
+        assert_eq!(
+            CommandTrace::parse("face/expression", br#"{"expression": "happy"}"#),
+            None
+        );
+        assert_eq!(
+            CommandTrace::parse("face/expression", br#"{"trace_id": "has spaces"}"#),
+            None
+        );
+        assert_eq!(
+            CommandTrace::parse("face/wave/raw", b"trace_id\0\0\0\0"),
+            None
+        );
+    }
+
+    #[derive(Event)]
+    struct Command;
+
+    #[test]
+    fn only_the_entered_command_is_traced() {
+        let trace = |trace_id: &str| CommandTrace {
+            trace_id: trace_id.to_owned(),
+            key_expr: "face/expression".to_owned(),
+        };
+        let mut events = Events::<Command>::default();
+        let traced = events.send(Command);
+        let untraced = events.send(Command);
+        let traces = CommandTraces::default();
+        traces.dispatched(traced, Some(trace("a")));
+        traces.dispatched(untraced, None);
+
+        assert_eq!(traces.get(traced), Some(trace("a")));
+        assert_eq!(traces.get(untraced), None);
+        {
+            let _traced = TraceGuard::enter(traces.get(traced));
+            assert_eq!(TraceGuard::current(), Some(trace("a")));
+            {
+                let _untraced = TraceGuard::enter(traces.get(untraced));
+                assert_eq!(TraceGuard::current(), None);
+            }
+            assert_eq!(TraceGuard::current(), Some(trace("a")));
+        }
+        assert_eq!(TraceGuard::current(), None);
+
+        for _ in 0..TRACE_FRAMES {
+            traces.start_frame();
+        }
+        assert!(traces.get(traced).is_some());
+        traces.start_frame();
+        assert_eq!(traces.get(traced), None);
+    }
+}
@@ -0,0 +1,164 @@
+use std::{ops::RangeInclusive, time::Duration};
+
+use bevy::prelude::*;
+
+use crate::{
+    face_clock::FaceClock,
+    params::{RegisterTunable, Tunable},
+    topics::RegisterTopic,
+};
+
+/// Wave that works harder the faster the robot drives
+///
+/// Velocities from `face/robot/velocity` scale the scroll speed and height of the wave,
+/// how strongly is adjustable as the `velocity` tunable
+pub struct VelocityPlugin;
+
+impl Plugin for VelocityPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<VelocityResponse>()
+            .init_resource::<RobotMotion>()
+            .init_resource::<LatestVelocity>()
+            .register_tunable::<VelocityResponse>("velocity")
+            .add_topic::<RobotVelocity>("face/robot/velocity")
+            .add_systems(Update, (record_robot_velocity, update_robot_motion).chain());
+    }
+}
+
+/// The robot is considered standing once no velocity arrived for this long
+const VELOCITY_TIMEOUT: Duration = Duration::from_secs(1);
+pub const GAIN_RANGE: RangeInclusive<f64> = 1.0..=10.0;
+
+/// How the wave reacts to the robot moving
+#[derive(Resource, Reflect, Clone, Debug)]
+#[reflect(Resource)]
+pub struct VelocityResponse {
+    /// Speed in m/s at which the wave reacts fully
+    pub max_speed: f64,
+    /// Turning counts as driving at this many m/s per rad/s
+    pub angular_weight: f64,
+    /// Scroll speed multiplier at full speed
+    pub speed_gain: f64,
+    /// Height multiplier at full speed
+    pub amplitude_gain: f64,
+    /// Seconds the reaction takes to follow the robot, hides jitter in the velocities
+    pub smoothing: f64,
+}
+
+impl Default for VelocityResponse {
+    fn default() -> Self {
+        Self {
+            max_speed: 1.0,
+            angular_weight: 0.2,
+            speed_gain: 3.0,
+            amplitude_gain: 1.5,
+            smoothing: 0.3,
+        }
+    }
+}
+
+impl Tunable for VelocityResponse {
+    fn sanitize(&mut self) {
+        let defaults = Self::default();
+        let positive = |value: f64, default: f64| {
+            if value.is_finite() && value > 0.0 {
+                value
+            } else {
+                default
+            }
+        };
+        self.max_speed = positive(self.max_speed, defaults.max_speed);
+        self.smoothing = positive(self.smoothing, defaults.smoothing);
+        self.angular_weight = if self.angular_weight.is_finite() {
+            self.angular_weight.max(0.0)
+        } else {
+            defaults.angular_weight
+        };
+        for gain in [&mut self.speed_gain, &mut self.amplitude_gain] {
+            *gain = if gain.is_nan() {
+                1.0
+            } else {
+                gain.clamp(*GAIN_RANGE.start(), *GAIN_RANGE.end())
+            };
+        }
+    }
+}
+
+/// Current velocity of the robot base
+#[derive(Event, serde::Deserialize, Debug)]
+pub struct RobotVelocity {
+    /// Forward speed in m/s
+    #[serde(default)]
+    pub linear: f64,
+    /// Turn rate in rad/s
+    #[serde(default)]
+    pub angular: f64,
+}
+
+/// Multipliers applied to the wave for the current motion of the robot
+#[derive(Resource, Debug)]
+pub struct RobotMotion {
+    pub speed_scale: f64,
+    pub amplitude_scale: f64,
+    /// Smoothed motion between 0.0 for standing and 1.0 for full speed
+    intensity: f64,
+}
+
+impl Default for RobotMotion {
+    fn default() -> Self {
+        Self {
+            speed_scale: 1.0,
+            amplitude_scale: 1.0,
+            intensity: 0.0,
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+struct LatestVelocity {
+    /// Motion between 0.0 and 1.0 the wave is moving toward
+    target: f64,
+    received_at: Option<Duration>,
+}
+
+fn record_robot_velocity(
+    mut messages: EventReader<RobotVelocity>,
+    mut latest: ResMut<LatestVelocity>,
+    response: Res<VelocityResponse>,
+    clock: Res<FaceClock>,
+) {
+    if let Some(velocity) = messages.read().last() {
+        let speed = velocity.linear.abs() + velocity.angular.abs() * response.angular_weight;
+        latest.target = if speed.is_finite() {
+            (speed / response.max_speed).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        latest.received_at = Some(clock.elapsed());
+    }
+}
+
+fn update_robot_motion(
+    mut motion: ResMut<RobotMotion>,
+    latest: Res<LatestVelocity>,
+    response: Res<VelocityResponse>,
+    clock: Res<FaceClock>,
+) {
+    let fresh = latest
+        .received_at
+        .is_some_and(|received_at| clock.elapsed() - received_at < VELOCITY_TIMEOUT);
+    let target = if fresh { latest.target } else { 0.0 };
+    if motion.intensity == target && !response.is_changed() {
+        return;
+    }
+
+    let blend = 1.0 - (-clock.delta_seconds_f64() / response.smoothing).exp();
+    let mut intensity = motion.intensity + (target - motion.intensity) * blend;
+    // settle instead of creeping toward the target forever
+    if (intensity - target).abs() < 1e-3 {
+        intensity = target;
+    }
+    motion.intensity = intensity;
+    motion.speed_scale = 1.0 + (response.speed_gain - 1.0) * intensity;
+    motion.amplitude_scale = 1.0 + (response.amplitude_gain - 1.0) * intensity;
+}
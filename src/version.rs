@@ -0,0 +1,30 @@
+/// Build of the running face, served on `face/version`
+#[derive(serde::Serialize, Debug, Clone, PartialEq)]
+pub struct BuildInfo {
+    pub version: &'static str,
+    pub git_hash: &'static str,
+    /// Cargo profile such as "release" or "debug"
+    pub profile: &'static str,
+    pub features: Vec<&'static str>,
+}
+
+impl BuildInfo {
+    pub fn current() -> Self {
+        let features = [
+            ("hw-telemetry", cfg!(feature = "hw-telemetry")),
+            ("oled", cfg!(feature = "oled")),
+            ("inspector", cfg!(feature = "inspector")),
+            ("hot-reload", cfg!(feature = "hot-reload")),
+            ("gamepad", cfg!(feature = "gamepad")),
+        ];
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            git_hash: env!("FACE_GIT_HASH"),
+            profile: env!("FACE_BUILD_PROFILE"),
+            features: features
+                .into_iter()
+                .filter_map(|(feature, enabled)| enabled.then_some(feature))
+                .collect(),
+        }
+    }
+}
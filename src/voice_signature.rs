@@ -0,0 +1,99 @@
+use bevy::prelude::*;
+
+use crate::noise_plugin::NoiseSeed;
+
+/// Idle wave that tells robots of a fleet apart while they share a config
+///
+/// The noise seed and small offsets of the wave speed, height and width
+/// are derived from the face id, so every robot keeps its own wave across restarts
+pub struct VoiceSignaturePlugin {
+    pub face_id: Option<String>,
+}
+
+impl Plugin for VoiceSignaturePlugin {
+    fn build(&self, app: &mut App) {
+        let Some(face_id) = &self.face_id else {
+            app.init_resource::<VoiceSignature>();
+            return;
+        };
+        let signature = VoiceSignature::from_face_id(face_id);
+        info!(face_id, ?signature, "Using voice signature");
+        app.insert_resource(NoiseSeed(signature.seed))
+            .insert_resource(signature);
+    }
+}
+
+/// Largest relative change of the wave speed, height and width
+const MAX_SPEED_OFFSET: f64 = 0.08;
+const MAX_HEIGHT_OFFSET: f64 = 0.08;
+const MAX_WIDTH_OFFSET: f64 = 0.12;
+
+/// Per robot changes of the wave, the identity without a face id
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct VoiceSignature {
+    pub seed: u32,
+    /// Multiplies the wave speed
+    pub speed_scale: f64,
+    /// Multiplies the wave height
+    pub height_scale: f64,
+    /// Multiplies the width divider, larger values stretch the wave
+    pub width_scale: f64,
+}
+
+impl Default for VoiceSignature {
+    fn default() -> Self {
+        Self {
+            seed: NoiseSeed::default().0,
+            speed_scale: 1.0,
+            height_scale: 1.0,
+            width_scale: 1.0,
+        }
+    }
+}
+
+impl VoiceSignature {
+    pub fn from_face_id(face_id: &str) -> Self {
+        // FNV-1a, std hashers aren't guaranteed to stay the same between releases
+        let hash = face_id.bytes().fold(0x811c_9dc5_u32, |hash, byte| {
+            (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+        });
+        let offset = |salt: u32, max: f64| 1.0 + (unit(mix(hash ^ salt)) * 2.0 - 1.0) * max;
+        Self {
+            seed: mix(hash),
+            speed_scale: offset(1, MAX_SPEED_OFFSET),
+            height_scale: offset(2, MAX_HEIGHT_OFFSET),
+            width_scale: offset(3, MAX_WIDTH_OFFSET),
+        }
+    }
+}
+
+/// Spread the bits of similar ids such as robot-1 and robot-2
+fn mix(mut value: u32) -> u32 {
+    value ^= value >> 16;
+    value = value.wrapping_mul(0x7feb_352d);
+    value ^= value >> 15;
+    value = value.wrapping_mul(0x846c_a68b);
+    value ^ (value >> 16)
+}
+
+fn unit(value: u32) -> f64 {
+    f64::from(value) / f64::from(u32::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signatures_are_stable_and_distinct() {
+        let first = VoiceSignature::from_face_id("robot-1");
+        assert_eq!(first, VoiceSignature::from_face_id("robot-1"));
+        assert_ne!(first.seed, VoiceSignature::from_face_id("robot-2").seed);
+        for face_id in ["", "robot-1", "robot-2", "kitchen"] {
+            let signature = VoiceSignature::from_face_id(face_id);
+            assert!((signature.speed_scale - 1.0).abs() <= MAX_SPEED_OFFSET);
+            assert!((signature.height_scale - 1.0).abs() <= MAX_HEIGHT_OFFSET);
+            assert!((signature.width_scale - 1.0).abs() <= MAX_WIDTH_OFFSET);
+        }
+    }
+}
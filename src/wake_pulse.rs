@@ -0,0 +1,238 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{
+    display::{DisplayControl, DisplayPower},
+    event_log::{EventKind, EventLog},
+    face_clock::FaceClock,
+    messaging::ZenohConnection,
+    rotation::FaceRotation,
+    safe_area::SafeArea,
+};
+
+/// Briefly wake a display that was turned off to show it is still alive
+///
+/// Every `interval` with the display off it is turned on for `duration` with a dim status
+/// frame over the face, then turned off again. Turning the display on over `face/display`
+/// ends the pulse and keeps the display on
+pub struct WakePulsePlugin {
+    pub interval: Duration,
+    pub duration: Duration,
+}
+
+impl Plugin for WakePulsePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(WakePulses {
+            interval: self.interval,
+            duration: self.duration,
+            off_since: None,
+            awake_until: None,
+        })
+        .add_systems(Startup, spawn_status_frame)
+        .add_systems(Update, (pulse_display, update_status_frame).chain());
+    }
+}
+
+/// Default seconds the display stays on for each pulse
+pub const DEFAULT_WAKE_PULSE_DURATION_S: f32 = 5.0;
+/// Dim so the pulses don't wear the panel
+const STATUS_COLOR: Color = Color::rgb(0.5, 0.5, 0.5);
+const STATUS_FONT_SIZE: f32 = 32.0;
+/// Above the face and below the shutdown fade
+const STATUS_FRAME_Z: f32 = 80.0;
+
+#[derive(Resource)]
+struct WakePulses {
+    interval: Duration,
+    duration: Duration,
+    /// Time the display was turned off or the last pulse ended
+    off_since: Option<Duration>,
+    awake_until: Option<Duration>,
+}
+
+impl WakePulses {
+    /// Display power to switch to at `now`, if any
+    fn update(&mut self, display_on: bool, now: Duration) -> Option<bool> {
+        if display_on {
+            self.off_since = None;
+            self.awake_until = None;
+            return None;
+        }
+        let off_since = *self.off_since.get_or_insert(now);
+        match self.awake_until {
+            Some(awake_until) if now >= awake_until => {
+                self.awake_until = None;
+                self.off_since = Some(now);
+                Some(false)
+            }
+            Some(_) => None,
+            None if now - off_since >= self.interval => {
+                self.awake_until = Some(now + self.duration);
+                Some(true)
+            }
+            None => None,
+        }
+    }
+
+    fn awake(&self) -> bool {
+        self.awake_until.is_some()
+    }
+}
+
+#[derive(Component)]
+struct StatusFrame;
+
+#[derive(Component)]
+struct StatusFrameText;
+
+fn spawn_status_frame(mut commands: Commands) {
+    commands
+        .spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: Color::BLACK,
+                    custom_size: Some(Vec2::splat(100_000.0)),
+                    ..default()
+                },
+                transform: Transform::from_xyz(0.0, 0.0, STATUS_FRAME_Z),
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            StatusFrame,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text2dBundle {
+                    text: Text::from_section(
+                        "",
+                        TextStyle {
+                            font_size: STATUS_FONT_SIZE,
+                            color: STATUS_COLOR,
+                            ..default()
+                        },
+                    )
+                    .with_justify(JustifyText::Center),
+                    transform: Transform::from_xyz(0.0, 0.0, 0.1),
+                    ..default()
+                },
+                StatusFrameText,
+            ));
+        });
+}
+
+fn pulse_display(
+    mut pulses: ResMut<WakePulses>,
+    display_power: Res<DisplayPower>,
+    display_control: Res<DisplayControl>,
+    event_log: Res<EventLog>,
+    time: Res<Time<Real>>,
+) {
+    let Some(on) = pulses.update(display_power.on, time.elapsed()) else {
+        return;
+    };
+    if on {
+        info!(duration = ?pulses.duration, "Waking display");
+    } else {
+        debug!("Turning display off after wake pulse");
+    }
+    set_display_power(display_control.clone(), event_log.clone(), on);
+}
+
+/// Switch the display from outside of the zenoh worker's runtime without waiting for it
+fn set_display_power(display_control: DisplayControl, event_log: EventLog, on: bool) {
+    std::thread::spawn(move || {
+        let result = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(anyhow::Error::from)
+            .and_then(|runtime| {
+                runtime.block_on(async {
+                    if on {
+                        display_control.turn_on_display().await
+                    } else {
+                        display_control.turn_off_display().await
+                    }
+                })
+            });
+        if let Err(error) = result {
+            error!(?error, on, "Failed to switch display for wake pulse");
+            event_log.record(EventKind::Error, format!("wake pulse: {error:#}"));
+        }
+    });
+}
+
+/// Show the time and connection state in the center of the safe area while awake
+fn update_status_frame(
+    (pulses, zenoh_connection, clock): (Res<WakePulses>, Res<ZenohConnection>, Res<FaceClock>),
+    mut frame: Query<(&mut Transform, &mut Visibility), With<StatusFrame>>,
+    mut text: Query<&mut Text, With<StatusFrameText>>,
+    query_camera: Query<&OrthographicProjection>,
+    (safe_area, rotation): (Res<SafeArea>, Res<FaceRotation>),
+) {
+    for (mut transform, mut visibility) in frame.iter_mut() {
+        let target = if pulses.awake() {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        };
+        if *visibility != target {
+            *visibility = target;
+        }
+        if !pulses.awake() {
+            continue;
+        }
+        for camera in query_camera.iter() {
+            let center = safe_area.apply(rotation.area(camera.area)).center();
+            transform.translation.x = center.x;
+            transform.translation.y = center.y;
+        }
+    }
+    if !pulses.awake() {
+        return;
+    }
+    let status = format!(
+        "{}\nzenoh {}",
+        clock.local_time().format("%H:%M"),
+        if zenoh_connection.connected {
+            "connected"
+        } else {
+            "offline"
+        }
+    );
+    for mut text in text.iter_mut() {
+        if text.sections[0].value != status {
+            text.sections[0].value = status.clone();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pulses_wake_the_display_while_it_is_off() {
+        let mut pulses = WakePulses {
+            interval: Duration::from_secs(600),
+            duration: Duration::from_secs(5),
+            off_since: None,
+            awake_until: None,
+        };
+        let at = Duration::from_secs;
+        assert_eq!(pulses.update(true, at(0)), None);
+        assert_eq!(pulses.update(false, at(100)), None);
+        assert_eq!(pulses.update(false, at(699)), None);
+        assert_eq!(pulses.update(false, at(700)), Some(true));
+        assert!(pulses.awake());
+        assert_eq!(pulses.update(false, at(704)), None);
+        assert_eq!(pulses.update(false, at(705)), Some(false));
+        assert_eq!(pulses.update(false, at(1304)), None);
+        assert_eq!(pulses.update(false, at(1305)), Some(true));
+
+        // turning the display on ends the pulse without turning it off again
+        assert_eq!(pulses.update(true, at(1306)), None);
+        assert!(!pulses.awake());
+        assert_eq!(pulses.update(true, at(5000)), None);
+    }
+}
@@ -0,0 +1,204 @@
+//! Wave geometry pipeline
+//!
+//! Kept free of ECS state so the hot loop can be benchmarked on its own
+
+use bevy::{
+    prelude::*,
+    render::{
+        mesh::{PrimitiveTopology, VertexAttributeValues},
+        render_asset::RenderAssetUsages,
+    },
+};
+use noise::{BasicMulti, NoiseFn, Perlin};
+
+/// Sample `count` points of the noise at `step`
+pub fn sample_noise(
+    generator: &BasicMulti<Perlin>,
+    step: f64,
+    width_divider: f64,
+    count: usize,
+) -> Vec<f64> {
+    (0..count)
+        .map(|i| generator.get([step, i as f64 / width_divider]))
+        .collect()
+}
+
+/// Blend each sample from `previous` toward `current`
+pub fn interpolate_samples<'a>(
+    previous: &'a [f64],
+    current: &'a [f64],
+    blend: f64,
+) -> impl Iterator<Item = f64> + 'a {
+    previous
+        .iter()
+        .zip(current)
+        .map(move |(previous, current)| previous + (current - previous) * blend)
+}
+
+/// Lay out samples left to right starting at `origin_x`
+pub fn wave_points(
+    samples: impl Iterator<Item = f64>,
+    origin_x: f32,
+    segment_width: f32,
+    height_multiplier: f64,
+) -> Vec<Vec2> {
+    samples
+        .enumerate()
+        .map(|(index, sample)| {
+            Vec2::new(
+                origin_x + (index as f32) * segment_width,
+                (sample * height_multiplier) as f32,
+            )
+        })
+        .collect()
+}
+
+/// Waves of a single heartbeat as (phase, amplitude, width) from P over QRS to T
+const HEARTBEAT_WAVES: [(f64, f64, f64); 5] = [
+    (0.10, 0.12, 0.025),
+    (0.20, -0.12, 0.008),
+    (0.23, 1.0, 0.01),
+    (0.26, -0.25, 0.008),
+    (0.45, 0.25, 0.04),
+];
+
+/// ECG like trace of a heartbeat at `phase` between 0.0 and 1.0, the R spike peaks at 1.0
+pub fn heartbeat(phase: f64) -> f64 {
+    let phase = phase.rem_euclid(1.0);
+    HEARTBEAT_WAVES
+        .iter()
+        .map(|(center, amplitude, width)| {
+            amplitude * (-((phase - center) / width).powi(2) / 2.0).exp()
+        })
+        .sum()
+}
+
+/// Add a heartbeat trace that scrolls from right to left across the samples
+///
+/// The rightmost sample shows the heart at `time`, the leftmost as it was `sweep` seconds earlier
+pub fn add_heartbeat(samples: &mut [f64], time: f64, bpm: f64, sweep: f64, amplitude: f64) {
+    let last = samples.len().saturating_sub(1).max(1) as f64;
+    let beats_per_second = bpm / 60.0;
+    for (index, sample) in samples.iter_mut().enumerate() {
+        let age = (1.0 - index as f64 / last) * sweep;
+        *sample += heartbeat((time - age) * beats_per_second) * amplitude;
+    }
+}
+
+/// Wave of `count` samples swinging with a loudness `envelope` between 0.0 and 1.0
+///
+/// Used to lip-sync the face to audio only known by its loudness, `time` keeps the wave moving
+pub fn envelope_wave(time: f32, envelope: f32, count: usize) -> Vec<f32> {
+    mouth_wave(time, envelope, 3.0, count)
+}
+
+/// Wave of `count` samples opened by `openness` between 0.0 and 1.0 with `cycles` swings across
+///
+/// Few cycles read as a rounded mouth and many as a wide one
+pub fn mouth_wave(time: f32, openness: f32, cycles: f32, count: usize) -> Vec<f32> {
+    use std::f32::consts::{PI, TAU};
+    (0..count)
+        .map(|index| {
+            let position = index as f32 / (count.max(2) - 1) as f32;
+            // fade out toward the edges so the wave stays attached to the sides
+            let window = (position * PI).sin();
+            openness * window * (position * cycles * TAU + time * 10.0).sin()
+        })
+        .collect()
+}
+
+/// Centered moving average over `window` samples, narrowing toward the ends
+///
+/// Windows below 2 leave the samples untouched
+pub fn smooth_samples(samples: &[f64], window: usize) -> Vec<f64> {
+    if window < 2 {
+        return samples.to_vec();
+    }
+    let radius = window / 2;
+    // prefix sums keep this linear no matter the window
+    let mut sums = Vec::with_capacity(samples.len() + 1);
+    sums.push(0.0);
+    for sample in samples {
+        sums.push(sums[sums.len() - 1] + sample);
+    }
+    (0..samples.len())
+        .map(|index| {
+            let start = index.saturating_sub(radius);
+            let end = (index + radius + 1).min(samples.len());
+            (sums[end] - sums[start]) / (end - start) as f64
+        })
+        .collect()
+}
+
+/// Catmull-Rom spline through `points` with `subdivisions` extra points per segment
+///
+/// The curve passes through every original point, zero subdivisions return them as is
+pub fn catmull_rom(points: &[Vec2], subdivisions: usize) -> Vec<Vec2> {
+    if subdivisions == 0 || points.len() < 2 {
+        return points.to_vec();
+    }
+    let last = points.len() - 1;
+    let mut curve = Vec::with_capacity(last * (subdivisions + 1) + 1);
+    for index in 0..last {
+        let p0 = points[index.saturating_sub(1)];
+        let p1 = points[index];
+        let p2 = points[index + 1];
+        let p3 = points[(index + 2).min(last)];
+        for step in 0..=subdivisions {
+            let t = step as f32 / (subdivisions + 1) as f32;
+            let t2 = t * t;
+            let t3 = t2 * t;
+            curve.push(
+                0.5 * (2.0 * p1
+                    + (p2 - p0) * t
+                    + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2
+                    + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3),
+            );
+        }
+    }
+    curve.push(points[last]);
+    curve
+}
+
+/// Triangle strip covering a line of `width` through `points`
+pub fn build_line_mesh(points: &[Vec2], width: f32) -> Mesh {
+    let mut mesh = Mesh::new(
+        PrimitiveTopology::TriangleStrip,
+        RenderAssetUsages::default(),
+    );
+    write_line_mesh(&mut mesh, points, width);
+    mesh
+}
+
+/// Update vertex positions of a line mesh in place
+///
+/// Buffers are only reallocated when the number of points changes
+pub fn write_line_mesh(mesh: &mut Mesh, points: &[Vec2], width: f32) {
+    write_tapered_line_mesh(mesh, points, |_| width);
+}
+
+/// Line mesh with a width per point, points with a width of 0.0 leave a gap
+pub fn write_tapered_line_mesh(mesh: &mut Mesh, points: &[Vec2], width: impl Fn(usize) -> f32) {
+    let vertex_count = points.len() * 2;
+    let Some(VertexAttributeValues::Float32x3(positions)) =
+        mesh.attribute_mut(Mesh::ATTRIBUTE_POSITION)
+    else {
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, vec![[0.0; 3]; vertex_count]);
+        return write_tapered_line_mesh(mesh, points, width);
+    };
+
+    let resized = positions.len() != vertex_count;
+    positions.resize(vertex_count, [0.0; 3]);
+    for (index, point) in points.iter().enumerate() {
+        let previous = points[index.saturating_sub(1)];
+        let next = points[(index + 1).min(points.len() - 1)];
+        let normal = (next - previous).normalize_or_zero().perp() * width(index) * 0.5;
+        positions[index * 2] = (*point + normal).extend(0.0).to_array();
+        positions[index * 2 + 1] = (*point - normal).extend(0.0).to_array();
+    }
+
+    if resized || mesh.attribute(Mesh::ATTRIBUTE_NORMAL).is_none() {
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0, 0.0, 1.0]; vertex_count]);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0, 0.0]; vertex_count]);
+    }
+}
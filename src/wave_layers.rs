@@ -0,0 +1,306 @@
+use std::ops::RangeInclusive;
+
+use bevy::{
+    prelude::*,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+};
+use noise::{BasicMulti, MultiFractal, Perlin};
+
+use crate::{
+    messaging::{AckSender, CommandAck},
+    night_mode::NightMode,
+    noise_plugin::{
+        clamp_setting, NoiseGenerator, NoiseGeneratorSettings, NoiseSeed, NoiseWave,
+        LINE_WIDTH_RANGE,
+    },
+    rotation::FaceRotation,
+    safe_area::SafeArea,
+    topics::RegisterTopic,
+    wave::{
+        build_line_mesh, catmull_rom, sample_noise, smooth_samples, wave_points, write_line_mesh,
+    },
+};
+
+/// Extra noise waves drawn behind the main one, such as harmonics of a richer voice
+///
+/// `face/waves` replaces the layers, each with its own seed, color, amplitude, speed and offset.
+/// Layers share the shape settings of the main wave and follow it around and out of sight
+pub struct WaveLayersPlugin;
+
+impl Plugin for WaveLayersPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_topic::<WaveLayersMessage>("face/waves")
+            .add_systems(
+                Update,
+                (
+                    process_wave_layers_messages,
+                    update_wave_layers,
+                    tint_wave_layers.run_if(resource_changed::<NightMode>),
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Messages with more layers are rejected, the Pi has to draw every one of them
+pub const MAX_WAVE_LAYERS: usize = 8;
+const AMPLITUDE_RANGE: RangeInclusive<f64> = 0.0..=4.0;
+const SPEED_RANGE: RangeInclusive<f64> = 0.05..=10.0;
+const FREQUENCY_RANGE: RangeInclusive<f64> = 0.1..=10.0;
+const OFFSET_RANGE: RangeInclusive<f64> = -1000.0..=1000.0;
+/// Layers are drawn behind the main wave and the wave fading out after a drastic change
+const LAYER_Z: f32 = -0.2;
+const LAYER_Z_STEP: f32 = 0.01;
+
+/// Settings of one wave layer relative to the main wave
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct WaveLayerSettings {
+    /// Noise seed, the seed of the main wave plus the layer's position unless set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u32>,
+    /// Hex color such as "ffffff80"
+    pub color: String,
+    /// Multiplies the height of the main wave
+    pub amplitude: f64,
+    /// Multiplies the speed of the main wave
+    pub speed: f64,
+    /// Multiplies how many swings fit across the face, 2.0 is an octave up
+    pub frequency: f64,
+    /// Pixels above the main wave
+    pub offset_y: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line_width: Option<f32>,
+}
+
+impl Default for WaveLayerSettings {
+    fn default() -> Self {
+        Self {
+            seed: None,
+            color: String::from("ffffff80"),
+            amplitude: 1.0,
+            speed: 1.0,
+            frequency: 1.0,
+            offset_y: 0.0,
+            line_width: None,
+        }
+    }
+}
+
+/// Wave layers replacing the current ones, an empty list leaves only the main wave
+#[derive(Event, serde::Deserialize, serde::Serialize, Debug, Clone, Default)]
+pub struct WaveLayersMessage {
+    pub waves: Vec<WaveLayerSettings>,
+    /// Key to publish an acknowledgement to once applied
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reply_to: Option<String>,
+}
+
+#[derive(Component)]
+struct WaveLayer {
+    generator: BasicMulti<Perlin>,
+    color: Color,
+    amplitude: f64,
+    speed: f64,
+    frequency: f64,
+    offset_y: f32,
+    line_width: Option<f32>,
+    step: f64,
+    /// Depth below the main wave, later layers are drawn further behind
+    z: f32,
+}
+
+impl WaveLayer {
+    fn new(
+        settings: &WaveLayerSettings,
+        index: usize,
+        seed: u32,
+        octaves: usize,
+    ) -> Result<Self, bevy::render::color::HexColorError> {
+        Ok(Self {
+            generator: BasicMulti::<Perlin>::new(settings.seed.unwrap_or(seed))
+                .set_octaves(octaves),
+            color: Color::hex(&settings.color)?,
+            amplitude: clamp_setting(settings.amplitude, AMPLITUDE_RANGE),
+            speed: clamp_setting(settings.speed, SPEED_RANGE),
+            frequency: clamp_setting(settings.frequency, FREQUENCY_RANGE),
+            offset_y: clamp_setting(settings.offset_y.into(), OFFSET_RANGE) as f32,
+            line_width: settings
+                .line_width
+                .map(|width| clamp_setting(width.into(), LINE_WIDTH_RANGE) as f32),
+            step: 0.0,
+            z: LAYER_Z - LAYER_Z_STEP * index as f32,
+        })
+    }
+}
+
+fn process_wave_layers_messages(
+    mut commands: Commands,
+    mut messages: EventReader<WaveLayersMessage>,
+    layers: Query<Entity, With<WaveLayer>>,
+    (seed, settings, night_mode): (Res<NoiseSeed>, Res<NoiseGeneratorSettings>, Res<NightMode>),
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    ack_sender: Res<AckSender>,
+) {
+    for (message, id) in messages.read_with_id() {
+        let _trace = ack_sender.trace(id);
+        if message.waves.len() > MAX_WAVE_LAYERS {
+            let error = format!(
+                "{} wave layers are more than the {MAX_WAVE_LAYERS} allowed",
+                message.waves.len()
+            );
+            error!(error, "Rejecting wave layers");
+            ack_sender.send(message.reply_to.as_deref(), CommandAck::rejected(error));
+            continue;
+        }
+        let new_layers: Result<Vec<_>, _> = message
+            .waves
+            .iter()
+            .enumerate()
+            .map(|(index, layer)| {
+                let seed = seed.0.wrapping_add(index as u32 + 1);
+                WaveLayer::new(layer, index, seed, settings.perlin_noise_octaves)
+            })
+            .collect();
+        let new_layers = match new_layers {
+            Ok(new_layers) => new_layers,
+            Err(error) => {
+                error!(?error, "Invalid wave layer color");
+                ack_sender.send(message.reply_to.as_deref(), CommandAck::rejected(error));
+                continue;
+            }
+        };
+
+        info!(layers = new_layers.len(), "Replacing wave layers");
+        for entity in layers.iter() {
+            commands.entity(entity).despawn();
+        }
+        for layer in new_layers {
+            let line_width = layer.line_width.unwrap_or(settings.line_width);
+            commands.spawn((
+                MaterialMesh2dBundle {
+                    mesh: meshes.add(build_line_mesh(&[], line_width)).into(),
+                    material: materials.add(ColorMaterial::from(night_mode.apply(layer.color))),
+                    visibility: Visibility::Hidden,
+                    ..default()
+                },
+                layer,
+            ));
+        }
+        ack_sender.send(
+            message.reply_to.as_deref(),
+            CommandAck::applied(&message.waves),
+        );
+    }
+}
+
+/// Redraw the layers, moving them as far as the main wave moved since the last frame
+fn update_wave_layers(
+    mut layers: Query<
+        (
+            &mut WaveLayer,
+            &Mesh2dHandle,
+            &mut Transform,
+            &mut Visibility,
+        ),
+        Without<NoiseWave>,
+    >,
+    main_wave: Query<(&Transform, &Visibility), With<NoiseWave>>,
+    query_camera: Query<&OrthographicProjection>,
+    (settings, safe_area, rotation, noise_generator): (
+        Res<NoiseGeneratorSettings>,
+        Res<SafeArea>,
+        Res<FaceRotation>,
+        Res<NoiseGenerator>,
+    ),
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut last_main_step: Local<Option<f64>>,
+) {
+    let main_step = noise_generator.elapsed_step;
+    let moved = main_step - last_main_step.replace(main_step).unwrap_or(main_step);
+    let (Ok((main_transform, main_visibility)), Some(camera)) =
+        (main_wave.get_single(), query_camera.iter().next())
+    else {
+        return;
+    };
+    let resolution = safe_area.apply(rotation.area(camera.area));
+    let width = (resolution.width() / settings.segment_width) as usize;
+
+    for (mut layer, mesh, mut transform, mut visibility) in layers.iter_mut() {
+        *visibility = *main_visibility;
+        *transform = Transform {
+            translation: main_transform.translation + Vec3::new(0.0, layer.offset_y, layer.z),
+            ..*main_transform
+        };
+        if *main_visibility == Visibility::Hidden {
+            continue;
+        }
+
+        layer.step += moved * layer.speed;
+        let samples = sample_noise(
+            &layer.generator,
+            layer.step,
+            settings.width_divider / layer.frequency,
+            width + 2,
+        );
+        let samples = smooth_samples(&samples, settings.smoothing_window);
+        let points = wave_points(
+            samples.into_iter(),
+            resolution.min.x,
+            settings.segment_width,
+            settings.height_multiplier * layer.amplitude,
+        );
+        let points = catmull_rom(&points, settings.curve_subdivisions);
+        if let Some(mesh) = meshes.get_mut(&mesh.0) {
+            write_line_mesh(
+                mesh,
+                &points,
+                layer.line_width.unwrap_or(settings.line_width),
+            );
+        }
+    }
+}
+
+fn tint_wave_layers(
+    layers: Query<(&WaveLayer, &Handle<ColorMaterial>)>,
+    night_mode: Res<NightMode>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for (layer, material) in layers.iter() {
+        if let Some(material) = materials.get_mut(material) {
+            material.color = night_mode.apply(layer.color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layer_settings_are_clamped() {
+        let message: WaveLayersMessage = serde_json::from_str(
+            r#"{"waves": [{"color": "ff000080", "amplitude": 0.5, "frequency": 2.0}, {"speed": 100.0, "offset_y": -40, "line_width": 1e9}]}"#,
+        )
+        .unwrap();
+        assert_eq!(message.waves[1].color, "ffffff80");
+
+        let harmonic = WaveLayer::new(&message.waves[0], 0, 1, 3).unwrap();
+        assert_eq!(harmonic.amplitude, 0.5);
+        assert_eq!(harmonic.frequency, 2.0);
+        assert_eq!(harmonic.speed, 1.0);
+        assert_eq!(harmonic.line_width, None);
+
+        let fast = WaveLayer::new(&message.waves[1], 1, 2, 3).unwrap();
+        assert_eq!(fast.speed, *SPEED_RANGE.end());
+        assert_eq!(fast.offset_y, -40.0);
+        assert_eq!(fast.line_width, Some(*LINE_WIDTH_RANGE.end() as f32));
+
+        let invalid = WaveLayerSettings {
+            color: String::from("not a color"),
+            ..default()
+        };
+        assert!(WaveLayer::new(&invalid, 0, 3, 3).is_err());
+    }
+}
@@ -0,0 +1,288 @@
+use std::{f32::consts::TAU, time::Duration};
+
+use bevy::prelude::*;
+use bevy_prototype_lyon::prelude::*;
+
+use crate::{
+    dnd::DoNotDisturb, face_clock::FaceClock, rotation::FaceRotation, safe_area::SafeArea,
+    scene::FaceScene, topics::RegisterTopic,
+};
+
+/// Small weather icon and temperature shown in the corner of the idle wave
+pub struct WeatherPlugin;
+
+impl Plugin for WeatherPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_topic::<WeatherMessage>("face/weather")
+            .init_resource::<Weather>()
+            .init_resource::<DoNotDisturb>()
+            .add_systems(Startup, spawn_weather_widget)
+            .add_systems(
+                Update,
+                (
+                    process_weather_messages,
+                    update_weather_widget,
+                    position_weather_widget,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Hide the widget when no update arrived for this long
+const WEATHER_TIMEOUT: Duration = Duration::from_secs(3 * 60 * 60);
+const WEATHER_MARGIN: f32 = 20.0;
+const WEATHER_ICON_SIZE: f32 = 40.0;
+const WEATHER_LINE_WIDTH: f32 = 2.0;
+const WEATHER_FONT_SIZE: f32 = 32.0;
+
+#[derive(Event, serde::Deserialize, serde::Serialize, clap::Args, Clone, Debug)]
+pub struct WeatherMessage {
+    #[arg(long)]
+    pub temperature_c: f32,
+    /// WMO weather interpretation code as reported by Open-Meteo
+    #[arg(long)]
+    pub condition_code: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WeatherCondition {
+    Clear,
+    PartlyCloudy,
+    Cloudy,
+    Fog,
+    Rain,
+    Snow,
+    Thunderstorm,
+}
+
+impl WeatherCondition {
+    fn from_wmo_code(code: u32) -> Self {
+        match code {
+            0 => Self::Clear,
+            1 | 2 => Self::PartlyCloudy,
+            45 | 48 => Self::Fog,
+            51..=67 | 80..=82 => Self::Rain,
+            71..=77 | 85 | 86 => Self::Snow,
+            95..=99 => Self::Thunderstorm,
+            _ => Self::Cloudy,
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+struct Weather {
+    latest: Option<WeatherMessage>,
+    received_at: Option<Duration>,
+}
+
+#[derive(Component)]
+struct WeatherWidget;
+
+#[derive(Component)]
+struct WeatherIcon;
+
+#[derive(Component)]
+struct WeatherTemperature;
+
+fn spawn_weather_widget(mut commands: Commands) {
+    commands
+        .spawn((
+            SpatialBundle {
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            WeatherWidget,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                ShapeBundle {
+                    path: build_weather_icon(WeatherCondition::Clear),
+                    spatial: SpatialBundle::from_transform(Transform::from_xyz(
+                        -WEATHER_ICON_SIZE * 0.5,
+                        0.0,
+                        0.0,
+                    )),
+                    ..default()
+                },
+                Stroke::new(Color::WHITE, WEATHER_LINE_WIDTH),
+                Fill::color(Color::NONE),
+                WeatherIcon,
+            ));
+            parent.spawn((
+                Text2dBundle {
+                    text: Text::from_section(
+                        "",
+                        TextStyle {
+                            font_size: WEATHER_FONT_SIZE,
+                            color: Color::WHITE,
+                            ..default()
+                        },
+                    ),
+                    text_anchor: bevy::sprite::Anchor::CenterRight,
+                    transform: Transform::from_xyz(-WEATHER_ICON_SIZE * 1.25, 0.0, 0.0),
+                    ..default()
+                },
+                WeatherTemperature,
+            ));
+        });
+}
+
+fn process_weather_messages(
+    mut messages: EventReader<WeatherMessage>,
+    mut weather: ResMut<Weather>,
+    clock: Res<FaceClock>,
+) {
+    if let Some(message) = messages.read().last() {
+        info!(
+            temperature_c = message.temperature_c,
+            condition_code = message.condition_code,
+            "Updating weather"
+        );
+        weather.latest = Some(message.clone());
+        weather.received_at = Some(clock.elapsed());
+    }
+}
+
+fn update_weather_widget(
+    (weather, do_not_disturb): (Res<Weather>, Res<DoNotDisturb>),
+    scene: Res<State<FaceScene>>,
+    clock: Res<FaceClock>,
+    mut widget: Query<&mut Visibility, With<WeatherWidget>>,
+    mut icon: Query<&mut Path, With<WeatherIcon>>,
+    mut temperature: Query<&mut Text, With<WeatherTemperature>>,
+) {
+    let fresh = weather
+        .received_at
+        .is_some_and(|received_at| clock.elapsed() - received_at < WEATHER_TIMEOUT);
+    let visible = fresh && *scene.get() == FaceScene::Wave && !do_not_disturb.active();
+    for mut visibility in widget.iter_mut() {
+        *visibility = if visible {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+
+    if !weather.is_changed() {
+        return;
+    }
+    let Some(latest) = &weather.latest else {
+        return;
+    };
+    let condition = WeatherCondition::from_wmo_code(latest.condition_code);
+    for mut path in icon.iter_mut() {
+        *path = build_weather_icon(condition);
+    }
+    for mut text in temperature.iter_mut() {
+        text.sections[0].value = format!("{:.0}°", latest.temperature_c);
+    }
+}
+
+/// Keep the widget in the top right corner of the safe area
+fn position_weather_widget(
+    mut query: Query<&mut Transform, With<WeatherWidget>>,
+    query_camera: Query<Ref<OrthographicProjection>>,
+    safe_area: Res<SafeArea>,
+    rotation: Res<FaceRotation>,
+) {
+    for camera in query_camera.iter() {
+        if !camera.is_changed() && !safe_area.is_changed() {
+            continue;
+        }
+        let area = safe_area.apply(rotation.area(camera.area));
+        for mut transform in query.iter_mut() {
+            transform.translation = Vec3::new(
+                area.max.x - WEATHER_MARGIN,
+                area.max.y - WEATHER_MARGIN - WEATHER_ICON_SIZE * 0.5,
+                1.0,
+            );
+        }
+    }
+}
+
+fn build_weather_icon(condition: WeatherCondition) -> Path {
+    let size = WEATHER_ICON_SIZE;
+    let mut builder = GeometryBuilder::new();
+
+    let sun_center = match condition {
+        WeatherCondition::Clear => Vec2::ZERO,
+        _ => Vec2::new(size * 0.2, size * 0.2),
+    };
+    if matches!(
+        condition,
+        WeatherCondition::Clear | WeatherCondition::PartlyCloudy
+    ) {
+        let radius = size * 0.2;
+        builder = builder.add(&shapes::Circle {
+            radius,
+            center: sun_center,
+        });
+        for ray in 0..8 {
+            let direction = Vec2::from_angle(ray as f32 / 8.0 * TAU);
+            builder = builder.add(&shapes::Line(
+                sun_center + direction * radius * 1.4,
+                sun_center + direction * radius * 2.0,
+            ));
+        }
+    }
+    if condition == WeatherCondition::Clear {
+        return builder.build();
+    }
+
+    // cloud made of three overlapping circles on a flat base
+    let cloud = [
+        (Vec2::new(-size * 0.25, 0.0), size * 0.18),
+        (Vec2::new(0.0, size * 0.08), size * 0.25),
+        (Vec2::new(size * 0.25, 0.0), size * 0.18),
+    ];
+    for (center, radius) in cloud {
+        builder = builder.add(&shapes::Circle { radius, center });
+    }
+    builder = builder.add(&shapes::Line(
+        Vec2::new(-size * 0.25, -size * 0.18),
+        Vec2::new(size * 0.25, -size * 0.18),
+    ));
+
+    let below = -size * 0.3;
+    match condition {
+        WeatherCondition::Fog => {
+            for line in 0..2 {
+                let y = below - line as f32 * size * 0.12;
+                builder = builder.add(&shapes::Line(
+                    Vec2::new(-size * 0.4, y),
+                    Vec2::new(size * 0.4, y),
+                ));
+            }
+        }
+        WeatherCondition::Rain => {
+            for x in [-0.2, 0.0, 0.2] {
+                builder = builder.add(&shapes::Line(
+                    Vec2::new(x * size, below),
+                    Vec2::new(x * size - size * 0.08, below - size * 0.2),
+                ));
+            }
+        }
+        WeatherCondition::Snow => {
+            for x in [-0.2, 0.0, 0.2] {
+                builder = builder.add(&shapes::Circle {
+                    radius: size * 0.04,
+                    center: Vec2::new(x * size, below - size * 0.1),
+                });
+            }
+        }
+        WeatherCondition::Thunderstorm => {
+            builder = builder.add(&shapes::Polygon {
+                points: vec![
+                    Vec2::new(size * 0.05, below),
+                    Vec2::new(-size * 0.08, below - size * 0.12),
+                    Vec2::new(size * 0.05, below - size * 0.12),
+                    Vec2::new(-size * 0.08, below - size * 0.25),
+                ],
+                closed: false,
+            });
+        }
+        _ => {}
+    }
+    builder.build()
+}
@@ -0,0 +1,60 @@
+use std::time::Duration;
+
+use bevy::{prelude::*, window::PrimaryWindow};
+
+/// Respawn the primary window if it ever goes away in kiosk mode
+///
+/// A despawned window otherwise leaves the face running headless behind a blank screen.
+/// The new window takes the settings the old one last had, so rotation and present mode carry over
+pub struct WindowWatchdogPlugin {
+    /// Window settings the face started with
+    pub window: Window,
+}
+
+impl Plugin for WindowWatchdogPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(LastWindow(self.window.clone()))
+            .add_systems(Last, (remember_window, respawn_window).chain());
+    }
+}
+
+/// Windows respawned in quick succession are spaced out by this
+const RESPAWN_BACKOFF: Duration = Duration::from_secs(1);
+
+#[derive(Resource)]
+struct LastWindow(Window);
+
+fn remember_window(
+    windows: Query<&Window, (With<PrimaryWindow>, Changed<Window>)>,
+    mut last_window: ResMut<LastWindow>,
+) {
+    if let Ok(window) = windows.get_single() {
+        last_window.0 = window.clone();
+    }
+}
+
+fn respawn_window(
+    mut commands: Commands,
+    windows: Query<(), With<PrimaryWindow>>,
+    last_window: Res<LastWindow>,
+    mut last_respawn: Local<Option<Duration>>,
+    time: Res<Time<Real>>,
+) {
+    if !windows.is_empty() {
+        return;
+    }
+    let now = time.elapsed();
+    if last_respawn.is_some_and(|last_respawn| now < last_respawn + RESPAWN_BACKOFF) {
+        return;
+    }
+    *last_respawn = Some(now);
+    warn!("Primary window is gone, respawning it");
+    commands.spawn((
+        Window {
+            // the startup window is only shown once the gpu is ready
+            visible: true,
+            ..last_window.0.clone()
+        },
+        PrimaryWindow,
+    ));
+}
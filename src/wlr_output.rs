@@ -0,0 +1,404 @@
+//! Output control using the wlr-output-management wayland protocol
+
+use bevy::log::info;
+use thiserror::Error;
+use wayland_client::{
+    delegate_noop, event_created_child,
+    globals::{registry_queue_init, BindError, GlobalError, GlobalListContents},
+    protocol::{wl_output::Transform, wl_registry::WlRegistry},
+    ConnectError, Connection, Dispatch, DispatchError, EventQueue, Proxy, QueueHandle, WEnum,
+};
+use wayland_protocols_wlr::output_management::v1::client::{
+    zwlr_output_configuration_head_v1::ZwlrOutputConfigurationHeadV1,
+    zwlr_output_configuration_v1::{self, ZwlrOutputConfigurationV1},
+    zwlr_output_head_v1::{self, ZwlrOutputHeadV1},
+    zwlr_output_manager_v1::{self, ZwlrOutputManagerV1},
+    zwlr_output_mode_v1::{self, ZwlrOutputModeV1},
+};
+
+use crate::display::{DisplayMode, OutputChange};
+
+#[derive(Error, Debug)]
+pub enum OutputError {
+    #[error("Failed to connect to wayland compositor")]
+    Connect(#[from] ConnectError),
+    #[error("Failed to list wayland globals")]
+    Globals(#[from] GlobalError),
+    #[error("Compositor does not support wlr-output-management")]
+    Unsupported(#[from] BindError),
+    #[error("Wayland dispatch failed")]
+    Dispatch(#[from] DispatchError),
+    #[error("Output {name} not found, available outputs: {available:?}")]
+    OutputNotFound {
+        name: String,
+        available: Vec<String>,
+    },
+    #[error("Mode {requested} not supported, available modes: {available:?}")]
+    ModeNotFound {
+        requested: DisplayMode,
+        available: Vec<String>,
+    },
+    #[error("Unknown output transform {0}")]
+    InvalidTransform(String),
+    #[error("Compositor rejected output configuration")]
+    ConfigurationFailed,
+    #[error("Output configuration was cancelled by a concurrent change")]
+    ConfigurationCancelled,
+}
+
+/// Parse a transform in the same format as wlr-randr
+pub fn parse_transform(transform: &str) -> Result<Transform, OutputError> {
+    match transform {
+        "normal" | "0" => Ok(Transform::Normal),
+        "90" => Ok(Transform::_90),
+        "180" => Ok(Transform::_180),
+        "270" => Ok(Transform::_270),
+        "flipped" => Ok(Transform::Flipped),
+        "flipped-90" => Ok(Transform::Flipped90),
+        "flipped-180" => Ok(Transform::Flipped180),
+        "flipped-270" => Ok(Transform::Flipped270),
+        _ => Err(OutputError::InvalidTransform(transform.to_owned())),
+    }
+}
+
+/// Apply a change to the output called `name`
+///
+/// Blocks until the compositor accepted or rejected the configuration.
+/// With `dry_run` the requests for every head are only logged
+pub fn configure_output(
+    name: &str,
+    change: &OutputChange,
+    dry_run: bool,
+) -> Result<(), OutputError> {
+    let transform = change
+        .transform
+        .as_deref()
+        .map(parse_transform)
+        .transpose()?;
+
+    let (connection, mut queue, manager, mut state) = list_heads()?;
+    let target = state.find_head(name)?;
+    let mode = change.mode.map(|mode| target.find_mode(mode)).transpose()?;
+    let enabled = |head: &Head| match change.enabled {
+        Some(enabled) if head.name == name => enabled,
+        _ => head.enabled,
+    };
+
+    if dry_run {
+        for head in &state.heads {
+            let is_target = head.name == name;
+            let mode = match mode {
+                Some(mode) if is_target => Some(mode.to_string()),
+                _ => head.current_mode().map(|mode| mode.to_string()),
+            };
+            let transform = match transform {
+                Some(transform) if is_target => transform,
+                _ => head.transform,
+            };
+            info!(
+                head = head.name,
+                enabled = enabled(head),
+                mode,
+                position = ?head.position,
+                scale = head.scale,
+                ?transform,
+                "Dry run, not configuring head"
+            );
+        }
+        return Ok(());
+    }
+    let mode = mode.map(|mode| mode.proxy.clone());
+
+    let configuration =
+        manager.create_configuration(state.serial.unwrap_or_default(), &queue.handle(), ());
+    // every head has to be part of the configuration
+    // so unrelated heads are configured with their current state
+    for head in &state.heads {
+        let is_target = head.name == name;
+        if !enabled(head) {
+            configuration.disable_head(&head.proxy);
+            continue;
+        }
+        let head_configuration = configuration.enable_head(&head.proxy, &queue.handle(), ());
+        match (&mode, &head.current_mode) {
+            (Some(mode), _) if is_target => head_configuration.set_mode(mode),
+            (_, Some(current_mode)) => head_configuration.set_mode(current_mode),
+            _ => {}
+        }
+        head_configuration.set_position(head.position.0, head.position.1);
+        head_configuration.set_scale(head.scale);
+        match transform {
+            Some(transform) if is_target => head_configuration.set_transform(transform),
+            _ => head_configuration.set_transform(head.transform),
+        }
+    }
+    configuration.apply();
+
+    while state.result.is_none() {
+        queue.blocking_dispatch(&mut state)?;
+    }
+    configuration.destroy();
+    manager.stop();
+    connection.flush().ok();
+
+    state
+        .result
+        .unwrap_or(Err(OutputError::ConfigurationCancelled))
+}
+
+/// Current mode and transform of an output
+#[derive(Debug, Clone, Copy)]
+pub struct OutputInfo {
+    /// Size of the current mode before the transform is applied
+    pub width: i32,
+    pub height: i32,
+    pub transform: Transform,
+}
+
+/// Look up the current state of the output called `name`
+pub fn output_info(name: &str) -> Result<OutputInfo, OutputError> {
+    let (connection, _queue, manager, state) = list_heads()?;
+    let head = state.find_head(name)?;
+    let info = OutputInfo {
+        width: head.current_mode().map_or(0, |mode| mode.width),
+        height: head.current_mode().map_or(0, |mode| mode.height),
+        transform: head.transform,
+    };
+    manager.stop();
+    connection.flush().ok();
+    Ok(info)
+}
+
+/// Connect to the compositor and wait for the state of all heads
+fn list_heads() -> Result<
+    (
+        Connection,
+        EventQueue<OutputState>,
+        ZwlrOutputManagerV1,
+        OutputState,
+    ),
+    OutputError,
+> {
+    let connection = Connection::connect_to_env()?;
+    let (globals, mut queue) = registry_queue_init::<OutputState>(&connection)?;
+    let manager: ZwlrOutputManagerV1 = globals.bind(&queue.handle(), 1..=4, ())?;
+
+    // the manager sends all heads followed by a done event
+    let mut state = OutputState::default();
+    while state.serial.is_none() {
+        queue.blocking_dispatch(&mut state)?;
+    }
+    Ok((connection, queue, manager, state))
+}
+
+struct Mode {
+    proxy: ZwlrOutputModeV1,
+    width: i32,
+    height: i32,
+    /// Refresh rate in mHz
+    refresh: i32,
+}
+
+impl std::fmt::Display for Mode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}x{}@{}Hz",
+            self.width,
+            self.height,
+            self.refresh as f32 / 1000.0
+        )
+    }
+}
+
+struct Head {
+    proxy: ZwlrOutputHeadV1,
+    name: String,
+    enabled: bool,
+    modes: Vec<Mode>,
+    current_mode: Option<ZwlrOutputModeV1>,
+    position: (i32, i32),
+    transform: Transform,
+    scale: f64,
+}
+
+impl Head {
+    /// Mode the output is currently using, if the compositor reported one
+    fn current_mode(&self) -> Option<&Mode> {
+        let current_mode = self.current_mode.as_ref()?;
+        self.modes.iter().find(|mode| mode.proxy == *current_mode)
+    }
+
+    /// Find the mode matching the resolution with the closest refresh rate
+    ///
+    /// Picks the highest refresh rate if none was requested
+    fn find_mode(&self, requested: DisplayMode) -> Result<&Mode, OutputError> {
+        let candidates = self
+            .modes
+            .iter()
+            .filter(|mode| mode.width == requested.width && mode.height == requested.height);
+        let found = match requested.refresh {
+            Some(refresh) => {
+                let refresh = (refresh * 1000.0) as i32;
+                // allow for rounding of fractional rates such as 59.94 Hz
+                candidates
+                    .filter(|mode| (mode.refresh - refresh).abs() <= 1000)
+                    .min_by_key(|mode| (mode.refresh - refresh).abs())
+            }
+            None => candidates.max_by_key(|mode| mode.refresh),
+        };
+        found.ok_or_else(|| OutputError::ModeNotFound {
+            requested,
+            available: self.modes.iter().map(|mode| mode.to_string()).collect(),
+        })
+    }
+}
+
+#[derive(Default)]
+struct OutputState {
+    heads: Vec<Head>,
+    serial: Option<u32>,
+    result: Option<Result<(), OutputError>>,
+}
+
+impl OutputState {
+    fn find_head(&self, name: &str) -> Result<&Head, OutputError> {
+        self.heads
+            .iter()
+            .find(|head| head.name == name)
+            .ok_or_else(|| OutputError::OutputNotFound {
+                name: name.to_owned(),
+                available: self.heads.iter().map(|head| head.name.clone()).collect(),
+            })
+    }
+}
+
+impl Dispatch<WlRegistry, GlobalListContents> for OutputState {
+    fn event(
+        _state: &mut Self,
+        _proxy: &WlRegistry,
+        _event: <WlRegistry as Proxy>::Event,
+        _data: &GlobalListContents,
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ZwlrOutputManagerV1, ()> for OutputState {
+    fn event(
+        state: &mut Self,
+        _proxy: &ZwlrOutputManagerV1,
+        event: zwlr_output_manager_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        match event {
+            zwlr_output_manager_v1::Event::Head { head } => state.heads.push(Head {
+                proxy: head,
+                name: String::new(),
+                enabled: false,
+                modes: Vec::new(),
+                current_mode: None,
+                position: (0, 0),
+                transform: Transform::Normal,
+                scale: 1.0,
+            }),
+            zwlr_output_manager_v1::Event::Done { serial } => state.serial = Some(serial),
+            _ => {}
+        }
+    }
+
+    event_created_child!(OutputState, ZwlrOutputManagerV1, [
+        zwlr_output_manager_v1::EVT_HEAD_OPCODE => (ZwlrOutputHeadV1, ()),
+    ]);
+}
+
+impl Dispatch<ZwlrOutputHeadV1, ()> for OutputState {
+    fn event(
+        state: &mut Self,
+        proxy: &ZwlrOutputHeadV1,
+        event: zwlr_output_head_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        let Some(head) = state.heads.iter_mut().find(|head| head.proxy == *proxy) else {
+            return;
+        };
+        match event {
+            zwlr_output_head_v1::Event::Name { name } => head.name = name,
+            zwlr_output_head_v1::Event::Enabled { enabled } => head.enabled = enabled != 0,
+            zwlr_output_head_v1::Event::Mode { mode } => head.modes.push(Mode {
+                proxy: mode,
+                width: 0,
+                height: 0,
+                refresh: 0,
+            }),
+            zwlr_output_head_v1::Event::CurrentMode { mode } => head.current_mode = Some(mode),
+            zwlr_output_head_v1::Event::Position { x, y } => head.position = (x, y),
+            zwlr_output_head_v1::Event::Transform {
+                transform: WEnum::Value(transform),
+            } => head.transform = transform,
+            zwlr_output_head_v1::Event::Scale { scale } => head.scale = scale,
+            _ => {}
+        }
+    }
+
+    event_created_child!(OutputState, ZwlrOutputHeadV1, [
+        zwlr_output_head_v1::EVT_MODE_OPCODE => (ZwlrOutputModeV1, ()),
+    ]);
+}
+
+impl Dispatch<ZwlrOutputModeV1, ()> for OutputState {
+    fn event(
+        state: &mut Self,
+        proxy: &ZwlrOutputModeV1,
+        event: zwlr_output_mode_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        let Some(mode) = state
+            .heads
+            .iter_mut()
+            .flat_map(|head| head.modes.iter_mut())
+            .find(|mode| mode.proxy == *proxy)
+        else {
+            return;
+        };
+        match event {
+            zwlr_output_mode_v1::Event::Size { width, height } => {
+                mode.width = width;
+                mode.height = height;
+            }
+            zwlr_output_mode_v1::Event::Refresh { refresh } => mode.refresh = refresh,
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<ZwlrOutputConfigurationV1, ()> for OutputState {
+    fn event(
+        state: &mut Self,
+        _proxy: &ZwlrOutputConfigurationV1,
+        event: zwlr_output_configuration_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        state.result = match event {
+            zwlr_output_configuration_v1::Event::Succeeded => Some(Ok(())),
+            zwlr_output_configuration_v1::Event::Failed => {
+                Some(Err(OutputError::ConfigurationFailed))
+            }
+            zwlr_output_configuration_v1::Event::Cancelled => {
+                Some(Err(OutputError::ConfigurationCancelled))
+            }
+            _ => return,
+        };
+    }
+}
+
+delegate_noop!(OutputState: ignore ZwlrOutputConfigurationHeadV1);